@@ -0,0 +1,92 @@
+use joker::track::*;
+
+use id::Id;
+use expr::Expr;
+use fun::Fun;
+use obj::PropKey;
+use ty::{Ty, TyParam, FunTy};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecoratorData(pub Expr);
+
+impl Untrack for DecoratorData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.0.relocate(r);
+    }
+}
+
+pub type Decorator = Tracked<DecoratorData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClassData {
+    pub decorators: Vec<Decorator>,
+    pub is_abstract: bool,
+    pub id: Option<Id>,
+    pub ty_params: Vec<TyParam>,
+    pub extends: Option<Box<Expr>>,
+    pub implements: Vec<Ty>,
+    pub body: Vec<ClassMember>
+}
+
+impl Untrack for ClassData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.decorators.relocate(r);
+        self.id.relocate(r);
+        self.ty_params.relocate(r);
+        self.extends.relocate(r);
+        self.implements.relocate(r);
+        self.body.relocate(r);
+    }
+}
+
+pub type Class = Tracked<ClassData>;
+
+// TypeScript accessibility modifiers.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Access {
+    Public,
+    Protected,
+    Private
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClassMemberVal {
+    Method(Fun),
+    Get(Fun),
+    Set(Fun),
+    Field(Option<Ty>, Option<Expr>),
+    Sig(FunTy)
+}
+
+impl Untrack for ClassMemberVal {
+    fn relocate(&mut self, r: &mut Relocation) {
+        match *self {
+            ClassMemberVal::Method(ref mut fun)             => { fun.relocate(r); }
+            ClassMemberVal::Get(ref mut fun)                => { fun.relocate(r); }
+            ClassMemberVal::Set(ref mut fun)                => { fun.relocate(r); }
+            ClassMemberVal::Field(ref mut ty, ref mut init) => { ty.relocate(r); init.relocate(r); }
+            ClassMemberVal::Sig(ref mut sig)                => { sig.relocate(r); }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClassMemberData {
+    pub decorators: Vec<Decorator>,
+    pub access: Option<Access>,
+    pub is_abstract: bool,
+    pub is_static: bool,
+    pub readonly: bool,
+    pub key: PropKey,
+    pub val: ClassMemberVal
+}
+
+impl Untrack for ClassMemberData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.decorators.relocate(r);
+        self.key.relocate(r);
+        self.val.relocate(r);
+    }
+}
+
+pub type ClassMember = Tracked<ClassMemberData>;
@@ -1,7 +1,7 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use joker::track::{Span, IntoTracked};
-use expr::{Expr, ExprData};
+use expr::{Expr, ExprData, take};
 use patt::{Patt, AssignTarget, AssignTargetData, CompoundPattData, PropPatt, PropPattData};
 use obj::{Prop, PropValData};
 
@@ -30,29 +30,39 @@ pub trait IntoAssignPatt {
 }
 
 impl IntoAssignPatt for Expr {
-    fn into_assign_patt(self) -> Result<Patt<AssignTarget>, Error> {
+    fn into_assign_patt(mut self) -> Result<Patt<AssignTarget>, Error> {
+        let location = self.location;
         Ok(match self.value {
-            ExprData::Id(id)           => Patt::Simple(AssignTargetData::Id(id).tracked(self.location)),
-            ExprData::Dot(obj, key)    => Patt::Simple(AssignTargetData::Dot(obj, key).tracked(self.location)),
-            ExprData::Brack(obj, prop) => Patt::Simple(AssignTargetData::Brack(obj, prop).tracked(self.location)),
-            ExprData::Obj(props) => {
+            ExprData::Id(ref id)                       => Patt::Simple(AssignTargetData::Id(id.clone()).tracked(location)),
+            ExprData::Dot(ref mut obj, ref key)        => {
+                Patt::Simple(AssignTargetData::Dot(Box::new(take(obj)), key.clone()).tracked(location))
+            }
+            ExprData::Brack(ref mut obj, ref mut prop) => {
+                Patt::Simple(AssignTargetData::Brack(Box::new(take(obj)), Box::new(take(prop))).tracked(location))
+            }
+            // Only a simple target may be parenthesized.
+            ExprData::Paren(ref mut expr) => match try!(take(expr).into_assign_patt()) {
+                Patt::Compound(_) => { return Err(Error::InvalidAssignTarget(location)); }
+                patt              => patt
+            },
+            ExprData::Obj(ref mut props) => {
                 let mut prop_patts = Vec::with_capacity(props.len());
-                for prop in props {
+                for prop in props.drain(..) {
                     prop_patts.push(try!(prop.into_assign_prop()));
                 }
-                Patt::Compound(CompoundPattData::Obj(prop_patts).tracked(self.location))
+                Patt::Compound(CompoundPattData::Obj(prop_patts).tracked(location))
             }
-            ExprData::Arr(exprs) => {
+            ExprData::Arr(ref mut exprs) => {
                 let mut patts = Vec::with_capacity(exprs.len());
-                for expr in exprs {
+                for expr in exprs.drain(..) {
                     patts.push(match expr {
                         Some(expr) => Some(try!(expr.into_assign_patt())),
                         None => None
                     });
                 }
-                Patt::Compound(CompoundPattData::Arr(patts).tracked(self.location))
+                Patt::Compound(CompoundPattData::Arr(patts).tracked(location))
             }
-            _ => { return Err(Error::InvalidAssignTarget(self.location)); }
+            _ => { return Err(Error::InvalidAssignTarget(location)); }
         })
     }
 }
@@ -1,36 +1,79 @@
 use joker::track::*;
+use joker::token::StringLiteral;
 
 use id::Id;
 use fun::Fun;
+use class::Class;
 use patt::{Patt, CompoundPatt};
 use expr::Expr;
+use obj::PropKey;
+use stmt::StmtListItem;
+use ty::{Ty, TyParam, TyMember, FunTy};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum DeclData {
-    Fun(Fun)
+    Fun(Fun),
+    Class(Class),
+    FunSig(Id, FunTy),
+    Interface(Id, Vec<TyParam>, Vec<Ty>, Vec<TyMember>),
+    TyAlias(Id, Vec<TyParam>, Ty),
+    Enum(Id, Vec<EnumMember>),
+    ConstEnum(Id, Vec<EnumMember>),
+    Declare(Box<StmtListItem>),
+    // A member of a namespace marked with `export`.
+    Export(Box<StmtListItem>),
+    Namespace(Vec<Id>, Vec<StmtListItem>),
+    // An ambient module, whose body may be left out.
+    Module(StringLiteral, Option<Vec<StmtListItem>>)
 }
 
 impl Untrack for DeclData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            DeclData::Fun(ref mut fun) => { fun.untrack(); }
+            DeclData::Fun(ref mut fun)                  => { fun.relocate(r); }
+            DeclData::Class(ref mut class)              => { class.relocate(r); }
+            DeclData::FunSig(ref mut id, ref mut sig)   => { id.relocate(r); sig.relocate(r); }
+            DeclData::Interface(ref mut id, ref mut params,
+                                ref mut extends, ref mut body) => { id.relocate(r); params.relocate(r); extends.relocate(r); body.relocate(r); }
+            DeclData::TyAlias(ref mut id, ref mut params, ref mut ty) => { id.relocate(r); params.relocate(r); ty.relocate(r); }
+            DeclData::Enum(ref mut id, ref mut members)      => { id.relocate(r); members.relocate(r); }
+            DeclData::ConstEnum(ref mut id, ref mut members) => { id.relocate(r); members.relocate(r); }
+            DeclData::Declare(ref mut item)             => { item.relocate(r); }
+            DeclData::Export(ref mut item)              => { item.relocate(r); }
+            DeclData::Namespace(ref mut name, ref mut body) => { name.relocate(r); body.relocate(r); }
+            DeclData::Module(_, ref mut body)           => { body.relocate(r); }
         }
     }
 }
 
 pub type Decl = Tracked<DeclData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnumMemberData {
+    pub key: PropKey,
+    pub init: Option<Expr>
+}
+
+impl Untrack for EnumMemberData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.key.relocate(r);
+        self.init.relocate(r);
+    }
+}
+
+pub type EnumMember = Tracked<EnumMemberData>;
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum DtorData {
     Simple(Id, Option<Expr>),
     Compound(CompoundPatt<Id>, Expr)
 }
 
 impl Untrack for DtorData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            DtorData::Simple(ref mut id, ref mut init)     => { id.untrack(); init.untrack(); }
-            DtorData::Compound(ref mut patt, ref mut init) => { patt.untrack(); init.untrack(); }
+            DtorData::Simple(ref mut id, ref mut init)     => { id.relocate(r); init.relocate(r); }
+            DtorData::Compound(ref mut patt, ref mut init) => { patt.relocate(r); init.relocate(r); }
         }
     }
 }
@@ -1,13 +1,18 @@
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::mem::replace;
 use joker::track::*;
 use joker::token::{NumberLiteral, StringLiteral, RegExpLiteral};
 
 use obj::{DotKey, Prop};
 use fun::Fun;
+use class::Class;
 use punc::{Unop, Binop, Assop, Logop};
 use id::Id;
 use patt::{Patt, AssignTarget};
+use jsx::{JSXElement, JSXFragment};
+use ty::Ty;
+use stack::grow;
 
 pub enum ExprData {
     This,
@@ -15,6 +20,7 @@ pub enum ExprData {
     Arr(Vec<Option<Expr>>),
     Obj(Vec<Prop>),
     Fun(Fun),
+    Class(Class),
     Seq(Vec<Expr>),
     Unop(Unop, Box<Expr>),
     Binop(Binop, Box<Expr>, Box<Expr>),
@@ -30,22 +36,137 @@ pub enum ExprData {
     Dot(Box<Expr>, DotKey),
     Brack(Box<Expr>, Box<Expr>),
     NewTarget,
+    ImportMeta,
+    Import(Box<Expr>),
     True,
     False,
     Null,
     Number(NumberLiteral),
     RegExp(RegExpLiteral),
-    String(StringLiteral)
+    String(StringLiteral),
+    JSXElement(JSXElement),
+    JSXFragment(JSXFragment),
+    As(Box<Expr>, Ty),
+    Satisfies(Box<Expr>, Ty),
+    Instantiation(Box<Expr>, Vec<Ty>),
+    // TypeScript's non-null assertion, `x!`.
+    NonNull(Box<Expr>),
+    // A parenthesized expression, produced only with the `preserve_parens`
+    // parse option.
+    Paren(Box<Expr>),
+    // A placeholder for an expression that failed to parse, produced only
+    // by the error-recovering parser.
+    Invalid
+}
+
+impl Clone for ExprData {
+    fn clone(&self) -> ExprData {
+        grow(|| match *self {
+            ExprData::This                         => ExprData::This,
+            ExprData::Id(ref id)                   => ExprData::Id(id.clone()),
+            ExprData::Arr(ref elts)                => ExprData::Arr(elts.clone()),
+            ExprData::Obj(ref props)               => ExprData::Obj(props.clone()),
+            ExprData::Fun(ref fun)                 => ExprData::Fun(fun.clone()),
+            ExprData::Class(ref class)             => ExprData::Class(class.clone()),
+            ExprData::Seq(ref exprs)               => ExprData::Seq(exprs.clone()),
+            ExprData::Unop(ref op, ref arg)        => ExprData::Unop(op.clone(), arg.clone()),
+            ExprData::Binop(ref op, ref l, ref r)  => ExprData::Binop(op.clone(), l.clone(), r.clone()),
+            ExprData::Logop(ref op, ref l, ref r)  => ExprData::Logop(op.clone(), l.clone(), r.clone()),
+            ExprData::PreInc(ref arg)              => ExprData::PreInc(arg.clone()),
+            ExprData::PostInc(ref arg)             => ExprData::PostInc(arg.clone()),
+            ExprData::PreDec(ref arg)              => ExprData::PreDec(arg.clone()),
+            ExprData::PostDec(ref arg)             => ExprData::PostDec(arg.clone()),
+            ExprData::Assign(ref op, ref patt, ref arg) => ExprData::Assign(op.clone(), patt.clone(), arg.clone()),
+            ExprData::Cond(ref test, ref cons, ref alt) => ExprData::Cond(test.clone(), cons.clone(), alt.clone()),
+            ExprData::Call(ref callee, ref args)   => ExprData::Call(callee.clone(), args.clone()),
+            ExprData::New(ref callee, ref args)    => ExprData::New(callee.clone(), args.clone()),
+            ExprData::Dot(ref obj, ref key)        => ExprData::Dot(obj.clone(), key.clone()),
+            ExprData::Brack(ref obj, ref prop)     => ExprData::Brack(obj.clone(), prop.clone()),
+            ExprData::NewTarget                    => ExprData::NewTarget,
+            ExprData::ImportMeta                   => ExprData::ImportMeta,
+            ExprData::Import(ref src)              => ExprData::Import(src.clone()),
+            ExprData::True                         => ExprData::True,
+            ExprData::False                        => ExprData::False,
+            ExprData::Null                         => ExprData::Null,
+            ExprData::Number(ref lit)              => ExprData::Number(lit.clone()),
+            ExprData::RegExp(ref lit)              => ExprData::RegExp(lit.clone()),
+            ExprData::String(ref lit)              => ExprData::String(lit.clone()),
+            ExprData::JSXElement(ref el)           => ExprData::JSXElement(el.clone()),
+            ExprData::JSXFragment(ref f)           => ExprData::JSXFragment(f.clone()),
+            ExprData::As(ref expr, ref ty)         => ExprData::As(expr.clone(), ty.clone()),
+            ExprData::Satisfies(ref expr, ref ty)  => ExprData::Satisfies(expr.clone(), ty.clone()),
+            ExprData::Instantiation(ref expr, ref args) => ExprData::Instantiation(expr.clone(), args.clone()),
+            ExprData::NonNull(ref expr)            => ExprData::NonNull(expr.clone()),
+            ExprData::Paren(ref expr)              => ExprData::Paren(expr.clone()),
+            ExprData::Invalid                      => ExprData::Invalid
+        })
+    }
+}
+
+// Dropping an expression moves its operands onto a list and drops them one
+// by one, so that long chains don't recurse.
+impl Drop for ExprData {
+    fn drop(&mut self) {
+        let mut operands = Vec::new();
+        self.take_operands(&mut operands);
+        while let Some(mut expr) = operands.pop() {
+            expr.value.take_operands(&mut operands);
+        }
+    }
+}
+
+impl ExprData {
+    fn take_operands(&mut self, operands: &mut Vec<Expr>) {
+        match *self {
+            ExprData::Unop(_, ref mut arg)
+          | ExprData::PreInc(ref mut arg)
+          | ExprData::PostInc(ref mut arg)
+          | ExprData::PreDec(ref mut arg)
+          | ExprData::PostDec(ref mut arg)
+          | ExprData::Assign(_, _, ref mut arg)
+          | ExprData::Dot(ref mut arg, _)
+          | ExprData::Import(ref mut arg)
+          | ExprData::As(ref mut arg, _)
+          | ExprData::Satisfies(ref mut arg, _)
+          | ExprData::Instantiation(ref mut arg, _)
+          | ExprData::NonNull(ref mut arg)
+          | ExprData::Paren(ref mut arg)                   => { operands.push(take(arg)); }
+            ExprData::Binop(_, ref mut left, ref mut right)
+          | ExprData::Logop(_, ref mut left, ref mut right)
+          | ExprData::Brack(ref mut left, ref mut right)   => { operands.push(take(left)); operands.push(take(right)); }
+            ExprData::Cond(ref mut test, ref mut cons, ref mut alt) => {
+                operands.push(take(test));
+                operands.push(take(cons));
+                operands.push(take(alt));
+            }
+            ExprData::Call(ref mut callee, ref mut args)
+          | ExprData::New(ref mut callee, Some(ref mut args)) => {
+                operands.push(take(callee));
+                operands.extend(args.drain(..));
+            }
+            ExprData::New(ref mut callee, None)             => { operands.push(take(callee)); }
+            ExprData::Seq(ref mut exprs)                    => { operands.extend(exprs.drain(..)); }
+            ExprData::Arr(ref mut elts)                     => { operands.extend(elts.drain(..).filter_map(|elt| elt)); }
+            _ => { }
+        }
+    }
+}
+
+// Moves an expression out of its place, leaving an invalid expression behind.
+// `ExprData` implements `Drop`, so its fields can't be moved out directly.
+pub fn take(expr: &mut Expr) -> Expr {
+    replace(expr, ExprData::Invalid.tracked(None))
 }
 
 impl PartialEq for ExprData {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
+        grow(|| match (self, other) {
             (&ExprData::This,                      &ExprData::This)                      => true,
             (&ExprData::Id(ref id_l),              &ExprData::Id(ref id_r))              => id_l == id_r,
             (&ExprData::Arr(ref elts_l),           &ExprData::Arr(ref elts_r))           => elts_l == elts_r,
             (&ExprData::Obj(ref props_l),          &ExprData::Obj(ref props_r))          => props_l == props_r,
             (&ExprData::Fun(ref fun_l),            &ExprData::Fun(ref fun_r))            => fun_l == fun_r,
+            (&ExprData::Class(ref class_l),        &ExprData::Class(ref class_r))        => class_l == class_r,
             (&ExprData::Seq(ref exprs_l),          &ExprData::Seq(ref exprs_r))          => exprs_l == exprs_r,
             (&ExprData::Unop(ref op_l, ref arg_l), &ExprData::Unop(ref op_r, ref arg_r)) => (op_l, arg_l) == (op_r, arg_r),
             (&ExprData::Binop(ref op_l, ref arg1_l, ref arg2_l),
@@ -72,25 +193,39 @@ impl PartialEq for ExprData {
             (&ExprData::Brack(ref obj_l, ref prop_l),
              &ExprData::Brack(ref obj_r, ref prop_r))                                    => (obj_l, prop_l) == (obj_r, prop_r),
             (&ExprData::NewTarget,          &ExprData::NewTarget)                        => true,
+            (&ExprData::ImportMeta,         &ExprData::ImportMeta)                       => true,
+            (&ExprData::Import(ref src_l),  &ExprData::Import(ref src_r))                => src_l == src_r,
             (&ExprData::True,               &ExprData::True)                             => true,
             (&ExprData::False,              &ExprData::False)                            => true,
             (&ExprData::Null,               &ExprData::Null)                             => true,
             (&ExprData::Number(ref lit_l),  &ExprData::Number(ref lit_r))                => lit_l == lit_r,
             (&ExprData::RegExp(ref lit_l),  &ExprData::RegExp(ref lit_r))                => lit_l == lit_r,
             (&ExprData::String(ref lit_l),  &ExprData::String(ref lit_r))                => lit_l == lit_r,
+            (&ExprData::JSXElement(ref el_l), &ExprData::JSXElement(ref el_r))           => el_l == el_r,
+            (&ExprData::JSXFragment(ref f_l), &ExprData::JSXFragment(ref f_r))           => f_l == f_r,
+            (&ExprData::As(ref expr_l, ref ty_l),
+             &ExprData::As(ref expr_r, ref ty_r))                                        => (expr_l, ty_l) == (expr_r, ty_r),
+            (&ExprData::Satisfies(ref expr_l, ref ty_l),
+             &ExprData::Satisfies(ref expr_r, ref ty_r))                                 => (expr_l, ty_l) == (expr_r, ty_r),
+            (&ExprData::Instantiation(ref expr_l, ref args_l),
+             &ExprData::Instantiation(ref expr_r, ref args_r))                           => (expr_l, args_l) == (expr_r, args_r),
+            (&ExprData::NonNull(ref expr_l), &ExprData::NonNull(ref expr_r))             => expr_l == expr_r,
+            (&ExprData::Paren(ref expr_l),  &ExprData::Paren(ref expr_r))                => expr_l == expr_r,
+            (&ExprData::Invalid,            &ExprData::Invalid)                          => true,
             _ => false
-        }
+        })
     }
 }
 
 impl Debug for ExprData {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
-        match self {
+        grow(|| match self {
             &ExprData::This                                => fmt.write_str("This"),
             &ExprData::Id(ref id)                          => fmt.debug_tuple("Id").field(id).finish(),
             &ExprData::Arr(ref elts)                       => fmt.debug_tuple("Arr").field(elts).finish(),
             &ExprData::Obj(ref props)                      => fmt.debug_tuple("Obj").field(props).finish(),
             &ExprData::Fun(ref fun)                        => fmt.debug_tuple("Fun").field(fun).finish(),
+            &ExprData::Class(ref class)                    => fmt.debug_tuple("Class").field(class).finish(),
             &ExprData::Seq(ref exprs)                      => fmt.debug_tuple("Seq").field(exprs).finish(),
             &ExprData::Unop(ref op, ref arg)               => fmt.debug_tuple("Unop").field(op).field(arg).finish(),
             &ExprData::Binop(ref op, ref left, ref right)  => fmt.debug_tuple("Binop").field(op).field(left).field(right).finish(),
@@ -110,46 +245,67 @@ impl Debug for ExprData {
             &ExprData::Dot(ref expr, ref key)              => fmt.debug_tuple("Dot").field(expr).field(key).finish(),
             &ExprData::Brack(ref expr, ref prop)           => fmt.debug_tuple("Brack").field(expr).field(prop).finish(),
             &ExprData::NewTarget                           => fmt.write_str("NewTarget"),
+            &ExprData::ImportMeta                          => fmt.write_str("ImportMeta"),
+            &ExprData::Import(ref src)                     => fmt.debug_tuple("Import").field(src).finish(),
             &ExprData::True                                => fmt.write_str("True"),
             &ExprData::False                               => fmt.write_str("False"),
             &ExprData::Null                                => fmt.write_str("Null"),
             &ExprData::Number(ref lit)                     => fmt.debug_tuple("Number").field(lit).finish(),
             &ExprData::RegExp(ref lit)                     => fmt.debug_tuple("RegExp").field(lit).finish(),
-            &ExprData::String(ref lit)                     => fmt.debug_tuple("String").field(lit).finish()
-        }
+            &ExprData::String(ref lit)                     => fmt.debug_tuple("String").field(lit).finish(),
+            &ExprData::JSXElement(ref el)                  => fmt.debug_tuple("JSXElement").field(el).finish(),
+            &ExprData::JSXFragment(ref f)                  => fmt.debug_tuple("JSXFragment").field(f).finish(),
+            &ExprData::As(ref expr, ref ty)                => fmt.debug_tuple("As").field(expr).field(ty).finish(),
+            &ExprData::Satisfies(ref expr, ref ty)         => fmt.debug_tuple("Satisfies").field(expr).field(ty).finish(),
+            &ExprData::Instantiation(ref expr, ref args)   => fmt.debug_tuple("Instantiation").field(expr).field(args).finish(),
+            &ExprData::NonNull(ref expr)                   => fmt.debug_tuple("NonNull").field(expr).finish(),
+            &ExprData::Paren(ref expr)                     => fmt.debug_tuple("Paren").field(expr).finish(),
+            &ExprData::Invalid                             => fmt.write_str("Invalid")
+        })
     }
 }
 
 impl Untrack for ExprData {
-    fn untrack(&mut self) {
-        match *self {
+    fn relocate(&mut self, r: &mut Relocation) {
+        grow(|| match *self {
             ExprData::This                                           => { }
-            ExprData::Id(ref mut id)                                 => { id.untrack(); }
-            ExprData::Arr(ref mut exprs)                             => { exprs.untrack(); }
-            ExprData::Obj(ref mut props)                             => { props.untrack(); }
-            ExprData::Fun(ref mut fun)                               => { fun.untrack(); }
-            ExprData::Seq(ref mut exprs)                             => { exprs.untrack(); }
-            ExprData::Unop(ref mut op, ref mut expr)                 => { op.untrack(); expr.untrack(); }
-            ExprData::Binop(ref mut op, ref mut left, ref mut right) => { op.untrack(); left.untrack(); right.untrack(); }
-            ExprData::Logop(ref mut op, ref mut left, ref mut right) => { op.untrack(); left.untrack(); right.untrack(); }
-            ExprData::PreInc(ref mut expr)                           => { expr.untrack(); }
-            ExprData::PostInc(ref mut expr)                          => { expr.untrack(); }
-            ExprData::PreDec(ref mut expr)                           => { expr.untrack(); }
-            ExprData::PostDec(ref mut expr)                          => { expr.untrack(); }
-            ExprData::Assign(ref mut op, ref mut patt, ref mut expr) => { op.untrack(); patt.untrack(); expr.untrack(); }
-            ExprData::Cond(ref mut test, ref mut cons, ref mut alt)  => { test.untrack(); cons.untrack(); alt.untrack(); }
-            ExprData::Call(ref mut callee, ref mut args)             => { callee.untrack(); args.untrack(); }
-            ExprData::New(ref mut ctor, ref mut args)                => { ctor.untrack(); args.untrack(); }
-            ExprData::Dot(ref mut obj, ref mut key)                  => { obj.untrack(); key.untrack(); }
-            ExprData::Brack(ref mut obj, ref mut prop)               => { obj.untrack(); prop.untrack(); }
+            ExprData::Id(ref mut id)                                 => { id.relocate(r); }
+            ExprData::Arr(ref mut exprs)                             => { exprs.relocate(r); }
+            ExprData::Obj(ref mut props)                             => { props.relocate(r); }
+            ExprData::Fun(ref mut fun)                               => { fun.relocate(r); }
+            ExprData::Class(ref mut class)                           => { class.relocate(r); }
+            ExprData::Seq(ref mut exprs)                             => { exprs.relocate(r); }
+            ExprData::Unop(ref mut op, ref mut expr)                 => { op.relocate(r); expr.relocate(r); }
+            ExprData::Binop(ref mut op, ref mut left, ref mut right) => { op.relocate(r); left.relocate(r); right.relocate(r); }
+            ExprData::Logop(ref mut op, ref mut left, ref mut right) => { op.relocate(r); left.relocate(r); right.relocate(r); }
+            ExprData::PreInc(ref mut expr)                           => { expr.relocate(r); }
+            ExprData::PostInc(ref mut expr)                          => { expr.relocate(r); }
+            ExprData::PreDec(ref mut expr)                           => { expr.relocate(r); }
+            ExprData::PostDec(ref mut expr)                          => { expr.relocate(r); }
+            ExprData::Assign(ref mut op, ref mut patt, ref mut expr) => { op.relocate(r); patt.relocate(r); expr.relocate(r); }
+            ExprData::Cond(ref mut test, ref mut cons, ref mut alt)  => { test.relocate(r); cons.relocate(r); alt.relocate(r); }
+            ExprData::Call(ref mut callee, ref mut args)             => { callee.relocate(r); args.relocate(r); }
+            ExprData::New(ref mut ctor, ref mut args)                => { ctor.relocate(r); args.relocate(r); }
+            ExprData::Dot(ref mut obj, ref mut key)                  => { obj.relocate(r); key.relocate(r); }
+            ExprData::Brack(ref mut obj, ref mut prop)               => { obj.relocate(r); prop.relocate(r); }
             ExprData::NewTarget                                      => { }
+            ExprData::ImportMeta                                     => { }
+            ExprData::Import(ref mut src)                            => { src.relocate(r); }
             ExprData::True                                           => { }
             ExprData::False                                          => { }
             ExprData::Null                                           => { }
             ExprData::Number(_)                                      => { }
             ExprData::RegExp(_)                                      => { }
             ExprData::String(_)                                      => { }
-        }
+            ExprData::JSXElement(ref mut el)                         => { el.relocate(r); }
+            ExprData::JSXFragment(ref mut f)                         => { f.relocate(r); }
+            ExprData::As(ref mut expr, ref mut ty)                   => { expr.relocate(r); ty.relocate(r); }
+            ExprData::Satisfies(ref mut expr, ref mut ty)            => { expr.relocate(r); ty.relocate(r); }
+            ExprData::Instantiation(ref mut expr, ref mut args)      => { expr.relocate(r); args.relocate(r); }
+            ExprData::NonNull(ref mut expr)                          => { expr.relocate(r); }
+            ExprData::Paren(ref mut expr)                            => { expr.relocate(r); }
+            ExprData::Invalid                                        => { }
+        })
     }
 }
 
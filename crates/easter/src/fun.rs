@@ -1,10 +1,12 @@
 use joker::track::*;
+use joker::context::Mode;
 
 use id::Id;
 use patt::Patt;
 use stmt::StmtListItem;
+use ty::{Ty, TyParam};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct ParamsData {
     pub list: Vec<Patt<Id>>
 }
@@ -12,23 +14,65 @@ pub struct ParamsData {
 pub type Params = Tracked<ParamsData>;
 
 impl Untrack for ParamsData {
-    fn untrack(&mut self) {
-        self.list.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.list.relocate(r);
     }
 }
 
-#[derive(Debug, PartialEq)]
+// The kind of function a body belongs to, which decides whether `yield` and
+// `await` are reserved within it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FunctionKind {
+    Normal,
+    Generator,
+    Async,
+    AsyncGenerator
+}
+
+impl FunctionKind {
+    pub fn is_generator(self) -> bool {
+        self == FunctionKind::Generator || self == FunctionKind::AsyncGenerator
+    }
+
+    pub fn is_async(self) -> bool {
+        self == FunctionKind::Async || self == FunctionKind::AsyncGenerator
+    }
+}
+
+// A function body that a lazy parse left out of the tree: the source between
+// its braces and the mode and kind of function it is to be parsed as. The
+// function's `body` is left empty until it is reparsed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LazyBody {
+    pub span: Span,
+    pub mode: Mode,
+    pub kind: FunctionKind
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct FunData {
     pub id: Option<Id>,
+    pub ty_params: Vec<TyParam>,
     pub params: Params,
-    pub body: Vec<StmtListItem>
+    pub ret_ty: Option<Ty>,
+    pub body: Vec<StmtListItem>,
+    pub lazy: Option<LazyBody>
 }
 
 impl Untrack for FunData {
-    fn untrack(&mut self) {
-        self.id.untrack();
-        self.params.untrack();
-        self.body.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.id.relocate(r);
+        self.ty_params.relocate(r);
+        self.params.relocate(r);
+        self.ret_ty.relocate(r);
+        self.body.relocate(r);
+        // The span of a skipped body is needed to reparse it, so it is kept
+        // when locations are dropped.
+        if let Some(ref mut lazy) = self.lazy {
+            if let Some(span) = r.apply(Some(lazy.span)) {
+                lazy.span = span;
+            }
+        }
     }
 }
 
@@ -4,14 +4,25 @@ use joker::track::*;
 use expr::{Expr, ExprData};
 use decl::{Dtor, DtorData};
 use patt::Patt;
+use ty::Ty;
+use class::Access;
 
-#[derive(Debug, Eq, PartialEq)]
+// Bindings may carry a TypeScript type annotation and, for parameters,
+// an optional marker (`x?: T`). A constructor parameter with an access or
+// `readonly` modifier (`private x: T`) also declares a property.
+#[derive(Debug, PartialEq, Clone)]
 pub struct IdData {
-    pub name: Name
+    pub name: Name,
+    pub ty: Option<Box<Ty>>,
+    pub optional: bool,
+    pub access: Option<Access>,
+    pub readonly: bool
 }
 
 impl Untrack for IdData {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.ty.relocate(r);
+    }
 }
 
 pub type Id = Tracked<IdData>;
@@ -26,7 +37,7 @@ pub trait IdExt {
 impl IdExt for Id {
     fn new(name: Name, location: Option<Span>) -> Id {
         Id {
-            value: IdData { name: name },
+            value: IdData { name: name, ty: None, optional: false, access: None, readonly: false },
             location: location
         }
     }
@@ -0,0 +1,160 @@
+use std::mem::replace;
+use joker::track::*;
+use joker::token::StringLiteral;
+
+use expr::Expr;
+use stack::grow;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct JSXIdData(pub String);
+
+impl Untrack for JSXIdData {
+    fn relocate(&mut self, _: &mut Relocation) { }
+}
+
+pub type JSXId = Tracked<JSXIdData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JSXNameData {
+    Id(JSXId),
+    Namespaced(JSXId, JSXId),
+    Member(Box<JSXName>, JSXId)
+}
+
+// Dropping a member name takes its object out first, so that long names such
+// as `a.b.c` don't recurse.
+impl Drop for JSXNameData {
+    fn drop(&mut self) {
+        let mut next = self.take_obj();
+        while let Some(mut name) = next {
+            next = name.value.take_obj();
+        }
+    }
+}
+
+impl JSXNameData {
+    fn take_obj(&mut self) -> Option<JSXName> {
+        match *self {
+            JSXNameData::Member(ref mut obj, _) => {
+                let placeholder = JSXNameData::Id(JSXIdData(String::new()).tracked(None)).tracked(None);
+                Some(replace(&mut **obj, placeholder))
+            }
+            _ => None
+        }
+    }
+}
+
+impl Untrack for JSXNameData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        grow(|| match *self {
+            JSXNameData::Id(ref mut id)                     => { id.relocate(r); }
+            JSXNameData::Namespaced(ref mut ns, ref mut id) => { ns.relocate(r); id.relocate(r); }
+            JSXNameData::Member(ref mut obj, ref mut id)    => { obj.relocate(r); id.relocate(r); }
+        })
+    }
+}
+
+impl JSXNameData {
+    // The name as written, e.g. `svg:rect` or `Foo.Bar.Baz`.
+    pub fn qualified_name(&self) -> String {
+        match *self {
+            JSXNameData::Id(ref id)                 => id.value.0.clone(),
+            JSXNameData::Namespaced(ref ns, ref id) => format!("{}:{}", ns.value.0, id.value.0),
+            JSXNameData::Member(ref obj, ref id)    => format!("{}.{}", obj.value.qualified_name(), id.value.0)
+        }
+    }
+}
+
+pub type JSXName = Tracked<JSXNameData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JSXAttrData {
+    Attr(JSXName, Option<JSXAttrVal>),
+    Spread(Expr)
+}
+
+impl Untrack for JSXAttrData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        match *self {
+            JSXAttrData::Attr(ref mut name, ref mut val) => { name.relocate(r); val.relocate(r); }
+            JSXAttrData::Spread(ref mut expr)            => { expr.relocate(r); }
+        }
+    }
+}
+
+pub type JSXAttr = Tracked<JSXAttrData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JSXAttrValData {
+    String(StringLiteral),
+    Expr(Expr),
+    Element(JSXElement),
+    Fragment(JSXFragment)
+}
+
+impl Untrack for JSXAttrValData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        match *self {
+            JSXAttrValData::String(_)           => { }
+            JSXAttrValData::Expr(ref mut expr)  => { expr.relocate(r); }
+            JSXAttrValData::Element(ref mut el) => { el.relocate(r); }
+            JSXAttrValData::Fragment(ref mut f) => { f.relocate(r); }
+        }
+    }
+}
+
+pub type JSXAttrVal = Tracked<JSXAttrValData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JSXChildData {
+    Text(StringLiteral),
+    Expr(Option<Expr>),
+    Spread(Expr),
+    Element(JSXElement),
+    Fragment(JSXFragment)
+}
+
+impl Untrack for JSXChildData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        match *self {
+            JSXChildData::Text(_)              => { }
+            JSXChildData::Expr(ref mut expr)   => { expr.relocate(r); }
+            JSXChildData::Spread(ref mut expr) => { expr.relocate(r); }
+            JSXChildData::Element(ref mut el)  => { el.relocate(r); }
+            JSXChildData::Fragment(ref mut f)  => { f.relocate(r); }
+        }
+    }
+}
+
+pub type JSXChild = Tracked<JSXChildData>;
+
+// A self-closing element has no children.
+#[derive(Debug, PartialEq, Clone)]
+pub struct JSXElementData {
+    pub name: JSXName,
+    pub attrs: Vec<JSXAttr>,
+    pub children: Option<Vec<JSXChild>>
+}
+
+impl Untrack for JSXElementData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.name.relocate(r);
+        self.attrs.relocate(r);
+        self.children.relocate(r);
+    }
+}
+
+pub type JSXElement = Tracked<JSXElementData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct JSXFragmentData {
+    pub children: Vec<JSXChild>
+}
+
+impl Untrack for JSXFragmentData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.children.relocate(r);
+    }
+}
+
+pub type JSXFragment = Tracked<JSXFragmentData>;
@@ -1,4 +1,5 @@
 extern crate joker;
+extern crate stacker;
 
 pub mod id;
 pub mod fun;
@@ -10,3 +11,7 @@ pub mod patt;
 pub mod prog;
 pub mod punc;
 pub mod cover;
+pub mod jsx;
+pub mod ty;
+pub mod class;
+pub mod stack;
@@ -1,4 +1,5 @@
 use joker::track::*;
+use joker::word::Name;
 use joker::token::{StringLiteral, NumberLiteral};
 
 use id::Id;
@@ -6,44 +7,44 @@ use expr::Expr;
 use stmt::StmtListItem;
 use patt::Patt;
 
-#[derive(Debug, PartialEq)]
-pub struct DotKeyData(pub String);
+#[derive(Debug, PartialEq, Clone)]
+pub struct DotKeyData(pub Name);
 
 pub type DotKey = Tracked<DotKeyData>;
 
 impl Untrack for DotKeyData {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, _: &mut Relocation) { }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct PropData {
     pub key: PropKey,
     pub val: PropVal
 }
 
 impl Untrack for PropData {
-    fn untrack(&mut self) {
-        self.key.untrack();
-        self.val.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.key.relocate(r);
+        self.val.relocate(r);
     }
 }
 
 pub type Prop = Tracked<PropData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum PropKeyData {
-    Id(String),
+    Id(Name),
     String(StringLiteral),
     Number(NumberLiteral)
 }
 
 impl Untrack for PropKeyData {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, _: &mut Relocation) { }
 }
 
 pub type PropKey = Tracked<PropKeyData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum PropValData {
     Init(Expr),
     Get(Vec<StmtListItem>),
@@ -51,11 +52,11 @@ pub enum PropValData {
 }
 
 impl Untrack for PropValData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            PropValData::Init(ref mut expr)               => { expr.untrack(); }
-            PropValData::Get(ref mut stmts)               => { stmts.untrack(); }
-            PropValData::Set(ref mut patt, ref mut stmts) => { patt.untrack(); stmts.untrack(); }
+            PropValData::Init(ref mut expr)               => { expr.relocate(r); }
+            PropValData::Get(ref mut stmts)               => { stmts.relocate(r); }
+            PropValData::Set(ref mut patt, ref mut stmts) => { patt.relocate(r); stmts.relocate(r); }
         }
     }
 }
@@ -4,39 +4,39 @@ use id::Id;
 use expr::Expr;
 use obj::{PropKey, DotKey};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum CompoundPattData<T> {
     Arr(Vec<Option<Patt<T>>>),
     Obj(Vec<PropPatt<T>>)
 }
 
 impl<T: Untrack> Untrack for CompoundPattData<T> {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            CompoundPattData::Arr(ref mut patts) => { patts.untrack(); }
-            CompoundPattData::Obj(ref mut props) => { props.untrack(); }
+            CompoundPattData::Arr(ref mut patts) => { patts.relocate(r); }
+            CompoundPattData::Obj(ref mut props) => { props.relocate(r); }
         }
     }
 }
 
 pub type CompoundPatt<T> = Tracked<CompoundPattData<T>>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct PropPattData<T> {
     pub key: PropKey,
     pub patt: Patt<T>
 }
 
 impl<T: Untrack> Untrack for PropPattData<T> {
-    fn untrack(&mut self) {
-        self.key.untrack();
-        self.patt.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.key.relocate(r);
+        self.patt.relocate(r);
     }
 }
 
 pub type PropPatt<T> = Tracked<PropPattData<T>>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Patt<T> {
     Simple(T),
     Compound(CompoundPatt<T>)
@@ -61,15 +61,15 @@ impl<T: Track> Track for Patt<T> {
 }
 
 impl<T: Untrack> Untrack for Patt<T> {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            Patt::Simple(ref mut simple) => { simple.untrack(); }
-            Patt::Compound(ref mut patt) => { patt.untrack(); }
+            Patt::Simple(ref mut simple) => { simple.relocate(r); }
+            Patt::Compound(ref mut patt) => { patt.relocate(r); }
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum AssignTargetData {
     Id(Id),
     Dot(Box<Expr>, DotKey),
@@ -77,11 +77,11 @@ pub enum AssignTargetData {
 }
 
 impl Untrack for AssignTargetData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            AssignTargetData::Id(ref mut id)                   => { id.untrack(); }
-            AssignTargetData::Dot(ref mut obj, ref mut prop)   => { obj.untrack(); prop.untrack(); }
-            AssignTargetData::Brack(ref mut obj, ref mut prop) => { obj.untrack(); prop.untrack(); }
+            AssignTargetData::Id(ref mut id)                   => { id.relocate(r); }
+            AssignTargetData::Dot(ref mut obj, ref mut prop)   => { obj.relocate(r); prop.relocate(r); }
+            AssignTargetData::Brack(ref mut obj, ref mut prop) => { obj.relocate(r); prop.relocate(r); }
         }
     }
 }
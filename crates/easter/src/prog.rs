@@ -2,24 +2,46 @@ use joker::track::*;
 
 use stmt::StmtListItem;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct ScriptData {
     pub body: Vec<StmtListItem>
 }
 
 impl Untrack for ScriptData {
-    fn untrack(&mut self) {
-        self.body.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.body.relocate(r);
     }
 }
 
 pub type Script = Tracked<ScriptData>;
 
-/*
-#[derive(Debug, PartialEq)]
+// ES6: import and export declarations
+#[derive(Debug, PartialEq, Clone)]
 pub struct ModuleData {
-    pub body: Vec<ModItem>
+    pub body: Vec<StmtListItem>
+}
+
+impl Untrack for ModuleData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.body.relocate(r);
+    }
 }
 
 pub type Module = Tracked<ModuleData>;
-*/
+
+// A script or a module, for APIs that parse either according to their
+// options.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Program {
+    Script(Script),
+    Module(Module)
+}
+
+impl Untrack for Program {
+    fn relocate(&mut self, r: &mut Relocation) {
+        match *self {
+            Program::Script(ref mut script) => { script.relocate(r); }
+            Program::Module(ref mut module) => { module.relocate(r); }
+        }
+    }
+}
@@ -12,12 +12,18 @@ pub enum Semi {
 }
 
 impl Untrack for Semi {
-    fn untrack(&mut self) {
-        *self = Semi::Explicit(None);
+    fn relocate(&mut self, r: &mut Relocation) {
+        *self = match *self {
+            Semi::Explicit(Some(posn)) => {
+                Semi::Explicit(r.apply(Some(Span { start: posn, end: posn })).map(|span| span.start))
+            }
+            Semi::Inserted if !r.drops() => Semi::Inserted,
+            _ => Semi::Explicit(None)
+        };
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum UnopTag {
     Minus,
     Plus,
@@ -48,7 +54,7 @@ impl FromStr for UnopTag {
 pub type Unop = Tracked<UnopTag>;
 
 impl Untrack for UnopTag {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, _: &mut Relocation) { }
 }
 
 pub trait Precedence {
@@ -61,7 +67,7 @@ impl<T: Precedence> Precedence for Tracked<T> {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum BinopTag {
     Eq,
     NEq,
@@ -176,10 +182,10 @@ impl Display for BinopTag {
 }
 
 impl Untrack for BinopTag {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, _: &mut Relocation) { }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum LogopTag {
     Or,
     And
@@ -218,10 +224,10 @@ impl Display for LogopTag {
 }
 
 impl Untrack for LogopTag {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, _: &mut Relocation) { }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum AssopTag {
     Eq,
     PlusEq,
@@ -285,7 +291,7 @@ impl Display for AssopTag {
 }
 
 impl Untrack for AssopTag {
-    fn untrack(&mut self) { }
+    fn relocate(&mut self, _: &mut Relocation) { }
 }
 
 pub trait ToOp {
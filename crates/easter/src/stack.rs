@@ -0,0 +1,16 @@
+use stacker;
+
+// Chains such as `a + b + c`, `x.f().g()` or "else if" are read in a loop,
+// so no parse limit bounds how deep a tree they build. Code that recurses
+// into trees runs on a stack grown on demand instead. A level may take over
+// 100 KiB of stack in a debug build, so the stack is grown in large steps
+// well before it runs out.
+const RED_ZONE: usize = 256 * 1024;
+const GROWTH: usize = 2 * 1024 * 1024;
+
+// Runs `f`, on a new stack segment if this one is running low.
+pub fn grow<F, T>(f: F) -> T
+  where F: FnOnce() -> T
+{
+    stacker::maybe_grow(RED_ZONE, GROWTH, f)
+}
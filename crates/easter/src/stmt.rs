@@ -1,3 +1,6 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::mem::replace;
 use joker::track::*;
 
 use id::Id;
@@ -5,8 +8,9 @@ use expr::Expr;
 use decl::{Decl, Dtor};
 use patt::Patt;
 use punc::Semi;
+use fun::Fun;
+use stack::grow;
 
-#[derive(Debug, PartialEq)]
 pub enum StmtData {
     Empty,
     Block(Vec<StmtListItem>),
@@ -26,38 +30,184 @@ pub enum StmtData {
     For(Option<Box<ForHead>>, Option<Expr>, Option<Expr>, Box<Stmt>),
     ForIn(Box<ForInHead>, Expr, Box<Stmt>),
     ForOf(Box<ForOfHead>, Expr, Box<Stmt>),
-    Debugger(Semi)
+    Debugger(Semi),
+    Fun(Fun),
+    // The skipped text of a statement that failed to parse, produced only by
+    // the error-recovering parser.
+    Invalid
 }
 
-impl Untrack for StmtData {
-    fn untrack(&mut self) {
+impl Clone for StmtData {
+    fn clone(&self) -> StmtData {
+        grow(|| match *self {
+            StmtData::Empty                                       => StmtData::Empty,
+            StmtData::Block(ref items)                            => StmtData::Block(items.clone()),
+            StmtData::Var(ref dtors, ref semi)                    => StmtData::Var(dtors.clone(), semi.clone()),
+            StmtData::Expr(ref expr, ref semi)                    => StmtData::Expr(expr.clone(), semi.clone()),
+            StmtData::If(ref test, ref cons, ref alt)             => StmtData::If(test.clone(), cons.clone(), alt.clone()),
+            StmtData::Label(ref lab, ref stmt)                    => StmtData::Label(lab.clone(), stmt.clone()),
+            StmtData::Break(ref lab, ref semi)                    => StmtData::Break(lab.clone(), semi.clone()),
+            StmtData::Cont(ref lab, ref semi)                     => StmtData::Cont(lab.clone(), semi.clone()),
+            StmtData::With(ref expr, ref stmt)                    => StmtData::With(expr.clone(), stmt.clone()),
+            StmtData::Switch(ref expr, ref cases)                 => StmtData::Switch(expr.clone(), cases.clone()),
+            StmtData::Return(ref expr, ref semi)                  => StmtData::Return(expr.clone(), semi.clone()),
+            StmtData::Throw(ref expr, ref semi)                   => StmtData::Throw(expr.clone(), semi.clone()),
+            StmtData::Try(ref body, ref catch, ref finally)       => StmtData::Try(body.clone(), catch.clone(), finally.clone()),
+            StmtData::While(ref expr, ref stmt)                   => StmtData::While(expr.clone(), stmt.clone()),
+            StmtData::DoWhile(ref stmt, ref expr, ref semi)       => StmtData::DoWhile(stmt.clone(), expr.clone(), semi.clone()),
+            StmtData::For(ref init, ref test, ref incr, ref body) => StmtData::For(init.clone(), test.clone(), incr.clone(), body.clone()),
+            StmtData::ForIn(ref lhs, ref rhs, ref body)           => StmtData::ForIn(lhs.clone(), rhs.clone(), body.clone()),
+            StmtData::ForOf(ref lhs, ref rhs, ref body)           => StmtData::ForOf(lhs.clone(), rhs.clone(), body.clone()),
+            StmtData::Debugger(ref semi)                          => StmtData::Debugger(semi.clone()),
+            StmtData::Fun(ref fun)                                => StmtData::Fun(fun.clone()),
+            StmtData::Invalid                                     => StmtData::Invalid
+        })
+    }
+}
+
+// Dropping a statement moves its nested statements onto a list and drops
+// them one by one, so that long `else if` chains don't recurse.
+impl Drop for StmtData {
+    fn drop(&mut self) {
+        let mut stmts = Vec::new();
+        self.take_stmts(&mut stmts);
+        while let Some(mut stmt) = stmts.pop() {
+            stmt.value.take_stmts(&mut stmts);
+        }
+    }
+}
+
+impl StmtData {
+    fn take_stmts(&mut self, stmts: &mut Vec<Stmt>) {
         match *self {
-            StmtData::Empty                                                       => { }
-            StmtData::Block(ref mut items)                                        => { items.untrack(); }
-            StmtData::Var(ref mut dtors, ref mut semi)                            => { dtors.untrack(); semi.untrack(); }
-            StmtData::Expr(ref mut expr, ref mut semi)                            => { expr.untrack(); semi.untrack(); }
-            StmtData::If(ref mut test, ref mut cons, ref mut alt)                 => { test.untrack(); cons.untrack(); alt.untrack(); }
-            StmtData::Label(ref mut lab, ref mut stmt)                            => { lab.untrack(); stmt.untrack(); }
-            StmtData::Break(ref mut lab, ref mut semi)                            => { lab.untrack(); semi.untrack(); }
-            StmtData::Cont(ref mut lab, ref mut semi)                             => { lab.untrack(); semi.untrack(); }
-            StmtData::With(ref mut expr, ref mut stmt)                            => { expr.untrack(); stmt.untrack(); }
-            StmtData::Switch(ref mut expr, ref mut cases)                         => { expr.untrack(); cases.untrack(); }
-            StmtData::Return(ref mut expr, ref mut semi)                          => { expr.untrack(); semi.untrack(); }
-            StmtData::Throw(ref mut expr, ref mut semi)                           => { expr.untrack(); semi.untrack(); }
-            StmtData::Try(ref mut body, ref mut catch, ref mut finally)           => { body.untrack(); catch.untrack(); finally.untrack(); }
-            StmtData::While(ref mut expr, ref mut stmt)                           => { expr.untrack(); stmt.untrack(); }
-            StmtData::DoWhile(ref mut stmt, ref mut expr, ref mut semi)           => { stmt.untrack(); expr.untrack(); semi.untrack(); }
-            StmtData::For(ref mut init, ref mut test, ref mut incr, ref mut body) => { init.untrack(); test.untrack(); incr.untrack(); body.untrack(); }
-            StmtData::ForIn(ref mut lhs, ref mut rhs, ref mut body)               => { lhs.untrack(); rhs.untrack(); body.untrack(); }
-            StmtData::ForOf(ref mut lhs, ref mut rhs, ref mut body)               => { lhs.untrack(); rhs.untrack(); body.untrack(); }
-            StmtData::Debugger(ref mut semi)                                      => { semi.untrack(); }
+            StmtData::If(_, ref mut cons, ref mut alt) => {
+                stmts.push(take(cons));
+                if let Some(ref mut alt) = *alt {
+                    stmts.push(take(alt));
+                }
+            }
+            StmtData::Label(_, ref mut body)
+          | StmtData::With(_, ref mut body)
+          | StmtData::While(_, ref mut body)
+          | StmtData::DoWhile(ref mut body, _, _)
+          | StmtData::For(_, _, _, ref mut body)
+          | StmtData::ForIn(_, _, ref mut body)
+          | StmtData::ForOf(_, _, ref mut body) => { stmts.push(take(body)); }
+            _ => { }
         }
     }
 }
 
+// Moves a statement out of its place, leaving an empty statement behind.
+// `StmtData` implements `Drop`, so its fields can't be moved out directly.
+pub fn take(stmt: &mut Stmt) -> Stmt {
+    replace(stmt, StmtData::Empty.tracked(None))
+}
+
+impl PartialEq for StmtData {
+    fn eq(&self, other: &Self) -> bool {
+        grow(|| match (self, other) {
+            (&StmtData::Empty,                    &StmtData::Empty)                => true,
+            (&StmtData::Block(ref items_l),       &StmtData::Block(ref items_r))   => items_l == items_r,
+            (&StmtData::Var(ref dtors_l, ref semi_l),
+             &StmtData::Var(ref dtors_r, ref semi_r))                              => (dtors_l, semi_l) == (dtors_r, semi_r),
+            (&StmtData::Expr(ref expr_l, ref semi_l),
+             &StmtData::Expr(ref expr_r, ref semi_r))                              => (expr_l, semi_l) == (expr_r, semi_r),
+            (&StmtData::If(ref test_l, ref cons_l, ref alt_l),
+             &StmtData::If(ref test_r, ref cons_r, ref alt_r))                     => (test_l, cons_l, alt_l) == (test_r, cons_r, alt_r),
+            (&StmtData::Label(ref lab_l, ref stmt_l),
+             &StmtData::Label(ref lab_r, ref stmt_r))                              => (lab_l, stmt_l) == (lab_r, stmt_r),
+            (&StmtData::Break(ref lab_l, ref semi_l),
+             &StmtData::Break(ref lab_r, ref semi_r))                              => (lab_l, semi_l) == (lab_r, semi_r),
+            (&StmtData::Cont(ref lab_l, ref semi_l),
+             &StmtData::Cont(ref lab_r, ref semi_r))                               => (lab_l, semi_l) == (lab_r, semi_r),
+            (&StmtData::With(ref expr_l, ref stmt_l),
+             &StmtData::With(ref expr_r, ref stmt_r))                              => (expr_l, stmt_l) == (expr_r, stmt_r),
+            (&StmtData::Switch(ref expr_l, ref cases_l),
+             &StmtData::Switch(ref expr_r, ref cases_r))                           => (expr_l, cases_l) == (expr_r, cases_r),
+            (&StmtData::Return(ref expr_l, ref semi_l),
+             &StmtData::Return(ref expr_r, ref semi_r))                            => (expr_l, semi_l) == (expr_r, semi_r),
+            (&StmtData::Throw(ref expr_l, ref semi_l),
+             &StmtData::Throw(ref expr_r, ref semi_r))                             => (expr_l, semi_l) == (expr_r, semi_r),
+            (&StmtData::Try(ref body_l, ref catch_l, ref finally_l),
+             &StmtData::Try(ref body_r, ref catch_r, ref finally_r))               => (body_l, catch_l, finally_l) == (body_r, catch_r, finally_r),
+            (&StmtData::While(ref expr_l, ref stmt_l),
+             &StmtData::While(ref expr_r, ref stmt_r))                             => (expr_l, stmt_l) == (expr_r, stmt_r),
+            (&StmtData::DoWhile(ref stmt_l, ref expr_l, ref semi_l),
+             &StmtData::DoWhile(ref stmt_r, ref expr_r, ref semi_r))               => (stmt_l, expr_l, semi_l) == (stmt_r, expr_r, semi_r),
+            (&StmtData::For(ref init_l, ref test_l, ref incr_l, ref body_l),
+             &StmtData::For(ref init_r, ref test_r, ref incr_r, ref body_r))       => (init_l, test_l, incr_l, body_l) == (init_r, test_r, incr_r, body_r),
+            (&StmtData::ForIn(ref lhs_l, ref rhs_l, ref body_l),
+             &StmtData::ForIn(ref lhs_r, ref rhs_r, ref body_r))                   => (lhs_l, rhs_l, body_l) == (lhs_r, rhs_r, body_r),
+            (&StmtData::ForOf(ref lhs_l, ref rhs_l, ref body_l),
+             &StmtData::ForOf(ref lhs_r, ref rhs_r, ref body_r))                   => (lhs_l, rhs_l, body_l) == (lhs_r, rhs_r, body_r),
+            (&StmtData::Debugger(ref semi_l),     &StmtData::Debugger(ref semi_r)) => semi_l == semi_r,
+            (&StmtData::Fun(ref fun_l),           &StmtData::Fun(ref fun_r))       => fun_l == fun_r,
+            (&StmtData::Invalid,                  &StmtData::Invalid)              => true,
+            _ => false
+        })
+    }
+}
+
+impl Debug for StmtData {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        grow(|| match self {
+            &StmtData::Empty                                       => fmt.write_str("Empty"),
+            &StmtData::Block(ref items)                            => fmt.debug_tuple("Block").field(items).finish(),
+            &StmtData::Var(ref dtors, ref semi)                    => fmt.debug_tuple("Var").field(dtors).field(semi).finish(),
+            &StmtData::Expr(ref expr, ref semi)                    => fmt.debug_tuple("Expr").field(expr).field(semi).finish(),
+            &StmtData::If(ref test, ref cons, ref alt)             => fmt.debug_tuple("If").field(test).field(cons).field(alt).finish(),
+            &StmtData::Label(ref lab, ref stmt)                    => fmt.debug_tuple("Label").field(lab).field(stmt).finish(),
+            &StmtData::Break(ref lab, ref semi)                    => fmt.debug_tuple("Break").field(lab).field(semi).finish(),
+            &StmtData::Cont(ref lab, ref semi)                     => fmt.debug_tuple("Cont").field(lab).field(semi).finish(),
+            &StmtData::With(ref expr, ref stmt)                    => fmt.debug_tuple("With").field(expr).field(stmt).finish(),
+            &StmtData::Switch(ref expr, ref cases)                 => fmt.debug_tuple("Switch").field(expr).field(cases).finish(),
+            &StmtData::Return(ref expr, ref semi)                  => fmt.debug_tuple("Return").field(expr).field(semi).finish(),
+            &StmtData::Throw(ref expr, ref semi)                   => fmt.debug_tuple("Throw").field(expr).field(semi).finish(),
+            &StmtData::Try(ref body, ref catch, ref finally)       => fmt.debug_tuple("Try").field(body).field(catch).field(finally).finish(),
+            &StmtData::While(ref expr, ref stmt)                   => fmt.debug_tuple("While").field(expr).field(stmt).finish(),
+            &StmtData::DoWhile(ref stmt, ref expr, ref semi)       => fmt.debug_tuple("DoWhile").field(stmt).field(expr).field(semi).finish(),
+            &StmtData::For(ref init, ref test, ref incr, ref body) => fmt.debug_tuple("For").field(init).field(test).field(incr).field(body).finish(),
+            &StmtData::ForIn(ref lhs, ref rhs, ref body)           => fmt.debug_tuple("ForIn").field(lhs).field(rhs).field(body).finish(),
+            &StmtData::ForOf(ref lhs, ref rhs, ref body)           => fmt.debug_tuple("ForOf").field(lhs).field(rhs).field(body).finish(),
+            &StmtData::Debugger(ref semi)                          => fmt.debug_tuple("Debugger").field(semi).finish(),
+            &StmtData::Fun(ref fun)                                => fmt.debug_tuple("Fun").field(fun).finish(),
+            &StmtData::Invalid                                     => fmt.write_str("Invalid")
+        })
+    }
+}
+
+impl Untrack for StmtData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        grow(|| match *self {
+            StmtData::Empty                                                       => { }
+            StmtData::Block(ref mut items)                                        => { items.relocate(r); }
+            StmtData::Var(ref mut dtors, ref mut semi)                            => { dtors.relocate(r); semi.relocate(r); }
+            StmtData::Expr(ref mut expr, ref mut semi)                            => { expr.relocate(r); semi.relocate(r); }
+            StmtData::If(ref mut test, ref mut cons, ref mut alt)                 => { test.relocate(r); cons.relocate(r); alt.relocate(r); }
+            StmtData::Label(ref mut lab, ref mut stmt)                            => { lab.relocate(r); stmt.relocate(r); }
+            StmtData::Break(ref mut lab, ref mut semi)                            => { lab.relocate(r); semi.relocate(r); }
+            StmtData::Cont(ref mut lab, ref mut semi)                             => { lab.relocate(r); semi.relocate(r); }
+            StmtData::With(ref mut expr, ref mut stmt)                            => { expr.relocate(r); stmt.relocate(r); }
+            StmtData::Switch(ref mut expr, ref mut cases)                         => { expr.relocate(r); cases.relocate(r); }
+            StmtData::Return(ref mut expr, ref mut semi)                          => { expr.relocate(r); semi.relocate(r); }
+            StmtData::Throw(ref mut expr, ref mut semi)                           => { expr.relocate(r); semi.relocate(r); }
+            StmtData::Try(ref mut body, ref mut catch, ref mut finally)           => { body.relocate(r); catch.relocate(r); finally.relocate(r); }
+            StmtData::While(ref mut expr, ref mut stmt)                           => { expr.relocate(r); stmt.relocate(r); }
+            StmtData::DoWhile(ref mut stmt, ref mut expr, ref mut semi)           => { stmt.relocate(r); expr.relocate(r); semi.relocate(r); }
+            StmtData::For(ref mut init, ref mut test, ref mut incr, ref mut body) => { init.relocate(r); test.relocate(r); incr.relocate(r); body.relocate(r); }
+            StmtData::ForIn(ref mut lhs, ref mut rhs, ref mut body)               => { lhs.relocate(r); rhs.relocate(r); body.relocate(r); }
+            StmtData::ForOf(ref mut lhs, ref mut rhs, ref mut body)               => { lhs.relocate(r); rhs.relocate(r); body.relocate(r); }
+            StmtData::Debugger(ref mut semi)                                      => { semi.relocate(r); }
+            StmtData::Fun(ref mut fun)                                            => { fun.relocate(r); }
+            StmtData::Invalid                                                     => { }
+        })
+    }
+}
+
 pub type Stmt = Tracked<StmtData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ForHeadData {
     Var(Vec<Dtor>),
     Let(Vec<Dtor>),
@@ -65,18 +215,18 @@ pub enum ForHeadData {
 }
 
 impl Untrack for ForHeadData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            ForHeadData::Var(ref mut vec)   => { vec.untrack(); }
-            ForHeadData::Let(ref mut vec)   => { vec.untrack(); }
-            ForHeadData::Expr(ref mut expr) => { expr.untrack(); }
+            ForHeadData::Var(ref mut vec)   => { vec.relocate(r); }
+            ForHeadData::Let(ref mut vec)   => { vec.relocate(r); }
+            ForHeadData::Expr(ref mut expr) => { expr.relocate(r); }
         }
     }
 }
 
 pub type ForHead = Tracked<ForHeadData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ForInHeadData {
     VarInit(Id, Expr),
     Var(Patt<Id>),
@@ -85,19 +235,19 @@ pub enum ForInHeadData {
 }
 
 impl Untrack for ForInHeadData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            ForInHeadData::VarInit(ref mut id, ref mut expr) => { id.untrack(); expr.untrack(); }
-            ForInHeadData::Var(ref mut patt)                 => { patt.untrack(); }
-            ForInHeadData::Let(ref mut patt)                 => { patt.untrack(); }
-            ForInHeadData::Expr(ref mut expr)                => { expr.untrack(); }
+            ForInHeadData::VarInit(ref mut id, ref mut expr) => { id.relocate(r); expr.relocate(r); }
+            ForInHeadData::Var(ref mut patt)                 => { patt.relocate(r); }
+            ForInHeadData::Let(ref mut patt)                 => { patt.relocate(r); }
+            ForInHeadData::Expr(ref mut expr)                => { expr.relocate(r); }
         }
     }
 }
 
 pub type ForInHead = Tracked<ForInHeadData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ForOfHeadData {
     Var(Patt<Id>),
     Let(Patt<Id>),
@@ -105,58 +255,58 @@ pub enum ForOfHeadData {
 }
 
 impl Untrack for ForOfHeadData {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            ForOfHeadData::Var(ref mut patt)  => { patt.untrack(); }
-            ForOfHeadData::Let(ref mut patt)  => { patt.untrack(); }
-            ForOfHeadData::Expr(ref mut expr) => { expr.untrack(); }
+            ForOfHeadData::Var(ref mut patt)  => { patt.relocate(r); }
+            ForOfHeadData::Let(ref mut patt)  => { patt.relocate(r); }
+            ForOfHeadData::Expr(ref mut expr) => { expr.relocate(r); }
         }
     }
 }
 
 pub type ForOfHead = Tracked<ForOfHeadData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct CatchData {
     pub param: Patt<Id>,
     pub body: Vec<StmtListItem>
 }
 
 impl Untrack for CatchData {
-    fn untrack(&mut self) {
-        self.param.untrack();
-        self.body.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.param.relocate(r);
+        self.body.relocate(r);
     }
 }
 
 pub type Catch = Tracked<CatchData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct CaseData {
     pub test: Option<Expr>,
     pub body: Vec<StmtListItem>
 }
 
 impl Untrack for CaseData {
-    fn untrack(&mut self) {
-        self.test.untrack();
-        self.body.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.test.relocate(r);
+        self.body.relocate(r);
     }
 }
 
 pub type Case = Tracked<CaseData>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum StmtListItem {
     Decl(Decl),
     Stmt(Stmt)
 }
 
 impl Untrack for StmtListItem {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            StmtListItem::Decl(ref mut decl) => { decl.untrack(); }
-            StmtListItem::Stmt(ref mut stmt) => { stmt.untrack(); }
+            StmtListItem::Decl(ref mut decl) => { decl.relocate(r); }
+            StmtListItem::Stmt(ref mut stmt) => { stmt.relocate(r); }
         }
     }
 }
@@ -0,0 +1,244 @@
+use std::mem::replace;
+use joker::track::*;
+use joker::token::{StringLiteral, NumberLiteral};
+
+use id::Id;
+use obj::PropKey;
+use fun::Params;
+use stack::grow;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TyKeyword {
+    Any,
+    Unknown,
+    Never,
+    Void,
+    Undefined,
+    Null,
+    Number,
+    BigInt,
+    String,
+    Boolean,
+    Symbol,
+    Object,
+    This
+}
+
+impl TyKeyword {
+    pub fn from_name(name: &str) -> Option<TyKeyword> {
+        Some(match name {
+            "any"       => TyKeyword::Any,
+            "unknown"   => TyKeyword::Unknown,
+            "never"     => TyKeyword::Never,
+            "undefined" => TyKeyword::Undefined,
+            "number"    => TyKeyword::Number,
+            "bigint"    => TyKeyword::BigInt,
+            "string"    => TyKeyword::String,
+            "boolean"   => TyKeyword::Boolean,
+            "symbol"    => TyKeyword::Symbol,
+            "object"    => TyKeyword::Object,
+            _           => { return None; }
+        })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TyOp {
+    Keyof,
+    Unique,
+    Readonly
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TyData {
+    Keyword(TyKeyword),
+    Ref(Vec<Id>, Vec<Ty>),
+    String(StringLiteral),
+    Number(NumberLiteral),
+    True,
+    False,
+    Typeof(Vec<Id>),
+    Array(Box<Ty>),
+    Tuple(Vec<Ty>),
+    Index(Box<Ty>, Box<Ty>),
+    Union(Vec<Ty>),
+    Intersection(Vec<Ty>),
+    Op(TyOp, Box<Ty>),
+    Infer(Id),
+    Cond(Box<Ty>, Box<Ty>, Box<Ty>, Box<Ty>),
+    Fun(FunTy),
+    Ctor(FunTy),
+    Obj(Vec<TyMember>),
+    Predicate(Id, Box<Ty>),
+    // A negated numeric literal type, such as `-1`.
+    Negative(NumberLiteral),
+    Mapped(Box<MappedTy>)
+}
+
+// Dropping a type moves its operand types onto a list and drops them one by
+// one, so that long chains such as `T[][]` don't recurse.
+impl Drop for TyData {
+    fn drop(&mut self) {
+        let mut operands = Vec::new();
+        self.take_operands(&mut operands);
+        while let Some(mut ty) = operands.pop() {
+            ty.value.take_operands(&mut operands);
+        }
+    }
+}
+
+impl TyData {
+    fn take_operands(&mut self, operands: &mut Vec<Ty>) {
+        match *self {
+            TyData::Array(ref mut ty)
+          | TyData::Op(_, ref mut ty)
+          | TyData::Predicate(_, ref mut ty)      => { operands.push(take(ty)); }
+            TyData::Index(ref mut obj, ref mut index) => { operands.push(take(obj)); operands.push(take(index)); }
+            TyData::Cond(ref mut check, ref mut extends, ref mut cons, ref mut alt) => {
+                operands.push(take(check));
+                operands.push(take(extends));
+                operands.push(take(cons));
+                operands.push(take(alt));
+            }
+            _ => { }
+        }
+    }
+}
+
+// Moves a type out of its place, leaving `any` behind. `TyData` implements
+// `Drop`, so its fields can't be moved out directly.
+pub fn take(ty: &mut Ty) -> Ty {
+    replace(ty, TyData::Keyword(TyKeyword::Any).tracked(None))
+}
+
+impl Untrack for TyData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        grow(|| match *self {
+            TyData::Keyword(_)                                 => { }
+            TyData::Ref(ref mut name, ref mut args)            => { name.relocate(r); args.relocate(r); }
+            TyData::String(_)                                  => { }
+            TyData::Number(_)                                  => { }
+            TyData::True                                       => { }
+            TyData::False                                      => { }
+            TyData::Typeof(ref mut name)                       => { name.relocate(r); }
+            TyData::Array(ref mut elt)                         => { elt.relocate(r); }
+            TyData::Tuple(ref mut elts)                        => { elts.relocate(r); }
+            TyData::Index(ref mut obj, ref mut index)          => { obj.relocate(r); index.relocate(r); }
+            TyData::Union(ref mut tys)                         => { tys.relocate(r); }
+            TyData::Intersection(ref mut tys)                  => { tys.relocate(r); }
+            TyData::Op(_, ref mut ty)                          => { ty.relocate(r); }
+            TyData::Infer(ref mut id)                          => { id.relocate(r); }
+            TyData::Cond(ref mut check, ref mut extends,
+                         ref mut cons, ref mut alt)            => { check.relocate(r); extends.relocate(r); cons.relocate(r); alt.relocate(r); }
+            TyData::Fun(ref mut fun)                           => { fun.relocate(r); }
+            TyData::Ctor(ref mut fun)                          => { fun.relocate(r); }
+            TyData::Obj(ref mut members)                       => { members.relocate(r); }
+            TyData::Predicate(ref mut id, ref mut ty)          => { id.relocate(r); ty.relocate(r); }
+            TyData::Negative(_)                                => { }
+            TyData::Mapped(ref mut mapped)                     => { mapped.relocate(r); }
+        })
+    }
+}
+
+pub type Ty = Tracked<TyData>;
+
+// A modifier of a mapped type's members, added with `+` or removed with `-`.
+// A modifier without a sign is added.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum MappedModifier {
+    Add,
+    Remove
+}
+
+// `{ readonly [K in T as N]?: V }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct MappedTy {
+    pub readonly: Option<MappedModifier>,
+    pub param: Id,
+    pub constraint: Ty,
+    pub name: Option<Ty>,
+    pub optional: Option<MappedModifier>,
+    pub ty: Option<Ty>
+}
+
+impl Untrack for MappedTy {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.param.relocate(r);
+        self.constraint.relocate(r);
+        self.name.relocate(r);
+        self.ty.relocate(r);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TyParamData {
+    pub id: Id,
+    pub constraint: Option<Ty>,
+    pub default: Option<Ty>
+}
+
+impl Untrack for TyParamData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.id.relocate(r);
+        self.constraint.relocate(r);
+        self.default.relocate(r);
+    }
+}
+
+pub type TyParam = Tracked<TyParamData>;
+
+// The signature of a function type, method signature or function overload.
+// A missing return type is implicitly `any`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunTyData {
+    pub ty_params: Vec<TyParam>,
+    pub params: Params,
+    pub ret_ty: Option<Box<Ty>>
+}
+
+impl Untrack for FunTyData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.ty_params.relocate(r);
+        self.params.relocate(r);
+        self.ret_ty.relocate(r);
+    }
+}
+
+pub type FunTy = Tracked<FunTyData>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TySig {
+    Prop(PropKey, Option<Ty>),
+    Method(PropKey, FunTy),
+    Index(Id, Ty),
+    Call(FunTy),
+    Ctor(FunTy)
+}
+
+impl Untrack for TySig {
+    fn relocate(&mut self, r: &mut Relocation) {
+        match *self {
+            TySig::Prop(ref mut key, ref mut ty)    => { key.relocate(r); ty.relocate(r); }
+            TySig::Method(ref mut key, ref mut fun) => { key.relocate(r); fun.relocate(r); }
+            TySig::Index(ref mut id, ref mut ty)    => { id.relocate(r); ty.relocate(r); }
+            TySig::Call(ref mut fun)                => { fun.relocate(r); }
+            TySig::Ctor(ref mut fun)                => { fun.relocate(r); }
+        }
+    }
+}
+
+// A member of an interface body or object type literal.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TyMemberData {
+    pub readonly: bool,
+    pub optional: bool,
+    pub sig: TySig
+}
+
+impl Untrack for TyMemberData {
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.sig.relocate(r);
+    }
+}
+
+pub type TyMember = Tracked<TyMemberData>;
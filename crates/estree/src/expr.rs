@@ -14,7 +14,8 @@ use result::Result;
 use error::{Error, string_error, node_type_error, type_error};
 use node::ExtractNode;
 use fun::IntoFun;
-use lit::{IntoStringLiteral, IntoNumberLiteral};
+use jsx::IntoJSX;
+use lit::{IntoStringLiteral, IntoNumberLiteral, WithRaw};
 
 pub trait IntoExpr {
     fn into_expr(self) -> Result<Expr>;
@@ -26,27 +27,7 @@ impl IntoExpr for Object {
         let tag = try!(self.tag());
         Ok(match tag {
             Tag::Identifier => { return Ok(try!(self.into_id()).into_expr()); }
-            Tag::Literal => {
-                let json = try!(self.extract_field("value").map_err(Error::Json));
-                match json {
-                    Value::Null => ExprData::Null,
-                    Value::Bool(val) => if val { ExprData::True } else { ExprData::False },
-                    Value::String(value) => ExprData::String(value.into_string_literal()),
-                    Value::I64(val) => ExprData::Number(val.into_number_literal()),
-                    Value::U64(val) => ExprData::Number(val.into_number_literal()),
-                    Value::F64(val) => ExprData::Number(val.into_number_literal()),
-                    Value::Object(_) => {
-                        let mut regex = try!(self.extract_object("regex").map_err(Error::Json));
-                        let pattern = try!(regex.extract_string("pattern").map_err(Error::Json));
-                        let flags = try!(regex.extract_string("flags").map_err(Error::Json));
-                        ExprData::RegExp(RegExpLiteral {
-                            pattern: pattern,
-                            flags: flags.chars().collect()
-                        })
-                    }
-                    _ => { return type_error("null, number, boolean, string, or object", json.ty()); }
-                }
-            }
+            Tag::Literal => { return self.into_lit(); }
             Tag::BinaryExpression => {
                 let str = try!(self.extract_string("operator").map_err(Error::Json));
                 let op: Binop = match str.parse() {
@@ -105,7 +86,7 @@ impl IntoExpr for Object {
                     ExprData::Brack(obj, prop)
                 } else {
                     let id = try!(try!(self.extract_object("property").map_err(Error::Json)).into_id());
-                    let key = DotKeyData(id.value.name.into_string()).tracked(None);
+                    let key = DotKeyData(id.value.name).tracked(None);
                     ExprData::Dot(obj, key)
                 }
             }
@@ -131,6 +112,10 @@ impl IntoExpr for Object {
                 let exprs = try!(self.extract_expr_list("expressions"));
                 ExprData::Seq(exprs)
             }
+            Tag::ParenthesizedExpression => {
+                let expr = try!(self.extract_expr("expression"));
+                ExprData::Paren(Box::new(expr))
+            }
             Tag::ObjectExpression => {
                 let props = try!(self.extract_prop_list("properties"));
                 ExprData::Obj(props)
@@ -142,19 +127,36 @@ impl IntoExpr for Object {
                 ExprData::Cond(test, cons, alt)
             }
             Tag::ThisExpression => ExprData::This,
+            Tag::MetaProperty => {
+                let meta = try!(try!(self.extract_object("meta").map_err(Error::Json)).into_id());
+                let property = try!(try!(self.extract_object("property").map_err(Error::Json)).into_id());
+                match (&meta.value.name.into_string()[..], &property.value.name.into_string()[..]) {
+                    ("new", "target") => ExprData::NewTarget,
+                    ("import", "meta") => ExprData::ImportMeta,
+                    (meta, property) => { return string_error("new.target or import.meta", format!("{}.{}", meta, property)); }
+                }
+            }
+            Tag::ImportExpression => {
+                let source = try!(self.extract_expr("source"));
+                ExprData::Import(Box::new(source))
+            }
+            Tag::JSXElement => ExprData::JSXElement(try!(self.into_jsx_element())),
+            Tag::JSXFragment => ExprData::JSXFragment(try!(self.into_jsx_fragment())),
             _ => { return node_type_error("expression", tag); }
         }.tracked(None))
     }
 
+    // A literal's `raw` text, when present, is kept as its source.
     fn into_lit(mut self) -> Result<Expr> {
         let json = try!(self.extract_field("value").map_err(Error::Json));
+        let raw = try!(self.extract_string_opt("raw").map_err(Error::Json));
         Ok(match json {
             Value::Null => ExprData::Null,
             Value::Bool(val) => if val { ExprData::True } else { ExprData::False },
-            Value::String(value) => ExprData::String(value.into_string_literal()),
-            Value::I64(val) => ExprData::Number(val.into_number_literal()),
-            Value::U64(val) => ExprData::Number(val.into_number_literal()),
-            Value::F64(val) => ExprData::Number(val.into_number_literal()),
+            Value::String(value) => ExprData::String(value.into_string_literal().with_raw(raw)),
+            Value::I64(val) => ExprData::Number(val.into_number_literal().with_raw(raw)),
+            Value::U64(val) => ExprData::Number(val.into_number_literal().with_raw(raw)),
+            Value::F64(val) => ExprData::Number(val.into_number_literal().with_raw(raw)),
             Value::Object(_) => {
                 let mut regex = try!(self.extract_object("regex").map_err(Error::Json));
                 let pattern = try!(regex.extract_string("pattern").map_err(Error::Json));
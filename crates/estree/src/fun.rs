@@ -1,3 +1,4 @@
+use std::mem::replace;
 use easter::fun::{Fun, FunData, ParamsData};
 use easter::stmt::StmtData;
 use unjson::ty::Object;
@@ -23,9 +24,9 @@ impl IntoFun for Object {
         let obj = try!(self.extract_object("body").map_err(Error::Json));
         let tag = try!(obj.tag());
         let body = match try!(obj.into_stmt()).value {
-            StmtData::Block(items) => items,
+            StmtData::Block(ref mut items) => replace(items, Vec::new()),
             _ => { return node_type_error("BlockStatement", tag); }
         };
-        Ok(FunData { id: id, params: params, body: body }.tracked(None))
+        Ok(FunData { id: id, ty_params: vec![], params: params, ret_ty: None, body: body, lazy: None }.tracked(None))
     }
 }
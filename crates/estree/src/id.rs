@@ -19,7 +19,11 @@ impl IntoId for Object {
             return node_type_error("identifier", tag);
         }
         Ok(IdData {
-            name: Name::from(try!(self.extract_string("name").map_err(Error::Json)))
+            name: Name::from(try!(self.extract_string("name").map_err(Error::Json))),
+            ty: None,
+            optional: false,
+            access: None,
+            readonly: false
         }.tracked(None))
     }
 }
@@ -0,0 +1,124 @@
+use easter::jsx::{JSXIdData, JSXId, JSXNameData, JSXName, JSXAttrData, JSXAttr, JSXAttrValData, JSXAttrVal, JSXChildData, JSXChild, JSXElementData, JSXElement, JSXFragmentData, JSXFragment};
+use easter::expr::ExprData;
+use unjson::ty::{Object, Ty};
+use unjson::{ExtractField, Unjson};
+use joker::token::StringLiteral;
+use joker::track::*;
+
+use tag::{Tag, TagOf};
+use result::{Result, Map};
+use error::{Error, node_type_error, type_error};
+use node::ExtractNode;
+use expr::IntoExpr;
+
+pub trait IntoJSX {
+    fn into_jsx_element(self) -> Result<JSXElement>;
+    fn into_jsx_fragment(self) -> Result<JSXFragment>;
+    fn into_jsx_id(self) -> Result<JSXId>;
+    fn into_jsx_name(self) -> Result<JSXName>;
+    fn into_jsx_attr(self) -> Result<JSXAttr>;
+    fn into_jsx_attr_val(self) -> Result<JSXAttrVal>;
+    fn into_jsx_child(self) -> Result<JSXChild>;
+    fn extract_jsx_children(&mut self) -> Result<Vec<JSXChild>>;
+}
+
+impl IntoJSX for Object {
+    fn into_jsx_element(mut self) -> Result<JSXElement> {
+        let mut opening = try!(self.extract_object("openingElement").map_err(Error::Json));
+        let name = try!(try!(opening.extract_object("name").map_err(Error::Json)).into_jsx_name());
+        let attrs = try!(opening.extract_array("attributes").map_err(Error::Json));
+        let attrs = try!(try!(attrs.map(|v| v.into_object().map_err(Error::Json))).map(|o| o.into_jsx_attr()));
+        let children = if try!(opening.extract_bool("selfClosing").map_err(Error::Json)) {
+            None
+        } else {
+            Some(try!(self.extract_jsx_children()))
+        };
+        Ok(JSXElementData { name: name, attrs: attrs, children: children }.tracked(None))
+    }
+
+    fn into_jsx_fragment(mut self) -> Result<JSXFragment> {
+        let children = try!(self.extract_jsx_children());
+        Ok(JSXFragmentData { children: children }.tracked(None))
+    }
+
+    fn into_jsx_id(mut self) -> Result<JSXId> {
+        let tag = try!(self.tag());
+        if tag != Tag::JSXIdentifier {
+            return node_type_error("JSX identifier", tag);
+        }
+        Ok(JSXIdData(try!(self.extract_string("name").map_err(Error::Json))).tracked(None))
+    }
+
+    fn into_jsx_name(mut self) -> Result<JSXName> {
+        Ok(match try!(self.tag()) {
+            Tag::JSXIdentifier => JSXNameData::Id(try!(self.into_jsx_id())),
+            Tag::JSXNamespacedName => {
+                let ns = try!(try!(self.extract_object("namespace").map_err(Error::Json)).into_jsx_id());
+                let name = try!(try!(self.extract_object("name").map_err(Error::Json)).into_jsx_id());
+                JSXNameData::Namespaced(ns, name)
+            }
+            Tag::JSXMemberExpression => {
+                let obj = try!(try!(self.extract_object("object").map_err(Error::Json)).into_jsx_name());
+                let prop = try!(try!(self.extract_object("property").map_err(Error::Json)).into_jsx_id());
+                JSXNameData::Member(Box::new(obj), prop)
+            }
+            tag => { return node_type_error("JSX name", tag); }
+        }.tracked(None))
+    }
+
+    fn into_jsx_attr(mut self) -> Result<JSXAttr> {
+        Ok(match try!(self.tag()) {
+            Tag::JSXAttribute => {
+                let name = try!(try!(self.extract_object("name").map_err(Error::Json)).into_jsx_name());
+                let val = match try!(self.extract_object_opt("value").map_err(Error::Json)) {
+                    Some(obj) => Some(try!(obj.into_jsx_attr_val())),
+                    None      => None
+                };
+                JSXAttrData::Attr(name, val)
+            }
+            Tag::JSXSpreadAttribute => JSXAttrData::Spread(try!(self.extract_expr("argument"))),
+            tag => { return node_type_error("JSX attribute", tag); }
+        }.tracked(None))
+    }
+
+    fn into_jsx_attr_val(mut self) -> Result<JSXAttrVal> {
+        Ok(match try!(self.tag()) {
+            Tag::Literal => match try!(self.into_lit()).value {
+                ExprData::String(ref lit) => JSXAttrValData::String(lit.clone()),
+                _ => { return type_error("string literal", Ty::Object); }
+            },
+            Tag::JSXExpressionContainer => JSXAttrValData::Expr(try!(self.extract_expr("expression"))),
+            Tag::JSXElement => JSXAttrValData::Element(try!(self.into_jsx_element())),
+            Tag::JSXFragment => JSXAttrValData::Fragment(try!(self.into_jsx_fragment())),
+            tag => { return node_type_error("JSX attribute value", tag); }
+        }.tracked(None))
+    }
+
+    fn into_jsx_child(mut self) -> Result<JSXChild> {
+        Ok(match try!(self.tag()) {
+            Tag::JSXText => {
+                let value = try!(self.extract_string("value").map_err(Error::Json));
+                let source = try!(self.extract_string_opt("raw").map_err(Error::Json));
+                JSXChildData::Text(StringLiteral { source: source, value: value })
+            }
+            Tag::JSXExpressionContainer => {
+                let expr = try!(self.extract_object("expression").map_err(Error::Json));
+                if try!(expr.tag()) == Tag::JSXEmptyExpression {
+                    JSXChildData::Expr(None)
+                } else {
+                    JSXChildData::Expr(Some(try!(expr.into_expr())))
+                }
+            }
+            Tag::JSXSpreadChild => JSXChildData::Spread(try!(self.extract_expr("expression"))),
+            Tag::JSXElement => JSXChildData::Element(try!(self.into_jsx_element())),
+            Tag::JSXFragment => JSXChildData::Fragment(try!(self.into_jsx_fragment())),
+            tag => { return node_type_error("JSX child", tag); }
+        }.tracked(None))
+    }
+
+    fn extract_jsx_children(&mut self) -> Result<Vec<JSXChild>> {
+        let list = try!(self.extract_array("children").map_err(Error::Json));
+        let objs = try!(list.map(|v| v.into_object().map_err(Error::Json)));
+        objs.map(|o| o.into_jsx_child())
+    }
+}
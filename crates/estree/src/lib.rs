@@ -17,6 +17,7 @@ mod obj;
 mod decl;
 mod prog;
 mod lit;
+mod jsx;
 
 use serde::de::Error;
 use serde::de::{Deserialize, Deserializer};
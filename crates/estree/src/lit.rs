@@ -13,6 +13,26 @@ impl IntoStringLiteral for String {
     }
 }
 
+// Replaces a literal's source with the `raw` text of an ESTree node.
+pub trait WithRaw {
+    fn with_raw(self, raw: Option<String>) -> Self;
+}
+
+impl WithRaw for StringLiteral {
+    fn with_raw(self, raw: Option<String>) -> StringLiteral {
+        StringLiteral { source: raw.or(self.source), value: self.value }
+    }
+}
+
+impl WithRaw for NumberLiteral {
+    fn with_raw(self, raw: Option<String>) -> NumberLiteral {
+        NumberLiteral {
+            source: raw.map(|raw| NumberSource::parse(&raw)).or(self.source),
+            value: self.value
+        }
+    }
+}
+
 pub trait IntoNumberLiteral {
     fn into_number_literal(self) -> NumberLiteral;
 }
@@ -35,10 +55,11 @@ impl IntoNumberLiteral for u64 {
     }
 }
 
+// Without a `raw` text the source is the shortest decimal form of the value.
 impl IntoNumberLiteral for f64 {
     fn into_number_literal(self) -> NumberLiteral {
         NumberLiteral {
-            source: None,
+            source: Some(NumberSource::parse(&self.to_string())),
             value: self
         }
     }
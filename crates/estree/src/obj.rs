@@ -42,11 +42,11 @@ impl IntoObj for Object {
     fn into_prop_key(self) -> Result<PropKey> {
         if try!(self.tag()) == Tag::Identifier {
             let id = try!(self.into_id());
-            return Ok(PropKeyData::Id(id.value.name.into_string()).tracked(None));
+            return Ok(PropKeyData::Id(id.value.name).tracked(None));
         }
         match try!(self.into_lit()).value {
-            ExprData::Number(lit) => Ok(PropKeyData::Number(lit).tracked(None)),
-            ExprData::String(lit) => Ok(PropKeyData::String(lit).tracked(None)),
+            ExprData::Number(ref lit) => Ok(PropKeyData::Number(lit.clone()).tracked(None)),
+            ExprData::String(ref lit) => Ok(PropKeyData::String(lit.clone()).tracked(None)),
             _ => { return type_error("identifier, number literal, or string literal", Ty::Object); }
         }
     }
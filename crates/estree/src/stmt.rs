@@ -201,6 +201,9 @@ impl IntoStmt for Object {
             Tag::DebuggerStatement => {
                 StmtData::Debugger(Semi::Explicit(None))
             }
+            Tag::FunctionDeclaration => {
+                StmtData::Fun(try!(self.into_fun()))
+            }
             Tag::TryStatement => {
                 let mut block = try!(self.extract_object("block").map_err(Error::Json));
                 let body = try!(block.extract_stmt_list("body"));
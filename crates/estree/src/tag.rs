@@ -23,9 +23,12 @@ pub enum Tag {
     ArrayExpression,
     FunctionExpression,
     SequenceExpression,
+    ParenthesizedExpression,
     ObjectExpression,
     ConditionalExpression,
     ThisExpression,
+    MetaProperty,
+    ImportExpression,
 
     FunctionDeclaration,
     VariableDeclaration,
@@ -47,7 +50,19 @@ pub enum Tag {
     WithStatement,
     ThrowStatement,
     DebuggerStatement,
-    TryStatement
+    TryStatement,
+
+    JSXElement,
+    JSXFragment,
+    JSXIdentifier,
+    JSXNamespacedName,
+    JSXMemberExpression,
+    JSXAttribute,
+    JSXSpreadAttribute,
+    JSXExpressionContainer,
+    JSXEmptyExpression,
+    JSXSpreadChild,
+    JSXText
 }
 
 impl Display for Tag {
@@ -74,9 +89,12 @@ impl FromStr for Tag {
             "ArrayExpression"       => Tag::ArrayExpression,
             "FunctionExpression"    => Tag::FunctionExpression,
             "SequenceExpression"    => Tag::SequenceExpression,
+            "ParenthesizedExpression" => Tag::ParenthesizedExpression,
             "ObjectExpression"      => Tag::ObjectExpression,
             "ConditionalExpression" => Tag::ConditionalExpression,
             "ThisExpression"        => Tag::ThisExpression,
+            "MetaProperty"          => Tag::MetaProperty,
+            "ImportExpression"      => Tag::ImportExpression,
             "FunctionDeclaration"   => Tag::FunctionDeclaration,
             "VariableDeclaration"   => Tag::VariableDeclaration,
             "EmptyStatement"        => Tag::EmptyStatement,
@@ -97,6 +115,17 @@ impl FromStr for Tag {
             "ThrowStatement"        => Tag::ThrowStatement,
             "DebuggerStatement"     => Tag::DebuggerStatement,
             "TryStatement"          => Tag::TryStatement,
+            "JSXElement"            => Tag::JSXElement,
+            "JSXFragment"           => Tag::JSXFragment,
+            "JSXIdentifier"         => Tag::JSXIdentifier,
+            "JSXNamespacedName"     => Tag::JSXNamespacedName,
+            "JSXMemberExpression"   => Tag::JSXMemberExpression,
+            "JSXAttribute"          => Tag::JSXAttribute,
+            "JSXSpreadAttribute"    => Tag::JSXSpreadAttribute,
+            "JSXExpressionContainer" => Tag::JSXExpressionContainer,
+            "JSXEmptyExpression"    => Tag::JSXEmptyExpression,
+            "JSXSpreadChild"        => Tag::JSXSpreadChild,
+            "JSXText"               => Tag::JSXText,
             _ => { return Err(Error::InvalidTypeTag(String::from(s))); }
         })
     }
@@ -0,0 +1,105 @@
+// Times `Lexer` against `SliceLexer` over a source file:
+//
+//     cargo run --release --example bench -- path/to/file.js [iterations]
+
+extern crate joker;
+
+use std::cell::Cell;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use joker::{Lexer, SliceLexer};
+use joker::context::{Context, Mode};
+use joker::slice::{SliceToken, SliceData};
+use joker::token::{Token, TokenData};
+use joker::word::Reserved;
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+// Whether a token can end an operand, so that a following `/` divides. A
+// parser decides this from the grammar; this guess is good enough to lex
+// most real code without errors.
+fn ends_operand(value: &TokenData) -> bool {
+    match *value {
+        TokenData::Identifier(_)
+      | TokenData::Number(_)
+      | TokenData::String(_)
+      | TokenData::RegExp(_)
+      | TokenData::RParen
+      | TokenData::RBrack
+      | TokenData::RBrace => true,
+        TokenData::Reserved(word) => {
+            word == Reserved::This || word == Reserved::Super || word == Reserved::Null ||
+            word == Reserved::True || word == Reserved::False
+        }
+        _ => false
+    }
+}
+
+fn slice_ends_operand(value: &SliceData) -> bool {
+    match *value {
+        SliceData::Other(ref value) => ends_operand(value),
+        _ => true
+    }
+}
+
+fn time<F: FnMut() -> usize>(name: &str, bytes: usize, iterations: usize, mut lex: F) {
+    let start = Instant::now();
+    let mut tokens = 0;
+    for _ in 0..iterations {
+        tokens = lex();
+    }
+    let elapsed = seconds(start.elapsed()) / iterations as f64;
+    println!("{:<12} {:>9} tokens {:>10.3} ms {:>9.1} MB/s",
+             name, tokens, elapsed * 1e3, bytes as f64 / elapsed / 1e6);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args.next().expect("usage: bench <file.js> [iterations]");
+    let iterations = args.next().map_or(10, |n| n.parse().expect("iterations must be a number"));
+    let mut source = String::new();
+    File::open(&path).and_then(|mut file| file.read_to_string(&mut source)).expect("cannot read source");
+
+    // Both lexers skip a character after an error, as parsers recovering
+    // from one do, so that every file is read to the end.
+    time("Lexer", source.len(), iterations, || {
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new(source.chars(), cx.clone());
+        let mut tokens = 0;
+        loop {
+            match lexer.read_token() {
+                Ok(Token { value: TokenData::EOF, .. }) => { return tokens; }
+                Ok(token) => {
+                    tokens += 1;
+                    let mut context = cx.get();
+                    context.operator = ends_operand(&token.value);
+                    cx.set(context);
+                }
+                Err(_) => { lexer.skip_char(); }
+            }
+        }
+    });
+    time("SliceLexer", source.len(), iterations, || {
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = SliceLexer::new(&source, cx.clone());
+        let mut tokens = 0;
+        loop {
+            match lexer.read_token() {
+                Ok(SliceToken { value: SliceData::Other(TokenData::EOF), .. }) => { return tokens; }
+                Ok(token) => {
+                    tokens += 1;
+                    let mut context = cx.get();
+                    context.operator = slice_ends_operand(&token.value);
+                    cx.set(context);
+                }
+                Err(_) => { lexer.skip_char(); }
+            }
+        }
+    });
+}
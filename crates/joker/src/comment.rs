@@ -0,0 +1,38 @@
+use track::*;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CommentKind {
+    // `// ...`
+    Line,
+    // `/* ... */`
+    Block,
+    // Annex B `<!-- ...` and `--> ...`
+    Html,
+    // `#! ...` at the very start of the source
+    Hashbang
+}
+
+// A comment skipped by the lexer. The text excludes the delimiters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    pub text: String,
+    pub location: Span
+}
+
+impl Comment {
+    pub fn new(kind: CommentKind, text: String, location: Span) -> Comment {
+        Comment { kind: kind, text: text, location: location }
+    }
+
+    // Whether the comment spans more than one line.
+    pub fn is_multiline(&self) -> bool {
+        self.location.start.line != self.location.end.line
+    }
+}
+
+impl Track for Comment {
+    fn location(&self) -> Option<Span> {
+        Some(self.location)
+    }
+}
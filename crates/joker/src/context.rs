@@ -15,11 +15,25 @@ impl Mode {
     }
 }
 
+// The lexical goal within JSX: the parser switches between these as it moves
+// through tags, children and embedded expressions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum JSXMode {
+    Off,
+    Tag,
+    Text
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Context {
     pub mode: Mode,
     pub operator: bool,
-    pub generator: bool
+    pub generator: bool,
+    pub annex_b: bool,
+    pub jsx: bool,
+    pub jsx_mode: JSXMode,
+    pub typescript: bool,
+    pub decorators: bool
 }
 
 impl Context {
@@ -27,7 +41,24 @@ impl Context {
         Context {
             mode: mode,
             operator: false,
-            generator: false
+            generator: false,
+            annex_b: true,
+            jsx: false,
+            jsx_mode: JSXMode::Off,
+            typescript: false,
+            decorators: false
         }
     }
+
+    // B.1 Additional Syntax: the web-compatibility extensions are never
+    // available in module code, regardless of the annex_b flag.
+    pub fn has_annex_b(&self) -> bool {
+        self.annex_b && self.mode != Mode::Module
+    }
+
+    // Most of the extensions (legacy octals, labelled functions, functions as
+    // if-statement bodies, for-in initializers) are also sloppy-mode only.
+    pub fn has_sloppy_annex_b(&self) -> bool {
+        self.has_annex_b() && !self.mode.is_strict()
+    }
 }
@@ -1,8 +1,9 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use track::*;
 
-#[derive(Debug, PartialEq)]
-pub enum Error {
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorData {
     IncompleteWordEscape(Option<char>),
     UnterminatedComment,
     UnterminatedRegExp(Option<char>),
@@ -15,51 +16,105 @@ pub enum Error {
     InvalidDigit(char),
     IllegalUnicode(u32),
     IdAfterNumber(char),
-    DigitAfterNumber(char)
+    DigitAfterNumber(char),
+    LegacyOctalNumber,
+    LegacyOctalEscape,
+    SourceTooLong,
+    TooManyTokens,
+    Cancelled
 }
 
-impl Display for Error {
+impl ErrorData {
+    // Whether the error stops the lexer, so that nothing more can be read.
+    pub fn is_fatal(&self) -> bool {
+        match *self {
+            ErrorData::SourceTooLong
+          | ErrorData::TooManyTokens
+          | ErrorData::Cancelled => true,
+            _                    => false
+        }
+    }
+}
+
+impl Display for ErrorData {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         match self {
-            &Error::IncompleteWordEscape(_) => {
+            &ErrorData::IncompleteWordEscape(_) => {
                 fmt.write_str("incomplete word escape")
             }
-            &Error::UnterminatedComment => {
+            &ErrorData::UnterminatedComment => {
                 fmt.write_str("unterminated block comment")
             }
-            &Error::UnterminatedRegExp(_) => {
+            &ErrorData::UnterminatedRegExp(_) => {
                 fmt.write_str("unterminated regexp literal")
             }
-            &Error::MissingExponent(_) => {
+            &ErrorData::MissingExponent(_) => {
                 fmt.write_str("missing exponent")
             }
-            &Error::UnterminatedString(_) => {
+            &ErrorData::UnterminatedString(_) => {
                 fmt.write_str("unterminated string")
             }
-            &Error::MissingBinaryDigits => {
+            &ErrorData::MissingBinaryDigits => {
                 fmt.write_str("missing binary digits")
             }
-            &Error::MissingOctalDigits => {
+            &ErrorData::MissingOctalDigits => {
                 fmt.write_str("missing octal digits")
             }
-            &Error::MissingHexDigits => {
+            &ErrorData::MissingHexDigits => {
                 fmt.write_str("missing hex digits")
             }
-            &Error::IllegalChar(ref ch) => {
+            &ErrorData::IllegalChar(ref ch) => {
                 fmt.write_fmt(format_args!("illegal character: {:?}", *ch))
             }
-            &Error::InvalidDigit(ref ch) => {
+            &ErrorData::InvalidDigit(ref ch) => {
                 fmt.write_fmt(format_args!("invalid digit: {:?}", *ch))
             }
-            &Error::IllegalUnicode(ref u) => {
+            &ErrorData::IllegalUnicode(ref u) => {
                 fmt.write_fmt(format_args!("illegal code unit: \\u{{{:04x}}}", u))
             }
-            &Error::IdAfterNumber(_) => {
+            &ErrorData::IdAfterNumber(_) => {
                 fmt.write_str("identifier starts immediately after numeric literal")
             }
-            &Error::DigitAfterNumber(_) => {
+            &ErrorData::DigitAfterNumber(_) => {
                 fmt.write_str("numeric literal starts immediately after previous numeric literal")
             }
+            &ErrorData::LegacyOctalNumber => {
+                fmt.write_str("legacy octal literals are not allowed in strict mode")
+            }
+            &ErrorData::LegacyOctalEscape => {
+                fmt.write_str("legacy octal escape sequences are not allowed in strict mode")
+            }
+            &ErrorData::SourceTooLong => {
+                fmt.write_str("source exceeds the maximum length")
+            }
+            &ErrorData::TooManyTokens => {
+                fmt.write_str("source exceeds the maximum number of tokens")
+            }
+            &ErrorData::Cancelled => {
+                fmt.write_str("lexing cancelled")
+            }
         }
     }
 }
+
+// A lexer error and where it happened. `token` runs from the start of the
+// token being read to the point where lexing failed, and `location` covers
+// the offending character (or is empty at the end of input).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub value: ErrorData,
+    pub token: Span,
+    pub location: Span
+}
+
+impl Track for Error {
+    fn location(&self) -> Option<Span> {
+        Some(self.location)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        self.value.fmt(fmt)
+    }
+}
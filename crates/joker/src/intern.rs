@@ -0,0 +1,287 @@
+// Tables of identifier names. Interning a name yields a `Symbol`, a small
+// `Copy` handle that compares and hashes in constant time, so later passes
+// (scope analysis, minifiers) can key their maps by symbol.
+//
+// Names go into the thread's current `Interner`: the one entered with
+// `Interner::enter`, or else a process-wide default, which only grows until
+// `Interner::reset_default` replaces it. A long-running process that parses
+// untrusted input should reset the default now and then, or parse within
+// interners of its own. A table's names are freed once the last handle to it
+// is dropped; its symbols have no text from then on.
+//
+// A symbol carries a hash of its text, so a name is equal to itself in every
+// interner: symbols from the same interner compare by index, and symbols from
+// different interners by hash and then by text. Symbols of a dropped interner
+// are only equal to symbols of the same interner. The contextual keywords of
+// `word::Atom` come first in every table, in declaration order, and have the
+// same symbols everywhere.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock, RwLock, Weak};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use word::{Atom, ATOMS};
+
+// The table of atoms.
+const ATOMS_ID: u32 = 0;
+
+#[derive(Clone, Copy)]
+pub struct Symbol {
+    interner: u32,
+    index: u32,
+    hash: u32
+}
+
+impl Symbol {
+    // The symbol for `s` in the current interner, adding it if it is new.
+    pub fn intern(s: &str) -> Symbol {
+        Interner::current().intern(s)
+    }
+
+    // The symbol's text, or `None` if its interner has been dropped.
+    pub fn text(self) -> Option<Arc<str>> {
+        if self.interner == ATOMS_ID {
+            return Some(atoms().strings[self.index as usize].clone());
+        }
+        find(self.interner).map(|table| table.text(self.index))
+    }
+
+    // The symbol's position in its interner, for maps backed by a `Vec`.
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+
+    pub fn atom(self) -> Option<Atom> {
+        if self.interner == ATOMS_ID {
+            ATOMS.get(self.index as usize).cloned()
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Atom> for Symbol {
+    fn from(atom: Atom) -> Symbol {
+        let index = atom as u32;
+        Symbol { interner: ATOMS_ID, index: index, hash: atoms().hashes[index as usize] }
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        if self.interner == other.interner {
+            return self.index == other.index;
+        }
+        self.hash == other.hash && match (self.text(), other.text()) {
+            (Some(a), Some(b)) => a == b,
+            _                  => false
+        }
+    }
+}
+
+impl Eq for Symbol { }
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.text() {
+            Some(text) => Debug::fmt(&text[..], fmt),
+            None       => fmt.write_fmt(format_args!("<dropped symbol {}:{}>", self.interner, self.index))
+        }
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.text() {
+            Some(text) => fmt.write_str(&text),
+            None       => fmt.write_fmt(format_args!("<dropped symbol {}:{}>", self.interner, self.index))
+        }
+    }
+}
+
+// A handle to a table of names, shared by its clones and across threads.
+#[derive(Clone)]
+pub struct Interner(Arc<Table>);
+
+impl Interner {
+    pub fn new() -> Interner {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(ATOMS_ID + 1);
+        let table = Arc::new(Table::new(NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+        registry().write().unwrap().insert(table.id, Arc::downgrade(&table));
+        Interner(table)
+    }
+
+    // The interner that names are added to on this thread.
+    pub fn current() -> Interner {
+        CURRENT.with(|current| current.borrow().last().cloned())
+            .unwrap_or_else(|| default().read().unwrap().clone())
+    }
+
+    // Replaces the default interner with an empty one. The old one is freed
+    // once nothing else holds it, such as a lexer that is still running.
+    pub fn reset_default() {
+        *default().write().unwrap() = Interner::new();
+    }
+
+    // Runs `f` with this as the current interner of the thread.
+    pub fn enter<F, T>(&self, f: F) -> T
+      where F: FnOnce() -> T
+    {
+        struct Exit;
+
+        impl Drop for Exit {
+            fn drop(&mut self) {
+                CURRENT.with(|current| { current.borrow_mut().pop(); });
+            }
+        }
+
+        CURRENT.with(|current| current.borrow_mut().push(self.clone()));
+        let _exit = Exit;
+        f()
+    }
+
+    // The symbol for `s`, adding it to the table if it is new.
+    pub fn intern(&self, s: &str) -> Symbol {
+        if let Some(symbol) = self.lookup(s) {
+            return symbol;
+        }
+        let mut names = self.0.names.write().unwrap();
+        // Another thread may have added it since the lookup.
+        let index = match names.map.get(s) {
+            Some(&index) => index,
+            None         => names.insert(s)
+        };
+        Symbol { interner: self.0.id, index: index, hash: names.hashes[index as usize] }
+    }
+
+    // The symbol for `s`, if it has already been interned.
+    pub fn lookup(&self, s: &str) -> Option<Symbol> {
+        if let Some(atom) = atom(s) {
+            return Some(Symbol::from(atom));
+        }
+        let names = self.0.names.read().unwrap();
+        names.map.get(s).map(|&index| {
+            Symbol { interner: self.0.id, index: index, hash: names.hashes[index as usize] }
+        })
+    }
+
+    // The number of symbols interned so far. Every symbol's index is below it.
+    pub fn len(&self) -> usize {
+        self.0.names.read().unwrap().strings.len()
+    }
+}
+
+// The symbol for `s` in the current interner, if it has already been
+// interned.
+pub fn lookup(s: &str) -> Option<Symbol> {
+    Interner::current().lookup(s)
+}
+
+// The atom spelled `s`, if any.
+pub fn atom(s: &str) -> Option<Atom> {
+    atoms().map.get(s).map(|&index| ATOMS[index as usize])
+}
+
+struct Table {
+    id: u32,
+    names: RwLock<Names>
+}
+
+struct Names {
+    map: HashMap<Arc<str>, u32>,
+    strings: Vec<Arc<str>>,
+    hashes: Vec<u32>
+}
+
+impl Names {
+    // A table whose first entries are the atoms.
+    fn new() -> Names {
+        let mut names = Names { map: HashMap::new(), strings: Vec::new(), hashes: Vec::new() };
+        for &atom in ATOMS.iter() {
+            names.insert(atom.name());
+        }
+        names
+    }
+
+    fn insert(&mut self, s: &str) -> u32 {
+        let index = self.strings.len() as u32;
+        let s: Arc<str> = Arc::from(s);
+        self.strings.push(s.clone());
+        self.hashes.push(hash_text(&s));
+        self.map.insert(s, index);
+        index
+    }
+}
+
+impl Table {
+    fn new(id: u32) -> Table {
+        let mut names = Names::new();
+        // Atoms are only ever found in their own table.
+        names.map.clear();
+        Table { id: id, names: RwLock::new(names) }
+    }
+
+    fn text(&self, index: u32) -> Arc<str> {
+        self.names.read().unwrap().strings[index as usize].clone()
+    }
+}
+
+impl Drop for Table {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = registry().write() {
+            registry.remove(&self.id);
+        }
+    }
+}
+
+// FNV-1a, which gives the same hash for a name in every interner.
+fn hash_text(s: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for &b in s.as_bytes() {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+thread_local! {
+    static CURRENT: RefCell<Vec<Interner>> = RefCell::new(Vec::new());
+}
+
+// The atoms never change, so they are read without a lock.
+fn atoms() -> &'static Names {
+    static ATOMS_TABLE: OnceLock<Names> = OnceLock::new();
+    ATOMS_TABLE.get_or_init(Names::new)
+}
+
+fn default() -> &'static RwLock<Interner> {
+    static DEFAULT: OnceLock<RwLock<Interner>> = OnceLock::new();
+    DEFAULT.get_or_init(|| RwLock::new(Interner::new()))
+}
+
+// Every live interner, so that symbols can be read on any thread.
+fn registry() -> &'static RwLock<HashMap<u32, Weak<Table>>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<u32, Weak<Table>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn find(id: u32) -> Option<Arc<Table>> {
+    let current = CURRENT.with(|current| {
+        current.borrow().iter().rev().find(|interner| interner.0.id == id).map(|interner| interner.0.clone())
+    });
+    if current.is_some() {
+        return current;
+    }
+    let table = registry().read().unwrap().get(&id).and_then(Weak::upgrade);
+    table
+}
@@ -0,0 +1,31 @@
+use track::*;
+use token::Token;
+use comment::Comment;
+use error::Error;
+
+// Everything the lexer reads, in source order: tokens, the trivia between
+// them, and errors.
+#[derive(Debug, PartialEq)]
+pub enum Lexeme {
+    Token(Token),
+    // A run of whitespace other than line terminators.
+    Whitespace(Span),
+    // A run of line terminators.
+    Newline(Span),
+    Comment(Comment),
+    // Input that failed to lex, from the start of the attempted token
+    // through the offending character.
+    Error(Error)
+}
+
+impl Track for Lexeme {
+    fn location(&self) -> Option<Span> {
+        match *self {
+            Lexeme::Token(ref token)     => Some(token.location),
+            Lexeme::Whitespace(span)
+          | Lexeme::Newline(span)        => Some(span),
+            Lexeme::Comment(ref comment) => Some(comment.location),
+            Lexeme::Error(ref err)       => Some(Span { start: err.token.start, end: err.location.end })
+        }
+    }
+}
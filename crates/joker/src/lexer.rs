@@ -1,18 +1,29 @@
 use std::char;
 
 use track::*;
+use comment::{Comment, CommentKind};
+use lexeme::Lexeme;
 use token::{Token, TokenData, Exp, CharCase, Sign, NumberSource, Radix, StringLiteral, RegExpLiteral};
 use word::Map as WordMap;
+use intern::Interner;
 
 use std::cell::Cell;
+use std::mem::replace;
 use std::rc::Rc;
-use context::Context;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use context::{Context, JSXMode};
 use char::ESCharExt;
+use entities::ENTITIES;
+use reader;
 use reader::Reader;
 use lookahead::Buffer;
-use error::Error;
+use error::{Error, ErrorData};
 use result::Result;
 
+// Lexing fails with a bare error; `read_next_token` adds its location.
+type LexResult<T> = ::std::result::Result<T, ErrorData>;
+
 fn add_digits(digits: Vec<u32>, radix: u32) -> u32 {
     let mut place = 1;
     let mut sum = 0;
@@ -23,6 +34,34 @@ fn add_digits(digits: Vec<u32>, radix: u32) -> u32 {
     sum
 }
 
+// Decodes the character references allowed in JSX text and attribute strings:
+// numeric references and the named references of HTML 4. Anything else is
+// left as written.
+fn decode_jsx_entities(s: &str) -> String {
+    let mut value = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        value.push_str(&rest[..i]);
+        rest = &rest[i..];
+        match rest.find(';').and_then(|j| decode_jsx_entity(&rest[1..j]).map(|ch| (ch, j))) {
+            Some((ch, j)) => { value.push(ch); rest = &rest[j + 1..]; }
+            None          => { value.push('&'); rest = &rest[1..]; }
+        }
+    }
+    value.push_str(rest);
+    value
+}
+
+fn decode_jsx_entity(name: &str) -> Option<char> {
+    if name.starts_with("#x") {
+        return u32::from_str_radix(&name[2..], 16).ok().and_then(char::from_u32);
+    }
+    if name.starts_with("#") {
+        return name[1..].parse().ok().and_then(char::from_u32);
+    }
+    ENTITIES.binary_search_by(|&(entity, _)| entity.cmp(name)).ok().map(|i| ENTITIES[i].1)
+}
+
 struct SpanTracker {
     start: Posn
 }
@@ -36,11 +75,53 @@ impl SpanTracker {
     }
 }
 
+pub struct Checkpoint {
+    reader: reader::Checkpoint,
+    lookahead: Option<Token>,
+    started: bool,
+    last_token: (Span, Posn),
+    tokens: usize,
+    comments: usize,
+    trace: usize
+}
+
 pub struct Lexer<I> {
     reader: Reader<I>,
     cx: Rc<Cell<Context>>,
     lookahead: Buffer,
-    wordmap: WordMap
+    wordmap: WordMap,
+    // The interner that was current when the lexer was made.
+    interner: Interner,
+    started: bool,
+    token_start: Posn,
+    // The last token handed out, and the end of the one before it.
+    last_token: (Span, Posn),
+    comments: Option<Vec<Comment>>,
+    trace: Option<Vec<Lexeme>>,
+    limits: Limits
+}
+
+// Guards against untrusted input: the source length in bytes, the number of
+// tokens lexed and a flag to stop at, set from another thread.
+struct Limits {
+    max_source_len: usize,
+    max_tokens: usize,
+    tokens: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    // The error the lexer stopped with, once a limit was hit.
+    stopped: Option<ErrorData>
+}
+
+impl Limits {
+    fn new() -> Limits {
+        Limits {
+            max_source_len: usize::max_value(),
+            max_tokens: usize::max_value(),
+            tokens: 0,
+            cancel: None,
+            stopped: None
+        }
+    }
 }
 
 impl<I> Lexer<I> where I: Iterator<Item=char> {
@@ -51,7 +132,33 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
             reader: Reader::new(chars),
             cx: cx,
             lookahead: Buffer::new(),
-            wordmap: WordMap::new()
+            wordmap: WordMap::new(),
+            interner: Interner::current(),
+            started: false,
+            token_start: Posn::origin(),
+            last_token: (Span { start: Posn::origin(), end: Posn::origin() }, Posn::origin()),
+            comments: None,
+            trace: None,
+            limits: Limits::new()
+        }
+    }
+
+    // Lexes text that begins at `posn` in some larger source, such as a
+    // function body being reparsed, giving tokens their positions in the
+    // whole source.
+    pub fn starting_at(chars: I, cx: Rc<Cell<Context>>, posn: Posn) -> Lexer<I> {
+        Lexer {
+            reader: Reader::starting_at(chars, posn),
+            cx: cx,
+            lookahead: Buffer::new(),
+            wordmap: WordMap::new(),
+            interner: Interner::current(),
+            started: posn != Posn::origin(),
+            token_start: posn,
+            last_token: (Span { start: posn, end: posn }, posn),
+            comments: None,
+            trace: None,
+            limits: Limits::new()
         }
     }
 
@@ -77,21 +184,149 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
 
     pub fn reread_token(&mut self) -> Token {
         debug_assert!(!self.lookahead.is_empty());
-        self.lookahead.read_token()
+        let token = self.lookahead.read_token();
+        self.consumed(&token);
+        token
     }
 
     pub fn read_token(&mut self) -> Result<Token> {
-        if self.lookahead.is_empty() {
-            self.read_next_token()
+        let token = if self.lookahead.is_empty() {
+            try!(self.read_next_token())
         } else {
-            Ok(self.lookahead.read_token())
-        }
+            self.lookahead.read_token()
+        };
+        self.consumed(&token);
+        Ok(token)
     }
 
+    // Puts back the last token read, or the unconsumed remainder of it.
     pub fn unread_token(&mut self, token: Token) {
+        let (last, before) = self.last_token;
+        let end = if token.location == last { before } else { token.location.start };
+        self.last_token = (Span { start: end, end: end }, end);
         self.lookahead.unread_token(token);
     }
 
+    fn consumed(&mut self, token: &Token) {
+        self.last_token = (token.location, self.last_token.0.end);
+    }
+
+    // The start of the next token, skipping any whitespace and comments
+    // before it.
+    pub fn next_token_start(&mut self) -> Posn {
+        if !self.lookahead.is_empty() {
+            return self.lookahead.peek_token().location.start;
+        }
+        if self.cx.get().jsx_mode == JSXMode::Text {
+            return self.posn();
+        }
+        let checkpoint = self.checkpoint();
+        let _ = self.skip_trivia();
+        let posn = self.posn();
+        self.rewind(checkpoint);
+        posn
+    }
+
+    // The end of the last token read.
+    pub fn last_token_end(&self) -> Posn {
+        self.last_token.0.end
+    }
+
+    // Speculative lexing: a checkpoint saves the lexer state, including any
+    // buffered token, so that everything read after it can be rewound.
+
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        Checkpoint {
+            reader: self.reader.checkpoint(),
+            lookahead: self.lookahead.save(),
+            started: self.started,
+            last_token: self.last_token,
+            tokens: self.limits.tokens,
+            comments: self.comments.as_ref().map_or(0, |comments| comments.len()),
+            trace: self.trace.as_ref().map_or(0, |trace| trace.len())
+        }
+    }
+
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.reader.rewind(checkpoint.reader);
+        self.lookahead.restore(checkpoint.lookahead);
+        self.started = checkpoint.started;
+        self.last_token = checkpoint.last_token;
+        self.limits.tokens = checkpoint.tokens;
+        if let Some(ref mut comments) = self.comments {
+            comments.truncate(checkpoint.comments);
+        }
+        if let Some(ref mut trace) = self.trace {
+            trace.truncate(checkpoint.trace);
+        }
+    }
+
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        self.reader.commit(checkpoint.reader);
+    }
+
+    // Error recovery: skips the character where lexing failed, so that the
+    // next token is read from just past it.
+    pub fn skip_char(&mut self) {
+        if self.peek().is_some() {
+            self.skip();
+        }
+    }
+
+    // Comments are discarded unless collection is enabled, in which case
+    // every comment skipped from here on is kept, in source order.
+
+    pub fn collect_comments(&mut self) {
+        if self.comments.is_none() {
+            self.comments = Some(Vec::new());
+        }
+    }
+
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        self.comments.as_mut().map_or(Vec::new(), |comments| replace(comments, Vec::new()))
+    }
+
+    // Tracing records everything read from here on, including trivia and
+    // errors. Only lexemes that survive rewinding are kept.
+
+    pub fn trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(Vec::new());
+        }
+    }
+
+    pub fn take_trace(&mut self) -> Vec<Lexeme> {
+        self.trace.as_mut().map_or(Vec::new(), |trace| replace(trace, Vec::new()))
+    }
+
+    // Resource limits. Once one is exceeded, the lexer stops: every later read
+    // fails with the same error, even after rewinding. Tokens read again after
+    // rewinding are only counted once. The length of the
+    // source is checked as each token starts, and the cancellation flag
+    // before each token is read.
+
+    pub fn max_source_len(&mut self, max_source_len: usize) {
+        self.limits.max_source_len = max_source_len;
+    }
+
+    pub fn max_tokens(&mut self, max_tokens: usize) {
+        self.limits.max_tokens = max_tokens;
+    }
+
+    pub fn cancel_on(&mut self, cancel: Arc<AtomicBool>) {
+        self.limits.cancel = Some(cancel);
+    }
+
+    fn push_lexeme(&mut self, lexeme: Lexeme) {
+        if let Some(ref mut trace) = self.trace {
+            trace.push(lexeme);
+        }
+    }
+
+    fn trivia_span(&self) -> Span {
+        Span { start: self.token_start, end: self.posn() }
+    }
+
     // source location
 
     pub fn posn(&self) -> Posn {
@@ -133,6 +368,14 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         self.skip();
     }
 
+    fn peek_str(&mut self, s: &str) -> bool {
+        let mut chars = s.chars();
+        if (chars.next(), chars.next()) != self.peek2() {
+            return false;
+        }
+        chars.enumerate().all(|(i, ch)| self.reader.char_ahead(i) == Some(ch))
+    }
+
     fn matches(&mut self, ch: char) -> bool {
         (self.peek() == Some(ch)) && { self.skip(); true }
     }
@@ -184,9 +427,9 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         }
     }
 
-    fn read_until_with<F, G>(&mut self, pred: &F, read: &mut G) -> Result<()>
+    fn read_until_with<F, G>(&mut self, pred: &F, read: &mut G) -> LexResult<()>
       where F: Fn(char) -> bool,
-            G: FnMut(&mut Self) -> Result<()>
+            G: FnMut(&mut Self) -> LexResult<()>
     {
         loop {
             match self.peek() {
@@ -224,34 +467,86 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         self.skip_while(&|ch| ch.is_es_whitespace());
     }
 
+    fn keeps_comments(&self) -> bool {
+        self.comments.is_some() || self.trace.is_some()
+    }
+
+    fn push_comment(&mut self, kind: CommentKind, text: String) {
+        let comment = Comment::new(kind, text, self.trivia_span());
+        if self.trace.is_some() {
+            self.push_lexeme(Lexeme::Comment(comment.clone()));
+        }
+        if let Some(ref mut comments) = self.comments {
+            comments.push(comment);
+        }
+    }
+
+    // Skips the rest of a single-line comment whose opening delimiter has
+    // already been skipped.
+    fn skip_comment_line(&mut self, kind: CommentKind) {
+        if self.keeps_comments() {
+            let mut text = String::new();
+            self.read_into_until(&mut text, &|ch| ch.is_es_newline());
+            self.push_comment(kind, text);
+        } else {
+            self.skip_until(&|ch| ch.is_es_newline());
+        }
+    }
+
     fn skip_line_comment(&mut self) {
         self.skip2();
-        self.skip_until(&|ch| ch.is_es_newline());
+        self.skip_comment_line(CommentKind::Line);
+    }
+
+    // B.1.3 HTML-like Comments
+    fn skip_html_comment(&mut self, len: usize) {
+        for _ in 0..len {
+            self.skip();
+        }
+        self.skip_comment_line(CommentKind::Html);
     }
 
-    fn skip_block_comment(&mut self) -> Result<bool> {
+    fn skip_hashbang_comment(&mut self) {
+        self.skip2();
+        self.skip_comment_line(CommentKind::Hashbang);
+    }
+
+    fn skip_block_comment(&mut self) -> LexResult<bool> {
         self.skip2();
         let mut found_newline = false;
+        let mut text = String::new();
+        let collecting = self.keeps_comments();
         loop {
             match self.peek2() {
-                (None, _) | (_, None)  => { return Err(Error::UnterminatedComment); }
+                (None, _)              => { return Err(ErrorData::UnterminatedComment); }
+                (Some(_), None)        => {
+                    self.skip();
+                    return Err(ErrorData::UnterminatedComment);
+                }
                 (Some('*'), Some('/')) => { self.skip2(); break; }
                 (Some(ch), _) => {
                     if ch.is_es_newline() {
                         found_newline = true;
                     }
+                    if collecting {
+                        text.push(ch);
+                    }
                     self.skip();
                 }
             }
         }
+        self.push_comment(CommentKind::Block, text);
         Ok(found_newline)
     }
 
-    fn read_regexp(&mut self) -> Result<Token> {
+    fn read_regexp(&mut self) -> LexResult<Token> {
         let span = self.start();
         let mut s = String::new();
         self.reread('/');
         try!(self.read_until_with(&|ch| ch == '/', &mut |this| { this.read_regexp_char(&mut s) }));
+        if self.peek().is_none() {
+            return Err(ErrorData::UnterminatedRegExp(None));
+        }
         self.reread('/');
         let flags = try!(self.read_word_parts());
         Ok(span.end(self, TokenData::RegExp(RegExpLiteral {
@@ -260,37 +555,40 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         })))
     }
 
-    fn read_regexp_char(&mut self, s: &mut String) -> Result<()> {
+    fn read_regexp_char(&mut self, s: &mut String) -> LexResult<()> {
         match self.peek() {
             Some('\\') => self.read_regexp_backslash(s),
             Some('[') => self.read_regexp_class(s),
-            Some(ch) if ch.is_es_newline() => Err(Error::UnterminatedRegExp(Some(ch))),
+            Some(ch) if ch.is_es_newline() => Err(ErrorData::UnterminatedRegExp(Some(ch))),
             Some(ch) => { s.push(self.reread(ch)); Ok(()) }
-            None => Err(Error::UnterminatedRegExp(None))
+            None => Err(ErrorData::UnterminatedRegExp(None))
         }
     }
 
-    fn read_regexp_backslash(&mut self, s: &mut String) -> Result<()> {
+    fn read_regexp_backslash(&mut self, s: &mut String) -> LexResult<()> {
         s.push(self.reread('\\'));
         match self.peek() {
-            Some(ch) if ch.is_es_newline() => Err(Error::UnterminatedRegExp(Some(ch))),
+            Some(ch) if ch.is_es_newline() => Err(ErrorData::UnterminatedRegExp(Some(ch))),
             Some(ch) => { s.push(self.reread(ch)); Ok(()) }
-            None => Err(Error::UnterminatedRegExp(None))
+            None => Err(ErrorData::UnterminatedRegExp(None))
         }
     }
 
-    fn read_regexp_class(&mut self, s: &mut String) -> Result<()> {
+    fn read_regexp_class(&mut self, s: &mut String) -> LexResult<()> {
         s.push(self.reread('['));
         try!(self.read_until_with(&|ch| ch == ']', &mut |this| { this.read_regexp_class_char(s) }));
+        if self.peek().is_none() {
+            return Err(ErrorData::UnterminatedRegExp(None));
+        }
         s.push(self.reread(']'));
         Ok(())
     }
 
-    fn read_regexp_class_char(&mut self, s: &mut String) -> Result<()> {
+    fn read_regexp_class_char(&mut self, s: &mut String) -> LexResult<()> {
         match self.peek() {
             Some('\\') => self.read_regexp_backslash(s),
             Some(ch) => { s.push(self.reread(ch)); Ok(()) }
-            None => Err(Error::UnterminatedRegExp(None))
+            None => Err(ErrorData::UnterminatedRegExp(None))
         }
     }
 
@@ -304,7 +602,7 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         s
     }
 
-    fn read_exp_part(&mut self) -> Result<Option<Exp>> {
+    fn read_exp_part(&mut self) -> LexResult<Option<Exp>> {
         let e = match self.peek() {
             Some('e') => CharCase::LowerCase,
             Some('E') => CharCase::UpperCase,
@@ -318,8 +616,8 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         };
         let mut value = String::new();
         match self.peek() {
-            Some(ch) if !ch.is_digit(10) => return Err(Error::MissingExponent(Some(ch))),
-            None => { return Err(Error::MissingExponent(None)); }
+            Some(ch) if !ch.is_digit(10) => return Err(ErrorData::MissingExponent(Some(ch))),
+            None => { return Err(ErrorData::MissingExponent(None)); }
             _ => ()
         }
         self.read_decimal_digits_into(&mut value);
@@ -332,7 +630,7 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         s
     }
 
-    fn read_radix_int<F, G>(&mut self, radix: u32, pred: &F, cons: &G, missing_digits: Error) -> Result<Token>
+    fn read_radix_int<F, G>(&mut self, radix: u32, pred: &F, cons: &G, missing_digits: ErrorData) -> LexResult<Token>
       where F: Fn(char) -> bool,
             G: Fn(CharCase, String) -> TokenData
     {
@@ -351,42 +649,45 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         Ok(span.end(self, cons(flag, s)))
     }
 
-    fn read_hex_int(&mut self) -> Result<Token> {
+    fn read_hex_int(&mut self) -> LexResult<Token> {
         self.read_radix_int(16, &|ch| ch.is_es_hex_digit(), &|cc, s| {
             NumberSource::RadixInt(Radix::Hex(cc), s).into_token_data()
-        }, Error::MissingHexDigits)
+        }, ErrorData::MissingHexDigits)
     }
 
-    fn read_oct_int(&mut self) -> Result<Token> {
+    fn read_oct_int(&mut self) -> LexResult<Token> {
         self.read_radix_int(8, &|ch| ch.is_es_oct_digit(), &|cc, s| {
             NumberSource::RadixInt(Radix::Oct(Some(cc)), s).into_token_data()
-        }, Error::MissingOctalDigits)
+        }, ErrorData::MissingOctalDigits)
     }
 
-    fn read_bin_int(&mut self) -> Result<Token> {
+    fn read_bin_int(&mut self) -> LexResult<Token> {
         self.read_radix_int(2, &|ch| ch.is_es_bin_digit(), &|cc, s| {
             NumberSource::RadixInt(Radix::Bin(cc), s).into_token_data()
-        }, Error::MissingBinaryDigits)
+        }, ErrorData::MissingBinaryDigits)
     }
 
-    fn read_deprecated_oct_int(&mut self) -> Token {
+    fn read_deprecated_oct_int(&mut self) -> LexResult<Token> {
+        if !self.cx.get().has_sloppy_annex_b() {
+            return Err(ErrorData::LegacyOctalNumber);
+        }
         let span = self.start();
         self.skip();
         let mut s = String::new();
         self.read_into_until(&mut s, &|ch| !ch.is_digit(10));
-        span.end(self, if s.chars().all(|ch| ch.is_es_oct_digit()) {
+        Ok(span.end(self, if s.chars().all(|ch| ch.is_es_oct_digit()) {
             NumberSource::RadixInt(Radix::Oct(None), s).into_token_data()
         } else {
             NumberSource::DecimalInt(format!("0{}", s), None).into_token_data()
-        })
+        }))
     }
 
-    fn read_number(&mut self) -> Result<Token> {
+    fn read_number(&mut self) -> LexResult<Token> {
         let result = try!(match self.peek2() {
             (Some('0'), Some('x')) | (Some('0'), Some('X')) => self.read_hex_int(),
             (Some('0'), Some('o')) | (Some('0'), Some('O')) => self.read_oct_int(),
             (Some('0'), Some('b')) | (Some('0'), Some('B')) => self.read_bin_int(),
-            (Some('0'), Some(ch)) if ch.is_digit(10) => Ok(self.read_deprecated_oct_int()),
+            (Some('0'), Some(ch)) if ch.is_digit(10) => self.read_deprecated_oct_int(),
             (Some('.'), _) => {
                 let span = self.start();
                 self.skip();
@@ -416,14 +717,14 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
             (None, _) => { panic!("read_number() called at EOF"); }
         });
         match self.peek() {
-            Some(ch) if ch.is_es_identifier_start() => { return Err(Error::IdAfterNumber(ch)); }
-            Some(ch) if ch.is_digit(10) => { return Err(Error::DigitAfterNumber(ch)); }
+            Some(ch) if ch.is_es_identifier_start() => { return Err(ErrorData::IdAfterNumber(ch)); }
+            Some(ch) if ch.is_digit(10) => { return Err(ErrorData::DigitAfterNumber(ch)); }
             _ => {}
         }
         Ok(result)
     }
 
-    fn read_string(&mut self) -> Result<Token> {
+    fn read_string(&mut self) -> LexResult<Token> {
         debug_assert!(self.peek().is_some());
         let span = self.start();
         let mut source = String::new();
@@ -441,14 +742,14 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
                     try!(self.read_string_escape(&mut source, &mut value));
                 }
                 Some(ch) if ch.is_es_newline() => {
-                    return Err(Error::UnterminatedString(Some(ch)));
+                    return Err(ErrorData::UnterminatedString(Some(ch)));
                 }
                 Some(_) => {
                     source.push(quote);
                     self.skip();
                     break;
                 }
-                None => return Err(Error::UnterminatedString(None))
+                None => return Err(ErrorData::UnterminatedString(None))
             }
         }
         Ok(span.end(self, TokenData::String(StringLiteral {
@@ -457,7 +758,7 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         })))
     }
 
-    fn read_unicode_escape_seq(&mut self, s: &mut String) -> Result<u32> {
+    fn read_unicode_escape_seq(&mut self, s: &mut String) -> LexResult<u32> {
         if self.matches('{') {
             s.push('{');
             let mut digits = Vec::with_capacity(8);
@@ -479,12 +780,21 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         }
     }
 
-    fn read_string_escape(&mut self, source: &mut String, value: &mut String) -> Result<()> {
+    fn read_string_escape(&mut self, source: &mut String, value: &mut String) -> LexResult<()> {
         source.push(self.reread('\\'));
         match self.peek() {
+            Some('0') if !self.reader.next_char().map_or(false, |ch| ch.is_digit(10)) => {
+                source.push(self.reread('0'));
+                value.push('\u{0}');
+            }
+            Some(ch) if ch.is_digit(10) && !self.cx.get().has_sloppy_annex_b() => {
+                return Err(ErrorData::LegacyOctalEscape);
+            }
+            // B.1.2 LegacyOctalEscapeSequence
             Some(ch) if ch.is_digit(8) => {
+                let max_len = if ch <= '3' { 3 } else { 2 };
                 let mut code = 0;
-                for _ in 0..3 {
+                for _ in 0..max_len {
                     match self.peek() {
                         Some(ch) if ch.is_digit(8) => {
                             source.push(self.reread(ch));
@@ -523,7 +833,7 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         Ok(())
     }
 
-    fn read_digit_into<F>(&mut self, s: &mut String, radix: u32, pred: &F, missing_digits: Error) -> Result<u32>
+    fn read_digit_into<F>(&mut self, s: &mut String, radix: u32, pred: &F, missing_digits: ErrorData) -> LexResult<u32>
       where F: Fn(char) -> bool
     {
         match self.peek() {
@@ -532,16 +842,16 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
                 debug_assert!(ch.is_digit(radix));
                 Ok(ch.to_digit(radix).unwrap())
             },
-            Some(ch) => Err(Error::InvalidDigit(ch)),
+            Some(ch) => Err(ErrorData::InvalidDigit(ch)),
             None => Err(missing_digits)
         }
     }
 
-    fn read_hex_digit_into(&mut self, s: &mut String) -> Result<u32> {
-        self.read_digit_into(s, 16, &|ch| ch.is_es_hex_digit(), Error::MissingHexDigits)
+    fn read_hex_digit_into(&mut self, s: &mut String) -> LexResult<u32> {
+        self.read_digit_into(s, 16, &|ch| ch.is_es_hex_digit(), ErrorData::MissingHexDigits)
     }
 
-    fn read_word_parts(&mut self) -> Result<String> {
+    fn read_word_parts(&mut self) -> LexResult<String> {
         let mut s = String::new();
         try!(self.read_until_with(&|ch| ch != '\\' && !ch.is_es_identifier_continue(), &mut |this| {
             match this.read() {
@@ -552,24 +862,24 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         Ok(s)
     }
 
-    fn read_word(&mut self) -> Result<Token> {
+    fn read_word(&mut self) -> LexResult<Token> {
         debug_assert!(self.peek().map_or(false, |ch| ch == '\\' || ch.is_es_identifier_start()));
         let span = self.start();
         let s = try!(self.read_word_parts());
         debug_assert!(s.len() > 0);
-        Ok(span.end(self, self.wordmap.tokenize(s)))
+        Ok(span.end(self, self.wordmap.tokenize(&s, &self.interner)))
     }
 
-    fn read_word_escape(&mut self, s: &mut String) -> Result<()> {
+    fn read_word_escape(&mut self, s: &mut String) -> LexResult<()> {
         match self.peek() {
             Some('u') => { self.reread('u'); }
-            cho => { return Err(Error::IncompleteWordEscape(cho)); }
+            cho => { return Err(ErrorData::IncompleteWordEscape(cho)); }
         }
         let mut dummy = String::new();
         let code_point = try!(self.read_unicode_escape_seq(&mut dummy));
         match char::from_u32(code_point) {
             Some(ch) => { s.push(ch); Ok(()) }
-            None => Err(Error::IllegalUnicode(code_point))
+            None => Err(ErrorData::IllegalUnicode(code_point))
         }
     }
 
@@ -592,26 +902,181 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
         span.end(self, value)
     }
 
+    fn read_jsx_identifier(&mut self) -> Token {
+        let span = self.start();
+        let mut s = String::new();
+        self.read_into_until(&mut s, &|ch| ch != '-' && !ch.is_es_identifier_continue());
+        span.end(self, TokenData::JSXIdentifier(s))
+    }
+
+    fn read_jsx_string(&mut self) -> LexResult<Token> {
+        let span = self.start();
+        let mut source = String::new();
+        let quote = self.read();
+        source.push(quote);
+        self.read_into_until(&mut source, &|ch| ch == quote);
+        if self.peek().is_none() {
+            return Err(ErrorData::UnterminatedString(None));
+        }
+        source.push(self.reread(quote));
+        let value = decode_jsx_entities(&source[1..source.len() - 1]);
+        Ok(span.end(self, TokenData::String(StringLiteral {
+            source: Some(source),
+            value: value
+        })))
+    }
+
+    fn read_jsx_tag_token(&mut self) -> LexResult<Token> {
+        match self.peek() {
+            Some('"') | Some('\'')                   => self.read_jsx_string(),
+            Some(ch) if ch.is_es_identifier_start() => Ok(self.read_jsx_identifier()),
+            Some('<')                               => Ok(self.read_punc(TokenData::LAngle)),
+            Some('>')                               => Ok(self.read_punc(TokenData::RAngle)),
+            Some('/')                               => Ok(self.read_punc(TokenData::Slash)),
+            Some('=')                               => Ok(self.read_punc(TokenData::Assign)),
+            Some('{')                               => Ok(self.read_punc(TokenData::LBrace)),
+            Some('}')                               => Ok(self.read_punc(TokenData::RBrace)),
+            Some(':')                               => Ok(self.read_punc(TokenData::Colon)),
+            Some('.')                               => Ok(self.read_punc(TokenData::Dot)),
+            Some(ch)                                => Err(ErrorData::IllegalChar(ch)),
+            None                                    => {
+                let here = self.posn();
+                Ok(Token::new(here, here, TokenData::EOF))
+            }
+        }
+    }
+
+    fn read_jsx_text(&mut self) -> LexResult<Token> {
+        match self.peek() {
+            Some('{')             => { return Ok(self.read_punc(TokenData::LBrace)); }
+            Some('<')             => { return Ok(self.read_punc(TokenData::LAngle)); }
+            Some(ch @ '>')
+          | Some(ch @ '}')        => { return Err(ErrorData::IllegalChar(ch)); }
+            None                  => {
+                let here = self.posn();
+                return Ok(Token::new(here, here, TokenData::EOF));
+            }
+            _                     => { }
+        }
+        let span = self.start();
+        let mut source = String::new();
+        self.read_into_until(&mut source, &|ch| ch == '{' || ch == '<' || ch == '>' || ch == '}');
+        let value = decode_jsx_entities(&source);
+        Ok(span.end(self, TokenData::JSXText(StringLiteral {
+            source: Some(source),
+            value: value
+        })))
+    }
+
     fn read_next_token(&mut self) -> Result<Token> {
-        let mut pair;
-        let mut found_newline = false;
+        match self.lex_token() {
+            Ok(token) => {
+                if self.trace.is_some() {
+                    self.push_lexeme(Lexeme::Token(token.clone()));
+                }
+                Ok(token)
+            }
+            Err(value) => {
+                let location = self.next_char_span();
+                let err = Error {
+                    value: value,
+                    token: Span { start: self.token_start, end: location.start },
+                    location: location
+                };
+                if self.trace.is_some() {
+                    self.push_lexeme(Lexeme::Error(err.clone()));
+                }
+                Err(err)
+            }
+        }
+    }
+
+    // The span of the next character, or an empty span at the end of input.
+    fn next_char_span(&mut self) -> Span {
+        let start = self.posn();
+        let end = self.peek().map_or(start, |ch| start.after(ch));
+        Span { start: start, end: end }
+    }
 
-        // Skip whitespace and comments.
+    // Skips whitespace and comments, returning whether they contained a
+    // line terminator.
+    fn skip_trivia(&mut self) -> LexResult<bool> {
+        let mut found_newline = false;
         loop {
-            pair = self.peek2();
-            match pair {
-                (Some(ch), _) if ch.is_es_whitespace() => { self.skip_whitespace(); }
+            self.token_start = self.posn();
+            match self.peek2() {
+                (Some(ch), _) if ch.is_es_whitespace() => {
+                    self.skip_whitespace();
+                    let span = self.trivia_span();
+                    self.push_lexeme(Lexeme::Whitespace(span));
+                }
                 (Some(ch), _) if ch.is_es_newline() => {
                     self.skip_newlines();
                     found_newline = true;
+                    let span = self.trivia_span();
+                    self.push_lexeme(Lexeme::Newline(span));
+                }
+                (Some('#'), Some('!')) if !self.started && self.posn().offset == 0 => {
+                    self.skip_hashbang_comment();
                 }
                 (Some('/'), Some('/')) => { self.skip_line_comment(); }
                 (Some('/'), Some('*')) => {
                     found_newline = try!(self.skip_block_comment()) || found_newline;
                 }
+                (Some('<'), Some('!')) if self.cx.get().has_annex_b() && self.peek_str("<!--") => {
+                    self.skip_html_comment(4);
+                }
+                (Some('-'), Some('-')) if self.cx.get().has_annex_b() &&
+                                          (found_newline || !self.started) &&
+                                          self.peek_str("-->") => {
+                    self.skip_html_comment(3);
+                }
                 _ => { break; }
             }
         }
+        Ok(found_newline)
+    }
+
+    fn check_limits(&mut self) -> LexResult<()> {
+        if self.limits.stopped.is_none() {
+            self.limits.tokens += 1;
+            self.limits.stopped = if self.posn().byte as usize > self.limits.max_source_len {
+                Some(ErrorData::SourceTooLong)
+            } else if self.limits.tokens > self.limits.max_tokens {
+                Some(ErrorData::TooManyTokens)
+            } else if self.limits.cancel.as_ref().map_or(false, |cancel| cancel.load(Ordering::Relaxed)) {
+                Some(ErrorData::Cancelled)
+            } else {
+                None
+            };
+        }
+        match self.limits.stopped {
+            Some(ref err) => Err(err.clone()),
+            None          => Ok(())
+        }
+    }
+
+    fn lex_token(&mut self) -> LexResult<Token> {
+        self.token_start = self.posn();
+
+        // JSX text is significant whitespace and all.
+        if self.cx.get().jsx_mode == JSXMode::Text {
+            try!(self.check_limits());
+            return self.read_jsx_text();
+        }
+
+        let found_newline = try!(self.skip_trivia());
+        self.started = true;
+
+        self.token_start = self.posn();
+        try!(self.check_limits());
+        let pair = self.peek2();
+
+        if self.cx.get().jsx_mode == JSXMode::Tag {
+            let mut result = try!(self.read_jsx_tag_token());
+            result.newline = found_newline;
+            return Ok(result);
+        }
 
         let mut result = try!(match pair {
             (Some('/'), _) if !self.cx.get().operator    => self.read_regexp(),
@@ -620,8 +1085,15 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
             }
             (Some('/'), _)                               => Ok(self.read_punc(TokenData::Slash)),
             (Some('.'), Some(ch)) if ch.is_digit(10)     => self.read_number(),
+            (Some('.'), Some('.')) if self.peek_str("...") => Ok({
+                let span = self.start();
+                self.skip2();
+                self.skip();
+                span.end(self, TokenData::Ellipsis)
+            }),
             (Some('.'), _)                               => Ok(self.read_punc(TokenData::Dot)),
             (Some('{'), _)                               => Ok(self.read_punc(TokenData::LBrace)),
+            (Some('@'), _) if self.cx.get().decorators   => Ok(self.read_punc(TokenData::At)),
             (Some('}'), _)                               => Ok(self.read_punc(TokenData::RBrace)),
             (Some('['), _)                               => Ok(self.read_punc(TokenData::LBrack)),
             (Some(']'), _)                               => Ok(self.read_punc(TokenData::RBrack)),
@@ -651,6 +1123,7 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
             (Some('='), Some('='))                       => {
                 Ok(self.read_punc2_3('=', TokenData::Eq, TokenData::StrictEq))
             }
+            (Some('='), Some('>'))                       => Ok(self.read_punc2(TokenData::Arrow)),
             (Some('='), _)                               => Ok(self.read_punc(TokenData::Assign)),
             (Some('+'), Some('+'))                       => Ok(self.read_punc2(TokenData::Inc)),
             (Some('+'), Some('='))                       => {
@@ -698,7 +1171,7 @@ impl<I> Lexer<I> where I: Iterator<Item=char> {
             (Some(ch), _) if ch.is_digit(10)             => self.read_number(),
             (Some(ch), _) if ch.is_es_identifier_start() => self.read_word(),
             (Some('\\'), _)                              => self.read_word(),
-            (Some(ch), _)                                => Err(Error::IllegalChar(ch)),
+            (Some(ch), _)                                => Err(ErrorData::IllegalChar(ch)),
             (None, _)                                    => {
                 let here = self.posn();
                 Ok(Token::new(here, here, TokenData::EOF))
@@ -727,10 +1200,21 @@ mod tests {
     use test::{deserialize_lexer_tests, LexerTest};
     use lexer::Lexer;
     use result::Result;
-    use context::Context;
+    use context::{Context, Mode};
     use token::{Token, TokenData};
+    use comment::CommentKind;
+    use lines::LineIndex;
+    use slice::{SliceLexer, SliceToken, SliceData};
+    use word::{Atom, Name, ATOMS};
+    use std::thread;
+    use intern;
+    use intern::{Interner, Symbol};
+    use error::{Error, ErrorData};
     use std::cell::Cell;
     use std::rc::Rc;
+    use std::borrow::Cow;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
     use std;
 
     fn lex2(source: &String, context: Context) -> Result<(Token, Token)> {
@@ -773,4 +1257,277 @@ mod tests {
         }
     }
 
+    fn lex_error(source: &str) -> Error {
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new(source.chars(), cx);
+        loop {
+            match lexer.read_token() {
+                Ok(Token { value: TokenData::EOF, .. }) => panic!("expected lexer error"),
+                Ok(_)                                   => { }
+                Err(err)                                => { return err; }
+            }
+        }
+    }
+
+    #[test]
+    pub fn error_locations() {
+        let err = lex_error("x = 'abc\ny';");
+        assert_eq!(err.value, ErrorData::UnterminatedString(Some('\n')));
+        assert_eq!((err.token.start.line, err.token.start.column), (0, 4));
+        assert_eq!((err.location.start.line, err.location.start.column), (0, 8));
+        assert_eq!(err.token.end, err.location.start);
+
+        let err = lex_error("a;\n  /* b");
+        assert_eq!(err.value, ErrorData::UnterminatedComment);
+        assert_eq!((err.token.start.line, err.token.start.column), (1, 2));
+        assert_eq!(err.location.start, err.location.end);
+
+        let err = lex_error("f(1, #)");
+        assert_eq!(err.value, ErrorData::IllegalChar('#'));
+        assert_eq!((err.location.start.offset, err.location.end.offset), (5, 6));
+    }
+
+    #[test]
+    pub fn positions() {
+        let source = "é = '😀'\r\nx";
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new(source.chars(), cx);
+        let spans: Vec<_> = (0..4).map(|_| lexer.read_token().unwrap().location).collect();
+        let units: Vec<_> = spans.iter().map(|s| (s.start.offset, s.start.byte, s.start.utf16, s.end.byte, s.end.utf16)).collect();
+        assert_eq!(units, vec![(0, 0, 0, 2, 1), (2, 3, 2, 4, 3), (4, 5, 4, 11, 8), (9, 13, 10, 14, 11)]);
+        assert_eq!(spans[2].slice(source), "'😀'");
+        assert_eq!(&source[spans[3].byte_range()], "x");
+        assert_eq!((spans[3].start.line, spans[3].start.column), (1, 0));
+    }
+
+    #[test]
+    pub fn line_index() {
+        let source = "a\r\nbé\rc\n\u{2028}d\u{2029}😀 e\r\n\r\nf";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 8);
+
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new(source.chars(), cx);
+        let mut posns = vec![];
+        loop {
+            let token = lexer.read_token().unwrap_or_else(|_| { lexer.skip_char(); lexer.read_token().unwrap() });
+            posns.push(token.location.start);
+            posns.push(token.location.end);
+            if token.value == TokenData::EOF {
+                break;
+            }
+        }
+        for posn in posns {
+            assert_eq!(index.posn_at_byte(posn.byte as usize), Some(posn));
+            assert_eq!(index.posn_at_utf16(posn.utf16 as usize), Some(posn));
+            assert_eq!(index.posn_at_char(posn.offset as usize), Some(posn));
+            assert_eq!(index.posn_at_line_column(posn.line as usize, posn.column as usize), Some(posn));
+        }
+        assert_eq!(index.end().byte as usize, source.len());
+
+        // Inside "é" and "😀", and past the end.
+        assert_eq!(index.posn_at_byte(5), None);
+        assert_eq!(index.posn_at_utf16(12), None);
+        assert_eq!(index.posn_at_byte(source.len() + 1), None);
+
+        let e = index.posn_at_line_utf16_column(5, 3).unwrap();
+        assert_eq!((e.offset, e.line, e.column), (13, 5, 2));
+        assert_eq!(&source[e.byte as usize..], "e\r\n\r\nf");
+        assert_eq!(index.posn_at_line_column(1, 9), None);
+        assert_eq!(index.line_start(8), None);
+    }
+
+    #[test]
+    pub fn comments() {
+        let source = "#!/usr/bin/env node\n/** doc\n */ a // x\n<!-- y\n--> z\nb /*@__PURE__*/";
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new(source.chars(), cx);
+        lexer.collect_comments();
+        while lexer.read_token().unwrap().value != TokenData::EOF { }
+        let comments = lexer.take_comments();
+        let actual: Vec<(CommentKind, &str)> = comments.iter().map(|c| (c.kind, &c.text[..])).collect();
+        assert_eq!(actual, vec![(CommentKind::Hashbang, "/usr/bin/env node"),
+                                (CommentKind::Block, "* doc\n "),
+                                (CommentKind::Line, " x"),
+                                (CommentKind::Html, " y"),
+                                (CommentKind::Html, " z"),
+                                (CommentKind::Block, "@__PURE__")]);
+        assert!(comments[1].is_multiline());
+        assert_eq!((comments[2].location.start.line, comments[2].location.start.column), (2, 6));
+        assert_eq!((comments[2].location.end.line, comments[2].location.end.column), (2, 10));
+
+        // Comments read during a rewound lookahead are not duplicated.
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new("a /* 1 */ b /* 2 */ c".chars(), cx);
+        lexer.collect_comments();
+        lexer.read_token().unwrap();
+        let checkpoint = lexer.checkpoint();
+        lexer.read_token().unwrap();
+        lexer.read_token().unwrap();
+        lexer.rewind(checkpoint);
+        while lexer.read_token().unwrap().value != TokenData::EOF { }
+        assert_eq!(lexer.take_comments().len(), 2);
+
+        // Nor are the tokens counted against the limit.
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut lexer = Lexer::new("a b c".chars(), cx);
+        lexer.max_tokens(4);
+        for _ in 0..3 {
+            let checkpoint = lexer.checkpoint();
+            lexer.read_token().unwrap();
+            lexer.read_token().unwrap();
+            lexer.rewind(checkpoint);
+        }
+        while lexer.read_token().unwrap().value != TokenData::EOF { }
+
+        assert!(lex2(&"a #!x".to_string(), Context::new(Mode::Sloppy)).is_err());
+    }
+
+    #[test]
+    pub fn slice_lexer() {
+        let tests = deserialize_lexer_tests(include_str!("../tests/unit.json"));
+        for LexerTest { source, context, .. } in tests {
+            for source in vec![format!(" {} ", source), format!("{};\n{}\u{2028}/*é*/{}", source, source, source)] {
+                let mut lexer = Lexer::new(source.chars(), Rc::new(Cell::new(context)));
+                let mut slice = SliceLexer::new(&source, Rc::new(Cell::new(context)));
+                loop {
+                    let expected = lexer.read_token();
+                    assert_eq!(slice.read_token().map(SliceToken::into_token), expected, "{:?}", source);
+                    match expected {
+                        Ok(Token { value: TokenData::EOF, .. }) | Err(_) => { break; }
+                        _ => { }
+                    }
+                }
+            }
+        }
+
+        fn borrowed(cow: &Cow<str>) -> bool {
+            match *cow {
+                Cow::Borrowed(_) => true,
+                Cow::Owned(_)    => false
+            }
+        }
+
+        let source = "x = 'abc' + /[/]+/g + 0x1F + \"\\n\" + café";
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let tokens: Vec<_> = SliceLexer::new(source, cx).map(|token| token.value).collect();
+        assert_eq!(tokens.len(), 11);
+        match (&tokens[0], &tokens[2], &tokens[4], &tokens[6], &tokens[8], &tokens[10]) {
+            (&SliceData::Identifier(ref x),
+             &SliceData::String(ref abc_source, ref abc),
+             &SliceData::RegExp(ref pattern, ref flags),
+             &SliceData::Number(number, value),
+             &SliceData::String(ref newline_source, ref newline),
+             &SliceData::Identifier(ref cafe)) => {
+                assert!(borrowed(x) && borrowed(abc_source) && borrowed(abc));
+                assert!(borrowed(pattern) && borrowed(flags));
+                assert_eq!((&pattern[..], &flags[..]), ("[/]+", "g"));
+                assert_eq!((number, value), ("0x1F", 31.0));
+                assert_eq!((&newline_source[..], &newline[..]), ("\"\\n\"", "\n"));
+                assert!(borrowed(newline_source) && !borrowed(newline));
+                // Read by the fallback lexer, so owned.
+                assert_eq!(&cafe[..], "café");
+                assert!(!borrowed(cafe));
+            }
+            _ => panic!("unexpected tokens: {:?}", tokens)
+        }
+    }
+
+    #[test]
+    pub fn raw_literals() {
+        let source = "0 42 1e3 1E+3 2e-3 1. 1.50 .5 .5e10 0x1F 0XaB 0o17 0O7 0b101 0B1 017 019 09 \
+                      99999999999999999999 0x1ffffffffffffffff 'a' \"b\" 'it\\'s' \"\\x41\\u{1F600}\\n\"";
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let mut count = 0;
+        for token in Lexer::new(source.chars(), cx.clone()) {
+            let raw = &source[token.location.byte_range()];
+            match token.value {
+                TokenData::Number(ref lit) => { assert_eq!(lit.raw().unwrap(), raw); }
+                TokenData::String(ref lit) => { assert_eq!(lit.raw().unwrap(), raw); }
+                TokenData::EOF             => { break; }
+                _                          => panic!("unexpected token: {:?}", token)
+            }
+            count += 1;
+        }
+        assert_eq!(count, 24);
+        let tokens: Vec<Token> = SliceLexer::new(source, cx).map(SliceToken::into_token).collect();
+        assert_eq!(tokens, Lexer::new(source.chars(), Rc::new(Cell::new(Context::new(Mode::Sloppy)))).collect::<Vec<_>>());
+        match tokens[18].value {
+            TokenData::Number(ref lit) => { assert_eq!(lit.value, 1e20); }
+            _ => panic!("expected a number")
+        }
+    }
+
+    #[test]
+    fn interning() {
+        let source = "foo.bar = foo + let";
+        let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+        let names: Vec<Name> = Lexer::new(source.chars(), cx).filter_map(|token| match token.value {
+            TokenData::Identifier(name) => Some(name),
+            _ => None
+        }).collect();
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[0], names[2]);
+        assert_eq!(names[0].symbol(), Symbol::intern("foo"));
+        assert_eq!(&names[1].text().unwrap()[..], "bar");
+        assert_eq!(names[3], Name::Atom(Atom::Let));
+
+        // Atoms are the first entries of every table, and are never stored as
+        // plain symbols.
+        for (i, &atom) in ATOMS.iter().enumerate() {
+            assert_eq!(atom as usize, i);
+            assert_eq!(Symbol::intern(atom.name()), Symbol::from(atom));
+        }
+        assert_eq!(&Symbol::from(Atom::Yield).text().unwrap()[..], "yield");
+        assert_eq!(Symbol::intern("yield").atom(), Some(Atom::Yield));
+        assert_eq!(Name::from("yield"), Name::Atom(Atom::Yield));
+        assert_eq!(intern::lookup("an identifier nobody has interned"), None);
+        assert!(Symbol::intern("foo").index() < Interner::current().len());
+
+        // A parse within an interner of its own keeps its names there, where
+        // they can still be read from other threads.
+        let interner = Interner::new();
+        let names: Vec<Name> = interner.enter(|| {
+            let cx = Rc::new(Cell::new(Context::new(Mode::Sloppy)));
+            Lexer::new("baz.let".chars(), cx).filter_map(|token| match token.value {
+                TokenData::Identifier(name) => Some(name),
+                _ => None
+            }).collect()
+        });
+        assert_eq!(names[0].symbol(), interner.lookup("baz").unwrap());
+        assert_eq!(names[1], Name::Atom(Atom::Let));
+        assert_eq!(intern::lookup("baz"), None);
+        let name = names[0];
+        assert_eq!(thread::spawn(move || name.into_string()).join().unwrap(), "baz");
+
+        // The same name is equal, and hashes alike, in every interner.
+        let baz = Symbol::intern("baz");
+        assert!(names[0].symbol() == baz && names[0] == Name::from("baz"));
+        assert_eq!(hash(&names[0].symbol()), hash(&baz));
+        assert!(names[0].symbol() != Symbol::intern("bar"));
+
+        // Once its interner is dropped, a symbol has no text, and only equals
+        // symbols of the same interner.
+        drop(interner);
+        assert_eq!(names[0].text(), None);
+        assert!(format!("{:?}", names[0]).contains("dropped symbol"));
+        assert_eq!(names[0], names[0]);
+        assert!(names[0].symbol() != baz);
+        assert_eq!(names[1].text().map(|text| text.to_string()), Some("let".to_string()));
+
+        // A reset leaves the names of the old default to whoever holds them.
+        // Other tests may still be using it, so it is never freed here.
+        let old = Interner::current();
+        Interner::reset_default();
+        assert_eq!(intern::lookup("baz"), None);
+        assert_eq!(Symbol::intern("baz"), baz);
+        assert_eq!(&baz.text().unwrap()[..], "baz");
+        std::mem::forget(old);
+
+        fn hash<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+    }
 }
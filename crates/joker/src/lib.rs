@@ -2,15 +2,22 @@ extern crate serde_json;
 extern crate unjson;
 
 pub mod word;
+pub mod intern;
 pub mod token;
+pub mod comment;
+pub mod lexeme;
 pub mod lexer;
+pub mod slice;
 pub mod context;
 mod char;
+mod entities;
 mod reader;
 mod lookahead;
 mod test;
 pub mod track;
+pub mod lines;
 pub mod error;
 pub mod result;
 
 pub use lexer::Lexer;
+pub use slice::SliceLexer;
@@ -0,0 +1,129 @@
+use track::*;
+
+// A table of line starts for a source text, built in a single pass, for
+// converting between offsets and positions without rescanning the source.
+// Lines are split exactly as the lexer splits them: at "\r\n", "\r", "\n",
+// U+2028 and U+2029.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    // The position at which each line starts.
+    lines: Vec<Posn>,
+    // The span of every non-ASCII character, whose width differs by unit.
+    wide: Vec<Span>,
+    end: Posn
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> LineIndex {
+        let mut lines = vec![Posn::origin()];
+        let mut wide = Vec::new();
+        let mut posn = Posn::origin();
+        let mut chars = source.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let start = posn;
+            posn = start.after(ch);
+            if (ch == '\r' && chars.peek() != Some(&'\n')) ||
+               ch == '\n' ||
+               ch == '\u{2028}' ||
+               ch == '\u{2029}' {
+                posn.line += 1;
+                posn.column = 0;
+                lines.push(posn);
+            }
+            if ch.len_utf8() > 1 {
+                wide.push(Span { start: start, end: posn });
+            }
+        }
+        LineIndex { lines: lines, wide: wide, end: posn }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn line_start(&self, line: usize) -> Option<Posn> {
+        self.lines.get(line).cloned()
+    }
+
+    // The position at the end of the source.
+    pub fn end(&self) -> Posn {
+        self.end
+    }
+
+    // Each of the following returns `None` for an offset past the end of the
+    // source or inside a character.
+
+    pub fn posn_at_byte(&self, byte: usize) -> Option<Posn> {
+        self.locate(byte, |posn| posn.byte)
+    }
+
+    pub fn posn_at_utf16(&self, utf16: usize) -> Option<Posn> {
+        self.locate(utf16, |posn| posn.utf16)
+    }
+
+    pub fn posn_at_char(&self, offset: usize) -> Option<Posn> {
+        self.locate(offset, |posn| posn.offset)
+    }
+
+    // Looks up a 0-based line and column, with the column counted in
+    // characters. Returns `None` if the column is past the end of the line.
+    pub fn posn_at_line_column(&self, line: usize, column: usize) -> Option<Posn> {
+        let start = match self.line_start(line) {
+            Some(start) => start,
+            None        => { return None; }
+        };
+        self.locate(start.offset as usize + column, |posn| posn.offset)
+            .and_then(|posn| if posn.line as usize == line { Some(posn) } else { None })
+    }
+
+    // Like `posn_at_line_column`, but with the column counted in UTF-16 code
+    // units, as in LSP positions.
+    pub fn posn_at_line_utf16_column(&self, line: usize, column: usize) -> Option<Posn> {
+        let start = match self.line_start(line) {
+            Some(start) => start,
+            None        => { return None; }
+        };
+        self.locate(start.utf16 as usize + column, |posn| posn.utf16)
+            .and_then(|posn| if posn.line as usize == line { Some(posn) } else { None })
+    }
+
+    // Binary searches for the nearest known position at or before `target`,
+    // measured in the unit given by `key`. Everything between that position
+    // and the target is ASCII on a single line, so all units advance alike.
+    fn locate<F>(&self, target: usize, key: F) -> Option<Posn>
+      where F: Fn(&Posn) -> u32
+    {
+        if target > key(&self.end) as usize {
+            return None;
+        }
+        let target = target as u32;
+
+        let line = match self.lines.binary_search_by(|posn| key(posn).cmp(&target)) {
+            Ok(i)  => i,
+            Err(i) => i - 1
+        };
+        let mut base = self.lines[line];
+
+        let i = match self.wide.binary_search_by(|span| key(&span.start).cmp(&target)) {
+            Ok(i) | Err(i) => i
+        };
+        if i > 0 {
+            let prev = self.wide[i - 1];
+            if key(&prev.end) > target {
+                return None;
+            }
+            if key(&prev.end) >= key(&base) {
+                base = prev.end;
+            }
+        }
+
+        let delta = target - key(&base);
+        Some(Posn {
+            offset: base.offset + delta,
+            byte: base.byte + delta,
+            utf16: base.utf16 + delta,
+            line: base.line,
+            column: base.column + delta
+        })
+    }
+}
@@ -44,6 +44,14 @@ impl Buffer {
         self.token.as_ref().unwrap()
     }
 
+    pub fn save(&self) -> Option<Token> {
+        self.token.clone()
+    }
+
+    pub fn restore(&mut self, token: Option<Token>) {
+        self.token = token;
+    }
+
     pub fn unread_token(&mut self, token: Token) {
         //assert!(self.tokens.len() < 3);
         //self.tokens.push_front(token);
@@ -1,10 +1,22 @@
+use std::collections::VecDeque;
 use track::Posn;
 
 pub struct Reader<I> {
     chars: I,
     curr_char: Option<char>,
     next_char: Option<char>,
-    curr_posn: Posn
+    ahead: VecDeque<char>,
+    curr_posn: Posn,
+    log: Vec<char>,      // characters consumed since the oldest open checkpoint
+    checkpoints: usize
+}
+
+// A saved reader position that can be returned to, for speculative parsing.
+pub struct Checkpoint {
+    curr_char: Option<char>,
+    next_char: Option<char>,
+    curr_posn: Posn,
+    log_len: usize
 }
 
 impl<I> Reader<I> where I: Iterator<Item=char> {
@@ -15,25 +27,89 @@ impl<I> Reader<I> where I: Iterator<Item=char> {
             chars: chars,
             curr_char: curr_char,
             next_char: next_char,
-            curr_posn: Posn::origin()
+            ahead: VecDeque::new(),
+            curr_posn: Posn::origin(),
+            log: Vec::new(),
+            checkpoints: 0
         }
     }
 
+    // A reader for text that begins at `posn` in some larger source.
+    pub fn starting_at(chars: I, posn: Posn) -> Reader<I> {
+        let mut reader = Reader::new(chars);
+        reader.curr_posn = posn;
+        reader
+    }
+
     pub fn curr_char(&mut self) -> Option<char> { self.curr_char }
     pub fn curr_posn(&self) -> Posn { self.curr_posn }
     pub fn next_char(&mut self) -> Option<char> { self.next_char }
 
+    // Peeks at the nth character after the next character, buffering as needed.
+    pub fn char_ahead(&mut self, n: usize) -> Option<char> {
+        if self.next_char.is_none() {
+            return None;
+        }
+        while self.ahead.len() <= n {
+            match self.chars.next() {
+                Some(ch) => { self.ahead.push_back(ch); }
+                None     => { return None; }
+            }
+        }
+        Some(self.ahead[n])
+    }
+
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.checkpoints += 1;
+        Checkpoint {
+            curr_char: self.curr_char,
+            next_char: self.next_char,
+            curr_posn: self.curr_posn,
+            log_len: self.log.len()
+        }
+    }
+
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        for ch in self.log.drain(checkpoint.log_len..).rev() {
+            self.ahead.push_front(ch);
+        }
+        self.curr_char = checkpoint.curr_char;
+        self.next_char = checkpoint.next_char;
+        self.curr_posn = checkpoint.curr_posn;
+        self.commit(checkpoint);
+    }
+
+    pub fn commit(&mut self, _: Checkpoint) {
+        self.checkpoints -= 1;
+        if self.checkpoints == 0 {
+            self.log.clear();
+        }
+    }
+
     pub fn skip(&mut self) {
         let curr_char = self.next_char;
-        let next_char = if curr_char.is_some() { self.chars.next() } else { None };
+        let next_char = if curr_char.is_some() {
+            self.ahead.pop_front().or_else(|| self.chars.next())
+        } else {
+            None
+        };
 
+        if self.checkpoints > 0 {
+            if let Some(ch) = next_char {
+                self.log.push(ch);
+            }
+        }
+
+        // The position moves past the character being left behind, so a line
+        // starts after its terminator (counting "\r\n" once).
+        let prev_char = self.curr_char;
         self.curr_char = curr_char;
         self.next_char = next_char;
 
-        if (curr_char == Some('\r') && next_char != Some('\n')) ||
-           curr_char == Some('\n') ||
-           curr_char == Some('\u{2028}') ||
-           curr_char == Some('\u{2029}') {
+        if (prev_char == Some('\r') && curr_char != Some('\n')) ||
+           prev_char == Some('\n') ||
+           prev_char == Some('\u{2028}') ||
+           prev_char == Some('\u{2029}') {
             self.curr_posn.line += 1;
             self.curr_posn.column = 0;
         } else {
@@ -41,5 +117,9 @@ impl<I> Reader<I> where I: Iterator<Item=char> {
         }
 
         self.curr_posn.offset += 1;
+        if let Some(ch) = prev_char {
+            self.curr_posn.byte += ch.len_utf8() as u32;
+            self.curr_posn.utf16 += ch.len_utf16() as u32;
+        }
     }
 }
@@ -0,0 +1,634 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::char;
+use std::rc::Rc;
+
+use track::*;
+use token::{Token, TokenData, NumberSource, NumberLiteral, StringLiteral, RegExpLiteral};
+use word::{Map as WordMap, Name};
+use context::{Context, JSXMode};
+use char::ESCharExt;
+use lexer::Lexer;
+use error::{Error, ErrorData};
+use result::Result;
+
+// A token read by a `SliceLexer`, borrowing its text from the source where
+// it can.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SliceToken<'src> {
+    pub location: Span,
+    pub newline: bool,
+    pub value: SliceData<'src>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SliceData<'src> {
+    // An identifier that is not a reserved word or contextual keyword.
+    Identifier(Cow<'src, str>),
+    // A string literal's source, quotes included, and its value.
+    String(Cow<'src, str>, Cow<'src, str>),
+    // A number literal's source and its value.
+    Number(&'src str, f64),
+    // A regular expression literal's pattern and flags.
+    RegExp(Cow<'src, str>, Cow<'src, str>),
+    // Everything else: punctuators, reserved words, contextual keywords, the
+    // end of input, and JSX tokens, which own their text.
+    Other(TokenData)
+}
+
+impl<'src> SliceToken<'src> {
+    // The token `Lexer` would have read.
+    pub fn into_token(self) -> Token {
+        let value = match self.value {
+            SliceData::Identifier(name) => TokenData::Identifier(Name::from(&name[..])),
+            SliceData::String(source, value) => TokenData::String(StringLiteral {
+                source: Some(source.into_owned()),
+                value: value.into_owned()
+            }),
+            SliceData::Number(source, value) => TokenData::Number(NumberLiteral {
+                source: Some(NumberSource::parse(source)),
+                value: value
+            }),
+            SliceData::RegExp(pattern, flags) => TokenData::RegExp(RegExpLiteral {
+                pattern: pattern.into_owned(),
+                flags: flags.chars().collect()
+            }),
+            SliceData::Other(value) => value
+        };
+        Token { location: self.location, newline: self.newline, value: value }
+    }
+}
+
+// A lexer over source text held in memory. Tokens without escapes borrow
+// their text from the source, and whitespace, comments and ASCII tokens are
+// scanned a byte at a time. Anything less common (identifier escapes,
+// non-ASCII identifiers, octal escapes and literals, JSX, errors) is handed
+// to `Lexer`, so the two always agree. Comments are skipped, never collected.
+pub struct SliceLexer<'src> {
+    source: &'src str,
+    cx: Rc<Cell<Context>>,
+    wordmap: WordMap,
+    started: bool,
+    posn: Posn
+}
+
+impl<'src> SliceLexer<'src> {
+    pub fn new(source: &'src str, cx: Rc<Cell<Context>>) -> SliceLexer<'src> {
+        SliceLexer {
+            source: source,
+            cx: cx,
+            wordmap: WordMap::new(),
+            started: false,
+            posn: Posn::origin()
+        }
+    }
+
+    pub fn posn(&self) -> Posn {
+        self.posn
+    }
+
+    pub fn read_token(&mut self) -> Result<SliceToken<'src>> {
+        let cx = self.cx.get();
+
+        // JSX text is significant whitespace and all.
+        if cx.jsx_mode == JSXMode::Text {
+            return self.fallback();
+        }
+
+        let found_newline = try!(self.skip_trivia());
+        self.started = true;
+
+        let mut result = try!(if cx.jsx_mode == JSXMode::Tag {
+            self.fallback()
+        } else {
+            self.lex_token(cx)
+        });
+        result.newline = result.newline || found_newline;
+        Ok(result)
+    }
+
+    // Error recovery: skips the character where lexing failed.
+    pub fn skip_char(&mut self) {
+        let here = self.here();
+        if let Some(ch) = self.source[here..].chars().next() {
+            self.advance_to(here + ch.len_utf8());
+        }
+    }
+
+    // source location
+
+    fn here(&self) -> usize {
+        self.posn.byte as usize
+    }
+
+    fn byte_at(&self, i: usize) -> Option<u8> {
+        self.source.as_bytes().get(i).cloned()
+    }
+
+    // The length of the line terminator at byte `i`, or 0 if there is none.
+    fn newline_at(&self, i: usize) -> usize {
+        let bytes = self.source.as_bytes();
+        match bytes.get(i) {
+            Some(&b'\n') | Some(&b'\r') => 1,
+            Some(&0xE2) if bytes.get(i + 1) == Some(&0x80) &&
+                           (bytes.get(i + 2) == Some(&0xA8) || bytes.get(i + 2) == Some(&0xA9)) => 3,
+            _ => 0
+        }
+    }
+
+    // Moves past `len` bytes of ASCII text with no line terminators.
+    fn bump(&mut self, len: usize) {
+        let len = len as u32;
+        self.posn.offset += len;
+        self.posn.byte += len;
+        self.posn.utf16 += len;
+        self.posn.column += len;
+    }
+
+    // Moves to byte `end`, counting characters and lines as the reader does,
+    // and returns whether a line terminator was passed.
+    fn advance_to(&mut self, end: usize) -> bool {
+        let bytes = self.source.as_bytes();
+        let mut posn = self.posn;
+        let mut found_newline = false;
+        let mut i = posn.byte as usize;
+        while i < end {
+            let b = bytes[i];
+            i += 1;
+            // Only the first byte of a character counts; four-byte characters
+            // take two UTF-16 units.
+            if b & 0xC0 != 0x80 {
+                posn.offset += 1;
+                posn.utf16 += 1;
+                posn.column += 1;
+            }
+            if b >= 0xF0 {
+                posn.utf16 += 1;
+            }
+            let newline = match b {
+                b'\n'       => true,
+                b'\r'       => bytes.get(i) != Some(&b'\n'),
+                0xA8 | 0xA9 => i >= 3 && bytes[i - 3] == 0xE2 && bytes[i - 2] == 0x80,
+                _           => false
+            };
+            if newline {
+                posn.line += 1;
+                posn.column = 0;
+                found_newline = true;
+            }
+        }
+        posn.byte = end as u32;
+        self.posn = posn;
+        found_newline
+    }
+
+    fn token(&self, start: Posn, value: SliceData<'src>) -> SliceToken<'src> {
+        SliceToken {
+            location: Span { start: start, end: self.posn },
+            newline: false,
+            value: value
+        }
+    }
+
+    // A token of `len` bytes of ASCII text.
+    fn ascii_token(&mut self, len: usize, value: SliceData<'src>) -> SliceToken<'src> {
+        let start = self.posn;
+        self.bump(len);
+        self.token(start, value)
+    }
+
+    // A token ending at byte `end`.
+    fn token_to(&mut self, end: usize, value: SliceData<'src>) -> SliceToken<'src> {
+        let start = self.posn;
+        self.advance_to(end);
+        self.token(start, value)
+    }
+
+    fn illegal_char(&self, ch: char) -> Error {
+        let start = self.posn;
+        Error {
+            value: ErrorData::IllegalChar(ch),
+            token: Span { start: start, end: start },
+            location: Span { start: start, end: start.after(ch) }
+        }
+    }
+
+    // Reads the next token with `Lexer`, from a suffix of the source starting
+    // at the current position, for everything the byte scanners leave alone.
+    fn fallback(&mut self) -> Result<SliceToken<'src>> {
+        let origin = self.posn;
+        let rest = &self.source[self.here()..];
+
+        // A fresh lexer would take these for a hashbang or HTML comment.
+        if self.cx.get().jsx_mode != JSXMode::Text && (rest.starts_with("#!") || rest.starts_with("-->")) {
+            return Err(self.illegal_char(rest.chars().next().unwrap()));
+        }
+
+        let mut lexer = Lexer::new(rest.chars(), self.cx.clone());
+        match lexer.read_token() {
+            Ok(token) => {
+                let location = rebase_span(origin, token.location);
+                self.posn = location.end;
+                let value = match token.value {
+                    TokenData::Identifier(Name::Symbol(symbol)) => SliceData::Identifier(Cow::Owned(symbol.to_string())),
+                    TokenData::String(literal) => {
+                        SliceData::String(Cow::Owned(literal.source.unwrap_or_else(String::new)),
+                                          Cow::Owned(literal.value))
+                    }
+                    TokenData::Number(literal) => SliceData::Number(location.slice(self.source), literal.value),
+                    TokenData::RegExp(literal) => {
+                        SliceData::RegExp(Cow::Owned(literal.pattern),
+                                          Cow::Owned(literal.flags.into_iter().collect()))
+                    }
+                    value => SliceData::Other(value)
+                };
+                Ok(SliceToken { location: location, newline: token.newline, value: value })
+            }
+            Err(err) => {
+                let location = rebase_span(origin, err.location);
+                self.posn = location.start;
+                Err(Error {
+                    value: err.value,
+                    token: rebase_span(origin, err.token),
+                    location: location
+                })
+            }
+        }
+    }
+
+    // lexical grammar
+
+    // Skips whitespace and comments, returning whether they contained a
+    // line terminator.
+    fn skip_trivia(&mut self) -> Result<bool> {
+        let annex_b = self.cx.get().has_annex_b();
+        let mut found_newline = false;
+        loop {
+            let i = self.here();
+            match self.byte_at(i) {
+                Some(b' ') | Some(b'\t') | Some(0x0B) | Some(0x0C) => { self.bump(1); }
+                Some(b'\n') | Some(b'\r') => {
+                    self.advance_to(i + 1);
+                    found_newline = true;
+                }
+                Some(b'#') if !self.started && i == 0 && self.byte_at(1) == Some(b'!') => {
+                    self.skip_line();
+                }
+                Some(b'/') if self.byte_at(i + 1) == Some(b'/') => { self.skip_line(); }
+                Some(b'/') if self.byte_at(i + 1) == Some(b'*') => {
+                    match self.source[i + 2..].find("*/") {
+                        Some(len) => {
+                            found_newline = self.advance_to(i + 2 + len + 2) || found_newline;
+                        }
+                        None => { return self.fallback().map(|_| found_newline); }
+                    }
+                }
+                Some(b'<') if annex_b && self.source[i..].starts_with("<!--") => { self.skip_line(); }
+                Some(b'-') if annex_b && (found_newline || !self.started) &&
+                              self.source[i..].starts_with("-->") => {
+                    self.skip_line();
+                }
+                Some(b) if b >= 0x80 => {
+                    let ch = self.source[i..].chars().next().unwrap();
+                    if ch.is_es_whitespace() {
+                        self.advance_to(i + ch.len_utf8());
+                    } else if ch.is_es_newline() {
+                        self.advance_to(i + ch.len_utf8());
+                        found_newline = true;
+                    } else {
+                        break;
+                    }
+                }
+                _ => { break; }
+            }
+        }
+        Ok(found_newline)
+    }
+
+    // Skips to the next line terminator, or the end of input.
+    fn skip_line(&mut self) {
+        let start = self.here();
+        let bytes = self.source.as_bytes();
+        let mut end = start;
+        while end < bytes.len() && self.newline_at(end) == 0 {
+            end += 1;
+        }
+        self.advance_to(end);
+    }
+
+    fn lex_token(&mut self, cx: Context) -> Result<SliceToken<'src>> {
+        let i = self.here();
+        let (len, value) = match (self.byte_at(i), self.byte_at(i + 1)) {
+            (Some(b'/'), _) if !cx.operator          => { return self.read_regexp(); }
+            (Some(b'/'), Some(b'='))                  => (2, TokenData::SlashAssign),
+            (Some(b'/'), _)                           => (1, TokenData::Slash),
+            (Some(b'.'), Some(b'0'...b'9'))           => { return self.read_number(); }
+            (Some(b'.'), Some(b'.')) if self.byte_at(i + 2) == Some(b'.') => (3, TokenData::Ellipsis),
+            (Some(b'.'), _)                           => (1, TokenData::Dot),
+            (Some(b'{'), _)                           => (1, TokenData::LBrace),
+            (Some(b'@'), _) if cx.decorators          => (1, TokenData::At),
+            (Some(b'}'), _)                           => (1, TokenData::RBrace),
+            (Some(b'['), _)                           => (1, TokenData::LBrack),
+            (Some(b']'), _)                           => (1, TokenData::RBrack),
+            (Some(b'('), _)                           => (1, TokenData::LParen),
+            (Some(b')'), _)                           => (1, TokenData::RParen),
+            (Some(b';'), _)                           => (1, TokenData::Semi),
+            (Some(b':'), _)                           => (1, TokenData::Colon),
+            (Some(b','), _)                           => (1, TokenData::Comma),
+            (Some(b'<'), Some(b'<'))                  => {
+                if self.byte_at(i + 2) == Some(b'=') { (3, TokenData::LShiftAssign) } else { (2, TokenData::LShift) }
+            }
+            (Some(b'<'), Some(b'='))                  => (2, TokenData::LEq),
+            (Some(b'<'), _)                           => (1, TokenData::LAngle),
+            (Some(b'>'), Some(b'>'))                  => match (self.byte_at(i + 2), self.byte_at(i + 3)) {
+                (Some(b'>'), Some(b'=')) => (4, TokenData::URShiftAssign),
+                (Some(b'>'), _)          => (3, TokenData::URShift),
+                (Some(b'='), _)          => (3, TokenData::RShiftAssign),
+                _                        => (2, TokenData::RShift)
+            },
+            (Some(b'>'), Some(b'='))                  => (2, TokenData::GEq),
+            (Some(b'>'), _)                           => (1, TokenData::RAngle),
+            (Some(b'='), Some(b'='))                  => {
+                if self.byte_at(i + 2) == Some(b'=') { (3, TokenData::StrictEq) } else { (2, TokenData::Eq) }
+            }
+            (Some(b'='), Some(b'>'))                  => (2, TokenData::Arrow),
+            (Some(b'='), _)                           => (1, TokenData::Assign),
+            (Some(b'+'), Some(b'+'))                  => (2, TokenData::Inc),
+            (Some(b'+'), Some(b'='))                  => (2, TokenData::PlusAssign),
+            (Some(b'+'), _)                           => (1, TokenData::Plus),
+            (Some(b'-'), Some(b'-'))                  => (2, TokenData::Dec),
+            (Some(b'-'), Some(b'='))                  => (2, TokenData::MinusAssign),
+            (Some(b'-'), _)                           => (1, TokenData::Minus),
+            (Some(b'*'), Some(b'='))                  => (2, TokenData::StarAssign),
+            (Some(b'*'), _)                           => (1, TokenData::Star),
+            (Some(b'%'), Some(b'='))                  => (2, TokenData::ModAssign),
+            (Some(b'%'), _)                           => (1, TokenData::Mod),
+            (Some(b'^'), Some(b'='))                  => (2, TokenData::BitXorAssign),
+            (Some(b'^'), _)                           => (1, TokenData::BitXor),
+            (Some(b'&'), Some(b'&'))                  => (2, TokenData::LogicalAnd),
+            (Some(b'&'), Some(b'='))                  => (2, TokenData::BitAndAssign),
+            (Some(b'&'), _)                           => (1, TokenData::BitAnd),
+            (Some(b'|'), Some(b'|'))                  => (2, TokenData::LogicalOr),
+            (Some(b'|'), Some(b'='))                  => (2, TokenData::BitOrAssign),
+            (Some(b'|'), _)                           => (1, TokenData::BitOr),
+            (Some(b'~'), _)                           => (1, TokenData::Tilde),
+            (Some(b'!'), Some(b'='))                  => {
+                if self.byte_at(i + 2) == Some(b'=') { (3, TokenData::StrictNEq) } else { (2, TokenData::NEq) }
+            }
+            (Some(b'!'), _)                           => (1, TokenData::Bang),
+            (Some(b'?'), _)                           => (1, TokenData::Question),
+            (Some(b'"'), _) | (Some(b'\''), _)        => { return self.read_string(); }
+            (Some(b'0'...b'9'), _)                    => { return self.read_number(); }
+            (Some(b), _) if is_word_start(b)          => { return self.read_word(); }
+            (Some(_), _)                              => { return self.fallback(); }
+            (None, _)                                 => (0, TokenData::EOF)
+        };
+        Ok(self.ascii_token(len, SliceData::Other(value)))
+    }
+
+    fn read_word(&mut self) -> Result<SliceToken<'src>> {
+        let source = self.source;
+        let bytes = source.as_bytes();
+        let start = self.here();
+        let mut end = start + 1;
+        while end < bytes.len() && is_word_part(bytes[end]) {
+            end += 1;
+        }
+        match bytes.get(end) {
+            Some(&b) if b == b'\\' || b >= 0x80 => { return self.fallback(); }
+            _ => { }
+        }
+        let word = &source[start..end];
+        let value = match self.wordmap.lookup(word) {
+            Some(value) => SliceData::Other(value),
+            None        => SliceData::Identifier(Cow::Borrowed(word))
+        };
+        Ok(self.ascii_token(end - start, value))
+    }
+
+    // The source of a string literal is always borrowed; its value is too,
+    // unless it has escapes.
+    fn read_string(&mut self) -> Result<SliceToken<'src>> {
+        let source = self.source;
+        let bytes = source.as_bytes();
+        let start = self.here();
+        let quote = bytes[start];
+        let mut end = start + 1;
+        let mut escaped = false;
+        let mut value = String::new();
+        // The start of the text not yet copied into an owned value.
+        let mut copied = end;
+        loop {
+            match bytes.get(end) {
+                Some(&b) if b == quote => { break; }
+                Some(&b'\\') => {
+                    value.push_str(&source[copied..end]);
+                    match self.read_string_escape(end + 1, &mut value) {
+                        Some(next) => { end = next; }
+                        None       => { return self.fallback(); }
+                    }
+                    copied = end;
+                    escaped = true;
+                }
+                None => { return self.fallback(); }
+                Some(_) if self.newline_at(end) > 0 => { return self.fallback(); }
+                Some(_) => { end += 1; }
+            }
+        }
+        let value = if escaped {
+            value.push_str(&source[copied..end]);
+            Cow::Owned(value)
+        } else {
+            Cow::Borrowed(&source[start + 1..end])
+        };
+        end += 1;
+        Ok(self.token_to(end, SliceData::String(Cow::Borrowed(&source[start..end]), value)))
+    }
+
+    // Decodes the escape sequence after the backslash at byte `i - 1` into
+    // `value`, returning where it ends, or `None` to leave it to `Lexer`:
+    // octal escapes depend on the mode, and malformed ones are errors.
+    fn read_string_escape(&self, i: usize, value: &mut String) -> Option<usize> {
+        let bytes = self.source.as_bytes();
+        let ch = match self.source[i..].chars().next() {
+            Some(ch) => ch,
+            None     => { return None; }
+        };
+        match ch {
+            '0' if !bytes.get(i + 1).map_or(false, |&b| (b as char).is_digit(10)) => {
+                value.push('\u{0}');
+                Some(i + 1)
+            }
+            '0'...'9' => None,
+            'x' => hex_digits(bytes, i + 1, 2).map(|code| {
+                value.push(char::from_u32(code).unwrap_or('?'));
+                i + 3
+            }),
+            'u' => hex_digits(bytes, i + 1, 4).map(|code| {
+                value.push(char::from_u32(code).unwrap_or('?'));
+                i + 5
+            }),
+            '\r' if bytes.get(i + 1) == Some(&b'\n') => Some(i + 2),
+            _ if ch.is_es_newline() => Some(i + ch.len_utf8()),
+            _ if ch.is_es_single_escape_char() => {
+                value.push(ch.unescape());
+                Some(i + 1)
+            }
+            _ => {
+                value.push(ch);
+                Some(i + ch.len_utf8())
+            }
+        }
+    }
+
+    fn read_number(&mut self) -> Result<SliceToken<'src>> {
+        let source = self.source;
+        let bytes = source.as_bytes();
+        let start = self.here();
+        let radix = match (bytes[start], bytes.get(start + 1).cloned()) {
+            (b'0', Some(b'x')) | (b'0', Some(b'X')) => 16,
+            (b'0', Some(b'o')) | (b'0', Some(b'O')) => 8,
+            (b'0', Some(b'b')) | (b'0', Some(b'B')) => 2,
+            (b'0', Some(b'0'...b'9'))               => { return self.fallback(); }
+            _                                       => 10
+        };
+        // Plain integers and fractions parse as written.
+        let mut parses = radix == 10;
+        let mut end;
+        if radix != 10 {
+            end = skip_digits(bytes, start + 2, radix);
+            if end == start + 2 {
+                return self.fallback();
+            }
+        } else {
+            end = skip_digits(bytes, start, 10);
+            if bytes.get(end) == Some(&b'.') {
+                end = skip_digits(bytes, end + 1, 10);
+            }
+            match bytes.get(end) {
+                Some(&b'e') | Some(&b'E') => {
+                    let mut digits = end + 1;
+                    match bytes.get(digits) {
+                        Some(&b'+') | Some(&b'-') => { digits += 1; }
+                        _ => { }
+                    }
+                    end = skip_digits(bytes, digits, 10);
+                    if end == digits {
+                        return self.fallback();
+                    }
+                    parses = false;
+                }
+                _ => { }
+            }
+        }
+        match bytes.get(end) {
+            Some(&b) if b == b'\\' || b >= 0x80 || is_word_part(b) => { return self.fallback(); }
+            _ => { }
+        }
+        let raw = &source[start..end];
+        let value = if parses {
+            raw.parse().unwrap()
+        } else {
+            NumberSource::parse(raw).value()
+        };
+        Ok(self.ascii_token(end - start, SliceData::Number(raw, value)))
+    }
+
+    fn read_regexp(&mut self) -> Result<SliceToken<'src>> {
+        let source = self.source;
+        let bytes = source.as_bytes();
+        let start = self.here();
+        let mut end = start + 1;
+        let mut class = false;
+        loop {
+            match bytes.get(end) {
+                None => { return self.fallback(); }
+                Some(&b'/') if !class => { break; }
+                Some(&b'\\') => {
+                    if end + 1 == bytes.len() || self.newline_at(end + 1) > 0 {
+                        return self.fallback();
+                    }
+                    end += 2;
+                }
+                Some(&b'[') => { class = true; end += 1; }
+                Some(&b']') => { class = false; end += 1; }
+                // Line terminators end the literal outside a class, and are
+                // left to `Lexer` inside one.
+                Some(_) if self.newline_at(end) > 0 => { return self.fallback(); }
+                Some(_) => { end += 1; }
+            }
+        }
+        let pattern = &source[start + 1..end];
+        let flags_start = end + 1;
+        end = flags_start;
+        while end < bytes.len() && is_word_part(bytes[end]) {
+            end += 1;
+        }
+        match bytes.get(end) {
+            Some(&b) if b == b'\\' || b >= 0x80 => { return self.fallback(); }
+            _ => { }
+        }
+        let value = SliceData::RegExp(Cow::Borrowed(pattern), Cow::Borrowed(&source[flags_start..end]));
+        Ok(self.token_to(end, value))
+    }
+}
+
+impl<'src> Iterator for SliceLexer<'src> {
+    type Item = SliceToken<'src>;
+
+    fn next(&mut self) -> Option<SliceToken<'src>> {
+        match self.read_token() {
+            Ok(SliceToken { value: SliceData::Other(TokenData::EOF), .. }) => None,
+            Ok(t) => Some(t),
+            Err(_) => None
+        }
+    }
+}
+
+fn is_word_start(b: u8) -> bool {
+    match b {
+        b'a'...b'z' | b'A'...b'Z' | b'$' | b'_' => true,
+        _ => false
+    }
+}
+
+fn is_word_part(b: u8) -> bool {
+    is_word_start(b) || (b >= b'0' && b <= b'9')
+}
+
+// The value of `len` hex digits starting at byte `start`, if they are there.
+fn hex_digits(bytes: &[u8], start: usize, len: usize) -> Option<u32> {
+    let mut code = 0;
+    for i in start..start + len {
+        match bytes.get(i).and_then(|&b| (b as char).to_digit(16)) {
+            Some(digit) => { code = (code << 4) + digit; }
+            None        => { return None; }
+        }
+    }
+    Some(code)
+}
+
+fn skip_digits(bytes: &[u8], start: usize, radix: u32) -> usize {
+    let mut end = start;
+    while end < bytes.len() && (bytes[end] as char).is_digit(radix) {
+        end += 1;
+    }
+    end
+}
+
+// Moves a position read from a suffix of the source starting at `origin`
+// into the coordinates of the whole source.
+fn rebase(origin: Posn, posn: Posn) -> Posn {
+    Posn {
+        offset: origin.offset + posn.offset,
+        byte: origin.byte + posn.byte,
+        utf16: origin.utf16 + posn.utf16,
+        line: origin.line + posn.line,
+        column: if posn.line == 0 { origin.column + posn.column } else { posn.column }
+    }
+}
+
+fn rebase_span(origin: Posn, span: Span) -> Span {
+    Span { start: rebase(origin, span.start), end: rebase(origin, span.end) }
+}
+
@@ -268,7 +268,7 @@ impl IntoToken for Value {
             "LBrack"        => TokenData::LBrack,
             "RBrack"        => TokenData::RBrack,
             "Dot"           => TokenData::Dot,
-            //"Ellipsis"    => TokenData::Ellipsis,
+            "Ellipsis"      => TokenData::Ellipsis,
             "Semi"          => TokenData::Semi,
             "Comma"         => TokenData::Comma,
             "LAngle"        => TokenData::LAngle,
@@ -311,6 +311,7 @@ impl IntoToken for Value {
             "BitOrAssign"   => TokenData::BitOrAssign,
             "BitXorAssign"  => TokenData::BitXorAssign,
             "Arrow"         => TokenData::Arrow,
+            "At"            => TokenData::At,
             "EOF"           => TokenData::EOF,
             "DecimalInt"    => {
                 let (value, exp) = tuplify!(arr, ((), ()));
@@ -346,7 +347,7 @@ impl IntoToken for Value {
             "String"        => {
                 let value = try!(arr.remove(0).into_string());
                 TokenData::String(StringLiteral {
-                    source: format!("{:?}", Value::String(value.to_string())),
+                    source: Some(format!("{:?}", Value::String(value.to_string()))),
                     value: value
                 })
             }
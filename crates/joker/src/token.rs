@@ -1,9 +1,10 @@
 use std::fmt;
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use track::*;
 use word::{Reserved, Name};
+use char::ESCharExt;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub location: Span,
     pub newline: bool,    // was there a newline between the preceding token and this one?
@@ -26,7 +27,7 @@ impl Token {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum TokenData {
     Reserved(Reserved),
 
@@ -38,7 +39,7 @@ pub enum TokenData {
     LBrack,
     RBrack,
     Dot,
-    //Ellipsis,
+    Ellipsis,
     Semi,
     Comma,
     LAngle,
@@ -88,9 +89,94 @@ pub enum TokenData {
 
     Identifier(Name),
 
+    // JSX extension
+    JSXIdentifier(String),
+    JSXText(StringLiteral),
+
+    // Decorators extension
+    At,
+
     EOF
 }
 
+impl TokenData {
+    // The source text of a punctuator or of the `@` decorator sigil.
+    pub fn punctuator(&self) -> Option<&'static str> {
+        Some(match *self {
+            TokenData::LBrace        => "{",
+            TokenData::RBrace        => "}",
+            TokenData::LParen        => "(",
+            TokenData::RParen        => ")",
+            TokenData::LBrack        => "[",
+            TokenData::RBrack        => "]",
+            TokenData::Dot           => ".",
+            TokenData::Ellipsis      => "...",
+            TokenData::Semi          => ";",
+            TokenData::Comma         => ",",
+            TokenData::LAngle        => "<",
+            TokenData::RAngle        => ">",
+            TokenData::LEq           => "<=",
+            TokenData::GEq           => ">=",
+            TokenData::Eq            => "==",
+            TokenData::NEq           => "!=",
+            TokenData::StrictEq      => "===",
+            TokenData::StrictNEq     => "!==",
+            TokenData::Plus          => "+",
+            TokenData::Minus         => "-",
+            TokenData::Star          => "*",
+            TokenData::Mod           => "%",
+            TokenData::Slash         => "/",
+            TokenData::Inc           => "++",
+            TokenData::Dec           => "--",
+            TokenData::LShift        => "<<",
+            TokenData::RShift        => ">>",
+            TokenData::URShift       => ">>>",
+            TokenData::BitAnd        => "&",
+            TokenData::BitOr         => "|",
+            TokenData::BitXor        => "^",
+            TokenData::Bang          => "!",
+            TokenData::Tilde         => "~",
+            TokenData::LogicalAnd    => "&&",
+            TokenData::LogicalOr     => "||",
+            TokenData::Question      => "?",
+            TokenData::Colon         => ":",
+            TokenData::Assign        => "=",
+            TokenData::PlusAssign    => "+=",
+            TokenData::MinusAssign   => "-=",
+            TokenData::StarAssign    => "*=",
+            TokenData::SlashAssign   => "/=",
+            TokenData::ModAssign     => "%=",
+            TokenData::LShiftAssign  => "<<=",
+            TokenData::RShiftAssign  => ">>=",
+            TokenData::URShiftAssign => ">>>=",
+            TokenData::BitAndAssign  => "&=",
+            TokenData::BitOrAssign   => "|=",
+            TokenData::BitXorAssign  => "^=",
+            TokenData::Arrow         => "=>",
+            TokenData::At            => "@",
+            _                        => { return None; }
+        })
+    }
+}
+
+// Describes a token for use in error messages, e.g. "`)`" or "identifier `x`".
+impl Display for TokenData {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            TokenData::Reserved(ref word)       => fmt.write_fmt(format_args!("`{}`", word.name())),
+            TokenData::Number(_)                => fmt.write_str("number literal"),
+            TokenData::String(_)                => fmt.write_str("string literal"),
+            TokenData::RegExp(_)                => fmt.write_str("regular expression literal"),
+            TokenData::Identifier(ref name)     => fmt.write_fmt(format_args!("identifier `{}`", name)),
+            TokenData::JSXIdentifier(ref name)  => fmt.write_fmt(format_args!("JSX identifier `{}`", name)),
+            TokenData::JSXText(_)               => fmt.write_str("JSX text"),
+            TokenData::EOF                      => fmt.write_str("end of input"),
+            _                                   => fmt.write_fmt(format_args!("`{}`", self.punctuator().unwrap()))
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct RegExpLiteral {
     pub pattern: String,
     pub flags: Vec<char>
@@ -124,14 +210,24 @@ impl PartialEq for RegExpLiteral {
     }
 }
 
+#[derive(Clone)]
 pub struct StringLiteral {
     pub source: Option<String>,
     pub value: String
 }
 
+impl StringLiteral {
+    // The literal exactly as written, quotes and escapes included, if it was
+    // read from source.
+    pub fn raw(&self) -> Option<&str> {
+        self.source.as_ref().map(|source| &source[..])
+    }
+}
+
 impl Debug for StringLiteral {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("StringLiteral")
+            .field("source", &self.source)
             .field("value", &self.value)
             .finish()
     }
@@ -143,14 +239,23 @@ impl PartialEq for StringLiteral {
     }
 }
 
+#[derive(Clone)]
 pub struct NumberLiteral {
     pub source: Option<NumberSource>,
     pub value: f64
 }
 
+impl NumberLiteral {
+    // The literal exactly as written, if its source is known.
+    pub fn raw(&self) -> Option<String> {
+        self.source.as_ref().map(|source| source.to_string())
+    }
+}
+
 impl Debug for NumberLiteral {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("NumberLiteral")
+            .field("source", &self.source)
             .field("value", &self.value)
             .finish()
     }
@@ -162,7 +267,7 @@ impl PartialEq for NumberLiteral {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum NumberSource {
     DecimalInt(String, Option<Exp>),
     RadixInt(Radix, String),
@@ -184,21 +289,63 @@ fn format_int(src: &Option<String>) -> String {
 }
 
 impl NumberSource {
+    // Splits the source of a number literal into its parts, as the lexer does.
+    pub fn parse(raw: &str) -> NumberSource {
+        let bytes = raw.as_bytes();
+        if bytes.len() > 1 && bytes[0] == b'0' {
+            let case = if (bytes[1] as char).is_lowercase() { CharCase::LowerCase } else { CharCase::UpperCase };
+            let digits = raw[2..].to_string();
+            match bytes[1] {
+                b'x' | b'X' => { return NumberSource::RadixInt(Radix::Hex(case), digits); }
+                b'o' | b'O' => { return NumberSource::RadixInt(Radix::Oct(Some(case)), digits); }
+                b'b' | b'B' => { return NumberSource::RadixInt(Radix::Bin(case), digits); }
+                b'0'...b'9' => {
+                    return if raw[1..].chars().all(|ch| ch.is_es_oct_digit()) {
+                        NumberSource::RadixInt(Radix::Oct(None), raw[1..].to_string())
+                    } else {
+                        NumberSource::DecimalInt(raw.to_string(), None)
+                    };
+                }
+                _ => { }
+            }
+        }
+        let (mantissa, exp) = match raw.find(|ch| ch == 'e' || ch == 'E') {
+            Some(i) => {
+                let e = if bytes[i] == b'e' { CharCase::LowerCase } else { CharCase::UpperCase };
+                let (sign, value) = match bytes.get(i + 1) {
+                    Some(&b'+') => (Some(Sign::Plus), &raw[i + 2..]),
+                    Some(&b'-') => (Some(Sign::Minus), &raw[i + 2..]),
+                    _           => (None, &raw[i + 1..])
+                };
+                (&raw[..i], Some(Exp { e: e, sign: sign, value: value.to_string() }))
+            }
+            None => (raw, None)
+        };
+        match mantissa.find('.') {
+            Some(i) => {
+                let int = if i == 0 { None } else { Some(mantissa[..i].to_string()) };
+                NumberSource::Float(int, Some(mantissa[i + 1..].to_string()), exp)
+            }
+            None => NumberSource::DecimalInt(mantissa.to_string(), exp)
+        }
+    }
+
     pub fn value(&self) -> f64 {
         match *self {
             NumberSource::DecimalInt(ref mantissa, None) => {
-                let i: i64 = mantissa.parse().ok().unwrap();
-                i as f64
+                mantissa.parse().ok().unwrap()
             }
             NumberSource::DecimalInt(ref mantissa, Some(Exp { ref sign, ref value, .. })) => {
-                let mantissa: i64 = mantissa.parse().ok().unwrap();
-                let mantissa: f64 = mantissa as f64;
-                let exp: i32 = value.parse().ok().unwrap();
-                mantissa * (10 as f64).powi(if *sign == Some(Sign::Minus) { -exp } else { exp })
+                format!("{}e{}{}", mantissa, format_sign(sign), value).parse().ok().unwrap()
             }
             NumberSource::RadixInt(ref radix, ref src) => {
-                let i = i64::from_str_radix(&src[..], radix.value()).ok().unwrap();
-                i as f64
+                match u64::from_str_radix(&src[..], radix.value()) {
+                    Ok(i) => i as f64,
+                    // Too big for an integer: accumulate the digits instead.
+                    Err(_) => src.chars().fold(0.0, |value, ch| {
+                        value * radix.value() as f64 + ch.to_digit(radix.value()).unwrap() as f64
+                    })
+                }
             }
             NumberSource::Float(ref ip, ref fp, None) => {
                 format!("{}.{}", format_int(ip), format_int(fp)).parse().ok().unwrap()
@@ -218,13 +365,52 @@ impl NumberSource {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// The literal exactly as written.
+impl Display for NumberSource {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            NumberSource::DecimalInt(ref mantissa, ref exp) => {
+                try!(fmt.write_str(mantissa));
+                exp.as_ref().map_or(Ok(()), |exp| Display::fmt(exp, fmt))
+            }
+            NumberSource::RadixInt(Radix::Oct(None), ref src) => {
+                fmt.write_fmt(format_args!("0{}", src))
+            }
+            NumberSource::RadixInt(radix, ref src) => {
+                let prefix = match radix {
+                    Radix::Bin(case)       => case.apply('b'),
+                    Radix::Oct(Some(case)) => case.apply('o'),
+                    Radix::Oct(None)       => unreachable!(),
+                    Radix::Hex(case)       => case.apply('x')
+                };
+                fmt.write_fmt(format_args!("0{}{}", prefix, src))
+            }
+            NumberSource::Float(ref ip, ref fp, ref exp) => {
+                try!(fmt.write_fmt(format_args!("{}.{}", format_int(ip), format_int(fp))));
+                exp.as_ref().map_or(Ok(()), |exp| Display::fmt(exp, fmt))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Exp {
     pub e: CharCase,
     pub sign: Option<Sign>,
     pub value: String
 }
 
+impl Display for Exp {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let sign = match self.sign {
+            Some(Sign::Plus)  => "+",
+            Some(Sign::Minus) => "-",
+            None              => ""
+        };
+        fmt.write_fmt(format_args!("{}{}{}", self.e.apply('e'), sign, self.value))
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Radix {
     Bin(CharCase),
@@ -248,6 +434,16 @@ pub enum CharCase {
     UpperCase
 }
 
+impl CharCase {
+    // The letter `ch`, given in lower case, in this case.
+    pub fn apply(&self, ch: char) -> char {
+        match *self {
+            CharCase::LowerCase => ch,
+            CharCase::UpperCase => ch.to_ascii_uppercase()
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Sign {
     Plus,
@@ -1,10 +1,17 @@
 use std::fmt;
 use std::fmt::{Display, Debug, Formatter};
 use std::str::FromStr;
+use std::ops::Range;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+// A source position. `offset` counts Unicode scalar values, `byte` counts
+// UTF-8 bytes (as `&str` indices do) and `utf16` counts UTF-16 code units (as
+// JavaScript strings, LSP and source maps do). `line` and `column` are
+// 0-based, with columns counted in characters.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Posn {
     pub offset: u32,
+    pub byte: u32,
+    pub utf16: u32,
     pub line: u32,
     pub column: u32
 }
@@ -13,18 +20,59 @@ impl Posn {
     pub fn origin() -> Posn {
         Posn {
             offset: 0,
+            byte: 0,
+            utf16: 0,
             line: 0,
             column: 0
         }
     }
+
+    // The position `self` moves to when text ending at `from`, which is at
+    // or before `self`, is replaced by text ending at `to`.
+    pub fn shift(&self, from: Posn, to: Posn) -> Posn {
+        Posn {
+            offset: self.offset - from.offset + to.offset,
+            byte: self.byte - from.byte + to.byte,
+            utf16: self.utf16 - from.utf16 + to.utf16,
+            line: self.line - from.line + to.line,
+            column: if self.line == from.line { self.column - from.column + to.column } else { self.column }
+        }
+    }
+
+    // The position just past `ch`, on the same line.
+    pub fn after(&self, ch: char) -> Posn {
+        Posn {
+            offset: self.offset + 1,
+            byte: self.byte + ch.len_utf8() as u32,
+            utf16: self.utf16 + ch.len_utf16() as u32,
+            line: self.line,
+            column: self.column + 1
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Span {
     pub start: Posn,
     pub end: Posn
 }
 
+impl Span {
+    // The span as `&str` indices into the source it was read from.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start.byte as usize..self.end.byte as usize
+    }
+
+    pub fn utf16_range(&self) -> Range<usize> {
+        self.start.utf16 as usize..self.end.utf16 as usize
+    }
+
+    // The text of the span in the source it was read from.
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.byte_range()]
+    }
+}
+
 pub trait Track {
     fn location(&self) -> Option<Span>;
     fn track<U>(&self, other: U) -> Tracked<U> {
@@ -61,7 +109,7 @@ impl<T> Track for Option<T>
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone)]
 pub struct Tracked<T> {
     pub location: Option<Span>,
     pub value: T
@@ -106,33 +154,69 @@ impl<T> IntoTracked for T {
     // }
 }
 
+// What to do with the source locations in a tree.
+pub enum Relocation<'a> {
+    Drop,
+    Map(&'a mut FnMut(Span) -> Span)
+}
+
+impl<'a> Relocation<'a> {
+    pub fn apply(&mut self, location: Option<Span>) -> Option<Span> {
+        match *self {
+            Relocation::Drop           => None,
+            Relocation::Map(ref mut f) => location.map(|span| f(span))
+        }
+    }
+
+    pub fn drops(&self) -> bool {
+        match *self {
+            Relocation::Drop   => true,
+            Relocation::Map(_) => false
+        }
+    }
+}
+
+// Trees whose source locations can be dropped or moved.
 pub trait Untrack {
-    fn untrack(&mut self);
+    fn relocate(&mut self, r: &mut Relocation);
+
+    fn untrack(&mut self) {
+        self.relocate(&mut Relocation::Drop);
+    }
+
+    // Moves every location by the difference between `from` and `to`, as
+    // after an edit that ended at `from` and now ends at `to`. Locations
+    // are expected to lie after `from`.
+    fn shift(&mut self, from: Posn, to: Posn) {
+        self.relocate(&mut Relocation::Map(&mut |span| {
+            Span { start: span.start.shift(from, to), end: span.end.shift(from, to) }
+        }));
+    }
 }
 
 impl<T> Untrack for Tracked<T>
   where T: Untrack
 {
-    fn untrack(&mut self) {
-        self.location = None;
-        self.value.untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        self.location = r.apply(self.location);
+        self.value.relocate(r);
     }
 }
 
 impl<T> Untrack for Box<T>
   where T: Untrack
 {
-    fn untrack(&mut self) {
-        (**self).untrack();
+    fn relocate(&mut self, r: &mut Relocation) {
+        (**self).relocate(r);
     }
 }
 
 impl<T> Untrack for Option<T>
   where T: Untrack
 {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         match *self {
-            Some(ref mut x) => { x.untrack(); }
+            Some(ref mut x) => { x.relocate(r); }
             None => { }
         }
     }
@@ -141,9 +225,9 @@ impl<T> Untrack for Option<T>
 impl<T> Untrack for Vec<T>
   where T: Untrack
 {
-    fn untrack(&mut self) {
+    fn relocate(&mut self, r: &mut Relocation) {
         for x in self {
-            x.untrack();
+            x.relocate(r);
         }
     }
 }
@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 use std::convert::From;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use token::TokenData;
+use std::sync::{Arc, OnceLock};
+use intern;
+use intern::{Interner, Symbol};
 
 // Unconditionally reserved words.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -103,64 +108,109 @@ impl Reserved {
 // Contextually reserved words and special identifier names.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Atom {
+    Abstract,
     Arguments,
+    As,
     Async,
     Await,
+    Declare,
     Eval,
     From,
     Get,
     Implements,
+    Infer,
     Interface,
+    Is,
+    Keyof,
     Let,
+    Meta,
+    Module,
+    Namespace,
     Of,
     Package,
     Private,
     Protected,
     Public,
+    Readonly,
+    Satisfies,
     Set,
     Static,
     Target,
+    Type,
+    Unique,
     Yield
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+// Every atom, in declaration order. An atom's position here is its index in
+// every symbol table (see `intern`).
+pub const ATOMS: [Atom; 31] = [
+    Atom::Abstract,
+    Atom::Arguments, Atom::As,        Atom::Async,      Atom::Await,     Atom::Declare,
+    Atom::Eval,      Atom::From,      Atom::Get,        Atom::Implements, Atom::Infer,
+    Atom::Interface, Atom::Is,        Atom::Keyof,      Atom::Let,       Atom::Meta,
+    Atom::Module,    Atom::Namespace, Atom::Of,         Atom::Package,   Atom::Private,
+    Atom::Protected, Atom::Public,    Atom::Readonly,   Atom::Satisfies, Atom::Set,
+    Atom::Static,    Atom::Target,    Atom::Type,       Atom::Unique,    Atom::Yield
+];
+
+// An identifier name. Contextual keywords are always represented as atoms,
+// so that they can be matched on; every other name is an interned symbol.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Name {
     Atom(Atom),
-    String(String)
+    Symbol(Symbol)
 }
 
 impl Name {
+    // The name's text, or `None` if its interner has been dropped.
+    pub fn text(&self) -> Option<Arc<str>> {
+        self.symbol().text()
+    }
+
+    // The name's text, or a placeholder if its interner has been dropped.
     pub fn into_string(self) -> String {
         match self {
-            Name::Atom(atom) => atom.name().to_string(),
-            Name::String(s)  => s
+            Name::Atom(atom)     => atom.name().to_string(),
+            Name::Symbol(symbol) => symbol.to_string()
+        }
+    }
+
+    // The name's interned symbol. Atoms have fixed symbols of their own.
+    pub fn symbol(self) -> Symbol {
+        match self {
+            Name::Atom(atom)     => Symbol::from(atom),
+            Name::Symbol(symbol) => symbol
         }
     }
 }
 
+impl Display for Name {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            Name::Atom(atom)     => fmt.write_str(atom.name()),
+            Name::Symbol(symbol) => Display::fmt(&symbol, fmt)
+        }
+    }
+}
+
+impl From<Symbol> for Name {
+    fn from(symbol: Symbol) -> Name {
+        match symbol.atom() {
+            Some(atom) => Name::Atom(atom),
+            None       => Name::Symbol(symbol)
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Name {
+    fn from(s: &'a str) -> Name {
+        Name::from(Symbol::intern(s))
+    }
+}
+
 impl From<String> for Name {
     fn from(s: String) -> Name {
-        match &s[..] {
-            "arguments"  => Name::Atom(Atom::Arguments),
-            "await"      => Name::Atom(Atom::Await),
-            "eval"       => Name::Atom(Atom::Eval),
-            "async"      => Name::Atom(Atom::Async),
-            "from"       => Name::Atom(Atom::From),
-            "get"        => Name::Atom(Atom::Get),
-            "implements" => Name::Atom(Atom::Implements),
-            "interface"  => Name::Atom(Atom::Interface),
-            "let"        => Name::Atom(Atom::Let),
-            "of"         => Name::Atom(Atom::Of),
-            "package"    => Name::Atom(Atom::Package),
-            "private"    => Name::Atom(Atom::Private),
-            "protected"  => Name::Atom(Atom::Protected),
-            "public"     => Name::Atom(Atom::Public),
-            "set"        => Name::Atom(Atom::Set),
-            "static"     => Name::Atom(Atom::Static),
-            "target"     => Name::Atom(Atom::Target),
-            "yield"      => Name::Atom(Atom::Yield),
-            _            => Name::String(s)
-        }
+        Name::from(&s[..])
     }
 }
 
@@ -176,6 +226,9 @@ impl Atom {
             Atom::Implements => "implements",
             Atom::Interface  => "interface",
             Atom::Let        => "let",
+            Atom::Meta       => "meta",
+            Atom::Module     => "module",
+            Atom::Namespace  => "namespace",
             Atom::Of         => "of",
             Atom::Package    => "package",
             Atom::Private    => "private",
@@ -184,7 +237,17 @@ impl Atom {
             Atom::Set        => "set",
             Atom::Static     => "static",
             Atom::Target     => "target",
-            Atom::Yield      => "yield"
+            Atom::Yield      => "yield",
+            Atom::Abstract   => "abstract",
+            Atom::As         => "as",
+            Atom::Declare    => "declare",
+            Atom::Infer      => "infer",
+            Atom::Is         => "is",
+            Atom::Keyof      => "keyof",
+            Atom::Readonly   => "readonly",
+            Atom::Satisfies  => "satisfies",
+            Atom::Type       => "type",
+            Atom::Unique     => "unique"
         }
     }
 }
@@ -201,15 +264,19 @@ macro_rules! wordmap {
     };
 }
 
+// Reserved words. Contextual keywords are the pre-seeded entries of the
+// symbol table instead. The table is built once and shared by every lexer,
+// so making a map is free.
+#[derive(Clone, Copy)]
 pub struct Map {
-    reserved: HashMap<&'static str, Reserved>,
-    contextual: HashMap<&'static str, Atom>
+    reserved: &'static HashMap<&'static str, Reserved>
 }
 
 impl Map {
     pub fn new() -> Map {
+        static RESERVED: OnceLock<HashMap<&'static str, Reserved>> = OnceLock::new();
         Map {
-            reserved: wordmap!(Reserved, [
+            reserved: RESERVED.get_or_init(|| wordmap!(Reserved, [
                 // ReservedWord
                 ("null",       Null),       ("true",       True),       ("false",     False),
 
@@ -228,37 +295,23 @@ impl Map {
 
                 // FutureReservedWord
                 ("enum",       Enum)
-            ]),
-
-            contextual: wordmap!(Atom, [
-                // Restricted words in strict code
-                ("arguments",  Arguments),  ("eval",       Eval),
-
-                // Reserved in some contexts
-                ("yield",      Yield),
-
-                // Reserved words in module code
-                ("await",      Await),
-
-                // Reserved words in strict code
-                ("implements", Implements), ("interface",  Interface),  ("let",       Let),
-                ("package",    Package),    ("private",    Private),    ("protected", Protected),
-                ("public",     Public),     ("static",     Static),
-
-                // Purely contextual identifier names
-                ("async",      Async),      ("from",       From),       ("of",       Of),
-                ("get",        Get),        ("set",        Set),        ("target",   Target)
-            ])
+            ]))
         }
     }
 
-    pub fn tokenize(&self, s: String) -> TokenData {
-        match self.reserved.get(&s[..]) {
+    // Tokenizes a word, interning it into `interner` if it is an identifier.
+    pub fn tokenize(&self, s: &str, interner: &Interner) -> TokenData {
+        match self.reserved.get(s) {
             Some(&word) => TokenData::Reserved(word),
-            None => match self.contextual.get(&s[..]) {
-                Some(&atom) => TokenData::Identifier(Name::Atom(atom)),
-                None => TokenData::Identifier(Name::String(s))
-            }
+            None => TokenData::Identifier(Name::from(interner.intern(s)))
+        }
+    }
+
+    // Tokenizes a reserved word or contextual keyword without allocating.
+    pub fn lookup(&self, s: &str) -> Option<TokenData> {
+        match self.reserved.get(s) {
+            Some(&word) => Some(TokenData::Reserved(word)),
+            None => intern::atom(s).map(|atom| TokenData::Identifier(Name::Atom(atom)))
         }
     }
 }
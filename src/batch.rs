@@ -0,0 +1,136 @@
+use std::any::Any;
+use std::cmp::{max, min};
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use joker::intern::Interner;
+use easter::prog::Program;
+use options::{ParseOptions, SourceType};
+use parser::Parser;
+use error::Error;
+use result::Result;
+
+// The parser grows its own stack, but `f` gets the trees on the worker, where
+// code that walks them recursively needs room for every level of nesting.
+// Worker stacks leave room for that at the most a level of `max_depth` takes
+// in an unoptimized build, and are never smaller than the 4MB the test
+// harness defaults to.
+const STACK_PER_LEVEL: usize = 32 * 1024;
+const MIN_STACK: usize = 4 * 1024 * 1024;
+
+// The outcome of parsing one input of `parse_many`.
+#[derive(Debug)]
+pub struct Parsed<'a> {
+    pub path: &'a Path,
+    pub result: Result<Program>,
+    // How long the parse took, on its worker thread.
+    pub time: Duration
+}
+
+// Parses each `(path, source)` input as a script or module, according to
+// `options.source_type`, on a pool of worker threads, one per available CPU.
+// Each result is passed to `f` on the worker that parsed it, and what `f`
+// returns comes back in input order. Trees deeper than the caller's stack can
+// drop should be used up in `f` rather than returned. A parse that panics is
+// reported as that input's `Error::Panicked`; a panic in `f` is passed on to
+// the caller. The paths are only passed through, to label the results;
+// nothing is read from them. Names are interned into the caller's current
+// interner.
+pub fn parse_many<'a, P, S, F, T>(inputs: &'a [(P, S)], options: ParseOptions, f: F) -> Vec<T>
+  where P: AsRef<Path> + Sync,
+        S: AsRef<str> + Sync,
+        F: Fn(Parsed<'a>) -> T + Sync,
+        T: Send
+{
+    parse_each(inputs, options, parse, f)
+}
+
+// `parse_many`, with the parse of each input done by `parse`.
+fn parse_each<'a, P, S, G, F, T>(inputs: &'a [(P, S)], options: ParseOptions, parse: G, f: F) -> Vec<T>
+  where P: AsRef<Path> + Sync,
+        S: AsRef<str> + Sync,
+        G: Fn(&str, ParseOptions) -> Result<Program> + Sync,
+        F: Fn(Parsed<'a>) -> T + Sync,
+        T: Send
+{
+    let workers = min(thread::available_parallelism().map(|n| n.get()).unwrap_or(1), inputs.len());
+    let stack_size = max(MIN_STACK, options.max_depth.saturating_mul(STACK_PER_LEVEL));
+    let next = AtomicUsize::new(0);
+    let interner = Interner::current();
+    let (parse, f) = (&parse, &f);
+
+    // Each worker claims the next unparsed input until none are left, so a
+    // few large files don't hold up the rest.
+    let mut results = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| {
+            thread::Builder::new().stack_size(stack_size).spawn_scoped(scope, || interner.enter(|| {
+                let mut parsed = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let (path, source) = match inputs.get(i) {
+                        Some(&(ref path, ref source)) => (path, source),
+                        None                          => { break; }
+                    };
+                    let start = Instant::now();
+                    let result = match panic::catch_unwind(AssertUnwindSafe(|| parse(source.as_ref(), options))) {
+                        Ok(result)   => result,
+                        Err(payload) => Err(Error::Panicked(panic_message(payload)))
+                    };
+                    let time = start.elapsed();
+                    parsed.push((i, f(Parsed { path: path.as_ref(), result: result, time: time })));
+                }
+                parsed
+            })).expect("failed to spawn a parser thread")
+        }).collect();
+        handles.into_iter().flat_map(|handle| {
+            handle.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+        }).collect::<Vec<_>>()
+    });
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn parse(source: &str, options: ParseOptions) -> Result<Program> {
+    let mut parser = Parser::with_options(source.chars(), options);
+    match options.source_type {
+        SourceType::Script => parser.script().map(Program::Script),
+        SourceType::Module => parser.module().map(Program::Module)
+    }
+}
+
+// The message a panic was raised with, if it was raised with one.
+fn panic_message(payload: Box<Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast_ref::<&'static str>() {
+            Some(message) => message.to_string(),
+            None          => "unknown panic".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use error::Error;
+    use options::ParseOptions;
+    use super::{parse, parse_each};
+
+    #[test]
+    pub fn panics() {
+        let inputs = [("a.js", "a;"), ("b.js", "b;"), ("c.js", "c;")];
+        let results = parse_each(&inputs, ParseOptions::new(), |source, options| {
+            if source == "b;" {
+                panic!("failed on {}", source);
+            }
+            parse(source, options)
+        }, |parsed| (parsed.path, parsed.result));
+        assert_eq!(results[0].0, Path::new("a.js"));
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].1, Err(Error::Panicked("failed on b;".to_string())));
+        assert!(results[2].1.is_ok());
+    }
+}
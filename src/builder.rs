@@ -0,0 +1,911 @@
+use joker::track::*;
+use joker::token::{NumberLiteral, StringLiteral, RegExpLiteral};
+use easter::id::Id;
+use std::mem::replace;
+use easter::expr::{Expr, ExprData};
+use easter::expr::take as take_expr;
+use easter::stmt::take as take_stmt;
+use easter::stack::grow;
+use easter::stmt::{Stmt, StmtData, StmtListItem, ForHeadData, ForInHeadData, ForOfHeadData, CatchData, CaseData};
+use easter::decl::{Decl, DeclData, Dtor, DtorData};
+use easter::patt::{Patt, CompoundPattData, PropPattData, AssignTarget, AssignTargetData};
+use easter::fun::{Fun, FunData, LazyBody, ParamsData};
+use easter::obj::{DotKey, Prop, PropData, PropKey, PropValData};
+use easter::class::Class;
+use easter::jsx::{JSXElement, JSXFragment};
+use easter::ty::{Ty, TyParam};
+use easter::punc::{Unop, Binop, Logop, Assop, Semi};
+use easter::prog::{Script, ScriptData, Module, ModuleData};
+
+// The head of a `for` loop, with the builder's own node types.
+pub enum ForHead<D, E> {
+    Var(Vec<D>),
+    Let(Vec<D>),
+    Expr(E)
+}
+
+// The left-hand side of a `for-in` loop.
+pub enum ForInHead<I, P, E> {
+    VarInit(I, E),
+    Var(P),
+    Let(P),
+    Expr(E)
+}
+
+// The left-hand side of a `for-of` loop.
+pub enum ForOfHead<P, E> {
+    Var(P),
+    Let(P),
+    Expr(E)
+}
+
+// The value of an object literal property.
+pub enum PropVal<E, P, I> {
+    Init(E),
+    Get(Vec<I>),
+    Set(P, Vec<I>)
+}
+
+// Constructs another representation of a parsed tree. The parser itself
+// always produces easter; `build_script` and `build_module` then fold that
+// tree into the builder, bottom-up, so each method receives its children
+// already built along with the node's source location. The productions
+// don't call the builder, so this is a conversion pass and saves none of
+// the easter allocations.
+//
+// Leaf data (names, literals, operators, property keys) is passed in its easter
+// form. Classes, JSX and TypeScript types and declarations are also passed
+// through as easter nodes; builders that don't care about them can ignore them.
+pub trait Builder {
+    type Id;
+    type Patt;
+    type Target;
+    type Expr;
+    type Prop;
+    type Fun;
+    type Dtor;
+    type Stmt;
+    type Case;
+    type Catch;
+    type Decl;
+    type Item;
+    type Script;
+    type Module;
+
+    fn build_id(&mut self, id: Id) -> Self::Id;
+
+    // Binding patterns.
+    fn build_id_patt(&mut self, id: Self::Id) -> Self::Patt;
+    fn build_array_patt(&mut self, elts: Vec<Option<Self::Patt>>, location: Option<Span>) -> Self::Patt;
+    fn build_object_patt(&mut self, props: Vec<Tracked<(PropKey, Self::Patt)>>, location: Option<Span>) -> Self::Patt;
+
+    // Assignment targets.
+    fn build_id_target(&mut self, id: Self::Id, location: Option<Span>) -> Self::Target;
+    fn build_dot_target(&mut self, obj: Self::Expr, key: DotKey, location: Option<Span>) -> Self::Target;
+    fn build_brack_target(&mut self, obj: Self::Expr, prop: Self::Expr, location: Option<Span>) -> Self::Target;
+    fn build_array_target(&mut self, elts: Vec<Option<Self::Target>>, location: Option<Span>) -> Self::Target;
+    fn build_object_target(&mut self, props: Vec<Tracked<(PropKey, Self::Target)>>, location: Option<Span>) -> Self::Target;
+
+    // Expressions.
+    fn build_this(&mut self, location: Option<Span>) -> Self::Expr;
+    fn build_id_expr(&mut self, id: Self::Id, location: Option<Span>) -> Self::Expr;
+    fn build_array(&mut self, elts: Vec<Option<Self::Expr>>, location: Option<Span>) -> Self::Expr;
+    fn build_object(&mut self, props: Vec<Self::Prop>, location: Option<Span>) -> Self::Expr;
+    fn build_fun_expr(&mut self, fun: Self::Fun, location: Option<Span>) -> Self::Expr;
+    fn build_class_expr(&mut self, class: Class, location: Option<Span>) -> Self::Expr;
+    fn build_seq(&mut self, exprs: Vec<Self::Expr>, location: Option<Span>) -> Self::Expr;
+    fn build_unop(&mut self, op: Unop, arg: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_binop(&mut self, op: Binop, left: Self::Expr, right: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_logop(&mut self, op: Logop, left: Self::Expr, right: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_pre_inc(&mut self, arg: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_post_inc(&mut self, arg: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_pre_dec(&mut self, arg: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_post_dec(&mut self, arg: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_assign(&mut self, op: Assop, target: Self::Target, value: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_cond(&mut self, test: Self::Expr, cons: Self::Expr, alt: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_call(&mut self, callee: Self::Expr, args: Vec<Self::Expr>, location: Option<Span>) -> Self::Expr;
+    fn build_new(&mut self, callee: Self::Expr, args: Option<Vec<Self::Expr>>, location: Option<Span>) -> Self::Expr;
+    fn build_dot(&mut self, obj: Self::Expr, key: DotKey, location: Option<Span>) -> Self::Expr;
+    fn build_brack(&mut self, obj: Self::Expr, prop: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_new_target(&mut self, location: Option<Span>) -> Self::Expr;
+    fn build_import_meta(&mut self, location: Option<Span>) -> Self::Expr;
+    fn build_import(&mut self, source: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_true(&mut self, location: Option<Span>) -> Self::Expr;
+    fn build_false(&mut self, location: Option<Span>) -> Self::Expr;
+    fn build_null(&mut self, location: Option<Span>) -> Self::Expr;
+    fn build_number(&mut self, literal: NumberLiteral, location: Option<Span>) -> Self::Expr;
+    fn build_regexp(&mut self, literal: RegExpLiteral, location: Option<Span>) -> Self::Expr;
+    fn build_string(&mut self, literal: StringLiteral, location: Option<Span>) -> Self::Expr;
+    fn build_jsx_element(&mut self, element: JSXElement, location: Option<Span>) -> Self::Expr;
+    fn build_jsx_fragment(&mut self, fragment: JSXFragment, location: Option<Span>) -> Self::Expr;
+    fn build_as(&mut self, expr: Self::Expr, ty: Ty, location: Option<Span>) -> Self::Expr;
+    fn build_satisfies(&mut self, expr: Self::Expr, ty: Ty, location: Option<Span>) -> Self::Expr;
+    fn build_instantiation(&mut self, expr: Self::Expr, args: Vec<Ty>, location: Option<Span>) -> Self::Expr;
+    fn build_non_null(&mut self, expr: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_paren(&mut self, expr: Self::Expr, location: Option<Span>) -> Self::Expr;
+    fn build_invalid_expr(&mut self, location: Option<Span>) -> Self::Expr;
+
+    fn build_prop(&mut self, key: PropKey, val: Tracked<PropVal<Self::Expr, Self::Patt, Self::Item>>, location: Option<Span>) -> Self::Prop;
+
+    // A function whose body was left out by `lazy_functions` has an empty
+    // `body` and the record needed to reparse it later.
+    fn build_fun(&mut self, id: Option<Self::Id>, ty_params: Vec<TyParam>, params: Tracked<Vec<Self::Patt>>,
+                 ret_ty: Option<Ty>, body: Vec<Self::Item>, lazy: Option<LazyBody>, location: Option<Span>) -> Self::Fun;
+
+    fn build_dtor(&mut self, id: Self::Id, init: Option<Self::Expr>, location: Option<Span>) -> Self::Dtor;
+    fn build_compound_dtor(&mut self, patt: Self::Patt, init: Self::Expr, location: Option<Span>) -> Self::Dtor;
+
+    // Statements.
+    fn build_empty(&mut self, location: Option<Span>) -> Self::Stmt;
+    fn build_block(&mut self, body: Vec<Self::Item>, location: Option<Span>) -> Self::Stmt;
+    fn build_var_decl(&mut self, dtors: Vec<Self::Dtor>, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_expr_stmt(&mut self, expr: Self::Expr, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_if(&mut self, test: Self::Expr, cons: Self::Stmt, alt: Option<Self::Stmt>, location: Option<Span>) -> Self::Stmt;
+    fn build_label(&mut self, label: Self::Id, body: Self::Stmt, location: Option<Span>) -> Self::Stmt;
+    fn build_break(&mut self, label: Option<Self::Id>, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_continue(&mut self, label: Option<Self::Id>, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_with(&mut self, obj: Self::Expr, body: Self::Stmt, location: Option<Span>) -> Self::Stmt;
+    fn build_switch(&mut self, disc: Self::Expr, cases: Vec<Self::Case>, location: Option<Span>) -> Self::Stmt;
+    fn build_return(&mut self, arg: Option<Self::Expr>, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_throw(&mut self, arg: Self::Expr, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_try(&mut self, body: Vec<Self::Item>, catch: Option<Self::Catch>, finally: Option<Vec<Self::Item>>,
+                 location: Option<Span>) -> Self::Stmt;
+    fn build_while(&mut self, test: Self::Expr, body: Self::Stmt, location: Option<Span>) -> Self::Stmt;
+    fn build_do_while(&mut self, body: Self::Stmt, test: Self::Expr, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_for(&mut self, head: Option<Tracked<ForHead<Self::Dtor, Self::Expr>>>, test: Option<Self::Expr>,
+                 update: Option<Self::Expr>, body: Self::Stmt, location: Option<Span>) -> Self::Stmt;
+    fn build_for_in(&mut self, head: Tracked<ForInHead<Self::Id, Self::Patt, Self::Expr>>, obj: Self::Expr,
+                    body: Self::Stmt, location: Option<Span>) -> Self::Stmt;
+    fn build_for_of(&mut self, head: Tracked<ForOfHead<Self::Patt, Self::Expr>>, iter: Self::Expr,
+                    body: Self::Stmt, location: Option<Span>) -> Self::Stmt;
+    fn build_debugger(&mut self, semi: Semi, location: Option<Span>) -> Self::Stmt;
+    fn build_fun_stmt(&mut self, fun: Self::Fun, location: Option<Span>) -> Self::Stmt;
+    fn build_invalid_stmt(&mut self, location: Option<Span>) -> Self::Stmt;
+
+    fn build_case(&mut self, test: Option<Self::Expr>, body: Vec<Self::Item>, location: Option<Span>) -> Self::Case;
+    fn build_catch(&mut self, param: Self::Patt, body: Vec<Self::Item>, location: Option<Span>) -> Self::Catch;
+
+    // Declarations.
+    fn build_fun_decl(&mut self, fun: Self::Fun, location: Option<Span>) -> Self::Decl;
+    fn build_class_decl(&mut self, class: Class, location: Option<Span>) -> Self::Decl;
+    fn build_ts_decl(&mut self, decl: Decl) -> Self::Decl;
+
+    fn build_decl_item(&mut self, decl: Self::Decl) -> Self::Item;
+    fn build_stmt_item(&mut self, stmt: Self::Stmt) -> Self::Item;
+
+    fn build_script(&mut self, body: Vec<Self::Item>, location: Option<Span>) -> Self::Script;
+    fn build_module(&mut self, body: Vec<Self::Item>, location: Option<Span>) -> Self::Module;
+}
+
+// The identity builder, which rebuilds the easter AST it is given.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Easter;
+
+fn tracked<T>(value: T, location: Option<Span>) -> Tracked<T> {
+    Tracked { value: value, location: location }
+}
+
+impl Builder for Easter {
+    type Id = Id;
+    type Patt = Patt<Id>;
+    type Target = Patt<AssignTarget>;
+    type Expr = Expr;
+    type Prop = Prop;
+    type Fun = Fun;
+    type Dtor = Dtor;
+    type Stmt = Stmt;
+    type Case = Tracked<CaseData>;
+    type Catch = Tracked<CatchData>;
+    type Decl = Decl;
+    type Item = StmtListItem;
+    type Script = Script;
+    type Module = Module;
+
+    fn build_id(&mut self, id: Id) -> Id { id }
+
+    fn build_id_patt(&mut self, id: Id) -> Patt<Id> {
+        Patt::Simple(id)
+    }
+
+    fn build_array_patt(&mut self, elts: Vec<Option<Patt<Id>>>, location: Option<Span>) -> Patt<Id> {
+        Patt::Compound(tracked(CompoundPattData::Arr(elts), location))
+    }
+
+    fn build_object_patt(&mut self, props: Vec<Tracked<(PropKey, Patt<Id>)>>, location: Option<Span>) -> Patt<Id> {
+        let props = props.into_iter().map(|prop| {
+            prop.map(|(key, patt)| PropPattData { key: key, patt: patt })
+        }).collect();
+        Patt::Compound(tracked(CompoundPattData::Obj(props), location))
+    }
+
+    fn build_id_target(&mut self, id: Id, location: Option<Span>) -> Patt<AssignTarget> {
+        Patt::Simple(tracked(AssignTargetData::Id(id), location))
+    }
+
+    fn build_dot_target(&mut self, obj: Expr, key: DotKey, location: Option<Span>) -> Patt<AssignTarget> {
+        Patt::Simple(tracked(AssignTargetData::Dot(Box::new(obj), key), location))
+    }
+
+    fn build_brack_target(&mut self, obj: Expr, prop: Expr, location: Option<Span>) -> Patt<AssignTarget> {
+        Patt::Simple(tracked(AssignTargetData::Brack(Box::new(obj), Box::new(prop)), location))
+    }
+
+    fn build_array_target(&mut self, elts: Vec<Option<Patt<AssignTarget>>>, location: Option<Span>) -> Patt<AssignTarget> {
+        Patt::Compound(tracked(CompoundPattData::Arr(elts), location))
+    }
+
+    fn build_object_target(&mut self, props: Vec<Tracked<(PropKey, Patt<AssignTarget>)>>, location: Option<Span>) -> Patt<AssignTarget> {
+        let props = props.into_iter().map(|prop| {
+            prop.map(|(key, patt)| PropPattData { key: key, patt: patt })
+        }).collect();
+        Patt::Compound(tracked(CompoundPattData::Obj(props), location))
+    }
+
+    fn build_this(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::This, location)
+    }
+
+    fn build_id_expr(&mut self, id: Id, location: Option<Span>) -> Expr {
+        tracked(ExprData::Id(id), location)
+    }
+
+    fn build_array(&mut self, elts: Vec<Option<Expr>>, location: Option<Span>) -> Expr {
+        tracked(ExprData::Arr(elts), location)
+    }
+
+    fn build_object(&mut self, props: Vec<Prop>, location: Option<Span>) -> Expr {
+        tracked(ExprData::Obj(props), location)
+    }
+
+    fn build_fun_expr(&mut self, fun: Fun, location: Option<Span>) -> Expr {
+        tracked(ExprData::Fun(fun), location)
+    }
+
+    fn build_class_expr(&mut self, class: Class, location: Option<Span>) -> Expr {
+        tracked(ExprData::Class(class), location)
+    }
+
+    fn build_seq(&mut self, exprs: Vec<Expr>, location: Option<Span>) -> Expr {
+        tracked(ExprData::Seq(exprs), location)
+    }
+
+    fn build_unop(&mut self, op: Unop, arg: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Unop(op, Box::new(arg)), location)
+    }
+
+    fn build_binop(&mut self, op: Binop, left: Expr, right: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Binop(op, Box::new(left), Box::new(right)), location)
+    }
+
+    fn build_logop(&mut self, op: Logop, left: Expr, right: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Logop(op, Box::new(left), Box::new(right)), location)
+    }
+
+    fn build_pre_inc(&mut self, arg: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::PreInc(Box::new(arg)), location)
+    }
+
+    fn build_post_inc(&mut self, arg: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::PostInc(Box::new(arg)), location)
+    }
+
+    fn build_pre_dec(&mut self, arg: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::PreDec(Box::new(arg)), location)
+    }
+
+    fn build_post_dec(&mut self, arg: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::PostDec(Box::new(arg)), location)
+    }
+
+    fn build_assign(&mut self, op: Assop, target: Patt<AssignTarget>, value: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Assign(op, target, Box::new(value)), location)
+    }
+
+    fn build_cond(&mut self, test: Expr, cons: Expr, alt: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Cond(Box::new(test), Box::new(cons), Box::new(alt)), location)
+    }
+
+    fn build_call(&mut self, callee: Expr, args: Vec<Expr>, location: Option<Span>) -> Expr {
+        tracked(ExprData::Call(Box::new(callee), args), location)
+    }
+
+    fn build_new(&mut self, callee: Expr, args: Option<Vec<Expr>>, location: Option<Span>) -> Expr {
+        tracked(ExprData::New(Box::new(callee), args), location)
+    }
+
+    fn build_dot(&mut self, obj: Expr, key: DotKey, location: Option<Span>) -> Expr {
+        tracked(ExprData::Dot(Box::new(obj), key), location)
+    }
+
+    fn build_brack(&mut self, obj: Expr, prop: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Brack(Box::new(obj), Box::new(prop)), location)
+    }
+
+    fn build_new_target(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::NewTarget, location)
+    }
+
+    fn build_import_meta(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::ImportMeta, location)
+    }
+
+    fn build_import(&mut self, source: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Import(Box::new(source)), location)
+    }
+
+    fn build_true(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::True, location)
+    }
+
+    fn build_false(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::False, location)
+    }
+
+    fn build_null(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::Null, location)
+    }
+
+    fn build_number(&mut self, literal: NumberLiteral, location: Option<Span>) -> Expr {
+        tracked(ExprData::Number(literal), location)
+    }
+
+    fn build_regexp(&mut self, literal: RegExpLiteral, location: Option<Span>) -> Expr {
+        tracked(ExprData::RegExp(literal), location)
+    }
+
+    fn build_string(&mut self, literal: StringLiteral, location: Option<Span>) -> Expr {
+        tracked(ExprData::String(literal), location)
+    }
+
+    fn build_jsx_element(&mut self, element: JSXElement, location: Option<Span>) -> Expr {
+        tracked(ExprData::JSXElement(element), location)
+    }
+
+    fn build_jsx_fragment(&mut self, fragment: JSXFragment, location: Option<Span>) -> Expr {
+        tracked(ExprData::JSXFragment(fragment), location)
+    }
+
+    fn build_as(&mut self, expr: Expr, ty: Ty, location: Option<Span>) -> Expr {
+        tracked(ExprData::As(Box::new(expr), ty), location)
+    }
+
+    fn build_satisfies(&mut self, expr: Expr, ty: Ty, location: Option<Span>) -> Expr {
+        tracked(ExprData::Satisfies(Box::new(expr), ty), location)
+    }
+
+    fn build_instantiation(&mut self, expr: Expr, args: Vec<Ty>, location: Option<Span>) -> Expr {
+        tracked(ExprData::Instantiation(Box::new(expr), args), location)
+    }
+
+    fn build_non_null(&mut self, expr: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::NonNull(Box::new(expr)), location)
+    }
+
+    fn build_paren(&mut self, expr: Expr, location: Option<Span>) -> Expr {
+        tracked(ExprData::Paren(Box::new(expr)), location)
+    }
+
+    fn build_invalid_expr(&mut self, location: Option<Span>) -> Expr {
+        tracked(ExprData::Invalid, location)
+    }
+
+    fn build_prop(&mut self, key: PropKey, val: Tracked<PropVal<Expr, Patt<Id>, StmtListItem>>, location: Option<Span>) -> Prop {
+        let val = val.map(|val| match val {
+            PropVal::Init(expr)       => PropValData::Init(expr),
+            PropVal::Get(body)        => PropValData::Get(body),
+            PropVal::Set(param, body) => PropValData::Set(param, body)
+        });
+        tracked(PropData { key: key, val: val }, location)
+    }
+
+    fn build_fun(&mut self, id: Option<Id>, ty_params: Vec<TyParam>, params: Tracked<Vec<Patt<Id>>>,
+                 ret_ty: Option<Ty>, body: Vec<StmtListItem>, lazy: Option<LazyBody>, location: Option<Span>) -> Fun {
+        tracked(FunData {
+            id: id,
+            ty_params: ty_params,
+            params: params.map(|list| ParamsData { list: list }),
+            ret_ty: ret_ty,
+            body: body,
+            lazy: lazy
+        }, location)
+    }
+
+    fn build_dtor(&mut self, id: Id, init: Option<Expr>, location: Option<Span>) -> Dtor {
+        tracked(DtorData::Simple(id, init), location)
+    }
+
+    fn build_compound_dtor(&mut self, patt: Patt<Id>, init: Expr, location: Option<Span>) -> Dtor {
+        let patt = match patt {
+            Patt::Compound(patt) => patt,
+            Patt::Simple(_)      => { panic!("compound declarator with a simple pattern") }
+        };
+        tracked(DtorData::Compound(patt, init), location)
+    }
+
+    fn build_empty(&mut self, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Empty, location)
+    }
+
+    fn build_block(&mut self, body: Vec<StmtListItem>, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Block(body), location)
+    }
+
+    fn build_var_decl(&mut self, dtors: Vec<Dtor>, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Var(dtors, semi), location)
+    }
+
+    fn build_expr_stmt(&mut self, expr: Expr, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Expr(expr, semi), location)
+    }
+
+    fn build_if(&mut self, test: Expr, cons: Stmt, alt: Option<Stmt>, location: Option<Span>) -> Stmt {
+        tracked(StmtData::If(test, Box::new(cons), alt.map(Box::new)), location)
+    }
+
+    fn build_label(&mut self, label: Id, body: Stmt, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Label(label, Box::new(body)), location)
+    }
+
+    fn build_break(&mut self, label: Option<Id>, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Break(label, semi), location)
+    }
+
+    fn build_continue(&mut self, label: Option<Id>, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Cont(label, semi), location)
+    }
+
+    fn build_with(&mut self, obj: Expr, body: Stmt, location: Option<Span>) -> Stmt {
+        tracked(StmtData::With(obj, Box::new(body)), location)
+    }
+
+    fn build_switch(&mut self, disc: Expr, cases: Vec<Tracked<CaseData>>, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Switch(disc, cases), location)
+    }
+
+    fn build_return(&mut self, arg: Option<Expr>, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Return(arg, semi), location)
+    }
+
+    fn build_throw(&mut self, arg: Expr, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Throw(arg, semi), location)
+    }
+
+    fn build_try(&mut self, body: Vec<StmtListItem>, catch: Option<Tracked<CatchData>>, finally: Option<Vec<StmtListItem>>,
+                 location: Option<Span>) -> Stmt {
+        tracked(StmtData::Try(body, catch.map(Box::new), finally), location)
+    }
+
+    fn build_while(&mut self, test: Expr, body: Stmt, location: Option<Span>) -> Stmt {
+        tracked(StmtData::While(test, Box::new(body)), location)
+    }
+
+    fn build_do_while(&mut self, body: Stmt, test: Expr, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::DoWhile(Box::new(body), test, semi), location)
+    }
+
+    fn build_for(&mut self, head: Option<Tracked<ForHead<Dtor, Expr>>>, test: Option<Expr>,
+                 update: Option<Expr>, body: Stmt, location: Option<Span>) -> Stmt {
+        let head = head.map(|head| Box::new(head.map(|head| match head {
+            ForHead::Var(dtors) => ForHeadData::Var(dtors),
+            ForHead::Let(dtors) => ForHeadData::Let(dtors),
+            ForHead::Expr(expr) => ForHeadData::Expr(expr)
+        })));
+        tracked(StmtData::For(head, test, update, Box::new(body)), location)
+    }
+
+    fn build_for_in(&mut self, head: Tracked<ForInHead<Id, Patt<Id>, Expr>>, obj: Expr,
+                    body: Stmt, location: Option<Span>) -> Stmt {
+        let head = head.map(|head| match head {
+            ForInHead::VarInit(id, init) => ForInHeadData::VarInit(id, init),
+            ForInHead::Var(patt)         => ForInHeadData::Var(patt),
+            ForInHead::Let(patt)         => ForInHeadData::Let(patt),
+            ForInHead::Expr(expr)        => ForInHeadData::Expr(expr)
+        });
+        tracked(StmtData::ForIn(Box::new(head), obj, Box::new(body)), location)
+    }
+
+    fn build_for_of(&mut self, head: Tracked<ForOfHead<Patt<Id>, Expr>>, iter: Expr,
+                    body: Stmt, location: Option<Span>) -> Stmt {
+        let head = head.map(|head| match head {
+            ForOfHead::Var(patt)  => ForOfHeadData::Var(patt),
+            ForOfHead::Let(patt)  => ForOfHeadData::Let(patt),
+            ForOfHead::Expr(expr) => ForOfHeadData::Expr(expr)
+        });
+        tracked(StmtData::ForOf(Box::new(head), iter, Box::new(body)), location)
+    }
+
+    fn build_debugger(&mut self, semi: Semi, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Debugger(semi), location)
+    }
+
+    fn build_fun_stmt(&mut self, fun: Fun, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Fun(fun), location)
+    }
+
+    fn build_invalid_stmt(&mut self, location: Option<Span>) -> Stmt {
+        tracked(StmtData::Invalid, location)
+    }
+
+    fn build_case(&mut self, test: Option<Expr>, body: Vec<StmtListItem>, location: Option<Span>) -> Tracked<CaseData> {
+        tracked(CaseData { test: test, body: body }, location)
+    }
+
+    fn build_catch(&mut self, param: Patt<Id>, body: Vec<StmtListItem>, location: Option<Span>) -> Tracked<CatchData> {
+        tracked(CatchData { param: param, body: body }, location)
+    }
+
+    fn build_fun_decl(&mut self, fun: Fun, location: Option<Span>) -> Decl {
+        tracked(DeclData::Fun(fun), location)
+    }
+
+    fn build_class_decl(&mut self, class: Class, location: Option<Span>) -> Decl {
+        tracked(DeclData::Class(class), location)
+    }
+
+    fn build_ts_decl(&mut self, decl: Decl) -> Decl { decl }
+
+    fn build_decl_item(&mut self, decl: Decl) -> StmtListItem {
+        StmtListItem::Decl(decl)
+    }
+
+    fn build_stmt_item(&mut self, stmt: Stmt) -> StmtListItem {
+        StmtListItem::Stmt(stmt)
+    }
+
+    fn build_script(&mut self, body: Vec<StmtListItem>, location: Option<Span>) -> Script {
+        tracked(ScriptData { body: body }, location)
+    }
+
+    fn build_module(&mut self, body: Vec<StmtListItem>, location: Option<Span>) -> Module {
+        tracked(ModuleData { body: body }, location)
+    }
+}
+
+// Hands a parsed script to a builder, bottom-up.
+pub fn build_script<B: Builder>(b: &mut B, script: Script) -> B::Script {
+    let body = items(b, script.value.body);
+    b.build_script(body, script.location)
+}
+
+// Hands a parsed module to a builder, bottom-up.
+pub fn build_module<B: Builder>(b: &mut B, module: Module) -> B::Module {
+    let body = items(b, module.value.body);
+    b.build_module(body, module.location)
+}
+
+fn items<B: Builder>(b: &mut B, items: Vec<StmtListItem>) -> Vec<B::Item> {
+    items.into_iter().map(|item| match item {
+        StmtListItem::Decl(decl) => { let decl = self::decl(b, decl); b.build_decl_item(decl) }
+        StmtListItem::Stmt(stmt) => { let stmt = self::stmt(b, stmt); b.build_stmt_item(stmt) }
+    }).collect()
+}
+
+fn decl<B: Builder>(b: &mut B, decl: Decl) -> B::Decl {
+    let location = decl.location;
+    match decl.value {
+        DeclData::Fun(mut f) => { let f = fun(b, &mut f); b.build_fun_decl(f, location) }
+        DeclData::Class(cls) => b.build_class_decl(cls, location),
+        value                => b.build_ts_decl(tracked(value, location))
+    }
+}
+
+fn fun<B: Builder>(b: &mut B, fun: &mut Fun) -> B::Fun {
+    let FunData { ref mut id, ref mut ty_params, ref mut params, ref mut ret_ty, ref mut body, ref mut lazy } = fun.value;
+    let id = id.take().map(|id| b.build_id(id));
+    let params = Tracked {
+        value: params.value.list.drain(..).map(|p| patt(b, p)).collect(),
+        location: params.location
+    };
+    let body = items(b, replace(body, Vec::new()));
+    b.build_fun(id, replace(ty_params, Vec::new()), params, ret_ty.take(), body, lazy.take(), fun.location)
+}
+
+fn patt<B: Builder>(b: &mut B, patt: Patt<Id>) -> B::Patt {
+    match patt {
+        Patt::Simple(id) => { let id = b.build_id(id); b.build_id_patt(id) }
+        Patt::Compound(compound) => {
+            let location = compound.location;
+            match compound.value {
+                CompoundPattData::Arr(elts) => {
+                    let elts = elts.into_iter().map(|elt| elt.map(|p| self::patt(b, p))).collect();
+                    b.build_array_patt(elts, location)
+                }
+                CompoundPattData::Obj(props) => {
+                    let props = props.into_iter().map(|prop| {
+                        let PropPattData { key, patt: p } = prop.value;
+                        Tracked { value: (key, self::patt(b, p)), location: prop.location }
+                    }).collect();
+                    b.build_object_patt(props, location)
+                }
+            }
+        }
+    }
+}
+
+fn target<B: Builder>(b: &mut B, patt: &mut Patt<AssignTarget>) -> B::Target {
+    match *patt {
+        Patt::Simple(ref mut target) => {
+            let location = target.location;
+            match target.value {
+                AssignTargetData::Id(ref id) => { let id = b.build_id(id.clone()); b.build_id_target(id, location) }
+                AssignTargetData::Dot(ref mut obj, ref key) => {
+                    let obj = expr(b, take_expr(obj));
+                    b.build_dot_target(obj, key.clone(), location)
+                }
+                AssignTargetData::Brack(ref mut obj, ref mut prop) => {
+                    let obj = expr(b, take_expr(obj));
+                    let prop = expr(b, take_expr(prop));
+                    b.build_brack_target(obj, prop, location)
+                }
+            }
+        }
+        Patt::Compound(ref mut compound) => {
+            let location = compound.location;
+            match compound.value {
+                CompoundPattData::Arr(ref mut elts) => {
+                    let elts = elts.drain(..).map(|elt| elt.map(|mut p| target(b, &mut p))).collect();
+                    b.build_array_target(elts, location)
+                }
+                CompoundPattData::Obj(ref mut props) => {
+                    let props = props.drain(..).map(|prop| {
+                        let PropPattData { key, patt: mut p } = prop.value;
+                        Tracked { value: (key, target(b, &mut p)), location: prop.location }
+                    }).collect();
+                    b.build_object_target(props, location)
+                }
+            }
+        }
+    }
+}
+
+fn exprs<B: Builder, I: Iterator<Item=Expr>>(b: &mut B, exprs: I) -> Vec<B::Expr> {
+    exprs.map(|e| expr(b, e)).collect()
+}
+
+// `ExprData` implements `Drop`, so operands are taken out of the node rather
+// than moved, and leaf data is cloned.
+fn expr<B: Builder>(b: &mut B, mut expr: Expr) -> B::Expr {
+    let location = expr.location;
+    grow(|| match expr.value {
+        ExprData::This                 => b.build_this(location),
+        ExprData::Id(ref id)           => { let id = b.build_id(id.clone()); b.build_id_expr(id, location) }
+        ExprData::Arr(ref mut elts)    => {
+            let elts = elts.drain(..).map(|elt| elt.map(|e| self::expr(b, e))).collect();
+            b.build_array(elts, location)
+        }
+        ExprData::Obj(ref mut props)   => {
+            let props = props.drain(..).map(|p| prop(b, p)).collect();
+            b.build_object(props, location)
+        }
+        ExprData::Fun(ref mut f)       => { let f = fun(b, f); b.build_fun_expr(f, location) }
+        ExprData::Class(ref cls)       => b.build_class_expr(cls.clone(), location),
+        ExprData::Seq(ref mut list)    => { let list = exprs(b, list.drain(..)); b.build_seq(list, location) }
+        ExprData::Unop(ref op, ref mut arg) => {
+            let arg = self::expr(b, take_expr(arg));
+            b.build_unop(op.clone(), arg, location)
+        }
+        ExprData::Binop(ref op, ref mut l, ref mut r) => {
+            let l = self::expr(b, take_expr(l));
+            let r = self::expr(b, take_expr(r));
+            b.build_binop(op.clone(), l, r, location)
+        }
+        ExprData::Logop(ref op, ref mut l, ref mut r) => {
+            let l = self::expr(b, take_expr(l));
+            let r = self::expr(b, take_expr(r));
+            b.build_logop(op.clone(), l, r, location)
+        }
+        ExprData::PreInc(ref mut arg)  => { let arg = self::expr(b, take_expr(arg)); b.build_pre_inc(arg, location) }
+        ExprData::PostInc(ref mut arg) => { let arg = self::expr(b, take_expr(arg)); b.build_post_inc(arg, location) }
+        ExprData::PreDec(ref mut arg)  => { let arg = self::expr(b, take_expr(arg)); b.build_pre_dec(arg, location) }
+        ExprData::PostDec(ref mut arg) => { let arg = self::expr(b, take_expr(arg)); b.build_post_dec(arg, location) }
+        ExprData::Assign(ref op, ref mut lhs, ref mut rhs) => {
+            let lhs = target(b, lhs);
+            let rhs = self::expr(b, take_expr(rhs));
+            b.build_assign(op.clone(), lhs, rhs, location)
+        }
+        ExprData::Cond(ref mut test, ref mut cons, ref mut alt) => {
+            let test = self::expr(b, take_expr(test));
+            let cons = self::expr(b, take_expr(cons));
+            let alt = self::expr(b, take_expr(alt));
+            b.build_cond(test, cons, alt, location)
+        }
+        ExprData::Call(ref mut callee, ref mut args) => {
+            let callee = self::expr(b, take_expr(callee));
+            let args = exprs(b, args.drain(..));
+            b.build_call(callee, args, location)
+        }
+        ExprData::New(ref mut callee, ref mut args) => {
+            let callee = self::expr(b, take_expr(callee));
+            let args = args.as_mut().map(|args| exprs(b, args.drain(..)));
+            b.build_new(callee, args, location)
+        }
+        ExprData::Dot(ref mut obj, ref key) => {
+            let obj = self::expr(b, take_expr(obj));
+            b.build_dot(obj, key.clone(), location)
+        }
+        ExprData::Brack(ref mut obj, ref mut prop) => {
+            let obj = self::expr(b, take_expr(obj));
+            let prop = self::expr(b, take_expr(prop));
+            b.build_brack(obj, prop, location)
+        }
+        ExprData::NewTarget            => b.build_new_target(location),
+        ExprData::ImportMeta           => b.build_import_meta(location),
+        ExprData::Import(ref mut source) => {
+            let source = self::expr(b, take_expr(source));
+            b.build_import(source, location)
+        }
+        ExprData::True                 => b.build_true(location),
+        ExprData::False                => b.build_false(location),
+        ExprData::Null                 => b.build_null(location),
+        ExprData::Number(ref lit)      => b.build_number(lit.clone(), location),
+        ExprData::RegExp(ref lit)      => b.build_regexp(lit.clone(), location),
+        ExprData::String(ref lit)      => b.build_string(lit.clone(), location),
+        ExprData::JSXElement(ref elt)  => b.build_jsx_element(elt.clone(), location),
+        ExprData::JSXFragment(ref frag) => b.build_jsx_fragment(frag.clone(), location),
+        ExprData::As(ref mut e, ref ty) => {
+            let e = self::expr(b, take_expr(e));
+            b.build_as(e, ty.clone(), location)
+        }
+        ExprData::Satisfies(ref mut e, ref ty) => {
+            let e = self::expr(b, take_expr(e));
+            b.build_satisfies(e, ty.clone(), location)
+        }
+        ExprData::Instantiation(ref mut e, ref mut args) => {
+            let e = self::expr(b, take_expr(e));
+            b.build_instantiation(e, replace(args, Vec::new()), location)
+        }
+        ExprData::NonNull(ref mut e)   => { let e = self::expr(b, take_expr(e)); b.build_non_null(e, location) }
+        ExprData::Paren(ref mut e)     => { let e = self::expr(b, take_expr(e)); b.build_paren(e, location) }
+        ExprData::Invalid              => b.build_invalid_expr(location)
+    })
+}
+
+fn prop<B: Builder>(b: &mut B, prop: Prop) -> B::Prop {
+    let PropData { key, val } = prop.value;
+    let val_location = val.location;
+    let val = match val.value {
+        PropValData::Init(e)           => PropVal::Init(expr(b, e)),
+        PropValData::Get(body)         => PropVal::Get(items(b, body)),
+        PropValData::Set(param, body)  => {
+            let param = patt(b, param);
+            PropVal::Set(param, items(b, body))
+        }
+    };
+    b.build_prop(key, Tracked { value: val, location: val_location }, prop.location)
+}
+
+fn dtor<B: Builder>(b: &mut B, dtor: Dtor) -> B::Dtor {
+    match dtor.value {
+        DtorData::Simple(id, init) => {
+            let id = b.build_id(id);
+            let init = init.map(|e| expr(b, e));
+            b.build_dtor(id, init, dtor.location)
+        }
+        DtorData::Compound(p, init) => {
+            let p = patt(b, Patt::Compound(p));
+            let init = expr(b, init);
+            b.build_compound_dtor(p, init, dtor.location)
+        }
+    }
+}
+
+fn dtors<B: Builder>(b: &mut B, list: Vec<Dtor>) -> Vec<B::Dtor> {
+    list.into_iter().map(|d| dtor(b, d)).collect()
+}
+
+// `StmtData` implements `Drop` too, so nested statements are taken out of the
+// node as well.
+fn stmt<B: Builder>(b: &mut B, mut stmt: Stmt) -> B::Stmt {
+    let location = stmt.location;
+    grow(|| match stmt.value {
+        StmtData::Empty                => b.build_empty(location),
+        StmtData::Block(ref mut body)  => { let body = items(b, replace(body, Vec::new())); b.build_block(body, location) }
+        StmtData::Var(ref mut list, semi) => {
+            let list = dtors(b, replace(list, Vec::new()));
+            b.build_var_decl(list, semi, location)
+        }
+        StmtData::Expr(ref mut e, semi) => { let e = expr(b, take_expr(e)); b.build_expr_stmt(e, semi, location) }
+        StmtData::If(ref mut test, ref mut cons, ref mut alt) => {
+            let test = expr(b, take_expr(test));
+            let cons = self::stmt(b, take_stmt(cons));
+            let alt = alt.as_mut().map(|alt| self::stmt(b, take_stmt(alt)));
+            b.build_if(test, cons, alt, location)
+        }
+        StmtData::Label(ref id, ref mut body) => {
+            let id = b.build_id(id.clone());
+            let body = self::stmt(b, take_stmt(body));
+            b.build_label(id, body, location)
+        }
+        StmtData::Break(ref mut id, semi) => { let id = id.take().map(|id| b.build_id(id)); b.build_break(id, semi, location) }
+        StmtData::Cont(ref mut id, semi)  => { let id = id.take().map(|id| b.build_id(id)); b.build_continue(id, semi, location) }
+        StmtData::With(ref mut obj, ref mut body) => {
+            let obj = expr(b, take_expr(obj));
+            let body = self::stmt(b, take_stmt(body));
+            b.build_with(obj, body, location)
+        }
+        StmtData::Switch(ref mut disc, ref mut cases) => {
+            let disc = expr(b, take_expr(disc));
+            let cases = cases.drain(..).map(|case| {
+                let CaseData { test, body } = case.value;
+                let test = test.map(|e| expr(b, e));
+                let body = items(b, body);
+                b.build_case(test, body, case.location)
+            }).collect();
+            b.build_switch(disc, cases, location)
+        }
+        StmtData::Return(ref mut arg, semi) => {
+            let arg = arg.take().map(|e| expr(b, e));
+            b.build_return(arg, semi, location)
+        }
+        StmtData::Throw(ref mut arg, semi) => { let arg = expr(b, take_expr(arg)); b.build_throw(arg, semi, location) }
+        StmtData::Try(ref mut body, ref mut catch, ref mut finally) => {
+            let body = items(b, replace(body, Vec::new()));
+            let catch = catch.take().map(|catch| {
+                let catch = *catch;
+                let CatchData { param, body } = catch.value;
+                let param = patt(b, param);
+                let body = items(b, body);
+                b.build_catch(param, body, catch.location)
+            });
+            let finally = finally.take().map(|body| items(b, body));
+            b.build_try(body, catch, finally, location)
+        }
+        StmtData::While(ref mut test, ref mut body) => {
+            let test = expr(b, take_expr(test));
+            let body = self::stmt(b, take_stmt(body));
+            b.build_while(test, body, location)
+        }
+        StmtData::DoWhile(ref mut body, ref mut test, semi) => {
+            let body = self::stmt(b, take_stmt(body));
+            let test = expr(b, take_expr(test));
+            b.build_do_while(body, test, semi, location)
+        }
+        StmtData::For(ref mut head, ref mut test, ref mut update, ref mut body) => {
+            let head = head.take().map(|head| {
+                let head = *head;
+                let value = match head.value {
+                    ForHeadData::Var(list) => ForHead::Var(dtors(b, list)),
+                    ForHeadData::Let(list) => ForHead::Let(dtors(b, list)),
+                    ForHeadData::Expr(e)   => ForHead::Expr(expr(b, e))
+                };
+                Tracked { value: value, location: head.location }
+            });
+            let test = test.take().map(|e| expr(b, e));
+            let update = update.take().map(|e| expr(b, e));
+            let body = self::stmt(b, take_stmt(body));
+            b.build_for(head, test, update, body, location)
+        }
+        StmtData::ForIn(ref mut head, ref mut obj, ref mut body) => {
+            let head = replace(&mut **head, ForInHeadData::Expr(ExprData::Invalid.tracked(None)).tracked(None));
+            let value = match head.value {
+                ForInHeadData::VarInit(id, init) => {
+                    let id = b.build_id(id);
+                    ForInHead::VarInit(id, expr(b, init))
+                }
+                ForInHeadData::Var(p)  => ForInHead::Var(patt(b, p)),
+                ForInHeadData::Let(p)  => ForInHead::Let(patt(b, p)),
+                ForInHeadData::Expr(e) => ForInHead::Expr(expr(b, e))
+            };
+            let head = Tracked { value: value, location: head.location };
+            let obj = expr(b, take_expr(obj));
+            let body = self::stmt(b, take_stmt(body));
+            b.build_for_in(head, obj, body, location)
+        }
+        StmtData::ForOf(ref mut head, ref mut iter, ref mut body) => {
+            let head = replace(&mut **head, ForOfHeadData::Expr(ExprData::Invalid.tracked(None)).tracked(None));
+            let value = match head.value {
+                ForOfHeadData::Var(p)  => ForOfHead::Var(patt(b, p)),
+                ForOfHeadData::Let(p)  => ForOfHead::Let(patt(b, p)),
+                ForOfHeadData::Expr(e) => ForOfHead::Expr(expr(b, e))
+            };
+            let head = Tracked { value: value, location: head.location };
+            let iter = expr(b, take_expr(iter));
+            let body = self::stmt(b, take_stmt(body));
+            b.build_for_of(head, iter, body, location)
+        }
+        StmtData::Debugger(semi)       => b.build_debugger(semi, location),
+        StmtData::Fun(ref mut f)       => { let f = fun(b, f); b.build_fun_stmt(f, location) }
+        StmtData::Invalid              => b.build_invalid_stmt(location)
+    })
+}
+
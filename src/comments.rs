@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use joker::comment::Comment;
+use joker::track::*;
+use easter::prog::{Script, Module};
+use easter::stmt::{Stmt, StmtData, StmtListItem, Case, Catch, ForHeadData, ForInHeadData, ForOfHeadData};
+use easter::decl::{Decl, DeclData, Dtor, DtorData};
+use easter::expr::{Expr, ExprData};
+use easter::fun::Fun;
+use easter::obj::{Prop, PropValData};
+use easter::class::{Class, ClassMember, ClassMemberVal};
+
+// Comments attached to the nodes of a tree.
+//
+// Each comment is attached to a single node: it trails the node before it if
+// it starts on the line that node ends on (and the next node starts on a later
+// line), and otherwise leads the node after it. A comment with no sibling on
+// either side, such as one in an empty block, dangles inside the enclosing
+// node.
+//
+// Leading comments are keyed by the start of the node and trailing comments by
+// its end, so a node shares them with any node beginning or ending at the same
+// place (an expression statement and its expression, for example).
+#[derive(Debug, Clone, Default)]
+pub struct CommentMap {
+    leading: HashMap<Posn, Vec<Comment>>,
+    trailing: HashMap<Posn, Vec<Comment>>,
+    dangling: HashMap<Span, Vec<Comment>>
+}
+
+impl CommentMap {
+    pub fn from_script(script: &Script, comments: Vec<Comment>) -> CommentMap {
+        CommentMap::attach(Node::Program(&script.value.body, script.location), comments)
+    }
+
+    pub fn from_module(module: &Module, comments: Vec<Comment>) -> CommentMap {
+        CommentMap::attach(Node::Program(&module.value.body, module.location), comments)
+    }
+
+    fn attach(root: Node, comments: Vec<Comment>) -> CommentMap {
+        let mut map = CommentMap::default();
+        for comment in comments {
+            map.attach_comment(root, comment);
+        }
+        map
+    }
+
+    fn attach_comment(&mut self, root: Node, comment: Comment) {
+        let target = comment.location;
+        let mut enclosing = root;
+        'descend: loop {
+            let mut preceding: Option<Span> = None;
+            let mut following: Option<Span> = None;
+            for (child, span) in enclosing.children() {
+                if span.end.offset <= target.start.offset {
+                    preceding = Some(span);
+                } else if span.start.offset >= target.end.offset {
+                    following = Some(span);
+                    break;
+                } else if span.start.offset <= target.start.offset && span.end.offset >= target.end.offset {
+                    enclosing = child;
+                    continue 'descend;
+                }
+            }
+
+            let (map, key) = match (preceding, following) {
+                (Some(before), Some(after)) => {
+                    if before.end.line == target.start.line && after.start.line > target.end.line {
+                        (&mut self.trailing, before.end)
+                    } else {
+                        (&mut self.leading, after.start)
+                    }
+                }
+                (Some(before), None) => (&mut self.trailing, before.end),
+                (None, Some(after))  => (&mut self.leading, after.start),
+                (None, None)         => {
+                    let span = enclosing.location().unwrap_or(target);
+                    self.dangling.entry(span).or_insert_with(Vec::new).push(comment);
+                    return;
+                }
+            };
+            map.entry(key).or_insert_with(Vec::new).push(comment);
+            return;
+        }
+    }
+
+    pub fn leading<T: Track>(&self, node: &T) -> &[Comment] {
+        node.location()
+            .and_then(|span| self.leading.get(&span.start))
+            .map_or(&[], |comments| &comments[..])
+    }
+
+    pub fn trailing<T: Track>(&self, node: &T) -> &[Comment] {
+        node.location()
+            .and_then(|span| self.trailing.get(&span.end))
+            .map_or(&[], |comments| &comments[..])
+    }
+
+    pub fn dangling<T: Track>(&self, node: &T) -> &[Comment] {
+        node.location()
+            .and_then(|span| self.dangling.get(&span))
+            .map_or(&[], |comments| &comments[..])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty() && self.dangling.is_empty()
+    }
+}
+
+// The nodes comments can be attached to. Patterns, types and JSX are not
+// descended into; comments inside them attach around the enclosing node.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Program(&'a Vec<StmtListItem>, Option<Span>),
+    Item(&'a StmtListItem),
+    Stmt(&'a Stmt),
+    Decl(&'a Decl),
+    Expr(&'a Expr),
+    Fun(&'a Fun),
+    Class(&'a Class),
+    ClassMember(&'a ClassMember),
+    Prop(&'a Prop),
+    Dtor(&'a Dtor),
+    Case(&'a Case),
+    Catch(&'a Catch)
+}
+
+impl<'a> Node<'a> {
+    fn location(&self) -> Option<Span> {
+        match *self {
+            Node::Program(_, location) => location,
+            Node::Item(item)           => item.location(),
+            Node::Stmt(stmt)           => stmt.location,
+            Node::Decl(decl)           => decl.location,
+            Node::Expr(expr)           => expr.location,
+            Node::Fun(fun)             => fun.location,
+            Node::Class(class)         => class.location,
+            Node::ClassMember(member)  => member.location,
+            Node::Prop(prop)           => prop.location,
+            Node::Dtor(dtor)           => dtor.location,
+            Node::Case(case)           => case.location,
+            Node::Catch(catch)         => catch.location
+        }
+    }
+
+    // The located children of the node, in source order.
+    fn children(&self) -> Vec<(Node<'a>, Span)> {
+        let mut children = Vec::new();
+        match *self {
+            Node::Program(items, _)   => { children.extend(items.iter().map(Node::Item)); }
+            Node::Item(&StmtListItem::Decl(ref decl)) => { children.push(Node::Decl(decl)); }
+            Node::Item(&StmtListItem::Stmt(ref stmt)) => { children.push(Node::Stmt(stmt)); }
+            Node::Stmt(stmt)          => { stmt_children(&stmt.value, &mut children); }
+            Node::Decl(decl)          => match decl.value {
+                DeclData::Fun(ref fun)     => { children.push(Node::Fun(fun)); }
+                DeclData::Class(ref class) => { children.push(Node::Class(class)); }
+                DeclData::Declare(ref item)
+              | DeclData::Export(ref item) => { children.push(Node::Item(item)); }
+                DeclData::Namespace(_, ref items)
+              | DeclData::Module(_, Some(ref items)) => { children.extend(items.iter().map(Node::Item)); }
+                _ => { }
+            },
+            Node::Expr(expr)          => { expr_children(&expr.value, &mut children); }
+            Node::Fun(fun)            => { children.extend(fun.value.body.iter().map(Node::Item)); }
+            Node::Class(class)        => {
+                children.extend(class.value.extends.iter().map(|expr| Node::Expr(expr)));
+                children.extend(class.value.body.iter().map(Node::ClassMember));
+            }
+            Node::ClassMember(member) => match member.value.val {
+                ClassMemberVal::Method(ref fun)
+              | ClassMemberVal::Get(ref fun)
+              | ClassMemberVal::Set(ref fun)               => { children.push(Node::Fun(fun)); }
+                ClassMemberVal::Field(_, Some(ref init))   => { children.push(Node::Expr(init)); }
+                _ => { }
+            },
+            Node::Prop(prop)          => match prop.value.val.value {
+                PropValData::Init(ref expr)  => { children.push(Node::Expr(expr)); }
+                PropValData::Get(ref body)
+              | PropValData::Set(_, ref body) => { children.extend(body.iter().map(Node::Item)); }
+            },
+            Node::Dtor(dtor)          => match dtor.value {
+                DtorData::Simple(_, Some(ref init))
+              | DtorData::Compound(_, ref init) => { children.push(Node::Expr(init)); }
+                _ => { }
+            },
+            Node::Case(case)          => {
+                children.extend(case.value.test.iter().map(Node::Expr));
+                children.extend(case.value.body.iter().map(Node::Item));
+            }
+            Node::Catch(catch)        => { children.extend(catch.value.body.iter().map(Node::Item)); }
+        }
+        let mut children: Vec<(Node, Span)> = children.into_iter()
+            .filter_map(|child| child.location().map(|span| (child, span)))
+            .collect();
+        children.sort_by_key(|&(_, span)| span.start.offset);
+        children
+    }
+}
+
+fn stmt_children<'a>(stmt: &'a StmtData, children: &mut Vec<Node<'a>>) {
+    match *stmt {
+        StmtData::Block(ref items)                => { children.extend(items.iter().map(Node::Item)); }
+        StmtData::Var(ref dtors, _)               => { children.extend(dtors.iter().map(Node::Dtor)); }
+        StmtData::Expr(ref expr, _)
+      | StmtData::Throw(ref expr, _)              => { children.push(Node::Expr(expr)); }
+        StmtData::Return(ref expr, _)             => { children.extend(expr.iter().map(Node::Expr)); }
+        StmtData::If(ref test, ref cons, ref alt) => {
+            children.push(Node::Expr(test));
+            children.push(Node::Stmt(cons));
+            children.extend(alt.iter().map(|alt| Node::Stmt(alt)));
+        }
+        StmtData::Label(_, ref body)              => { children.push(Node::Stmt(body)); }
+        StmtData::With(ref expr, ref body)
+      | StmtData::While(ref expr, ref body)       => {
+            children.push(Node::Expr(expr));
+            children.push(Node::Stmt(body));
+        }
+        StmtData::DoWhile(ref body, ref expr, _)  => {
+            children.push(Node::Stmt(body));
+            children.push(Node::Expr(expr));
+        }
+        StmtData::Switch(ref disc, ref cases)     => {
+            children.push(Node::Expr(disc));
+            children.extend(cases.iter().map(Node::Case));
+        }
+        StmtData::Try(ref body, ref catch, ref finally) => {
+            children.extend(body.iter().map(Node::Item));
+            children.extend(catch.iter().map(|catch| Node::Catch(catch)));
+            if let Some(ref finally) = *finally {
+                children.extend(finally.iter().map(Node::Item));
+            }
+        }
+        StmtData::For(ref head, ref test, ref update, ref body) => {
+            if let Some(ref head) = *head {
+                match head.value {
+                    ForHeadData::Var(ref dtors)
+                  | ForHeadData::Let(ref dtors) => { children.extend(dtors.iter().map(Node::Dtor)); }
+                    ForHeadData::Expr(ref expr) => { children.push(Node::Expr(expr)); }
+                }
+            }
+            children.extend(test.iter().map(Node::Expr));
+            children.extend(update.iter().map(Node::Expr));
+            children.push(Node::Stmt(body));
+        }
+        StmtData::ForIn(ref head, ref obj, ref body) => {
+            match head.value {
+                ForInHeadData::VarInit(_, ref expr)
+              | ForInHeadData::Expr(ref expr) => { children.push(Node::Expr(expr)); }
+                _ => { }
+            }
+            children.push(Node::Expr(obj));
+            children.push(Node::Stmt(body));
+        }
+        StmtData::ForOf(ref head, ref iter, ref body) => {
+            if let ForOfHeadData::Expr(ref expr) = head.value {
+                children.push(Node::Expr(expr));
+            }
+            children.push(Node::Expr(iter));
+            children.push(Node::Stmt(body));
+        }
+        StmtData::Fun(ref fun)                    => { children.push(Node::Fun(fun)); }
+        StmtData::Empty
+      | StmtData::Break(_, _)
+      | StmtData::Cont(_, _)
+      | StmtData::Debugger(_)
+      | StmtData::Invalid                         => { }
+    }
+}
+
+fn expr_children<'a>(expr: &'a ExprData, children: &mut Vec<Node<'a>>) {
+    match *expr {
+        ExprData::Arr(ref elts)                   => {
+            children.extend(elts.iter().filter_map(|elt| elt.as_ref()).map(Node::Expr));
+        }
+        ExprData::Obj(ref props)                  => { children.extend(props.iter().map(Node::Prop)); }
+        ExprData::Fun(ref fun)                    => { children.push(Node::Fun(fun)); }
+        ExprData::Class(ref class)                => { children.push(Node::Class(class)); }
+        ExprData::Seq(ref exprs)                  => { children.extend(exprs.iter().map(Node::Expr)); }
+        ExprData::Unop(_, ref arg)
+      | ExprData::PreInc(ref arg)
+      | ExprData::PostInc(ref arg)
+      | ExprData::PreDec(ref arg)
+      | ExprData::PostDec(ref arg)
+      | ExprData::Assign(_, _, ref arg)
+      | ExprData::Dot(ref arg, _)
+      | ExprData::Import(ref arg)
+      | ExprData::As(ref arg, _)
+      | ExprData::Satisfies(ref arg, _)
+      | ExprData::Instantiation(ref arg, _)
+      | ExprData::NonNull(ref arg)
+      | ExprData::Paren(ref arg)                => { children.push(Node::Expr(arg)); }
+        ExprData::Binop(_, ref left, ref right)
+      | ExprData::Logop(_, ref left, ref right)
+      | ExprData::Brack(ref left, ref right)    => {
+            children.push(Node::Expr(left));
+            children.push(Node::Expr(right));
+        }
+        ExprData::Cond(ref test, ref cons, ref alt) => {
+            children.push(Node::Expr(test));
+            children.push(Node::Expr(cons));
+            children.push(Node::Expr(alt));
+        }
+        ExprData::Call(ref callee, ref args)      => {
+            children.push(Node::Expr(callee));
+            children.extend(args.iter().map(Node::Expr));
+        }
+        ExprData::New(ref callee, ref args)       => {
+            children.push(Node::Expr(callee));
+            if let Some(ref args) = *args {
+                children.extend(args.iter().map(Node::Expr));
+            }
+        }
+        _ => { }
+    }
+}
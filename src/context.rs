@@ -1,7 +1,6 @@
 use std::collections::HashMap;
-use std::rc::Rc;
 use std::mem::replace;
-use joker::word::Name;
+use joker::word::{Atom, Name};
 use joker::track::{IntoTracked, span};
 use easter::stmt::{StmtData, Stmt};
 use easter::id::Id;
@@ -19,14 +18,14 @@ impl<I: Iterator<Item=char>> WithContext for Parser<I> {
     fn with_labels<F>(&mut self, mut labels: Vec<Id>, label_type: LabelType, op: F) -> Result<Stmt>
       where F: FnOnce(&mut Self) -> Result<Stmt>
     {
-        let mut label_strings = Vec::new();
+        let mut label_names = Vec::new();
         for id in labels.iter() {
-            let label = Rc::new(id.value.name.clone());
-            self.parser_cx.labels.insert(label.clone(), label_type);
-            label_strings.push(label);
+            let label = id.value.name;
+            self.parser_cx.labels.insert(label, label_type);
+            label_names.push(label);
         }
         let result = op(self);
-        for label in label_strings {
+        for label in label_names {
             self.parser_cx.labels.remove(&label);
         }
         let mut body = try!(result);
@@ -60,7 +59,12 @@ pub struct Context {
     pub iteration: bool,
     pub switch: bool,
     pub allow_in: bool,
-    pub labels: HashMap<Rc<Name>, LabelType>
+    pub allow_yield: bool,
+    pub allow_await: bool,
+    // Within the parameters of a TypeScript constructor, which may also
+    // declare properties (`constructor(private x: T)`).
+    pub param_props: bool,
+    pub labels: HashMap<Name, LabelType>
 }
 
 impl Context {
@@ -70,6 +74,9 @@ impl Context {
             iteration: false,
             switch: false,
             allow_in: true,
+            allow_yield: false,
+            allow_await: false,
+            param_props: false,
             labels: HashMap::new()
         }
     }
@@ -80,7 +87,20 @@ impl Context {
             iteration: false,
             switch: false,
             allow_in: true,
+            allow_yield: false,
+            allow_await: false,
+            param_props: false,
             labels: HashMap::new()
         }
     }
+
+    // `yield` is reserved within generator functions and `await` within
+    // async functions.
+    pub fn reserves(&self, name: &Name) -> bool {
+        match *name {
+            Name::Atom(Atom::Yield) => self.allow_yield,
+            Name::Atom(Atom::Await) => self.allow_await,
+            _                       => false
+        }
+    }
 }
@@ -0,0 +1,120 @@
+use std::cmp;
+use joker::track::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool
+}
+
+impl Label {
+    pub fn primary(span: Span, message: String) -> Label {
+        Label { span: span, message: message, primary: true }
+    }
+
+    pub fn secondary(span: Span, message: String) -> Label {
+        Label { span: span, message: message, primary: false }
+    }
+}
+
+// A reportable form of an error: a code, a message, the source locations it
+// refers to and an optional hint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub hint: Option<String>
+}
+
+impl Diagnostic {
+    // Renders the diagnostic in the style of rustc: a header, the location of
+    // the primary label, and an excerpt of each labelled line with the labelled
+    // text underlined (`^` for the primary label, `-` for the others).
+    //
+    //     error[E0023]: expected `)`, found `}`
+    //      --> 2:1
+    //       |
+    //     1 | f(a, b
+    //       |  - `(` opened here
+    //     2 | }
+    //       | ^ expected `)`
+    //       |
+    //       = hint: expected `)` to close `(` opened here
+    pub fn render(&self, source: &str) -> String {
+        let lines = source_lines(source);
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|label| (label.span.start.line, label.span.start.column));
+
+        let last_line = labels.iter().fold(0, |max, label| cmp::max(max, label.span.start.line));
+        let pad = " ".repeat((last_line + 1).to_string().len());
+
+        let mut out = format!("error[{}]: {}\n", self.code, self.message);
+        if let Some(label) = self.labels.iter().find(|label| label.primary) {
+            let start = label.span.start;
+            out.push_str(&format!("{}--> {}:{}\n", pad, start.line + 1, start.column + 1));
+        }
+        if !labels.is_empty() {
+            out.push_str(&format!("{} |\n", pad));
+        }
+
+        let mut prev_line = None;
+        for label in labels {
+            let Span { start, end } = label.span;
+            let text = lines.get(start.line as usize).map_or("", |line| &line[..]);
+            if prev_line != Some(start.line) {
+                let number = (start.line + 1).to_string();
+                out.push_str(&format!("{}{} | {}\n", number, &pad[number.len()..], text));
+                prev_line = Some(start.line);
+            }
+
+            // Indent with the line's own whitespace so tabs stay aligned.
+            let indent: String = text.chars()
+                .take(start.column as usize)
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+            let stop = if end.line == start.line { end.column } else { text.chars().count() as u32 };
+            let width = stop.saturating_sub(start.column);
+            let marker = if label.primary { "^" } else { "-" };
+            let mut underline = format!("{} | {}{}", pad, indent, marker.repeat(cmp::max(width, 1) as usize));
+            if !label.message.is_empty() {
+                underline.push(' ');
+                underline.push_str(&label.message);
+            }
+            out.push_str(&underline);
+            out.push('\n');
+        }
+
+        if let Some(ref hint) = self.hint {
+            out.push_str(&format!("{} |\n", pad));
+            out.push_str(&format!("{} = hint: {}\n", pad, hint));
+        }
+        out
+    }
+}
+
+// Splits the source at the same line terminators the lexer counts.
+fn source_lines(source: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut chars = source.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                lines.push(line);
+                line = String::new();
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {
+                lines.push(line);
+                line = String::new();
+            }
+            _ => { line.push(ch); }
+        }
+    }
+    lines.push(line);
+    lines
+}
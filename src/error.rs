@@ -1,8 +1,18 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::fmt::{Display, Formatter};
 use joker;
-use joker::token::Token;
+use joker::error::ErrorData;
+use joker::token::{Token, TokenData};
 use joker::track::*;
 use easter::id::Id;
 use easter::cover;
+use easter::jsx::JSXName;
+use options::Edition;
+use input::Encoding;
+use diagnostic::{Diagnostic, Label};
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -22,5 +32,331 @@ pub enum Error {
     ThrowArgument(Token),
     OrphanTry(Token),
     InvalidLHS(Option<Span>, cover::Error),
-    UnsupportedFeature(&'static str)
+    ImportMetaOutsideModule(Span),
+    ForInVarInit(Option<Span>),
+    MismatchedJSXTag(JSXName, JSXName),
+    UnsupportedFeature(&'static str, Option<Span>),
+    UnsupportedEdition(&'static str, Edition, Option<Span>),
+    Expected(TokenData, Token),
+    Unclosed(Token, TokenData, Token),
+    NestingTooDeep(Span),
+    InvalidEncoding(Encoding, Span),
+    Io(io::ErrorKind, String),
+    SourceTooLong(Span),
+    TooManyTokens(Span),
+    TooManyNodes(Option<Span>),
+    Cancelled(Span),
+    Panicked(String),
+    InvalidEdit(Range<usize>)
+}
+
+impl Error {
+    // A stable identifier for each kind of error. Codes are never reused or
+    // renumbered, so tools can match on them.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::UnexpectedToken(_)            => "E0001",
+            Error::FailedASI(_)                  => "E0002",
+            Error::LexError(_)                   => "E0003",
+            Error::TopLevelReturn(_)             => "E0004",
+            Error::IllegalBreak(_)               => "E0005",
+            Error::IllegalContinue(_)            => "E0006",
+            Error::InvalidLabel(_)               => "E0007",
+            Error::InvalidLabelType(_)           => "E0008",
+            Error::ContextualKeyword(_)          => "E0009",
+            Error::IllegalStrictBinding(_)       => "E0010",
+            Error::ForOfLetExpr(_)               => "E0011",
+            Error::DuplicateDefault(_)           => "E0012",
+            Error::StrictWith(_)                 => "E0013",
+            Error::ThrowArgument(_)              => "E0014",
+            Error::OrphanTry(_)                  => "E0015",
+            Error::InvalidLHS(_, _)              => "E0016",
+            Error::ImportMetaOutsideModule(_)    => "E0017",
+            Error::ForInVarInit(_)               => "E0018",
+            Error::MismatchedJSXTag(_, _)        => "E0019",
+            Error::UnsupportedFeature(_, _)      => "E0020",
+            Error::UnsupportedEdition(_, _, _)   => "E0021",
+            Error::Expected(_, _)                => "E0022",
+            Error::Unclosed(_, _, _)             => "E0023",
+            Error::NestingTooDeep(_)             => "E0024",
+            Error::InvalidEncoding(_, _)         => "E0025",
+            Error::Io(_, _)                      => "E0026",
+            Error::SourceTooLong(_)              => "E0027",
+            Error::TooManyTokens(_)              => "E0028",
+            Error::TooManyNodes(_)               => "E0029",
+            Error::Cancelled(_)                  => "E0030",
+            Error::Panicked(_)                   => "E0031",
+            Error::InvalidEdit(_)                => "E0032"
+        }
+    }
+
+    // Whether the error is a resource limit or cancellation, which ends the
+    // parse even when recovering from errors.
+    pub fn is_fatal(&self) -> bool {
+        match *self {
+            Error::SourceTooLong(_)
+          | Error::TooManyTokens(_)
+          | Error::TooManyNodes(_)
+          | Error::Cancelled(_)    => true,
+            _                      => false
+        }
+    }
+
+    // The source location the error is reported at.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Error::UnexpectedToken(ref token)
+          | Error::FailedASI(ref token)
+          | Error::IllegalBreak(ref token)
+          | Error::IllegalContinue(ref token)
+          | Error::DuplicateDefault(ref token)
+          | Error::StrictWith(ref token)
+          | Error::ThrowArgument(ref token)
+          | Error::OrphanTry(ref token)
+          | Error::Expected(_, ref token)
+          | Error::Unclosed(_, _, ref token)       => Some(token.location),
+            Error::LexError(ref err)               => Some(err.location),
+            Error::TopLevelReturn(span)
+          | Error::ForOfLetExpr(span)
+          | Error::ImportMetaOutsideModule(span)
+          | Error::NestingTooDeep(span)
+          | Error::InvalidEncoding(_, span)
+          | Error::SourceTooLong(span)
+          | Error::TooManyTokens(span)
+          | Error::Cancelled(span)                 => Some(span),
+            Error::Io(_, _)
+          | Error::Panicked(_)
+          | Error::InvalidEdit(_)                  => None,
+            Error::InvalidLabel(ref id)
+          | Error::InvalidLabelType(ref id)
+          | Error::ContextualKeyword(ref id)
+          | Error::IllegalStrictBinding(ref id)    => id.location,
+            Error::InvalidLHS(span, _)
+          | Error::ForInVarInit(span)
+          | Error::UnsupportedFeature(_, span)
+          | Error::UnsupportedEdition(_, _, span)
+          | Error::TooManyNodes(span)              => span,
+            Error::MismatchedJSXTag(_, ref name)   => name.location
+        }
+    }
+
+    // A suggestion for fixing the error, where there is a useful one.
+    pub fn hint(&self) -> Option<String> {
+        match *self {
+            Error::FailedASI(_) => {
+                Some("a semicolon is only inserted before `}`, at the end of input or after a line break".to_string())
+            }
+            Error::StrictWith(_) => {
+                Some("use a local variable to refer to the object instead".to_string())
+            }
+            Error::ThrowArgument(_) => {
+                Some("the thrown expression must start on the same line as `throw`".to_string())
+            }
+            Error::UnsupportedEdition(_, edition, _) => {
+                Some(format!("target {} or later in the parse options", edition))
+            }
+            Error::NestingTooDeep(_) => {
+                Some("raise `max_depth` in the parse options".to_string())
+            }
+            Error::SourceTooLong(_) => {
+                Some("raise `max_source_len` in the parse options".to_string())
+            }
+            Error::TooManyTokens(_) => {
+                Some("raise `max_tokens` in the parse options".to_string())
+            }
+            Error::TooManyNodes(_) => {
+                Some("raise `max_nodes` in the parse options".to_string())
+            }
+            Error::Unclosed(ref open, ref close, _) => {
+                Some(format!("expected {} to close {} opened here", close, open.value))
+            }
+            _ => None
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let mut labels = Vec::new();
+        if let Some(span) = self.span() {
+            labels.push(Label::primary(span, self.label()));
+        }
+        match *self {
+            Error::Unclosed(ref open, _, _) => {
+                labels.push(Label::secondary(open.location, format!("{} opened here", open.value)));
+            }
+            Error::MismatchedJSXTag(ref opening, _) => {
+                if let Some(span) = opening.location {
+                    labels.push(Label::secondary(span, "opening tag here".to_string()));
+                }
+            }
+            Error::LexError(ref err) => {
+                let what = match err.value {
+                    ErrorData::UnterminatedString(_)  => Some("string"),
+                    ErrorData::UnterminatedComment    => Some("comment"),
+                    ErrorData::UnterminatedRegExp(_)  => Some("regular expression"),
+                    _                                 => None
+                };
+                if let Some(what) = what {
+                    // Underline the opening quote or slash.
+                    let mut start = err.token;
+                    start.end = start.start.after('/');
+                    labels.push(Label::secondary(start, format!("{} starts here", what)));
+                }
+            }
+            _ => { }
+        }
+        Diagnostic {
+            code: self.code(),
+            message: self.to_string(),
+            labels: labels,
+            hint: self.hint()
+        }
+    }
+
+    // Renders the error against the source it was produced from.
+    pub fn render(&self, source: &str) -> String {
+        self.diagnostic().render(source)
+    }
+
+    fn label(&self) -> String {
+        match *self {
+            Error::Expected(ref expected, _)
+          | Error::Unclosed(_, ref expected, _)   => format!("expected {}", expected),
+            Error::FailedASI(_)                   => "expected `;`".to_string(),
+            Error::LexError(ref err) => match err.value {
+                ErrorData::UnterminatedString(Some(_))
+              | ErrorData::UnterminatedRegExp(Some(_)) => "line ends here".to_string(),
+                ErrorData::UnterminatedString(None)
+              | ErrorData::UnterminatedRegExp(None)
+              | ErrorData::UnterminatedComment          => "input ends here".to_string(),
+                _                                       => String::new()
+            },
+            Error::UnexpectedToken(_)             => "unexpected token".to_string(),
+            Error::MismatchedJSXTag(ref opening, _) => {
+                format!("expected `</{}>`", opening.value.qualified_name())
+            }
+            _                                     => String::new()
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            Error::UnexpectedToken(ref token) => {
+                fmt.write_fmt(format_args!("unexpected {}", token.value))
+            }
+            Error::FailedASI(ref token) => {
+                fmt.write_fmt(format_args!("expected `;`, found {}", token.value))
+            }
+            Error::LexError(ref err) => {
+                err.fmt(fmt)
+            }
+            Error::TopLevelReturn(_) => {
+                fmt.write_str("`return` outside of a function")
+            }
+            Error::IllegalBreak(_) => {
+                fmt.write_str("`break` outside of a loop or switch")
+            }
+            Error::IllegalContinue(_) => {
+                fmt.write_str("`continue` outside of a loop")
+            }
+            Error::InvalidLabel(ref id) => {
+                fmt.write_fmt(format_args!("undefined label `{}`", id.value.name))
+            }
+            Error::InvalidLabelType(ref id) => {
+                fmt.write_fmt(format_args!("label `{}` does not refer to a loop", id.value.name))
+            }
+            Error::ContextualKeyword(ref id) => {
+                fmt.write_fmt(format_args!("`{}` is a reserved word here", id.value.name))
+            }
+            Error::IllegalStrictBinding(ref id) => {
+                fmt.write_fmt(format_args!("cannot bind `{}` in strict mode", id.value.name))
+            }
+            Error::ForOfLetExpr(_) => {
+                fmt.write_str("the left-hand side of a for-of loop cannot start with `let`")
+            }
+            Error::DuplicateDefault(_) => {
+                fmt.write_str("multiple `default` clauses in one switch statement")
+            }
+            Error::StrictWith(_) => {
+                fmt.write_str("`with` statements are not allowed in strict mode")
+            }
+            Error::ThrowArgument(_) => {
+                fmt.write_str("line break after `throw`")
+            }
+            Error::OrphanTry(_) => {
+                fmt.write_str("`try` without `catch` or `finally`")
+            }
+            Error::InvalidLHS(_, ref err) => {
+                fmt.write_fmt(format_args!("invalid left-hand side: {}", err))
+            }
+            Error::ImportMetaOutsideModule(_) => {
+                fmt.write_str("`import.meta` is only allowed in module code")
+            }
+            Error::ForInVarInit(_) => {
+                fmt.write_str("for-in loop variable declaration cannot have an initializer")
+            }
+            Error::MismatchedJSXTag(ref opening, ref closing) => {
+                fmt.write_fmt(format_args!("closing tag `</{}>` does not match `<{}>`",
+                                           closing.value.qualified_name(),
+                                           opening.value.qualified_name()))
+            }
+            Error::UnsupportedFeature(feature, _) => {
+                fmt.write_fmt(format_args!("{} is not supported yet", feature))
+            }
+            Error::UnsupportedEdition(feature, edition, _) => {
+                fmt.write_fmt(format_args!("{} requires {} or later", feature, edition))
+            }
+            Error::Expected(ref expected, ref token)
+          | Error::Unclosed(_, ref expected, ref token) => {
+                fmt.write_fmt(format_args!("expected {}, found {}", expected, token.value))
+            }
+            Error::NestingTooDeep(_) => {
+                fmt.write_str("nesting exceeds the maximum depth")
+            }
+            Error::InvalidEncoding(encoding, _) => {
+                fmt.write_fmt(format_args!("invalid {} in the source", encoding))
+            }
+            Error::Io(_, ref message) => {
+                fmt.write_fmt(format_args!("failed to read the source: {}", message))
+            }
+            Error::SourceTooLong(_) => {
+                fmt.write_str("source exceeds the maximum length")
+            }
+            Error::TooManyTokens(_) => {
+                fmt.write_str("source exceeds the maximum number of tokens")
+            }
+            Error::TooManyNodes(_) => {
+                fmt.write_str("syntax tree exceeds the maximum number of nodes")
+            }
+            Error::Cancelled(_) => {
+                fmt.write_str("parse cancelled")
+            }
+            Error::Panicked(ref message) => {
+                fmt.write_fmt(format_args!("the parser panicked: {}", message))
+            }
+            Error::InvalidEdit(ref range) => {
+                fmt.write_fmt(format_args!("edit range {}..{} is out of bounds or splits a character", range.start, range.end))
+            }
+        }
+    }
+}
+
+// Lexer errors, except that the lexer's resource limits and cancellation
+// become errors of their own.
+impl From<joker::error::Error> for Error {
+    fn from(err: joker::error::Error) -> Error {
+        match err.value {
+            ErrorData::SourceTooLong => Error::SourceTooLong(err.location),
+            ErrorData::TooManyTokens => Error::TooManyTokens(err.location),
+            ErrorData::Cancelled     => Error::Cancelled(err.location),
+            _                        => Error::LexError(err)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "JavaScript syntax error"
+    }
 }
@@ -3,11 +3,14 @@ use joker::token::Token;
 use easter::punc::Unop;
 use easter::expr::{ExprData, Expr};
 use easter::obj::DotKey;
+use easter::ty::Ty;
 
 pub enum Prefix {
     Unop(Unop),
     Inc(Span),
-    Dec(Span)
+    Dec(Span),
+    // A TypeScript type assertion, `<T>x`, from its opening `<`.
+    Assert(Token, Ty)
 }
 
 pub enum Postfix {
@@ -37,7 +40,9 @@ impl Deref {
 
 pub enum Suffix {
     Deref(Deref),
-    Arguments(Arguments)
+    Arguments(Arguments),
+    TypeArguments(Vec<Ty>, Token),
+    NonNull(Token)
 }
 
 pub struct Arguments {
@@ -61,7 +66,15 @@ impl Suffix {
     pub fn append_to(self, expr: Expr) -> Expr {
         match self {
             Suffix::Deref(deref) => deref.append_to(expr),
-            Suffix::Arguments(args) => args.append_to(expr)
+            Suffix::Arguments(args) => args.append_to(expr),
+            Suffix::TypeArguments(args, end) => {
+                let location = span(&expr, &end);
+                ExprData::Instantiation(Box::new(expr), args).tracked(location)
+            }
+            Suffix::NonNull(bang) => {
+                let location = span(&expr, &bang);
+                ExprData::NonNull(Box::new(expr)).tracked(location)
+            }
         }
     }
 }
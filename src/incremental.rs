@@ -0,0 +1,233 @@
+use std::mem::replace;
+use std::ops::Range;
+use joker::lines::LineIndex;
+use joker::token::TokenData;
+use joker::track::*;
+use easter::prog::{Script, ScriptData};
+use easter::stmt::StmtListItem;
+use easter::decl::DeclData;
+use context::Context;
+use error::Error;
+use options::{ParseOptions, SourceType};
+use parser::Parser;
+use result::Result;
+use state::State;
+
+// A change to a source text: the bytes in `range` are replaced by `text`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub text: String
+}
+
+impl Edit {
+    pub fn new(range: Range<usize>, text: &str) -> Edit {
+        Edit { range: range, text: text.to_string() }
+    }
+
+    // The source after the edit. Fails with `Error::InvalidEdit` if the range
+    // is reversed, out of bounds or splits a character.
+    pub fn apply(&self, source: &str) -> Result<String> {
+        let Range { start, end } = self.range;
+        if start > end || end > source.len() || !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+            return Err(Error::InvalidEdit(self.range.clone()));
+        }
+        let mut result = String::with_capacity(source.len() - self.range.len() + self.text.len());
+        result.push_str(&source[..self.range.start]);
+        result.push_str(&self.text);
+        result.push_str(&source[self.range.end..]);
+        Ok(result)
+    }
+}
+
+// Parses the script that results from applying `edit` to `source`, given the
+// tree `old` that `source` parsed to with the same options.
+//
+// Only the statements around the edit are parsed again: the items of the
+// innermost function declaration body containing it, or else of the script
+// itself. Parsing starts at the item before the first one the edit touches
+// and stops once a reparsed item ends where an old item after the edit did;
+// the old items from there on are reused, with their locations shifted. The
+// result, including any error, is the same as a full parse of the new source.
+// `old` is taken apart for its reusable items, which are moved into the
+// result and shifted in place rather than copied.
+pub fn reparse_script(old: Script, source: &str, edit: &Edit, options: ParseOptions) -> Result<Script> {
+    let options = options.source_type(SourceType::Script);
+    let new_source = try!(edit.apply(source));
+    // TypeScript type arguments are parsed speculatively, looking arbitrarily
+    // far ahead, and the location of an empty script depends on its trivia.
+    // Token and node limits apply to the whole script.
+    if !options.locations || options.typescript || old.value.body.is_empty()
+        || options.max_tokens != usize::max_value() || options.max_nodes != usize::max_value() {
+        return Parser::with_options(new_source.chars(), options).script();
+    }
+    // `apply` checked that the edit falls on character boundaries.
+    let old_lines = LineIndex::new(source);
+    let new_lines = LineIndex::new(&new_source);
+    let reparse = Reparse {
+        source: &new_source,
+        edit: Span {
+            start: old_lines.posn_at_byte(edit.range.start).unwrap(),
+            end: old_lines.posn_at_byte(edit.range.end).unwrap()
+        },
+        end: new_lines.posn_at_byte(edit.range.start + edit.text.len()).unwrap(),
+        options: options
+    };
+    let body = match try!(reparse.list(old.value.body, Posn::origin(), None, 0)) {
+        Some(body) => body,
+        None       => { return Parser::with_options(new_source.chars(), options).script(); }
+    };
+    if body.is_empty() {
+        return Parser::with_options(new_source.chars(), options).script();
+    }
+    let location = span(&body[0], &body[body.len() - 1]);
+    Ok(Script { location: location, value: ScriptData { body: body } })
+}
+
+struct Reparse<'a> {
+    // The new source.
+    source: &'a str,
+    // The replaced text, in the old source.
+    edit: Span,
+    // The end of the replacement, in the new source.
+    end: Posn,
+    options: ParseOptions
+}
+
+impl<'a> Reparse<'a> {
+    // Where an old position at or after the end of the edit is now.
+    fn shift(&self, posn: Posn) -> Posn {
+        posn.shift(self.edit.end, self.end)
+    }
+
+    // Reparses the part of a statement list that the edit touches. The list
+    // is the script body, starting at `open`, or the body of a function whose
+    // parameters end at `open` and whose closing brace was at `close`; its
+    // items are parsed `depth` levels deep. Returns `None` if the edit does
+    // not stay within a function body.
+    fn list(&self, mut items: Vec<StmtListItem>, open: Posn, close: Option<Posn>, depth: usize) -> Result<Option<Vec<StmtListItem>>> {
+        let first = items.iter()
+                         .position(|item| location(item).end.byte >= self.edit.start.byte)
+                         .unwrap_or(items.len());
+        let last = items[first..].iter()
+                                 .position(|item| location(item).start.byte > self.edit.end.byte)
+                                 .map_or(items.len(), |n| first + n);
+
+        // An edit within a single function declaration may be contained by
+        // its body.
+        if last == first + 1 && self.function(&mut items[first], depth) {
+            self.shift_all(&mut items[last..]);
+            return Ok(Some(items));
+        }
+
+        let (keep, start) = if first == 0 {
+            (0, open)
+        } else {
+            (first - 1, location(&items[first - 1]).start)
+        };
+        let mut parser = Parser::resuming(self.source, start, depth, self.options);
+        if close.is_some() {
+            parser.parser_cx = Context::new_function();
+            if first == 0 {
+                let brace = try!(parser.expect(TokenData::LBrace));
+                if brace.location.end.byte > self.edit.start.byte {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let mut reparsed = Vec::new();
+        while let Some(item) = try!(parser.statement_list_item_opt()) {
+            let end = location(&item).end;
+            reparsed.push(item);
+            // Once an item ends where an old one did, past the edit, the
+            // parser is back in step with the old parse.
+            if let Some(next) = self.resync(&items, first, end) {
+                if try!(parser.peek()).location.start == self.shift(location(&items[next]).start) {
+                    let count = reparsed.len();
+                    items.splice(keep..next, reparsed);
+                    self.shift_all(&mut items[keep + count..]);
+                    return Ok(Some(items));
+                }
+            }
+        }
+
+        let token = try!(parser.read());
+        match close {
+            None => {
+                if token.value != TokenData::EOF {
+                    return Err(Error::UnexpectedToken(token));
+                }
+            }
+            Some(close) => {
+                if token.value != TokenData::RBrace || token.location.start != self.shift(close) {
+                    return Ok(None);
+                }
+            }
+        }
+        items.truncate(keep);
+        items.extend(reparsed);
+        Ok(Some(items))
+    }
+
+    // Reparses just the body of `item` in place if it is a function
+    // declaration whose body contains the edit. Any failure is left to the
+    // enclosing list, since the edit may have changed where the body ends;
+    // the item is reparsed there, so its body may be left empty.
+    fn function(&self, item: &mut StmtListItem, depth: usize) -> bool {
+        let decl = match *item {
+            StmtListItem::Decl(ref mut decl) => decl,
+            _ => { return false; }
+        };
+        let start = decl.location.unwrap().start;
+        let fun = match decl.value {
+            DeclData::Fun(ref mut fun) if fun.value.lazy.is_none() => fun,
+            _ => { return false; }
+        };
+        let header = fun.value.params.location.unwrap().end;
+        let end = fun.location.unwrap().end;
+        let close = Posn {
+            offset: end.offset - 1,
+            byte: end.byte - 1,
+            utf16: end.utf16 - 1,
+            line: end.line,
+            column: end.column - 1
+        };
+        if self.edit.start.byte <= header.byte || self.edit.end.byte > close.byte {
+            return false;
+        }
+        let body = replace(&mut fun.value.body, Vec::new());
+        fun.value.body = match self.list(body, header, Some(close), depth + 1) {
+            Ok(Some(body)) => body,
+            _              => { return false; }
+        };
+        fun.location = Some(Span { start: fun.location.unwrap().start, end: self.shift(end) });
+        decl.location = Some(Span { start: start, end: self.shift(end) });
+        true
+    }
+
+    // The index of the old item after the one that ended where a reparsed
+    // item now ends at `end`, if that is past the edit.
+    fn resync(&self, items: &[StmtListItem], first: usize, end: Posn) -> Option<usize> {
+        if end.byte < self.end.byte {
+            return None;
+        }
+        let old = end.byte - self.end.byte + self.edit.end.byte;
+        match items[first..].binary_search_by_key(&old, |item| location(item).end.byte) {
+            Ok(n) if first + n + 1 < items.len() && self.shift(location(&items[first + n]).end) == end => {
+                Some(first + n + 1)
+            }
+            _ => None
+        }
+    }
+
+    fn shift_all(&self, items: &mut [StmtListItem]) {
+        for item in items {
+            item.shift(self.edit.end, self.end);
+        }
+    }
+}
+
+fn location(item: &StmtListItem) -> Span {
+    item.location().unwrap()
+}
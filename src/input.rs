@@ -0,0 +1,278 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{BufReader, Read};
+use std::rc::Rc;
+use std::str;
+use joker::track::{Posn, Span};
+use error::Error;
+
+// The encodings source text can be read from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Encoding {
+    Utf8,
+    Utf16LE,
+    Utf16BE
+}
+
+impl Encoding {
+    // Maps a charset label, as found in a Content-Type header or a script
+    // tag's charset attribute, to an encoding. Labels follow the WHATWG
+    // Encoding Standard, where a bare "utf-16" means little-endian.
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        match &label.trim().to_lowercase()[..] {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Encoding::Utf8),
+            "utf-16" | "utf-16le"                  => Some(Encoding::Utf16LE),
+            "utf-16be"                             => Some(Encoding::Utf16BE),
+            _                                      => None
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.write_str(match *self {
+            Encoding::Utf8    => "UTF-8",
+            Encoding::Utf16LE => "UTF-16LE",
+            Encoding::Utf16BE => "UTF-16BE"
+        })
+    }
+}
+
+// Decodes source text held in memory, borrowing it if it is UTF-8.
+pub fn decode(bytes: &[u8], declared: Option<Encoding>) -> Result<Cow<str>, Error> {
+    let utf8 = declared.map_or(true, |encoding| encoding == Encoding::Utf8);
+    let text = if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        str::from_utf8(&bytes[3..]).ok()
+    } else if utf8 && !bytes.starts_with(&[0xFF, 0xFE]) && !bytes.starts_with(&[0xFE, 0xFF]) {
+        str::from_utf8(bytes).ok()
+    } else {
+        None
+    };
+    if let Some(text) = text {
+        return Ok(Cow::Borrowed(text));
+    }
+    // Other encodings, and invalid input that needs locating.
+    let mut decoder = Decoder::new(bytes, declared);
+    let text: String = decoder.by_ref().collect();
+    let failure = decoder.failure.borrow_mut().take();
+    match failure {
+        Some(err) => Err(err),
+        None      => Ok(Cow::Owned(text))
+    }
+}
+
+// Decodes a byte stream into characters as the lexer asks for them, so the
+// source never has to be held in memory as a whole. A byte order mark picks
+// the encoding and is dropped; without one the declared encoding is used,
+// falling back to UTF-8.
+//
+// The first invalid sequence or read failure ends the stream, and the error
+// is left in the slot returned by `failure`. Its position is within the
+// decoded text, as the lexer counts it.
+pub struct Decoder<R> {
+    input: BufReader<R>,
+    // Bytes read while looking for a byte order mark but not part of one.
+    sniffed: Vec<u8>,
+    encoding: Encoding,
+    // The last character produced, and the position it starts at.
+    prev: Option<char>,
+    posn: Posn,
+    failure: Rc<RefCell<Option<Error>>>
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(input: R, declared: Option<Encoding>) -> Decoder<R> {
+        let mut decoder = Decoder {
+            input: BufReader::new(input),
+            sniffed: Vec::new(),
+            encoding: declared.unwrap_or(Encoding::Utf8),
+            prev: None,
+            posn: Posn::origin(),
+            failure: Rc::new(RefCell::new(None))
+        };
+        decoder.sniff();
+        decoder
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    // Where the error that ended the stream, if any, is left.
+    pub fn failure(&self) -> Rc<RefCell<Option<Error>>> {
+        self.failure.clone()
+    }
+
+    fn sniff(&mut self) {
+        while self.sniffed.len() < 3 {
+            match self.read_byte() {
+                Ok(Some(byte)) => { self.sniffed.push(byte); }
+                Ok(None)       => { break; }
+                Err(err)       => { self.fail(Error::Io(err.kind(), err.to_string())); return; }
+            }
+        }
+        let (encoding, len) = if self.sniffed.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            (Encoding::Utf8, 3)
+        } else if self.sniffed.starts_with(&[0xFF, 0xFE]) {
+            (Encoding::Utf16LE, 2)
+        } else if self.sniffed.starts_with(&[0xFE, 0xFF]) {
+            (Encoding::Utf16BE, 2)
+        } else {
+            return;
+        };
+        self.encoding = encoding;
+        self.sniffed.drain(..len);
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0)    => { return Ok(None); }
+                Ok(_)    => { return Ok(Some(byte[0])); }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => { }
+                Err(err) => { return Err(err); }
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        if !self.sniffed.is_empty() {
+            return Ok(Some(self.sniffed.remove(0)));
+        }
+        self.read_byte()
+    }
+
+    // The position just past the last character produced.
+    fn end(&self) -> Posn {
+        match self.prev {
+            Some(ch) => advance(self.posn, ch, None),
+            None     => self.posn
+        }
+    }
+
+    fn fail(&mut self, err: Error) {
+        *self.failure.borrow_mut() = Some(err);
+    }
+
+    fn invalid(&self) -> Error {
+        let here = self.end();
+        Error::InvalidEncoding(self.encoding, Span { start: here, end: here })
+    }
+
+    fn decode_utf8(&mut self) -> io::Result<Step<char>> {
+        let lead = match try!(self.next_byte()) {
+            Some(byte) => byte,
+            None       => { return Ok(Step::End); }
+        };
+        let len = match lead {
+            0x00...0x7F => { return Ok(Step::Unit(lead as char)); }
+            0xC0...0xDF => 2,
+            0xE0...0xEF => 3,
+            0xF0...0xF7 => 4,
+            _           => { return Ok(Step::Invalid); }
+        };
+        let mut buf = [lead, 0, 0, 0];
+        for i in 1..len {
+            match try!(self.next_byte()) {
+                Some(byte) if byte & 0xC0 == 0x80 => { buf[i] = byte; }
+                _                                  => { return Ok(Step::Invalid); }
+            }
+        }
+        // Rejects overlong forms, surrogates and values past U+10FFFF.
+        Ok(match str::from_utf8(&buf[..len]) {
+            Ok(s)  => Step::Unit(s.chars().next().unwrap()),
+            Err(_) => Step::Invalid
+        })
+    }
+
+    fn next_utf16_unit(&mut self) -> io::Result<Step<u32>> {
+        let first = match try!(self.next_byte()) {
+            Some(byte) => byte as u32,
+            None       => { return Ok(Step::End); }
+        };
+        let second = match try!(self.next_byte()) {
+            Some(byte) => byte as u32,
+            None       => { return Ok(Step::Invalid); }
+        };
+        Ok(Step::Unit(match self.encoding {
+            Encoding::Utf16BE => (first << 8) | second,
+            _                 => (second << 8) | first
+        }))
+    }
+
+    fn decode_utf16(&mut self) -> io::Result<Step<char>> {
+        let high = match try!(self.next_utf16_unit()) {
+            Step::Unit(unit) => unit,
+            Step::End        => { return Ok(Step::End); }
+            Step::Invalid    => { return Ok(Step::Invalid); }
+        };
+        let code = match high {
+            0xD800...0xDBFF => match try!(self.next_utf16_unit()) {
+                Step::Unit(low) if low >= 0xDC00 && low <= 0xDFFF => {
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                }
+                _ => { return Ok(Step::Invalid); }
+            },
+            0xDC00...0xDFFF => { return Ok(Step::Invalid); }
+            _               => high
+        };
+        Ok(::std::char::from_u32(code).map_or(Step::Invalid, Step::Unit))
+    }
+}
+
+// The outcome of decoding one character, or one UTF-16 code unit.
+enum Step<T> {
+    Unit(T),
+    End,
+    Invalid
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.failure.borrow().is_some() {
+            return None;
+        }
+        let decoded = match self.encoding {
+            Encoding::Utf8 => self.decode_utf8(),
+            _              => self.decode_utf16()
+        };
+        match decoded {
+            Ok(Step::Unit(ch)) => {
+                if let Some(prev) = self.prev {
+                    self.posn = advance(self.posn, prev, Some(ch));
+                }
+                self.prev = Some(ch);
+                Some(ch)
+            }
+            Ok(Step::End) => None,
+            Ok(Step::Invalid) => {
+                let err = self.invalid();
+                self.fail(err);
+                None
+            }
+            Err(err) => {
+                self.fail(Error::Io(err.kind(), err.to_string()));
+                None
+            }
+        }
+    }
+}
+
+// The position past `ch`, counting line terminators as the lexer does.
+fn advance(posn: Posn, ch: char, next: Option<char>) -> Posn {
+    let mut posn = posn.after(ch);
+    if (ch == '\r' && next != Some('\n')) ||
+       ch == '\n' ||
+       ch == '\u{2028}' ||
+       ch == '\u{2029}' {
+        posn.line += 1;
+        posn.column = 0;
+    }
+    posn
+}
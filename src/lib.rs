@@ -5,7 +5,9 @@
 //!
 //! Currently the parser is hard-coded to produce the Easter AST
 //! data structures. Eventually it should be abstracted to support
-//! pluggable builders.
+//! pluggable builders. Until then, `script_with_builder` and
+//! `module_with_builder` convert the finished Easter tree through
+//! the `builder::Builder` trait, so the tree is still built first.
 
 extern crate serde;
 extern crate serde_json;
@@ -15,7 +17,14 @@ extern crate easter;
 extern crate joker;
 
 pub mod error;
+pub mod diagnostic;
+pub mod builder;
+pub mod comments;
 pub mod result;
+pub mod options;
+pub mod input;
+pub mod incremental;
+pub mod batch;
 mod context;
 mod tokens;
 mod atom;
@@ -24,14 +33,153 @@ mod parser;
 mod state;
 mod expr;
 mod stack;
+mod skip;
 mod test;
 
-// type Parser<I: Iterator<Item=char>> = parser::Parser<I>;
-
-use easter::prog::Script;
-use parser::Parser;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use easter::prog::{Script, Module};
+use easter::expr::Expr;
+use easter::stmt::{Stmt, StmtListItem};
+use easter::fun::{Params, LazyBody};
 use result::Result;
+use diagnostic::Diagnostic;
+use builder::Builder;
+use joker::comment::Comment;
+use joker::lexeme::Lexeme;
+use input::Decoder;
+use error::Error;
+
+pub use parser::Parser;
+pub use options::{ParseOptions, SourceType, Edition, FunctionKind};
+pub use input::Encoding;
+pub use incremental::{Edit, reparse_script};
+pub use batch::{Parsed, parse_many};
 
 pub fn script(s: &str) -> Result<Script> {
     Parser::from(s).script()
 }
+
+pub fn script_with(s: &str, options: ParseOptions) -> Result<Script> {
+    Parser::with_options(s.chars(), options.source_type(SourceType::Script)).script()
+}
+
+// Parses a script along with all of its comments, in source order.
+pub fn script_with_comments(s: &str, options: ParseOptions) -> Result<(Script, Vec<Comment>)> {
+    let options = options.source_type(SourceType::Script).comments(true);
+    let mut parser = Parser::with_options(s.chars(), options);
+    let script = try!(parser.script());
+    Ok((script, parser.comments()))
+}
+
+// Parses a script without stopping at the first error. The tree contains
+// `Invalid` placeholders wherever the parser had to skip input.
+pub fn script_recovering(s: &str) -> (Script, Vec<Diagnostic>) {
+    let (script, errors) = Parser::from(s).script_recovering();
+    (script, errors.iter().map(|err| err.diagnostic()).collect())
+}
+
+// Splits the source into tokens and trivia, with lexer errors in place.
+pub fn tokens(s: &str, options: ParseOptions) -> Vec<Lexeme> {
+    Parser::with_options(s.chars(), options).tokens()
+}
+
+pub fn script_with_builder<B: Builder>(s: &str, options: ParseOptions, builder: &mut B) -> Result<B::Script> {
+    let script = try!(script_with(s, options));
+    Ok(builder::build_script(builder, script))
+}
+
+// Parses a script from a byte stream, decoding it as it is read. See
+// `input::Decoder` for how the encoding is chosen.
+pub fn script_from_reader<R: Read>(reader: R, options: ParseOptions) -> Result<Script> {
+    from_reader(reader, options.source_type(SourceType::Script), |parser| parser.script())
+}
+
+// Parses a script from bytes in memory. Valid UTF-8 is lexed in place.
+pub fn script_from_bytes(bytes: &[u8], options: ParseOptions) -> Result<Script> {
+    script_with(&try!(input::decode(bytes, options.encoding)), options)
+}
+
+pub fn script_from_file<P: AsRef<Path>>(path: P, options: ParseOptions) -> Result<Script> {
+    script_from_reader(try!(open(path)), options)
+}
+
+// Parses a lone expression, such as a configuration value.
+pub fn expression(s: &str) -> Result<Expr> {
+    Parser::from(s).standalone_expression()
+}
+
+// Parses a single statement, as if it appeared at the top level of a script.
+pub fn statement(s: &str) -> Result<Stmt> {
+    Parser::from(s).standalone_statement()
+}
+
+// Parses the body of a function of the given kind, without its braces.
+pub fn function_body(s: &str, kind: FunctionKind) -> Result<Vec<StmtListItem>> {
+    Parser::from(s).standalone_function_body(kind)
+}
+
+// Parses the body of a function left out of the tree by the
+// `lazy_functions` option. `source` and `options` are those of the
+// original parse.
+pub fn reparse_function(source: &str, lazy: &LazyBody, options: ParseOptions) -> Result<Vec<StmtListItem>> {
+    Parser::for_lazy_body(source, lazy, options).standalone_function_body(lazy.kind)
+}
+
+// Parses a comma-separated parameter list, without its parentheses.
+pub fn formal_parameters(s: &str) -> Result<Params> {
+    Parser::from(s).standalone_formal_parameters()
+}
+
+pub fn module(s: &str) -> Result<Module> {
+    module_with(s, ParseOptions::new())
+}
+
+pub fn module_with(s: &str, options: ParseOptions) -> Result<Module> {
+    Parser::with_options(s.chars(), options.source_type(SourceType::Module)).module()
+}
+
+pub fn module_with_comments(s: &str, options: ParseOptions) -> Result<(Module, Vec<Comment>)> {
+    let options = options.source_type(SourceType::Module).comments(true);
+    let mut parser = Parser::with_options(s.chars(), options);
+    let module = try!(parser.module());
+    Ok((module, parser.comments()))
+}
+
+pub fn module_with_builder<B: Builder>(s: &str, options: ParseOptions, builder: &mut B) -> Result<B::Module> {
+    let module = try!(module_with(s, options));
+    Ok(builder::build_module(builder, module))
+}
+
+pub fn module_from_reader<R: Read>(reader: R, options: ParseOptions) -> Result<Module> {
+    from_reader(reader, options.source_type(SourceType::Module), |parser| parser.module())
+}
+
+pub fn module_from_bytes(bytes: &[u8], options: ParseOptions) -> Result<Module> {
+    module_with(&try!(input::decode(bytes, options.encoding)), options)
+}
+
+pub fn module_from_file<P: AsRef<Path>>(path: P, options: ParseOptions) -> Result<Module> {
+    module_from_reader(try!(open(path)), options)
+}
+
+fn open<P: AsRef<Path>>(path: P) -> Result<File> {
+    File::open(path).map_err(|err| Error::Io(err.kind(), err.to_string()))
+}
+
+fn from_reader<R, F, T>(reader: R, options: ParseOptions, parse: F) -> Result<T>
+  where R: Read,
+        F: FnOnce(&mut Parser<Decoder<R>>) -> Result<T>
+{
+    let decoder = Decoder::new(reader, options.encoding);
+    let failure = decoder.failure();
+    let result = parse(&mut Parser::with_options(decoder, options));
+    // Input that failed to decode ends early, which is likely what made the
+    // parse fail.
+    let failure = failure.borrow_mut().take();
+    match failure {
+        Some(err) => Err(err),
+        None      => result
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use joker;
+use joker::context::Mode;
+use input::Encoding;
+
+pub use easter::fun::FunctionKind;
+
+// The ECMAScript edition to accept. Syntax introduced after the target
+// edition is rejected with `Error::UnsupportedEdition`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Edition {
+    ES5,
+    ES2015,
+    ES2016,
+    ES2017,
+    ES2018,
+    ES2019,
+    ES2020,
+    ES2021,
+    ES2022
+}
+
+impl Edition {
+    pub fn latest() -> Edition {
+        Edition::ES2022
+    }
+}
+
+impl Display for Edition {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.write_str(match *self {
+            Edition::ES5    => "ES5",
+            Edition::ES2015 => "ES2015",
+            Edition::ES2016 => "ES2016",
+            Edition::ES2017 => "ES2017",
+            Edition::ES2018 => "ES2018",
+            Edition::ES2019 => "ES2019",
+            Edition::ES2020 => "ES2020",
+            Edition::ES2021 => "ES2021",
+            Edition::ES2022 => "ES2022"
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SourceType {
+    Script,
+    Module
+}
+
+// Configuration for a parse. The defaults match `esprit::script`: a sloppy
+// mode script targeting the latest edition, with Annex B enabled and
+// locations tracked.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ParseOptions {
+    pub source_type: SourceType,
+    pub edition: Edition,
+    pub strict: bool,
+    pub annex_b: bool,
+    pub locations: bool,
+    pub comments: bool,
+    pub preserve_parens: bool,
+    pub jsx: bool,
+    pub typescript: bool,
+    pub decorators: bool,
+    pub max_depth: usize,
+    pub max_source_len: usize,
+    pub max_tokens: usize,
+    pub max_nodes: usize,
+    pub lazy_functions: bool,
+    pub encoding: Option<Encoding>
+}
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        ParseOptions {
+            source_type: SourceType::Script,
+            edition: Edition::latest(),
+            strict: false,
+            annex_b: true,
+            locations: true,
+            comments: false,
+            preserve_parens: false,
+            jsx: false,
+            typescript: false,
+            decorators: false,
+            max_depth: 100,
+            max_source_len: usize::max_value(),
+            max_tokens: usize::max_value(),
+            max_nodes: usize::max_value(),
+            lazy_functions: false,
+            encoding: None
+        }
+    }
+
+    pub fn source_type(mut self, source_type: SourceType) -> ParseOptions {
+        self.source_type = source_type;
+        self
+    }
+
+    pub fn edition(mut self, edition: Edition) -> ParseOptions {
+        self.edition = edition;
+        self
+    }
+
+    // Start in strict mode, as if the source began with "use strict".
+    // Module code is always strict.
+    pub fn strict(mut self, strict: bool) -> ParseOptions {
+        self.strict = strict;
+        self
+    }
+
+    pub fn annex_b(mut self, annex_b: bool) -> ParseOptions {
+        self.annex_b = annex_b;
+        self
+    }
+
+    // When disabled, the resulting AST carries no source locations.
+    pub fn locations(mut self, locations: bool) -> ParseOptions {
+        self.locations = locations;
+        self
+    }
+
+    // Keep the comments skipped by the lexer, to be retrieved with
+    // `Parser::comments`.
+    pub fn comments(mut self, comments: bool) -> ParseOptions {
+        self.comments = comments;
+        self
+    }
+
+    // Wrap each parenthesized expression in an `ExprData::Paren` node, for
+    // tools that print the source back out. By default the parentheses are
+    // dropped and the inner expression stands in for them.
+    pub fn preserve_parens(mut self, preserve_parens: bool) -> ParseOptions {
+        self.preserve_parens = preserve_parens;
+        self
+    }
+
+    pub fn jsx(mut self, jsx: bool) -> ParseOptions {
+        self.jsx = jsx;
+        self
+    }
+
+    // Parse TypeScript's type syntax. Import declarations (and so `import
+    // type`), destructuring and template literal types are reported as
+    // unsupported features. With JSX enabled, `<` always starts an
+    // element, never a type assertion.
+    pub fn typescript(mut self, typescript: bool) -> ParseOptions {
+        self.typescript = typescript;
+        self
+    }
+
+    pub fn decorators(mut self, decorators: bool) -> ParseOptions {
+        self.decorators = decorators;
+        self
+    }
+
+    // The deepest nesting of statements, expressions, functions, types and
+    // JSX elements to accept before failing with `Error::NestingTooDeep`.
+    // Only recursion counts: chains read in a loop, such as `!!x`, `a.b.c`,
+    // `a + b + c` and "else if", are accepted however long they are. Their
+    // trees are dropped without recursion, and cloned, compared and printed
+    // on a stack that grows as needed.
+    pub fn max_depth(mut self, max_depth: usize) -> ParseOptions {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // Limits for parsing untrusted input, unlimited by default. The source
+    // length is in bytes. Exceeding one fails the parse with
+    // `Error::SourceTooLong`, `Error::TooManyTokens` or `Error::TooManyNodes`,
+    // even when recovering from errors. Nodes are counted as the parser builds
+    // them, one for every source location in the tree, and the parse stops at
+    // the first one over the limit.
+    pub fn max_source_len(mut self, max_source_len: usize) -> ParseOptions {
+        self.max_source_len = max_source_len;
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: usize) -> ParseOptions {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn max_nodes(mut self, max_nodes: usize) -> ParseOptions {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    // Leave function bodies out of the tree, for tools that only need a
+    // program's outline. Such a function has an empty body and a `lazy`
+    // record, which `esprit::reparse_function` parses on demand. Bodies are
+    // only lexed, to find where they end: lexical errors and unbalanced
+    // brackets are reported right away, other errors by the reparse.
+    // Ignored when recovering from errors.
+    pub fn lazy_functions(mut self, lazy_functions: bool) -> ParseOptions {
+        self.lazy_functions = lazy_functions;
+        self
+    }
+
+    // The encoding of byte input that has no byte order mark, such as one
+    // declared by a charset label. Defaults to UTF-8.
+    pub fn encoding(mut self, encoding: Encoding) -> ParseOptions {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub fn mode(&self) -> Mode {
+        match self.source_type {
+            SourceType::Module => Mode::Module,
+            _ if self.strict   => Mode::Strict,
+            SourceType::Script => Mode::Sloppy
+        }
+    }
+
+    // The initial lexer context for a parse with these options.
+    pub fn context(&self) -> joker::context::Context {
+        let mut cx = joker::context::Context::new(self.mode());
+        cx.annex_b = self.annex_b;
+        cx.jsx = self.jsx;
+        cx.typescript = self.typescript;
+        cx.decorators = self.decorators;
+        cx
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions::new()
+    }
+}
@@ -1,41 +1,59 @@
 use joker;
 use joker::track::*;
 use joker::token::{Token, TokenData};
+use joker::error::ErrorData;
+use joker::comment::Comment;
+use joker::lexeme::Lexeme;
 use joker::word::{Atom, Name, Reserved};
 use joker::lexer::Lexer;
-use joker::context::Mode;
-use easter::prog::{Script, ScriptData};
+use joker::context::{Mode, JSXMode};
+use easter::prog::{Script, ScriptData, Module, ModuleData};
 use easter::stmt::{Stmt, StmtData, StmtListItem, ForHead, ForHeadData, ForInHead, ForInHeadData, ForOfHead, ForOfHeadData, Case, CaseData, Catch, CatchData};
 use easter::expr::{Expr, ExprData};
-use easter::decl::{Decl, DeclData, Dtor, DtorData, DtorExt};
+use easter::decl::{Decl, DeclData, Dtor, DtorData, DtorExt, EnumMember, EnumMemberData};
 use easter::patt::{Patt, CompoundPatt};
-use easter::fun::{Fun, FunData, Params, ParamsData};
+use easter::fun::{Fun, FunData, LazyBody, Params, ParamsData};
 use easter::obj::{PropKey, PropKeyData, PropValData, Prop, PropData, DotKey, DotKeyData};
 use easter::id::{Id, IdData, IdExt};
-use easter::punc::{Unop, UnopTag, ToOp};
+use easter::punc::{Unop, UnopTag, BinopTag, ToOp, Precedence};
 use easter::cover::IntoAssignPatt;
+use easter::class::{Class, ClassData, ClassMember, ClassMemberData, ClassMemberVal, Access, Decorator, DecoratorData};
+use easter::ty::{Ty, TyData, TyKeyword, TyOp, TyParam, TyParamData, FunTy, FunTyData, TyMember, TyMemberData, TySig, MappedTy, MappedModifier};
+use easter::jsx::{JSXId, JSXIdData, JSXName, JSXNameData, JSXAttr, JSXAttrData, JSXAttrVal, JSXAttrValData, JSXChild, JSXChildData, JSXElement, JSXElementData, JSXFragment, JSXFragmentData};
 
 use std::cell::Cell;
 use std::rc::Rc;
 use std::mem::replace;
 use std::convert::From;
 use std::str::Chars;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use context;
 use context::{LabelType, WithContext};
 use tokens::{First, Follows, HasLabelType};
 use atom::AtomExt;
-use track::Newline;
+use track::{Newline, SpanTracker};
 use result::Result;
 use error::Error;
 use track::Tracking;
 use state::State;
 use expr::{Deref, Suffix, Arguments, Prefix, Postfix};
 use stack::{Stack, Infix};
+use skip;
+use skip::{Group, GroupKind};
+use easter::stack::grow;
+use options::{ParseOptions, SourceType, Edition, FunctionKind};
 
 pub struct Parser<I> {
     pub lexer: Lexer<I>,
     pub shared_cx: Rc<Cell<joker::context::Context>>,
-    pub parser_cx: context::Context
+    pub parser_cx: context::Context,
+    pub options: ParseOptions,
+    recovering: bool,
+    errors: Vec<Error>,
+    depth: usize,
+    // The located nodes built so far.
+    nodes: usize
 }
 
 impl<'a> From<&'a str> for Parser<Chars<'a>> {
@@ -44,6 +62,36 @@ impl<'a> From<&'a str> for Parser<Chars<'a>> {
     }
 }
 
+impl<'a> Parser<Chars<'a>> {
+    // A parser for the body of a function that a lazy parse left out.
+    // `source` is the whole text of the original parse, so the body's
+    // locations match those of an eager parse.
+    pub fn for_lazy_body(source: &'a str, lazy: &LazyBody, options: ParseOptions) -> Parser<Chars<'a>> {
+        let mut context = options.context();
+        context.mode = lazy.mode;
+        let cx = Rc::new(Cell::new(context));
+        let chars = source[lazy.span.byte_range()].chars();
+        let mut lexer = Lexer::starting_at(chars, cx.clone(), lazy.span.start);
+        if options.comments {
+            lexer.collect_comments();
+        }
+        let mut parser = Parser::new(lexer, cx);
+        parser.set_options(options);
+        parser
+    }
+
+    // Incremental reparsing: a parser that picks up `source` at `posn`, the
+    // start of a statement list item `depth` levels deep.
+    pub fn resuming(source: &'a str, posn: Posn, depth: usize, options: ParseOptions) -> Parser<Chars<'a>> {
+        let cx = Rc::new(Cell::new(options.context()));
+        let lexer = Lexer::starting_at(source[posn.byte as usize..].chars(), cx.clone(), posn);
+        let mut parser = Parser::new(lexer, cx);
+        parser.set_options(options);
+        parser.depth = depth;
+        parser
+    }
+}
+
 impl<I: Iterator<Item=char>> From<I> for Parser<I> {
     fn from(i: I) -> Parser<I> {
         let cx = Rc::new(Cell::new(joker::context::Context::new(Mode::Sloppy)));
@@ -54,1394 +102,4514 @@ impl<I: Iterator<Item=char>> From<I> for Parser<I> {
 
 impl<I: Iterator<Item=char>> Parser<I> {
     pub fn new(lexer: Lexer<I>, cx: Rc<Cell<joker::context::Context>>) -> Parser<I> {
-        Parser { lexer: lexer, shared_cx: cx, parser_cx: context::Context::new() }
-    }
-
-    pub fn script(&mut self) -> Result<Script> {
-        let items = try!(self.statement_list());
-        Ok(Script { location: self.vec_span(&items), value: ScriptData { body: items } })
-    }
-
-    fn statement_list(&mut self) -> Result<Vec<StmtListItem>> {
-        let mut items = Vec::new();
-        while !try!(self.peek()).follow_statement_list() {
-            //println!("statement at: {:?}", try!(self.peek()).location().unwrap().start);
-            match try!(self.declaration_opt()) {
-                Some(decl) => { items.push(StmtListItem::Decl(decl)); }
-                None       => { items.push(StmtListItem::Stmt(try!(self.statement()))); }
-            }
+        Parser {
+            lexer: lexer,
+            shared_cx: cx,
+            parser_cx: context::Context::new(),
+            options: ParseOptions::new(),
+            recovering: false,
+            errors: Vec::new(),
+            depth: 0,
+            nodes: 0
         }
-        Ok(items)
     }
 
-/*
-    pub fn declaration(&mut self) -> Result<Decl> {
-        match try!(self.declaration_opt()) {
-            Some(decl) => Ok(decl),
-            None       => Err(Error::UnexpectedToken(try!(self.read())))
+    pub fn with_options(i: I, options: ParseOptions) -> Parser<I> {
+        let cx = Rc::new(Cell::new(options.context()));
+        let mut lexer = Lexer::new(i, cx.clone());
+        if options.comments {
+            lexer.collect_comments();
         }
+        let mut parser = Parser::new(lexer, cx);
+        parser.set_options(options);
+        parser
     }
-*/
 
-    fn declaration_opt(&mut self) -> Result<Option<Decl>> {
-        match try!(self.peek()).value {
-            TokenData::Reserved(Reserved::Function) => Ok(Some(try!(self.function_declaration()))),
-            _                                       => Ok(None)
-        }
+    fn set_options(&mut self, options: ParseOptions) {
+        self.options = options;
+        self.lexer.max_source_len(options.max_source_len);
+        self.lexer.max_tokens(options.max_tokens);
     }
 
-    fn function_declaration(&mut self) -> Result<Decl> {
-        self.span(&mut |this| {
-            Ok(DeclData::Fun(try!(this.function())))
-        })
+    // Stops the parse with `Error::Cancelled` once `cancel` is set, checked
+    // before each token is read.
+    pub fn cancel_on(&mut self, cancel: Arc<AtomicBool>) {
+        self.lexer.cancel_on(cancel);
     }
 
-    fn formal_parameters(&mut self) -> Result<Params> {
-        self.span(&mut |this| {
-            try!(this.expect(TokenData::LParen));
-            let list = try!(this.pattern_list());
-            try!(this.expect(TokenData::RParen));
-            Ok(ParamsData { list: list })
-        })
+    // The comments read so far, if the `comments` option is enabled.
+    pub fn comments(&mut self) -> Vec<Comment> {
+        self.lexer.take_comments()
     }
 
-    fn pattern_list(&mut self) -> Result<Vec<Patt<Id>>> {
-        let mut patts = Vec::new();
-        if try!(self.peek()).value == TokenData::RParen {
-            return Ok(patts);
-        }
-        patts.push(try!(self.pattern()));
-        while try!(self.matches(TokenData::Comma)) {
-            patts.push(try!(self.pattern()));
+    // Rejects syntax introduced after the target edition.
+    fn require_edition(&self, edition: Edition, feature: &'static str, location: Option<Span>) -> Result<()> {
+        if self.options.edition < edition {
+            return Err(Error::UnsupportedEdition(feature, edition, location));
         }
-        Ok(patts)
+        Ok(())
     }
 
-    fn pattern(&mut self) -> Result<Patt<Id>> {
-        match try!(self.peek()).value {
-            TokenData::Identifier(_) => {
-                let id = try!(self.binding_id());
-                Ok(Patt::Simple(id))
+    // Runs `parse`, rewinding the lexer and shared context if it fails so
+    // that the caller can try a different parse.
+    fn speculate<F, T>(&mut self, parse: F) -> Option<T>
+      where F: FnOnce(&mut Self) -> Result<T>
+    {
+        let cx = self.shared_cx.get();
+        let checkpoint = self.lexer.checkpoint();
+        let nodes = self.nodes;
+        // A speculative parse has to fail outright rather than recover.
+        let recovering = replace(&mut self.recovering, false);
+        let result = parse(self);
+        self.recovering = recovering;
+        match result {
+            Ok(result) => {
+                self.lexer.commit(checkpoint);
+                Some(result)
             }
-            _ => {
-                let patt = try!(self.binding_pattern());
-                Ok(Patt::Compound(patt))
+            Err(_) => {
+                self.lexer.rewind(checkpoint);
+                self.shared_cx.set(cx);
+                self.nodes = nodes;
+                None
             }
         }
     }
 
-    fn binding_pattern(&mut self) -> Result<CompoundPatt<Id>> {
-        if !try!(self.peek()).first_binding() {
-            return Err(Error::UnexpectedToken(try!(self.read())));
+    // Runs `parse` one level deeper, failing instead of recursing further
+    // once `max_depth` levels are open. The stack is grown on demand, so the
+    // limit doesn't depend on the size of the thread's stack.
+    fn nested<F, T>(&mut self, parse: F) -> Result<T>
+      where F: FnOnce(&mut Self) -> Result<T>
+    {
+        if self.depth >= self.options.max_depth {
+            return Err(self.too_deep());
         }
-        Err(Error::UnsupportedFeature("destructuring"))
-    }
-
-    fn function(&mut self) -> Result<Fun> {
-        let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
-        let result = self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::Function));
-            let id = try!(this.id_opt());
-            let params = try!(this.formal_parameters());
-            try!(this.expect(TokenData::LBrace));
-            let body = try!(this.statement_list());
-            try!(this.expect(TokenData::RBrace));
-            Ok(FunData { id: id, params: params, body: body })
-        });
-        replace(&mut self.parser_cx, outer_cx);
+        self.depth += 1;
+        let result = grow(|| parse(self));
+        self.depth -= 1;
         result
     }
 
-    fn statement(&mut self) -> Result<Stmt> {
-        match try!(self.peek()).value {
-            TokenData::LBrace                       => self.block_statement(),
-            TokenData::Reserved(Reserved::Var)      => self.var_statement(),
-            TokenData::Semi                         => self.empty_statement(),
-            TokenData::Reserved(Reserved::If)       => self.if_statement(),
-            TokenData::Reserved(Reserved::Continue) => self.continue_statement(),
-            TokenData::Reserved(Reserved::Break)    => self.break_statement(),
-            TokenData::Reserved(Reserved::Return)   => self.return_statement(),
-            TokenData::Reserved(Reserved::With)     => self.with_statement(),
-            TokenData::Reserved(Reserved::Switch)   => self.switch_statement(),
-            TokenData::Reserved(Reserved::Throw)    => self.throw_statement(),
-            TokenData::Reserved(Reserved::Try)      => self.try_statement(),
-            TokenData::Reserved(Reserved::While)    => self.while_statement(),
-            TokenData::Reserved(Reserved::Do)       => self.do_statement(),
-            TokenData::Reserved(Reserved::For)      => self.for_statement(),
-            TokenData::Reserved(Reserved::Debugger) => self.debugger_statement(),
-            TokenData::Identifier(_)                => {
-                let id = self.id().ok().unwrap();
-                self.id_statement(id)
-            }
-            _                                       => self.expression_statement()
+    // Counts a located node against `max_nodes` as it is built.
+    pub fn count_node(&mut self) -> Result<()> {
+        self.nodes += 1;
+        if self.nodes > self.options.max_nodes {
+            return Err(match self.peek() {
+                Ok(token) => Error::TooManyNodes(Some(token.location)),
+                Err(err)  => err
+            });
         }
+        Ok(())
     }
 
-    fn id_statement(&mut self, id: Id) -> Result<Stmt> {
-        match try!(self.peek_op()).value {
-            TokenData::Colon => self.labelled_statement(id),
-            _                => {
-                let span = self.start();
-                let expr = try!(self.id_expression(id));
-                Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Expr(expr, semi))))
-            }
+    fn node<T>(&mut self, value: T, location: Option<Span>) -> Result<Tracked<T>> {
+        try!(self.count_node());
+        Ok(value.tracked(location))
+    }
+
+    fn too_deep(&mut self) -> Error {
+        match self.peek() {
+            Ok(token) => Error::NestingTooDeep(token.location),
+            Err(err)  => err
         }
     }
 
-    fn labelled_statement(&mut self, id: Id) -> Result<Stmt> {
-        self.reread(TokenData::Colon);
+    // Tests the token after the next one without consuming either.
+    fn peek2<F>(&mut self, pred: F) -> Result<bool>
+      where F: FnOnce(&Token) -> bool
+    {
+        let checkpoint = self.lexer.checkpoint();
+        let result = match self.read() {
+            Ok(_)    => self.peek().map(pred),
+            Err(err) => Err(err)
+        };
+        self.lexer.rewind(checkpoint);
+        result
+    }
 
-        let mut labels = vec![id]; // vector of consecutive labels
-        let mut expr_id = None;    // id that starts the statement following the labels, if any
+    // Automatic semicolon insertion for declarations that don't record
+    // their terminator.
+    fn auto_semi(&mut self) -> Result<()> {
+        let span = self.start();
+        try!(span.semi(self, Newline::Required));
+        Ok(())
+    }
 
-        while let TokenData::Identifier(_) = try!(self.peek()).value {
-            let id = self.id().ok().unwrap();
-            if !try!(self.matches_op(TokenData::Colon)) {
-                expr_id = Some(id);
-                break;
-            }
-            labels.push(id);
+    pub fn script(&mut self) -> Result<Script> {
+        let items = try!(self.program_body());
+        let mut script = Script { location: self.vec_span(&items), value: ScriptData { body: items } };
+        if !self.options.locations {
+            script.untrack();
         }
+        Ok(script)
+    }
 
-        match expr_id {
-            Some(id) => {
-                self.with_labels(labels, LabelType::Statement, |this| this.id_statement(id))
-            }
-            None     => {
-                let label_type = try!(self.peek()).label_type();
-                self.with_labels(labels, label_type, |this| this.statement())
-            }
+    // ES6: import and export declarations
+    pub fn module(&mut self) -> Result<Module> {
+        try!(self.require_edition(Edition::ES2015, "modules", None));
+        self.set_mode(Mode::Module);
+        let items = try!(self.program_body());
+        let mut module = Module { location: self.vec_span(&items), value: ModuleData { body: items } };
+        if !self.options.locations {
+            module.untrack();
         }
+        Ok(module)
     }
 
-    fn expression_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        let expr = try!(self.allow_in(true, |this| this.expression()));
-        Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Expr(expr, semi))))
+    // Standalone fragments. Each has to run to the end of input.
+
+    pub fn standalone_expression(&mut self) -> Result<Expr> {
+        let mut expr = try!(self.allow_in(true, |this| this.expression()));
+        try!(self.expect_eof());
+        if !self.options.locations {
+            expr.untrack();
+        }
+        Ok(expr)
     }
 
-    fn block_statement(&mut self) -> Result<Stmt> {
-        self.span(&mut |this| {
-            this.reread(TokenData::LBrace);
-            let items = try!(this.statement_list());
-            try!(this.expect(TokenData::RBrace));
-            Ok(StmtData::Block(items))
-        })
+    pub fn standalone_statement(&mut self) -> Result<Stmt> {
+        let mut stmt = try!(self.statement());
+        try!(self.expect_eof());
+        if !self.options.locations {
+            stmt.untrack();
+        }
+        Ok(stmt)
     }
 
-    fn var_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        self.reread(TokenData::Reserved(Reserved::Var));
-        let dtors = try!(self.declarator_list());
-        Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Var(dtors, semi))))
+    // The body of a function without its braces, as in the last argument to
+    // `new Function`.
+    pub fn standalone_function_body(&mut self, kind: FunctionKind) -> Result<Vec<StmtListItem>> {
+        let mut cx = context::Context::new_function();
+        cx.allow_yield = kind.is_generator();
+        cx.allow_await = kind.is_async();
+        let outer_cx = replace(&mut self.parser_cx, cx);
+        let result = self.statement_list();
+        replace(&mut self.parser_cx, outer_cx);
+        let mut body = try!(result);
+        try!(self.expect_eof());
+        if !self.options.locations {
+            body.untrack();
+        }
+        Ok(body)
     }
 
-    fn declarator_list(&mut self) -> Result<Vec<Dtor>> {
-        let mut items = Vec::new();
-        items.push(try!(self.declarator()));
-        while try!(self.matches(TokenData::Comma)) {
-            items.push(try!(self.declarator()));
+    // A parameter list without its parentheses, as in the leading arguments
+    // to `new Function`.
+    pub fn standalone_formal_parameters(&mut self) -> Result<Params> {
+        let mut params = try!(self.span(&mut |this| {
+            let list = if try!(this.peek()).value == TokenData::EOF {
+                Vec::new()
+            } else {
+                try!(this.pattern_list())
+            };
+            Ok(ParamsData { list: list })
+        }));
+        try!(self.expect_eof());
+        if !self.options.locations {
+            params.untrack();
         }
-        Ok(items)
+        Ok(params)
     }
 
-    fn binding_id(&mut self) -> Result<Id> {
-        let id = try!(self.id());
-        if self.shared_cx.get().mode.is_strict() && id.value.name.is_illegal_strict_binding() {
-            return Err(Error::IllegalStrictBinding(id));
+    fn expect_eof(&mut self) -> Result<()> {
+        let token = try!(self.read());
+        if token.value != TokenData::EOF {
+            return Err(Error::UnexpectedToken(token));
         }
-        Ok(id)
+        Ok(())
     }
 
-    fn id(&mut self) -> Result<Id> {
-        let Token { location, newline, value: data } = try!(self.read());
-        match data {
-            TokenData::Identifier(name) => {
-                if name.is_reserved(self.shared_cx.get().mode) {
-                    return Err(Error::ContextualKeyword(Id {
-                        value: IdData { name: name },
-                        location: Some(location)
-                    }));
+    // Parses a script instead of stopping at the first error, collecting
+    // the errors and leaving `Invalid` placeholders in the tree.
+    pub fn script_recovering(&mut self) -> (Script, Vec<Error>) {
+        self.recovering = true;
+        let script = match self.script() {
+            Ok(script) => script,
+            Err(err) => {
+                // Only a failure to read the end of input, or a resource
+                // limit, gets this far.
+                let location = err.span();
+                self.errors.push(err);
+                Script { location: location, value: ScriptData { body: Vec::new() } }
+            }
+        };
+        self.recovering = false;
+        (script, replace(&mut self.errors, Vec::new()))
+    }
+
+    // Reads the whole source as a stream of tokens, trivia and lexer errors.
+    // The parser drives the lexer so that `/` is read as division or as a
+    // regular expression according to the grammar; syntax errors are
+    // recovered from and not reported.
+    pub fn tokens(&mut self) -> Vec<Lexeme> {
+        self.lexer.trace();
+        self.recovering = true;
+        let _ = match self.options.source_type {
+            SourceType::Script => self.script().map(|_| ()),
+            SourceType::Module => self.module().map(|_| ())
+        };
+        self.recovering = false;
+        self.errors.clear();
+
+        // Lex whatever the parser didn't get to.
+        loop {
+            match self.lexer.read_token() {
+                Ok(Token { value: TokenData::EOF, .. }) => { break; }
+                Ok(_)                                   => { }
+                Err(err) => {
+                    if err.value.is_fatal() {
+                        break;
+                    }
+                    self.lexer.skip_char();
                 }
-                Ok(Id { location: Some(location), value: IdData { name: name } })
             }
-            _ => Err(Error::UnexpectedToken(Token {
-                location: location,
-                newline: newline,
-                value: data
-            }))
         }
+        self.lexer.take_trace().into_iter().filter(|lexeme| {
+            match *lexeme {
+                Lexeme::Token(Token { value: TokenData::EOF, .. }) => false,
+                _ => true
+            }
+        }).collect()
     }
 
-    fn id_opt(&mut self) -> Result<Option<Id>> {
-        let next = try!(self.read());
-        match next.value {
-            TokenData::Identifier(name) => {
-                Ok(Some(IdData { name: name }.tracked(Some(next.location))))
+    // ScriptBody or ModuleBody, which must run to the end of input.
+    fn program_body(&mut self) -> Result<Vec<StmtListItem>> {
+        let mut items = try!(self.statement_list());
+        loop {
+            let token = try!(self.read());
+            if token.value == TokenData::EOF {
+                break;
             }
-            _                           => { self.lexer.unread_token(next); Ok(None) }
+            // A stray '}', 'case' or 'default'.
+            if !self.recovering {
+                return Err(Error::UnexpectedToken(token));
+            }
+            let location = Some(token.location);
+            self.errors.push(Error::UnexpectedToken(token));
+            items.push(StmtListItem::Stmt(try!(self.node(StmtData::Invalid, location))));
+            items.extend(try!(self.statement_list()));
         }
+        Ok(items)
     }
 
-    fn declarator(&mut self) -> Result<Dtor> {
-        self.span(&mut |this| {
-            match try!(this.peek()).value {
-                TokenData::Identifier(_) => {
-                    let id = try!(this.binding_id());
-                    let init = if try!(this.matches(TokenData::Assign)) {
-                        Some(try!(this.assignment_expression()))
-                    } else {
-                        None
+    fn statement_list(&mut self) -> Result<Vec<StmtListItem>> {
+        let mut items = Vec::new();
+        loop {
+            let cx = self.shared_cx.get();
+            let (start, result) = match self.peek() {
+                Ok(token) if token.follow_statement_list() => { break; }
+                Ok(token) => (token.location.start, None),
+                Err(err) => (err.span().unwrap().start, Some(err))
+            };
+            let result = match result {
+                Some(err) => Err(err),
+                None      => self.statement_list_item()
+            };
+            match result {
+                Ok(item) => {
+                    let stuck = self.recovering && match self.peek() {
+                        Ok(token) => token.location.start == start,
+                        Err(_)    => false
                     };
-                    Ok(DtorData::Simple(id, init))
+                    if !stuck {
+                        items.push(item);
+                        continue;
+                    }
+                    // An expression statement made of nothing but a recovered
+                    // placeholder consumes no input, so skip past it here.
+                    let token = self.lexer.reread_token();
+                    let end = self.skip_statement(token.location.end, 0);
+                    let location = Some(Span { start: start, end: end });
+                    items.push(StmtListItem::Stmt(try!(self.node(StmtData::Invalid, location))));
                 }
-                _ => {
-                    let lhs = try!(this.binding_pattern());
-                    try!(this.expect(TokenData::Assign));
-                    let rhs = try!(this.assignment_expression());
-                    Ok(DtorData::Compound(lhs, rhs))
+                Err(err) => {
+                    if !self.recovering || err.is_fatal() {
+                        return Err(err);
+                    }
+                    self.shared_cx.set(cx);
+                    let stmt = self.recover_statement(start, err);
+                    try!(self.count_node());
+                    items.push(StmtListItem::Stmt(stmt));
                 }
             }
-        })
+        }
+        Ok(items)
     }
 
-    fn empty_statement(&mut self) -> Result<Stmt> {
-        self.span(&mut |this| {
-            try!(this.expect(TokenData::Semi));
-            Ok(StmtData::Empty)
-        })
+    // The next item of a statement list, or `None` at the end of the list.
+    pub fn statement_list_item_opt(&mut self) -> Result<Option<StmtListItem>> {
+        if try!(self.peek()).follow_statement_list() {
+            return Ok(None);
+        }
+        self.statement_list_item().map(Some)
     }
 
-    fn if_statement(&mut self) -> Result<Stmt> {
-        self.span(&mut |this| {
-            try!(this.expect(TokenData::Reserved(Reserved::If)));
-            let test = try!(this.paren_expression());
-            let cons = Box::new(try!(this.statement()));
-            let alt = if try!(this.peek()).value == TokenData::Reserved(Reserved::Else) {
-                this.reread(TokenData::Reserved(Reserved::Else));
-                Some(Box::new(try!(this.statement())))
-            } else {
-                None
-            };
-            Ok(StmtData::If(test, cons, alt))
+    fn statement_list_item(&mut self) -> Result<StmtListItem> {
+        Ok(match try!(self.declaration_opt()) {
+            Some(decl) => StmtListItem::Decl(decl),
+            None       => StmtListItem::Stmt(try!(self.statement()))
         })
     }
 
-    fn iteration_body(&mut self) -> Result<Stmt> {
-        let iteration = replace(&mut self.parser_cx.iteration, true);
-        let result = self.statement();
-        replace(&mut self.parser_cx.iteration, iteration);
-        result
-    }
-
-    fn do_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        self.reread(TokenData::Reserved(Reserved::Do));
-        let body = Box::new(try!(self.iteration_body()));
-        try!(self.expect(TokenData::Reserved(Reserved::While)));
-        let test = try!(self.paren_expression());
-        Ok(try!(span.end_with_auto_semi(self, Newline::Optional, |semi| {
-            StmtData::DoWhile(body, test, semi)
-        })))
+    // Error recovery: records `err` and skips to the end of the statement that
+    // began at `start`, returning a placeholder that spans the skipped text.
+    fn recover_statement(&mut self, start: Posn, err: Error) -> Stmt {
+        let mut end = err.span().map_or(start, |span| span.end);
+        let mut depth = 0;
+        match err {
+            // Give back a closing brace so that the enclosing block still
+            // finds it.
+            Error::UnexpectedToken(ref token)
+          | Error::FailedASI(ref token)
+          | Error::Expected(_, ref token)
+          | Error::Unclosed(_, _, ref token) if token.value == TokenData::RBrace => {
+                end = token.location.start;
+                self.lexer.unread_token(token.clone());
+            }
+            // An unexpected opening bracket has already been consumed.
+            Error::UnexpectedToken(ref token)
+          | Error::FailedASI(ref token)
+          | Error::Expected(_, ref token)
+          | Error::Unclosed(_, _, ref token) => match token.value {
+                TokenData::LBrace | TokenData::LParen | TokenData::LBrack => { depth = 1; }
+                _ => { }
+            },
+            // Step over the character that could not be lexed.
+            Error::LexError(_) => { self.lexer.skip_char(); }
+            _ => { }
+        }
+        self.errors.push(err);
+        let end = self.skip_statement(end, depth);
+        StmtData::Invalid.tracked(Some(Span { start: start, end: end }))
     }
 
-    fn while_statement(&mut self) -> Result<Stmt> {
-        self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::While));
-            let test = try!(this.paren_expression());
-            let body = Box::new(try!(this.iteration_body()));
-            Ok(StmtData::While(test, body))
-        })
-    }
-
-    fn for_statement(&mut self) -> Result<Stmt> {
-        self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::For));
-            try!(this.expect(TokenData::LParen));
-            match try!(this.peek()).value {
-                TokenData::Reserved(Reserved::Var)           => this.for_var(),
-                TokenData::Identifier(Name::Atom(Atom::Let)) => this.for_let(),
-                TokenData::Reserved(Reserved::Const)         => { return Err(Error::UnsupportedFeature("const")); }
-                TokenData::Semi                              => {
-                    this.reread(TokenData::Semi);
-                    this.more_for(None)
+    // Error recovery: skips the rest of a statement, stepping over balanced
+    // brackets. Stops after a ';', before a '}' that closes the enclosing
+    // block, or before a statement keyword on a new line.
+    fn skip_statement(&mut self, mut end: Posn, mut depth: u32) -> Posn {
+        let mut operand = false;
+        loop {
+            // After an operand a '/' is division, not the start of a regexp.
+            let token = match if operand { self.peek_op() } else { self.peek() } {
+                Ok(token) => token.clone(),
+                Err(err) => {
+                    if err.is_fatal() {
+                        return end;
+                    }
+                    end = err.span().unwrap().end;
+                    self.errors.push(err);
+                    self.lexer.skip_char();
+                    continue;
                 }
-                _                                            => this.for_expr()
+            };
+            if depth == 0 && (token.follow_statement_list() || (token.newline && token.first_statement_keyword())) {
+                return end;
             }
-        })
-    }
-
-    // 'for' '(' 'var' .
-    fn for_var(&mut self) -> Result<StmtData> {
-        let var_token = self.reread(TokenData::Reserved(Reserved::Var));
-        let var_location = var_token.location;
-        let lhs = try!(self.pattern());
-        match try!(self.peek()).value {
-            // 'for' '(' 'var' id   '=' .
-            // 'for' '(' 'var' patt '=' . ==> C-style
-            TokenData::Assign => {
-                self.reread(TokenData::Assign);
-                match lhs {
-                    Patt::Simple(id) => {
-                        let rhs = try!(self.allow_in(false, |this| this.assignment_expression()));
-                        match try!(self.peek()).value {
-                            // 'for' '(' 'var' id '=' expr ','  . ==> C-style
-                            // 'for' '(' 'var' id '=' expr ';'  . ==> C-style
-                            TokenData::Comma
-                          | TokenData::Semi => {
-                                let head = Some(try!(self.more_for_head(&var_location, Dtor::from_simple_init(id, rhs), ForHeadData::Var)));
-                                self.more_for(head)
-                            }
-                            // 'for' '(' 'var' id '=' expr 'in' . ==> legacy enumeration
-                            TokenData::Reserved(Reserved::In) => {
-                                self.reread(TokenData::Reserved(Reserved::In));
-                                let head = Box::new(ForInHead {
-                                    location: span(&var_location, &rhs),
-                                    value: ForInHeadData::VarInit(id, rhs)
-                                });
-                                self.more_for_in(head)
-                            }
-                            _ => Err(Error::UnexpectedToken(try!(self.read())))
-                        }
-                    }
-                    // 'for' '(' 'var' patt '=' . ==> C-style
-                    Patt::Compound(patt) => {
-                        let rhs = try!(self.allow_in(false, |this| this.assignment_expression()));
-                        let head = Some(try!(self.more_for_head(&var_location, Dtor::from_compound_init(patt, rhs), ForHeadData::Var)));
-                        self.more_for(head)
+            self.lexer.reread_token();
+            end = token.location.end;
+            match token.value {
+                TokenData::Semi if depth == 0 => { return end; }
+                TokenData::EOF => { return end; }
+                TokenData::LBrace | TokenData::LParen | TokenData::LBrack => { depth += 1; }
+                TokenData::RBrace | TokenData::RParen | TokenData::RBrack => {
+                    if depth > 0 {
+                        depth -= 1;
                     }
                 }
+                _ => { }
             }
-            TokenData::Comma
-          | TokenData::Semi => {
-                // 'for' '(' 'var' id   ',' . ==> C-style
-                // 'for' '(' 'var' id   ';' . ==> C-style
-                // 'for' '(' 'var' patt ',' . ==> syntax error
-                // 'for' '(' 'var' patt ';' . ==> syntax error
-                let dtor = match Dtor::from_init_opt(lhs, None) {
-                    Ok(dtor) => dtor,
-                    Err(_) => { return Err(Error::UnexpectedToken(try!(self.read()))); }
-                };
-                let head = Some(try!(self.more_for_head(&var_location, dtor, ForHeadData::Var)));
-                self.more_for(head)
-            }
-            // 'for' '(' 'var' id   'in' . ==> enumeration
-            // 'for' '(' 'var' patt 'in' . ==> enumeration
-            TokenData::Reserved(Reserved::In) => {
-                self.reread(TokenData::Reserved(Reserved::In));
-                let head = Box::new(ForInHead {
-                    location: span(&var_location, &lhs),
-                    value: ForInHeadData::Var(lhs)
-                });
-                self.more_for_in(head)
-            }
-            // 'for' '(' 'var' id   'of' . ==> enumeration
-            // 'for' '(' 'var' patt 'of' . ==> enumeration
-            TokenData::Identifier(Name::Atom(Atom::Of)) => {
-                self.reread(TokenData::Identifier(Name::Atom(Atom::Of)));
-                let head = Box::new(ForOfHead {
-                    location: span(&var_location, &lhs),
-                    value: ForOfHeadData::Var(lhs)
-                });
-                self.more_for_of(head)
-            }
-            _ => Err(Error::UnexpectedToken(try!(self.read())))
+            operand = match token.value {
+                TokenData::Identifier(_)
+              | TokenData::Number(_)
+              | TokenData::String(_)
+              | TokenData::RegExp(_)
+              | TokenData::RParen
+              | TokenData::RBrack
+              | TokenData::Reserved(Reserved::This)
+              | TokenData::Reserved(Reserved::Null)
+              | TokenData::Reserved(Reserved::True)
+              | TokenData::Reserved(Reserved::False) => true,
+                _ => false
+            };
         }
     }
 
-    // 'for' '(' 'let' .
-    fn for_let(&mut self) -> Result<StmtData> {
-        let let_token = self.reread(TokenData::Identifier(Name::Atom(Atom::Let)));
-        let let_location = let_token.location;
-        // 'for' '(' 'let' . !{id, patt} ==> error
-        let lhs = try!(self.pattern());
-        match try!(self.peek()).value {
-            // 'for' '(' 'let' id   '=' . ==> C-style
-            // 'for' '(' 'let' patt '=' . ==> C-style
-            TokenData::Assign => {
-                self.reread(TokenData::Assign);
-                let rhs = try!(self.allow_in(false, |this| this.assignment_expression()));
-                let head = Some(try!(self.more_for_head(&let_location, Dtor::from_init(lhs, rhs), ForHeadData::Let)));
-                self.more_for(head)
-            }
-            TokenData::Comma
-          | TokenData::Semi => {
-                // 'for' '(' 'let' id   ',' . ==> C-style
-                // 'for' '(' 'let' id   ';' . ==> C-style
-                // 'for' '(' 'let' patt ',' . ==> error
-                // 'for' '(' 'let' patt ';' . ==> error
-                let dtor = match Dtor::from_init_opt(lhs, None) {
-                    Ok(dtor) => dtor,
-                    Err(_) => { return Err(Error::UnexpectedToken(try!(self.read()))); }
-                };
-                let head = Some(try!(self.more_for_head(&let_location, dtor, ForHeadData::Let)));
-                self.more_for(head)
-            }
-            // 'for' '(' 'let' id   'in' . ==> enumeration
-            // 'for' '(' 'let' patt 'in' . ==> enumeration
-            TokenData::Reserved(Reserved::In) => {
-                self.reread(TokenData::Reserved(Reserved::In));
-                let head = Box::new(ForInHead {
-                    location: span(&let_location, &lhs),
-                    value: ForInHeadData::Let(lhs)
-                });
-                self.more_for_in(head)
-            }
-            // 'for' '(' 'let' id   'of' . ==> enumeration
-            // 'for' '(' 'let' patt 'of' . ==> enumeration
-            TokenData::Identifier(Name::Atom(Atom::Of)) => {
-                self.reread(TokenData::Identifier(Name::Atom(Atom::Of)));
-                let head = Box::new(ForOfHead {
-                    location: span(&let_location, &lhs),
-                    value: ForOfHeadData::Let(lhs)
-                });
-                self.more_for_of(head)
-            }
-            _ => Err(Error::UnexpectedToken(try!(self.read())))
+/*
+    pub fn declaration(&mut self) -> Result<Decl> {
+        match try!(self.declaration_opt()) {
+            Some(decl) => Ok(decl),
+            None       => Err(Error::UnexpectedToken(try!(self.read())))
         }
     }
+*/
 
-    fn for_expr(&mut self) -> Result<StmtData> {
-        let lhs = try!(self.allow_in(false, |this| this.expression()));
+    fn declaration_opt(&mut self) -> Result<Option<Decl>> {
+        let typescript = self.shared_cx.get().typescript;
         match try!(self.peek()).value {
-            TokenData::Semi => {
-                let semi_location = self.reread(TokenData::Semi).location;
-                let head = Some(Box::new(ForHead {
-                    location: span(&lhs, &semi_location),
-                    value: ForHeadData::Expr(lhs)
-                }));
-                self.more_for(head)
-            }
-            TokenData::Reserved(Reserved::In) => {
-                self.reread(TokenData::Reserved(Reserved::In));
-                let head = Box::new(ForInHead {
-                    location: lhs.location(),
-                    value: ForInHeadData::Expr(lhs)
-                });
-                self.more_for_in(head)
-            }
-            TokenData::Identifier(Name::Atom(Atom::Of)) => {
-                self.reread(TokenData::Identifier(Name::Atom(Atom::Of)));
-                let head = Box::new(ForOfHead {
-                    location: lhs.location(),
-                    value: ForOfHeadData::Expr(lhs)
-                });
-                self.more_for_of(head)
-            }
-            _ => Err(Error::UnexpectedToken(try!(self.read())))
+            TokenData::Reserved(Reserved::Function) => Ok(Some(try!(self.function_declaration()))),
+            TokenData::Reserved(Reserved::Class)
+          | TokenData::At                           => Ok(Some(try!(self.class_declaration()))),
+            _ if typescript                         => self.ts_declaration_opt(),
+            _                                       => Ok(None)
         }
     }
 
-    // 'for' '(' dtor .
-    fn more_for_head<F>(&mut self, start: &Span, dtor: Dtor, op: F) -> Result<Box<ForHead>>
-      where F: FnOnce(Vec<Dtor>) -> ForHeadData
-    {
-        let dtors = try!(self.allow_in(false, |this| {
-            let mut dtors = vec![dtor];
-            try!(this.more_dtors(&mut dtors));
-            Ok(dtors)
-        }));
-        let semi_location = try!(self.expect(TokenData::Semi)).location;
-        Ok(Box::new(ForHead {
-            location: span(start, &semi_location),
-            value: op(dtors)
-        }))
+    fn function_declaration(&mut self) -> Result<Decl> {
+        let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
+        let result = self.span(&mut |this| {
+            let start = this.start_posn();
+            let (id, sig) = try!(this.function_head());
+            // In TypeScript, a declaration without a body is an overload signature.
+            if this.shared_cx.get().typescript && try!(this.peek()).value != TokenData::LBrace {
+                let id = match id {
+                    Some(id) => id,
+                    None     => { return Err(Error::UnexpectedToken(try!(this.read()))); }
+                };
+                try!(this.auto_semi());
+                return Ok(DeclData::FunSig(id, sig));
+            }
+            let fun = try!(this.function_body(id, sig));
+            Ok(DeclData::Fun(try!(this.node(fun, Some(Span { start: start, end: this.end_posn() })))))
+        });
+        replace(&mut self.parser_cx, outer_cx);
+        result
     }
 
-    // 'for' '(' head ';' .
-    fn more_for(&mut self, head: Option<Box<ForHead>>) -> Result<StmtData> {
-        let test = try!(self.expression_opt_semi());
-        let update = if try!(self.matches(TokenData::RParen)) {
-            None
-        } else {
-            let node = Some(try!(self.allow_in(true, |this| this.expression())));
-            try!(self.expect(TokenData::RParen));
-            node
-        };
-        let body = Box::new(try!(self.iteration_body()));
-        Ok(StmtData::For(head, test, update, body))
+    fn class_declaration(&mut self) -> Result<Decl> {
+        self.span(&mut |this| {
+            Ok(DeclData::Class(try!(this.class(true))))
+        })
     }
 
-    // 'for' '(' head 'in' .
-    fn more_for_in(&mut self, head: Box<ForInHead>) -> Result<StmtData> {
-        let obj = try!(self.allow_in(true, |this| this.assignment_expression()));
-        try!(self.expect(TokenData::RParen));
-        let body = Box::new(try!(self.iteration_body()));
-        Ok(StmtData::ForIn(head, obj, body))
+    // Class ::=
+    //   Decorator* "abstract"? "class" BindingIdentifier? TypeParameters? ClassHeritage?
+    //   ("implements" PostfixType+[","])? "{" ClassMember* "}"
+    //
+    // Only TypeScript class declarations may be abstract.
+    fn class(&mut self, is_decl: bool) -> Result<Class> {
+        self.span(&mut |this| {
+            let decorators = try!(this.decorators());
+            let is_abstract = is_decl && this.shared_cx.get().typescript &&
+                              try!(this.matches(TokenData::Identifier(Name::Atom(Atom::Abstract))));
+            let class = try!(this.expect(TokenData::Reserved(Reserved::Class)));
+            try!(this.require_edition(Edition::ES2015, "classes", Some(class.location)));
+            // All parts of a class are strict mode code.
+            let outer_mode = this.shared_cx.get().mode;
+            if !outer_mode.is_strict() {
+                this.set_mode(Mode::Strict);
+            }
+            let result = this.class_tail(decorators, is_abstract, is_decl);
+            this.set_mode(outer_mode);
+            result
+        })
     }
 
-    // 'for' '(' head 'of' .
-    fn more_for_of(&mut self, head: Box<ForOfHead>) -> Result<StmtData> {
-        let obj = try!(self.allow_in(true, |this| this.assignment_expression()));
-        try!(self.expect(TokenData::RParen));
-        let body = Box::new(try!(self.iteration_body()));
-        Ok(StmtData::ForOf(head, obj, body))
+    fn set_mode(&mut self, mode: Mode) {
+        let mut cx = self.shared_cx.get();
+        cx.mode = mode;
+        self.shared_cx.set(cx);
     }
 
-    fn expression_opt_semi(&mut self) -> Result<Option<Expr>> {
-        Ok(if try!(self.matches(TokenData::Semi)) {
-            None
+    fn class_tail(&mut self, decorators: Vec<Decorator>, is_abstract: bool, is_decl: bool) -> Result<ClassData> {
+        let id = match try!(self.peek()).value {
+            TokenData::Identifier(_) => Some(try!(self.binding_id())),
+            _ if is_decl             => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+            _                        => None
+        };
+        let ty_params = try!(self.type_parameters_opt());
+        let extends = if try!(self.matches(TokenData::Reserved(Reserved::Extends))) {
+            Some(Box::new(try!(self.class_heritage())))
         } else {
-            let expr = try!(self.allow_in(true, |this| this.expression()));
-            try!(self.expect(TokenData::Semi));
-            Some(expr)
+            None
+        };
+        let mut implements = Vec::new();
+        if self.shared_cx.get().typescript &&
+           try!(self.matches_op(TokenData::Identifier(Name::Atom(Atom::Implements)))) {
+            loop {
+                implements.push(try!(self.postfix_type()));
+                if !try!(self.matches_op(TokenData::Comma)) {
+                    break;
+                }
+            }
+        }
+        try!(self.expect(TokenData::LBrace));
+        let mut body = Vec::new();
+        while !try!(self.matches(TokenData::RBrace)) {
+            if try!(self.matches(TokenData::Semi)) {
+                continue;
+            }
+            body.push(try!(self.class_member(is_abstract)));
+        }
+        Ok(ClassData {
+            decorators: decorators,
+            is_abstract: is_abstract,
+            id: id,
+            ty_params: ty_params,
+            extends: extends,
+            implements: implements,
+            body: body
         })
     }
 
-    fn more_dtors(&mut self, dtors: &mut Vec<Dtor>) -> Result<()> {
-        while try!(self.matches(TokenData::Comma)) {
-            dtors.push(try!(self.declarator()));
+    // ClassHeritage ::= "extends" LHSExpression TypeArguments?
+    fn class_heritage(&mut self) -> Result<Expr> {
+        let base = try!(self.lhs_expression());
+        if !self.shared_cx.get().typescript || try!(self.peek_op()).value != TokenData::LAngle {
+            return Ok(base);
         }
-        Ok(())
+        let (args, end) = try!(self.type_arguments());
+        let location = span(&base, &end);
+        self.node(ExprData::Instantiation(Box::new(base), args), location)
     }
 
-    fn switch_statement(&mut self) -> Result<Stmt> {
+    // ClassMember ::=
+    //   Decorator* Modifier* ("get" | "set")? PropertyName "?"? MethodTail
+    //   Decorator* Modifier* PropertyName TypeAnnotation? ("=" AssignmentExpression)? ";"
+    //
+    // Modifier ::=
+    //   "static"
+    //   "public" | "protected" | "private" | "readonly"    // TypeScript mode only
+    //   "abstract"                                         // abstract classes only
+    fn class_member(&mut self, in_abstract: bool) -> Result<ClassMember> {
         self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::Switch));
-            let disc = try!(this.paren_expression());
-            let outer_switch = replace(&mut this.parser_cx.switch, true);
-            let cases = this.switch_cases();
-            replace(&mut this.parser_cx.switch, outer_switch);
-            Ok(StmtData::Switch(disc, try!(cases)))
-        })
-    }
+            let decorators = try!(this.decorators());
+            let typescript = this.shared_cx.get().typescript;
+            let mut access = None;
+            let mut is_abstract = false;
+            let mut is_static = false;
+            let mut readonly = false;
 
-    fn switch_cases(&mut self) -> Result<Vec<Case>> {
-        try!(self.expect(TokenData::LBrace));
-        let mut cases = Vec::new();
-        let mut found_default = false;
-        loop {
-            match try!(self.peek()).value {
-                TokenData::Reserved(Reserved::Case) => { cases.push(try!(self.case())); }
-                TokenData::Reserved(Reserved::Default) => {
-                    if found_default {
-                        let token = self.reread(TokenData::Reserved(Reserved::Default));
-                        return Err(Error::DuplicateDefault(token));
-                    }
-                    found_default = true;
-                    cases.push(try!(self.default()));
+            // A modifier not followed by a property name is itself the name.
+            loop {
+                let atom = match try!(this.peek()).value {
+                    TokenData::Identifier(Name::Atom(atom)) => atom,
+                    _ => { break; }
+                };
+                let modifier = match atom {
+                    Atom::Static                                  => !is_static && !is_abstract,
+                    Atom::Public | Atom::Protected | Atom::Private => typescript && access.is_none() && !is_static && !is_abstract,
+                    Atom::Abstract                                => in_abstract && !is_abstract && !is_static,
+                    Atom::Readonly                                => typescript && !readonly,
+                    _                                             => false
+                };
+                if !modifier || !try!(this.peek2(|next| next.first_property_key())) {
+                    break;
+                }
+                try!(this.read());
+                match atom {
+                    Atom::Static    => { is_static = true; }
+                    Atom::Public    => { access = Some(Access::Public); }
+                    Atom::Protected => { access = Some(Access::Protected); }
+                    Atom::Private   => { access = Some(Access::Private); }
+                    Atom::Abstract  => { is_abstract = true; }
+                    _               => { readonly = true; }
                 }
-                _ => { break; }
             }
-        }
-        try!(self.expect(TokenData::RBrace));
-        Ok(cases)
+
+            let mut accessor = match try!(this.peek()).value {
+                TokenData::Identifier(Name::Atom(Atom::Get)) => Some(Atom::Get),
+                TokenData::Identifier(Name::Atom(Atom::Set)) => Some(Atom::Set),
+                _                                            => None
+            };
+            if accessor.is_some() {
+                if try!(this.peek2(|next| next.first_property_key())) {
+                    try!(this.read());
+                } else {
+                    accessor = None;
+                }
+            }
+
+            let key = try!(this.property_key());
+            let constructor = !is_static && accessor.is_none() && match key.value {
+                PropKeyData::Id(ref name) => name.to_string() == "constructor",
+                _                         => false
+            };
+            let val = match try!(this.peek()).value {
+                TokenData::LParen | TokenData::LAngle => try!(this.method(accessor, constructor)),
+                _ if accessor.is_some()               => { return Err(Error::UnexpectedToken(try!(this.read()))); }
+                _ => {
+                    try!(this.require_edition(Edition::ES2022, "class fields", key.location));
+                    let ty = if typescript { try!(this.type_annotation_opt()) } else { None };
+                    let init = if try!(this.matches_op(TokenData::Assign)) {
+                        Some(try!(this.allow_in(true, |this| this.assignment_expression())))
+                    } else {
+                        None
+                    };
+                    try!(this.auto_semi());
+                    ClassMemberVal::Field(ty, init)
+                }
+            };
+            Ok(ClassMemberData {
+                decorators: decorators,
+                access: access,
+                is_abstract: is_abstract,
+                is_static: is_static,
+                readonly: readonly,
+                key: key,
+                val: val
+            })
+        })
     }
 
-    fn case(&mut self) -> Result<Case> {
-        self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::Case));
-            let test = try!(this.allow_in(true, |this| this.expression()));
-            try!(this.expect(TokenData::Colon));
-            let body = try!(this.case_body());
-            Ok(CaseData { test: Some(test), body: body })
+    fn method(&mut self, accessor: Option<Atom>, constructor: bool) -> Result<ClassMemberVal> {
+        let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
+        self.parser_cx.param_props = constructor;
+        let result = self.method_tail(accessor);
+        replace(&mut self.parser_cx, outer_cx);
+        result
+    }
+
+    // MethodTail ::=
+    //   CallSignature "{" FunctionBody "}"
+    //   CallSignature ";"                                  // TypeScript mode only
+    fn method_tail(&mut self, accessor: Option<Atom>) -> Result<ClassMemberVal> {
+        let start = self.start_posn();
+        let sig = try!(self.fun_type(false));
+        self.parser_cx.param_props = false;
+        if self.shared_cx.get().typescript && accessor.is_none() &&
+           try!(self.peek()).value != TokenData::LBrace {
+            try!(self.auto_semi());
+            return Ok(ClassMemberVal::Sig(sig));
+        }
+        let fun = try!(self.function_body(None, sig));
+        let fun = try!(self.node(fun, Some(Span { start: start, end: self.end_posn() })));
+        Ok(match accessor {
+            Some(Atom::Get) => ClassMemberVal::Get(fun),
+            Some(_)         => ClassMemberVal::Set(fun),
+            None            => ClassMemberVal::Method(fun)
         })
     }
 
-    fn case_body(&mut self) -> Result<Vec<StmtListItem>> {
-        let mut items = Vec::new();
-        loop {
-            match try!(self.peek()).value {
-                TokenData::Reserved(Reserved::Case)
-              | TokenData::Reserved(Reserved::Default)
-              | TokenData::RBrace => { break; }
-                _ => { }
-            }
-            match try!(self.declaration_opt()) {
-                Some(decl) => { items.push(StmtListItem::Decl(decl)); }
-                None       => { items.push(StmtListItem::Stmt(try!(self.statement()))); }
-            }
+    fn decorators(&mut self) -> Result<Vec<Decorator>> {
+        let mut decorators = Vec::new();
+        while try!(self.peek()).value == TokenData::At {
+            decorators.push(try!(self.decorator()));
         }
-        Ok(items)
+        Ok(decorators)
     }
 
-    fn default(&mut self) -> Result<Case> {
+    // Decorator ::=
+    //   "@" IdentifierReference ("." IdentifierName)* Arguments?
+    //   "@" "(" Expression ")"
+    fn decorator(&mut self) -> Result<Decorator> {
         self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::Default));
-            try!(this.expect(TokenData::Colon));
-            let body = try!(this.case_body());
-            Ok(CaseData { test: None, body: body })
+            this.reread(TokenData::At);
+            if try!(this.peek()).value == TokenData::LParen {
+                return Ok(DecoratorData(try!(this.paren_expression())));
+            }
+            let id = try!(this.id());
+            let location = id.location();
+            let mut expr = try!(this.node(ExprData::Id(id), location));
+            while try!(this.peek_op()).value == TokenData::Dot {
+                try!(this.count_node());
+                expr = try!(this.deref_dot()).append_to(expr);
+            }
+            if try!(this.peek_op()).value == TokenData::LParen {
+                try!(this.count_node());
+                expr = try!(this.arguments()).append_to(expr);
+            }
+            Ok(DecoratorData(expr))
         })
     }
 
-    fn break_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        let break_token = self.reread(TokenData::Reserved(Reserved::Break));
-        let arg = if try!(self.has_arg_same_line()) {
-            let id = try!(self.id());
-            if !self.parser_cx.labels.contains_key(&Rc::new(id.value.name.clone())) {
-                return Err(Error::InvalidLabel(id));
+    // The TypeScript contextual keywords only start a declaration when
+    // followed by a name on the same line.
+    fn ts_declaration_opt(&mut self) -> Result<Option<Decl>> {
+        let decl = match try!(self.peek()).value {
+            TokenData::Identifier(Name::Atom(Atom::Interface)) => {
+                if !try!(self.peek2(|next| match *next {
+                    Token { newline: false, value: TokenData::Identifier(_), .. } => true,
+                    _ => false
+                })) {
+                    return Ok(None);
+                }
+                try!(self.interface_declaration())
             }
-            Some(id)
-        } else {
-            if !self.parser_cx.iteration && !self.parser_cx.switch {
-                return Err(Error::IllegalBreak(break_token));
+            TokenData::Identifier(Name::Atom(Atom::Type)) => {
+                if !try!(self.peek2(|next| match *next {
+                    Token { newline: false, value: TokenData::Identifier(_), .. } => true,
+                    _ => false
+                })) {
+                    return Ok(None);
+                }
+                try!(self.type_alias_declaration())
             }
-            None
+            TokenData::Identifier(Name::Atom(Atom::Declare)) => {
+                if !try!(self.peek2(|next| match *next {
+                    Token { newline: false, value: TokenData::Identifier(_), .. }
+                  | Token { newline: false, value: TokenData::Reserved(_), .. } => true,
+                    _ => false
+                })) {
+                    return Ok(None);
+                }
+                try!(self.ambient_declaration())
+            }
+            TokenData::Identifier(Name::Atom(Atom::Namespace)) => {
+                if !try!(self.peek2(|next| match *next {
+                    Token { newline: false, value: TokenData::Identifier(_), .. } => true,
+                    _ => false
+                })) {
+                    return Ok(None);
+                }
+                try!(self.namespace_declaration())
+            }
+            TokenData::Identifier(Name::Atom(Atom::Module)) => {
+                if !try!(self.peek2(|next| match *next {
+                    Token { newline: false, value: TokenData::Identifier(_), .. }
+                  | Token { newline: false, value: TokenData::String(_), .. } => true,
+                    _ => false
+                })) {
+                    return Ok(None);
+                }
+                try!(self.namespace_declaration())
+            }
+            TokenData::Identifier(Name::Atom(Atom::Abstract)) => {
+                if !try!(self.peek2(|next| match *next {
+                    Token { newline: false, value: TokenData::Reserved(Reserved::Class), .. } => true,
+                    _ => false
+                })) {
+                    return Ok(None);
+                }
+                try!(self.class_declaration())
+            }
+            TokenData::Reserved(Reserved::Const) => {
+                if !try!(self.peek2(|next| next.value == TokenData::Reserved(Reserved::Enum))) {
+                    return Ok(None);
+                }
+                try!(self.enum_declaration())
+            }
+            TokenData::Reserved(Reserved::Enum) => try!(self.enum_declaration()),
+            _ => { return Ok(None); }
         };
-        span.end_with_auto_semi(self, Newline::Required, |semi| {
-            StmtData::Break(arg, semi)
-        })
+        Ok(Some(decl))
     }
 
-    fn continue_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        let continue_token = self.reread(TokenData::Reserved(Reserved::Continue));
-        let arg = if try!(self.has_arg_same_line()) {
-            let id = try!(self.id());
-            match self.parser_cx.labels.get(&Rc::new(id.value.name.clone())) {
-                None                        => { return Err(Error::InvalidLabel(id)); }
-                Some(&LabelType::Statement) => { return Err(Error::InvalidLabelType(id)); }
-                _                           => { }
-            }
-            Some(id)
-        } else {
-            if !self.parser_cx.iteration {
-                return Err(Error::IllegalContinue(continue_token));
+    // InterfaceDeclaration ::=
+    //   "interface" BindingIdentifier TypeParameters? ("extends" PostfixType+[","])? ObjectType
+    fn interface_declaration(&mut self) -> Result<Decl> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Identifier(Name::Atom(Atom::Interface)));
+            let id = try!(this.binding_id());
+            let params = try!(this.type_parameters_opt());
+            let mut extends = Vec::new();
+            if try!(this.matches(TokenData::Reserved(Reserved::Extends))) {
+                loop {
+                    extends.push(try!(this.postfix_type()));
+                    if !try!(this.matches_op(TokenData::Comma)) {
+                        break;
+                    }
+                }
             }
-            None
-        };
-        span.end_with_auto_semi(self, Newline::Required, |semi| {
-            StmtData::Cont(arg, semi)
+            let body = try!(this.type_members());
+            Ok(DeclData::Interface(id, params, extends, body))
         })
     }
 
-    fn return_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        self.reread(TokenData::Reserved(Reserved::Return));
-        let arg = if try!(self.has_arg_same_line()) {
-            Some(try!(self.allow_in(true, |this| this.expression())))
-        } else {
-            None
-        };
-        let result = try!(span.end_with_auto_semi(self, Newline::Required, |semi| {
-            StmtData::Return(arg, semi)
-        }));
-        if !self.parser_cx.function {
-            Err(Error::TopLevelReturn(result.location.unwrap()))
-        } else {
-            Ok(result)
-        }
+    // TypeAliasDeclaration ::=
+    //   "type" BindingIdentifier TypeParameters? "=" Type ";"
+    fn type_alias_declaration(&mut self) -> Result<Decl> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Identifier(Name::Atom(Atom::Type)));
+            let id = try!(this.binding_id());
+            let params = try!(this.type_parameters_opt());
+            try!(this.expect(TokenData::Assign));
+            let ty = try!(this.ty());
+            try!(this.auto_semi());
+            Ok(DeclData::TyAlias(id, params, ty))
+        })
     }
 
-    fn with_statement(&mut self) -> Result<Stmt> {
+    // EnumDeclaration ::=
+    //   "const"? "enum" BindingIdentifier "{" EnumMember*[","] "}"
+    fn enum_declaration(&mut self) -> Result<Decl> {
         self.span(&mut |this| {
-            let token = this.reread(TokenData::Reserved(Reserved::With));
-            if this.shared_cx.get().mode.is_strict() {
-                return Err(Error::StrictWith(token));
+            let is_const = try!(this.matches(TokenData::Reserved(Reserved::Const)));
+            try!(this.expect(TokenData::Reserved(Reserved::Enum)));
+            let id = try!(this.binding_id());
+            try!(this.expect(TokenData::LBrace));
+            let mut members = Vec::new();
+            while !try!(this.matches(TokenData::RBrace)) {
+                members.push(try!(this.enum_member()));
+                if !try!(this.matches_op(TokenData::Comma)) {
+                    try!(this.expect(TokenData::RBrace));
+                    break;
+                }
             }
-            let obj = try!(this.paren_expression());
-            let body = Box::new(try!(this.statement()));
-            Ok(StmtData::With(obj, body))
+            Ok(if is_const {
+                DeclData::ConstEnum(id, members)
+            } else {
+                DeclData::Enum(id, members)
+            })
         })
     }
 
-    fn throw_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        let token = self.reread(TokenData::Reserved(Reserved::Throw));
-        if !try!(self.has_arg_same_line()) {
-            return Err(Error::ThrowArgument(token));
-        }
-        let arg = try!(self.allow_in(true, |this| this.expression()));
-        span.end_with_auto_semi(self, Newline::Required, |semi| {
-            StmtData::Throw(arg, semi)
+    // EnumMember ::= PropertyName ("=" AssignmentExpression)?
+    fn enum_member(&mut self) -> Result<EnumMember> {
+        self.span(&mut |this| {
+            let key = try!(this.property_key());
+            let init = if try!(this.matches_op(TokenData::Assign)) {
+                Some(try!(this.allow_in(true, |this| this.assignment_expression())))
+            } else {
+                None
+            };
+            Ok(EnumMemberData { key: key, init: init })
         })
     }
 
-    fn block(&mut self) -> Result<Vec<StmtListItem>> {
-        try!(self.expect(TokenData::LBrace));
-        let result = try!(self.statement_list());
-        try!(self.expect(TokenData::RBrace));
-        Ok(result)
+    // AmbientDeclaration ::=
+    //   "declare" (VariableStatement | Declaration)
+    fn ambient_declaration(&mut self) -> Result<Decl> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Identifier(Name::Atom(Atom::Declare)));
+            let item = try!(this.declaration_item());
+            Ok(DeclData::Declare(Box::new(item)))
+        })
     }
 
-    fn try_statement(&mut self) -> Result<Stmt> {
+    fn declaration_item(&mut self) -> Result<StmtListItem> {
+        Ok(match try!(self.declaration_opt()) {
+            Some(decl) => StmtListItem::Decl(decl),
+            None       => {
+                if try!(self.peek()).value != TokenData::Reserved(Reserved::Var) {
+                    return Err(Error::UnexpectedToken(try!(self.read())));
+                }
+                StmtListItem::Stmt(try!(self.var_statement()))
+            }
+        })
+    }
+
+    // NamespaceDeclaration ::=
+    //   ("namespace" | "module") Identifier ("." Identifier)* NamespaceBody
+    //   "module" StringLiteral (NamespaceBody | ";")
+    fn namespace_declaration(&mut self) -> Result<Decl> {
         self.span(&mut |this| {
-            this.reread(TokenData::Reserved(Reserved::Try));
-            let body = try!(this.block());
-            match try!(this.peek()).value {
-                TokenData::Reserved(Reserved::Catch) 
-              | TokenData::Reserved(Reserved::Finally) => { }
-                _ => {
-                    return Err(Error::OrphanTry(try!(this.read())));
+            let keyword = try!(this.read());
+            if keyword.value == TokenData::Identifier(Name::Atom(Atom::Module)) {
+                if let TokenData::String(_) = try!(this.peek()).value {
+                    let name = match try!(this.read()).value {
+                        TokenData::String(literal) => literal,
+                        _ => unreachable!()
+                    };
+                    if try!(this.peek()).value != TokenData::LBrace {
+                        try!(this.auto_semi());
+                        return Ok(DeclData::Module(name, None));
+                    }
+                    return Ok(DeclData::Module(name, Some(try!(this.namespace_body()))));
                 }
             }
-            let catch = try!(this.catch_opt()).map(Box::new);
-            let finally = try!(this.finally_opt());
-            Ok(StmtData::Try(body, catch, finally))
+            let mut name = vec![try!(this.binding_id())];
+            while try!(this.matches(TokenData::Dot)) {
+                name.push(try!(this.binding_id()));
+            }
+            Ok(DeclData::Namespace(name, try!(this.namespace_body())))
         })
     }
 
-    fn catch_opt(&mut self) -> Result<Option<Catch>> {
+    // NamespaceBody ::= "{" NamespaceItem* "}"
+    // NamespaceItem ::= "export"? (VariableStatement | Declaration) | StatementListItem
+    fn namespace_body(&mut self) -> Result<Vec<StmtListItem>> {
+        let open = try!(self.expect(TokenData::LBrace));
+        let outer_cx = replace(&mut self.parser_cx, context::Context::new());
+        let body = self.nested(|this| {
+            let mut items = Vec::new();
+            while !try!(this.peek()).follow_statement_list() {
+                items.push(match try!(this.peek()).value {
+                    TokenData::Reserved(Reserved::Export) => StmtListItem::Decl(try!(this.export_declaration())),
+                    _                                     => try!(this.statement_list_item())
+                });
+            }
+            Ok(items)
+        });
+        replace(&mut self.parser_cx, outer_cx);
+        let body = try!(body);
+        try!(self.expect_closing(&open, TokenData::RBrace));
+        Ok(body)
+    }
+
+    fn export_declaration(&mut self) -> Result<Decl> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::Export));
+            let item = try!(this.declaration_item());
+            Ok(DeclData::Export(Box::new(item)))
+        })
+    }
+
+    fn formal_parameters(&mut self) -> Result<Params> {
+        self.span(&mut |this| {
+            let open = try!(this.expect(TokenData::LParen));
+            let list = try!(this.pattern_list());
+            try!(this.expect_closing(&open, TokenData::RParen));
+            Ok(ParamsData { list: list })
+        })
+    }
+
+    fn pattern_list(&mut self) -> Result<Vec<Patt<Id>>> {
+        let mut patts = Vec::new();
+        if try!(self.peek()).value == TokenData::RParen {
+            return Ok(patts);
+        }
+        patts.push(try!(self.pattern()));
+        while try!(self.matches(TokenData::Comma)) {
+            patts.push(try!(self.pattern()));
+        }
+        Ok(patts)
+    }
+
+    fn pattern(&mut self) -> Result<Patt<Id>> {
+        if self.shared_cx.get().typescript && self.parser_cx.param_props {
+            self.param_property()
+        } else {
+            self.binding_element()
+        }
+    }
+
+    fn binding_element(&mut self) -> Result<Patt<Id>> {
+        let typescript = self.shared_cx.get().typescript;
         match try!(self.peek()).value {
-            TokenData::Reserved(Reserved::Catch) => {
-                self.span(&mut |this| {
-                    this.reread(TokenData::Reserved(Reserved::Catch));
-                    try!(this.expect(TokenData::LParen));
-                    let param = try!(this.pattern());
-                    try!(this.expect(TokenData::RParen));
+            TokenData::Identifier(_) => {
+                let id = try!(self.binding_id());
+                let id = try!(self.annotated_id(id, true));
+                Ok(Patt::Simple(id))
+            }
+            // A TypeScript function may declare the type of `this` as if it
+            // were its first parameter.
+            TokenData::Reserved(Reserved::This) if typescript => {
+                let token = try!(self.read());
+                try!(self.count_node());
+                let id = Id::new(Name::from(Reserved::This.name()), Some(token.location));
+                let id = try!(self.annotated_id(id, false));
+                Ok(Patt::Simple(id))
+            }
+            _ => {
+                let patt = try!(self.binding_pattern());
+                Ok(Patt::Compound(patt))
+            }
+        }
+    }
 
-                    let body = try!(this.block());
-                    Ok(CatchData { param: param, body: body })
-                }).map(Some)
+    // ParameterProperty ::=
+    //   ParameterModifier* BindingIdentifier "?"? TypeAnnotation?
+    //
+    // ParameterModifier ::=
+    //   "public" | "protected" | "private" | "readonly"
+    fn param_property(&mut self) -> Result<Patt<Id>> {
+        let mut first = None;
+        let mut access = None;
+        let mut readonly = false;
+
+        // A modifier not followed by a name is itself the name.
+        loop {
+            let atom = match try!(self.peek()).value {
+                TokenData::Identifier(Name::Atom(atom)) => atom,
+                _ => { break; }
+            };
+            let modifier = match atom {
+                Atom::Public | Atom::Protected | Atom::Private => access.is_none() && !readonly,
+                Atom::Readonly                                => !readonly,
+                _                                             => false
+            };
+            if !modifier || !try!(self.peek2(|next| match next.value {
+                TokenData::Identifier(_) => true,
+                _                        => false
+            })) {
+                break;
+            }
+            let token = try!(self.read());
+            if first.is_none() {
+                first = Some(token);
+            }
+            match atom {
+                Atom::Public    => { access = Some(Access::Public); }
+                Atom::Protected => { access = Some(Access::Protected); }
+                Atom::Private   => { access = Some(Access::Private); }
+                _               => { readonly = true; }
+            }
+        }
+
+        let first = match first {
+            Some(first) => first,
+            None        => { return self.binding_element(); }
+        };
+        let id = try!(self.binding_id());
+        let mut id = try!(self.annotated_id(id, true));
+        id.location = span(&first, &id);
+        id.value.access = access;
+        id.value.readonly = readonly;
+        Ok(Patt::Simple(id))
+    }
+
+    fn binding_pattern(&mut self) -> Result<CompoundPatt<Id>> {
+        if !try!(self.peek()).first_binding() {
+            return Err(Error::UnexpectedToken(try!(self.read())));
+        }
+        let location = try!(self.peek()).location;
+        Err(Error::UnsupportedFeature("destructuring", Some(location)))
+    }
+
+    fn function(&mut self) -> Result<Fun> {
+        let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
+        let result = self.span(&mut |this| {
+            let (id, sig) = try!(this.function_head());
+            this.function_body(id, sig)
+        });
+        replace(&mut self.parser_cx, outer_cx);
+        result
+    }
+
+    // FunctionHead ::=
+    //   "function" BindingIdentifier? CallSignature
+    fn function_head(&mut self) -> Result<(Option<Id>, FunTy)> {
+        self.reread(TokenData::Reserved(Reserved::Function));
+        let id = try!(self.id_opt());
+        let sig = try!(self.fun_type(false));
+        Ok((id, sig))
+    }
+
+    fn function_body(&mut self, id: Option<Id>, sig: FunTy) -> Result<FunData> {
+        let open = try!(self.expect(TokenData::LBrace));
+        let mode = self.shared_cx.get().mode;
+        // A lazy body is only skipped over, and what is needed to parse it
+        // again is kept instead.
+        let (body, lazy) = if self.options.lazy_functions && !self.recovering {
+            let close = try!(self.skip_function_body(open.clone()));
+            let lazy = LazyBody {
+                span: Span { start: open.location.end, end: close.location.start },
+                mode: mode,
+                kind: self.function_kind()
+            };
+            (Vec::new(), Some(lazy))
+        } else {
+            let body = try!(self.nested(|this| this.statement_list()));
+            try!(self.expect_closing(&open, TokenData::RBrace));
+            (body, None)
+        };
+        let FunTyData { ty_params, params, ret_ty } = sig.value;
+        Ok(FunData {
+            id: id,
+            ty_params: ty_params,
+            params: params,
+            ret_ty: ret_ty.map(|ty| *ty),
+            body: body,
+            lazy: lazy
+        })
+    }
+
+    // Pre-parses a lazy function body, up to and including its closing
+    // brace. The body is only lexed: brackets have to balance, and whether a
+    // '/' is division or starts a regexp is decided from the token before it,
+    // so lexical errors such as unterminated literals and bad escapes are
+    // reported now, as is `with` in strict code. All other errors are left to
+    // the reparse. JSX elements, whose text can't be lexed as tokens, are
+    // parsed in full.
+    fn skip_function_body(&mut self, open: Token) -> Result<Token> {
+        let mut groups = vec![Group::new(open, GroupKind::Block)];
+        // Whether the last token ended an operand, so that a '/' is division.
+        let mut operand = false;
+        // Whether the next token starts a statement.
+        let mut statement = true;
+        // Whether the last token was a '.', so that the next is a name.
+        let mut member = false;
+        // The keyword whose parenthesized head is next.
+        let mut head = false;
+        // A `return`, `break` or `continue`, which a newline ends.
+        let mut restricted = false;
+        // A function or class whose body is the next brace in the innermost
+        // group, and whether it is an expression.
+        let mut body: Option<(usize, bool)> = None;
+        loop {
+            let token = try!(if operand { self.read_op() } else { self.read() });
+            let starts_statement = statement || (token.newline && (operand || restricted));
+            let mut next_operand = false;
+            let mut next_statement = false;
+            let mut next_head = false;
+            let mut next_restricted = false;
+            let mut next_member = false;
+            match token.value {
+                TokenData::EOF => {
+                    let group = groups.pop().unwrap();
+                    let close = group.close();
+                    return Err(Error::Unclosed(group.open, close, token));
+                }
+                TokenData::LParen => {
+                    let kind = if head { GroupKind::Head } else { GroupKind::Paren };
+                    groups.push(Group::new(token, kind));
+                }
+                TokenData::LBrack => { groups.push(Group::new(token, GroupKind::Brack)); }
+                TokenData::LBrace => {
+                    let kind = match body {
+                        Some((depth, expr)) if depth == groups.len() => {
+                            body = None;
+                            GroupKind::Body(expr)
+                        }
+                        _ if starts_statement || operand => GroupKind::Block,
+                        _ => GroupKind::Object
+                    };
+                    next_statement = kind != GroupKind::Object;
+                    groups.push(Group::new(token, kind));
+                }
+                TokenData::RParen | TokenData::RBrack | TokenData::RBrace => {
+                    let group = groups.pop().unwrap();
+                    let close = group.close();
+                    if token.value != close {
+                        return Err(Error::Unclosed(group.open, close, token));
+                    }
+                    if groups.is_empty() {
+                        return Ok(token);
+                    }
+                    next_operand = group.is_operand();
+                    next_statement = !next_operand;
+                }
+                TokenData::LAngle if !operand && self.shared_cx.get().jsx => {
+                    try!(self.jsx_expression(token));
+                    next_operand = true;
+                }
+                TokenData::Identifier(_) => { next_operand = true; }
+                TokenData::Reserved(_) if member => { next_operand = true; }
+                TokenData::Reserved(Reserved::With) if self.shared_cx.get().mode.is_strict() => {
+                    return Err(Error::StrictWith(token));
+                }
+                TokenData::Reserved(Reserved::Function) | TokenData::Reserved(Reserved::Class) => {
+                    body = Some((groups.len(), !starts_statement));
+                }
+                TokenData::Reserved(Reserved::Return)
+              | TokenData::Reserved(Reserved::Break)
+              | TokenData::Reserved(Reserved::Continue) => { next_restricted = true; }
+                TokenData::Reserved(word) => {
+                    next_operand = skip::is_operand(word);
+                    next_statement = skip::precedes_statement(word);
+                    next_head = skip::has_head(word);
+                }
+                TokenData::Number(_) | TokenData::String(_) | TokenData::RegExp(_) => { next_operand = true; }
+                TokenData::Inc | TokenData::Dec => { next_operand = operand && !token.newline; }
+                TokenData::Dot => { next_member = true; }
+                TokenData::Semi => { next_statement = true; }
+                TokenData::Question => { groups.last_mut().unwrap().conds += 1; }
+                TokenData::Colon => {
+                    let group = groups.last_mut().unwrap();
+                    if group.conds > 0 {
+                        group.conds -= 1;
+                    } else {
+                        // A label, or the end of a `case` or `default`.
+                        next_statement = group.kind != GroupKind::Object;
+                    }
+                }
+                _ => { }
+            }
+            operand = next_operand;
+            statement = next_statement;
+            head = next_head;
+            restricted = next_restricted;
+            member = next_member;
+        }
+    }
+
+    // The kind of the function whose body is being parsed.
+    fn function_kind(&self) -> FunctionKind {
+        match (self.parser_cx.allow_yield, self.parser_cx.allow_await) {
+            (false, false) => FunctionKind::Normal,
+            (true, false)  => FunctionKind::Generator,
+            (false, true)  => FunctionKind::Async,
+            (true, true)   => FunctionKind::AsyncGenerator
+        }
+    }
+
+    fn statement(&mut self) -> Result<Stmt> {
+        self.nested(|this| this.any_statement())
+    }
+
+    fn any_statement(&mut self) -> Result<Stmt> {
+        match try!(self.peek()).value {
+            TokenData::LBrace                       => self.block_statement(),
+            TokenData::Reserved(Reserved::Var)      => self.var_statement(),
+            TokenData::Semi                         => self.empty_statement(),
+            TokenData::Reserved(Reserved::If)       => self.if_statement(),
+            TokenData::Reserved(Reserved::Continue) => self.continue_statement(),
+            TokenData::Reserved(Reserved::Break)    => self.break_statement(),
+            TokenData::Reserved(Reserved::Return)   => self.return_statement(),
+            TokenData::Reserved(Reserved::With)     => self.with_statement(),
+            TokenData::Reserved(Reserved::Switch)   => self.switch_statement(),
+            TokenData::Reserved(Reserved::Throw)    => self.throw_statement(),
+            TokenData::Reserved(Reserved::Try)      => self.try_statement(),
+            TokenData::Reserved(Reserved::While)    => self.while_statement(),
+            TokenData::Reserved(Reserved::Do)       => self.do_statement(),
+            TokenData::Reserved(Reserved::For)      => self.for_statement(),
+            TokenData::Reserved(Reserved::Debugger) => self.debugger_statement(),
+            TokenData::Reserved(Reserved::Function)
+          | TokenData::Reserved(Reserved::Class)
+          | TokenData::At                       => Err(Error::UnexpectedToken(try!(self.read()))),
+            TokenData::Identifier(_)                => {
+                let id = try!(self.id());
+                self.id_statement(id)
+            }
+            _                                       => self.expression_statement()
+        }
+    }
+
+    fn id_statement(&mut self, id: Id) -> Result<Stmt> {
+        match try!(self.peek_op()).value {
+            TokenData::Colon => self.labelled_statement(id),
+            _                => {
+                // The id has already been consumed, so the statement starts with it.
+                let span = SpanTracker::at(id.location.map_or_else(|| self.start_posn(), |loc| loc.start));
+                let expr = try!(self.id_expression(id));
+                Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Expr(expr, semi))))
+            }
+        }
+    }
+
+    fn labelled_statement(&mut self, id: Id) -> Result<Stmt> {
+        self.reread(TokenData::Colon);
+
+        let mut labels = vec![id]; // vector of consecutive labels
+        let mut expr_id = None;    // id that starts the statement following the labels, if any
+
+        while let TokenData::Identifier(_) = try!(self.peek()).value {
+            let id = try!(self.id());
+            if !try!(self.matches_op(TokenData::Colon)) {
+                expr_id = Some(id);
+                break;
+            }
+            try!(self.count_node());
+            labels.push(id);
+        }
+
+        match expr_id {
+            Some(id) => {
+                self.with_labels(labels, LabelType::Statement, |this| this.id_statement(id))
+            }
+            None     => {
+                let label_type = try!(self.peek()).label_type();
+                self.with_labels(labels, label_type, |this| this.annex_b_statement())
+            }
+        }
+    }
+
+    // B.3.2 Labelled Function Declarations
+    // B.3.4 FunctionDeclarations in IfStatement Statement Clauses
+    fn annex_b_statement(&mut self) -> Result<Stmt> {
+        if try!(self.peek()).value != TokenData::Reserved(Reserved::Function) ||
+           !self.shared_cx.get().has_sloppy_annex_b() {
+            return self.statement();
+        }
+        self.span(&mut |this| {
+            Ok(StmtData::Fun(try!(this.function())))
+        })
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        let expr = try!(self.allow_in(true, |this| this.expression()));
+        Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Expr(expr, semi))))
+    }
+
+    fn block_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            let open = this.reread(TokenData::LBrace);
+            let items = try!(this.statement_list());
+            try!(this.expect_closing(&open, TokenData::RBrace));
+            Ok(StmtData::Block(items))
+        })
+    }
+
+    fn var_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        self.reread(TokenData::Reserved(Reserved::Var));
+        let dtors = try!(self.declarator_list());
+        Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Var(dtors, semi))))
+    }
+
+    fn declarator_list(&mut self) -> Result<Vec<Dtor>> {
+        let mut items = Vec::new();
+        items.push(try!(self.declarator()));
+        while try!(self.matches(TokenData::Comma)) {
+            items.push(try!(self.declarator()));
+        }
+        Ok(items)
+    }
+
+    fn binding_id(&mut self) -> Result<Id> {
+        let id = try!(self.id());
+        if self.shared_cx.get().mode.is_strict() && id.value.name.is_illegal_strict_binding() {
+            return Err(Error::IllegalStrictBinding(id));
+        }
+        Ok(id)
+    }
+
+    fn id(&mut self) -> Result<Id> {
+        let Token { location, newline, value: data } = try!(self.read());
+        match data {
+            TokenData::Identifier(name) => {
+                if name.is_reserved(self.shared_cx.get().mode) || self.parser_cx.reserves(&name) {
+                    return Err(Error::ContextualKeyword(Id {
+                        value: IdData { name: name, ty: None, optional: false, access: None, readonly: false },
+                        location: Some(location)
+                    }));
+                }
+                self.node(IdData { name: name, ty: None, optional: false, access: None, readonly: false }, Some(location))
+            }
+            _ => Err(Error::UnexpectedToken(Token {
+                location: location,
+                newline: newline,
+                value: data
+            }))
+        }
+    }
+
+    // TypeScript bindings may be annotated with a type, and parameters may
+    // also be marked optional.
+    fn annotated_id(&mut self, mut id: Id, allow_optional: bool) -> Result<Id> {
+        if !self.shared_cx.get().typescript {
+            return Ok(id);
+        }
+        if allow_optional && try!(self.matches_op(TokenData::Question)) {
+            id.value.optional = true;
+        }
+        if let Some(ty) = try!(self.type_annotation_opt()) {
+            id.location = span(&id, &ty);
+            id.value.ty = Some(Box::new(ty));
+        }
+        Ok(id)
+    }
+
+    fn id_opt(&mut self) -> Result<Option<Id>> {
+        let next = try!(self.read());
+        match next.value {
+            TokenData::Identifier(name) => {
+                Ok(Some(try!(self.node(IdData { name: name, ty: None, optional: false, access: None, readonly: false }, Some(next.location)))))
+            }
+            _                           => { self.lexer.unread_token(next); Ok(None) }
+        }
+    }
+
+    fn declarator(&mut self) -> Result<Dtor> {
+        self.span(&mut |this| {
+            match try!(this.peek()).value {
+                TokenData::Identifier(_) => {
+                    let id = try!(this.binding_id());
+                    let id = try!(this.annotated_id(id, false));
+                    let init = if try!(this.matches(TokenData::Assign)) {
+                        Some(try!(this.assignment_expression()))
+                    } else {
+                        None
+                    };
+                    Ok(DtorData::Simple(id, init))
+                }
+                _ => {
+                    let lhs = try!(this.binding_pattern());
+                    try!(this.expect(TokenData::Assign));
+                    let rhs = try!(this.assignment_expression());
+                    Ok(DtorData::Compound(lhs, rhs))
+                }
+            }
+        })
+    }
+
+    fn empty_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            try!(this.expect(TokenData::Semi));
+            Ok(StmtData::Empty)
+        })
+    }
+
+    // An "else if" chain is read in a loop rather than by recursion; each
+    // "if" after the first is a link in the chain.
+    fn if_statement(&mut self) -> Result<Stmt> {
+        let mut clauses = Vec::new();
+        let mut alt = None;
+        try!(self.count_node());
+        loop {
+            let start = self.start_posn();
+            try!(self.expect(TokenData::Reserved(Reserved::If)));
+            let test = try!(self.paren_expression());
+            let cons = Box::new(try!(self.annex_b_statement()));
+            clauses.push((start, test, cons));
+            if !try!(self.matches(TokenData::Reserved(Reserved::Else))) {
+                break;
+            }
+            if try!(self.peek()).value != TokenData::Reserved(Reserved::If) {
+                alt = Some(Box::new(try!(self.annex_b_statement())));
+                break;
+            }
+            try!(self.count_node());
+        }
+        // Every "if" in the chain ends where the last one does.
+        let end = self.end_posn();
+        while let Some((start, test, cons)) = clauses.pop() {
+            let stmt = StmtData::If(test, cons, alt).tracked(Some(Span { start: start, end: end }));
+            alt = Some(Box::new(stmt));
+        }
+        Ok(*alt.unwrap())
+    }
+
+    fn iteration_body(&mut self) -> Result<Stmt> {
+        let iteration = replace(&mut self.parser_cx.iteration, true);
+        let result = self.statement();
+        replace(&mut self.parser_cx.iteration, iteration);
+        result
+    }
+
+    fn do_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        self.reread(TokenData::Reserved(Reserved::Do));
+        let body = Box::new(try!(self.iteration_body()));
+        try!(self.expect(TokenData::Reserved(Reserved::While)));
+        let test = try!(self.paren_expression());
+        Ok(try!(span.end_with_auto_semi(self, Newline::Optional, |semi| {
+            StmtData::DoWhile(body, test, semi)
+        })))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::While));
+            let test = try!(this.paren_expression());
+            let body = Box::new(try!(this.iteration_body()));
+            Ok(StmtData::While(test, body))
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::For));
+            try!(this.expect(TokenData::LParen));
+            match try!(this.peek()).value {
+                TokenData::Reserved(Reserved::Var)           => this.for_var(),
+                TokenData::Identifier(Name::Atom(Atom::Let)) => this.for_let(),
+                TokenData::Reserved(Reserved::Const)         => {
+                    let location = this.reread(TokenData::Reserved(Reserved::Const)).location;
+                    return Err(Error::UnsupportedFeature("const", Some(location)));
+                }
+                TokenData::Semi                              => {
+                    this.reread(TokenData::Semi);
+                    this.more_for(None)
+                }
+                _                                            => this.for_expr()
+            }
+        })
+    }
+
+    // 'for' '(' 'var' .
+    fn for_var(&mut self) -> Result<StmtData> {
+        let var_token = self.reread(TokenData::Reserved(Reserved::Var));
+        let var_location = var_token.location;
+        let lhs = try!(self.pattern());
+        match try!(self.peek()).value {
+            // 'for' '(' 'var' id   '=' .
+            // 'for' '(' 'var' patt '=' . ==> C-style
+            TokenData::Assign => {
+                self.reread(TokenData::Assign);
+                match lhs {
+                    Patt::Simple(id) => {
+                        let rhs = try!(self.allow_in(false, |this| this.assignment_expression()));
+                        match try!(self.peek()).value {
+                            // 'for' '(' 'var' id '=' expr ','  . ==> C-style
+                            // 'for' '(' 'var' id '=' expr ';'  . ==> C-style
+                            TokenData::Comma
+                          | TokenData::Semi => {
+                                let head = Some(try!(self.more_for_head(&var_location, Dtor::from_simple_init(id, rhs), ForHeadData::Var)));
+                                self.more_for(head)
+                            }
+                            // 'for' '(' 'var' id '=' expr 'in' . ==> legacy enumeration
+                            TokenData::Reserved(Reserved::In) => {
+                                self.reread(TokenData::Reserved(Reserved::In));
+                                let location = span(&var_location, &rhs);
+                                if !self.shared_cx.get().has_sloppy_annex_b() {
+                                    return Err(Error::ForInVarInit(location));
+                                }
+                                let head = Box::new(ForInHead {
+                                    location: location,
+                                    value: ForInHeadData::VarInit(id, rhs)
+                                });
+                                self.more_for_in(head)
+                            }
+                            _ => Err(Error::UnexpectedToken(try!(self.read())))
+                        }
+                    }
+                    // 'for' '(' 'var' patt '=' . ==> C-style
+                    Patt::Compound(patt) => {
+                        let rhs = try!(self.allow_in(false, |this| this.assignment_expression()));
+                        let head = Some(try!(self.more_for_head(&var_location, Dtor::from_compound_init(patt, rhs), ForHeadData::Var)));
+                        self.more_for(head)
+                    }
+                }
+            }
+            TokenData::Comma
+          | TokenData::Semi => {
+                // 'for' '(' 'var' id   ',' . ==> C-style
+                // 'for' '(' 'var' id   ';' . ==> C-style
+                // 'for' '(' 'var' patt ',' . ==> syntax error
+                // 'for' '(' 'var' patt ';' . ==> syntax error
+                let dtor = match Dtor::from_init_opt(lhs, None) {
+                    Ok(dtor) => dtor,
+                    Err(_) => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                };
+                let head = Some(try!(self.more_for_head(&var_location, dtor, ForHeadData::Var)));
+                self.more_for(head)
+            }
+            // 'for' '(' 'var' id   'in' . ==> enumeration
+            // 'for' '(' 'var' patt 'in' . ==> enumeration
+            TokenData::Reserved(Reserved::In) => {
+                self.reread(TokenData::Reserved(Reserved::In));
+                let head = Box::new(ForInHead {
+                    location: span(&var_location, &lhs),
+                    value: ForInHeadData::Var(lhs)
+                });
+                self.more_for_in(head)
+            }
+            // 'for' '(' 'var' id   'of' . ==> enumeration
+            // 'for' '(' 'var' patt 'of' . ==> enumeration
+            TokenData::Identifier(Name::Atom(Atom::Of)) => {
+                self.reread(TokenData::Identifier(Name::Atom(Atom::Of)));
+                let head = Box::new(ForOfHead {
+                    location: span(&var_location, &lhs),
+                    value: ForOfHeadData::Var(lhs)
+                });
+                self.more_for_of(head)
+            }
+            _ => Err(Error::UnexpectedToken(try!(self.read())))
+        }
+    }
+
+    // 'for' '(' 'let' .
+    fn for_let(&mut self) -> Result<StmtData> {
+        let let_token = self.reread(TokenData::Identifier(Name::Atom(Atom::Let)));
+        let let_location = let_token.location;
+        try!(self.require_edition(Edition::ES2015, "let declarations", Some(let_location)));
+        // 'for' '(' 'let' . !{id, patt} ==> error
+        let lhs = try!(self.pattern());
+        match try!(self.peek()).value {
+            // 'for' '(' 'let' id   '=' . ==> C-style
+            // 'for' '(' 'let' patt '=' . ==> C-style
+            TokenData::Assign => {
+                self.reread(TokenData::Assign);
+                let rhs = try!(self.allow_in(false, |this| this.assignment_expression()));
+                let head = Some(try!(self.more_for_head(&let_location, Dtor::from_init(lhs, rhs), ForHeadData::Let)));
+                self.more_for(head)
+            }
+            TokenData::Comma
+          | TokenData::Semi => {
+                // 'for' '(' 'let' id   ',' . ==> C-style
+                // 'for' '(' 'let' id   ';' . ==> C-style
+                // 'for' '(' 'let' patt ',' . ==> error
+                // 'for' '(' 'let' patt ';' . ==> error
+                let dtor = match Dtor::from_init_opt(lhs, None) {
+                    Ok(dtor) => dtor,
+                    Err(_) => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                };
+                let head = Some(try!(self.more_for_head(&let_location, dtor, ForHeadData::Let)));
+                self.more_for(head)
+            }
+            // 'for' '(' 'let' id   'in' . ==> enumeration
+            // 'for' '(' 'let' patt 'in' . ==> enumeration
+            TokenData::Reserved(Reserved::In) => {
+                self.reread(TokenData::Reserved(Reserved::In));
+                let head = Box::new(ForInHead {
+                    location: span(&let_location, &lhs),
+                    value: ForInHeadData::Let(lhs)
+                });
+                self.more_for_in(head)
+            }
+            // 'for' '(' 'let' id   'of' . ==> enumeration
+            // 'for' '(' 'let' patt 'of' . ==> enumeration
+            TokenData::Identifier(Name::Atom(Atom::Of)) => {
+                self.reread(TokenData::Identifier(Name::Atom(Atom::Of)));
+                let head = Box::new(ForOfHead {
+                    location: span(&let_location, &lhs),
+                    value: ForOfHeadData::Let(lhs)
+                });
+                self.more_for_of(head)
+            }
+            _ => Err(Error::UnexpectedToken(try!(self.read())))
+        }
+    }
+
+    fn for_expr(&mut self) -> Result<StmtData> {
+        let lhs = try!(self.allow_in(false, |this| this.expression()));
+        match try!(self.peek()).value {
+            TokenData::Semi => {
+                let semi_location = self.reread(TokenData::Semi).location;
+                let head = Some(Box::new(ForHead {
+                    location: span(&lhs, &semi_location),
+                    value: ForHeadData::Expr(lhs)
+                }));
+                self.more_for(head)
+            }
+            TokenData::Reserved(Reserved::In) => {
+                self.reread(TokenData::Reserved(Reserved::In));
+                let head = Box::new(ForInHead {
+                    location: lhs.location(),
+                    value: ForInHeadData::Expr(lhs)
+                });
+                self.more_for_in(head)
+            }
+            TokenData::Identifier(Name::Atom(Atom::Of)) => {
+                self.reread(TokenData::Identifier(Name::Atom(Atom::Of)));
+                let head = Box::new(ForOfHead {
+                    location: lhs.location(),
+                    value: ForOfHeadData::Expr(lhs)
+                });
+                self.more_for_of(head)
+            }
+            _ => Err(Error::UnexpectedToken(try!(self.read())))
+        }
+    }
+
+    // 'for' '(' dtor .
+    fn more_for_head<F>(&mut self, start: &Span, dtor: Dtor, op: F) -> Result<Box<ForHead>>
+      where F: FnOnce(Vec<Dtor>) -> ForHeadData
+    {
+        let dtors = try!(self.allow_in(false, |this| {
+            let mut dtors = vec![dtor];
+            try!(this.more_dtors(&mut dtors));
+            Ok(dtors)
+        }));
+        let semi_location = try!(self.expect(TokenData::Semi)).location;
+        Ok(Box::new(ForHead {
+            location: span(start, &semi_location),
+            value: op(dtors)
+        }))
+    }
+
+    // 'for' '(' head ';' .
+    fn more_for(&mut self, head: Option<Box<ForHead>>) -> Result<StmtData> {
+        let test = try!(self.expression_opt_semi());
+        let update = if try!(self.matches(TokenData::RParen)) {
+            None
+        } else {
+            let node = Some(try!(self.allow_in(true, |this| this.expression())));
+            try!(self.expect(TokenData::RParen));
+            node
+        };
+        let body = Box::new(try!(self.iteration_body()));
+        Ok(StmtData::For(head, test, update, body))
+    }
+
+    // 'for' '(' head 'in' .
+    fn more_for_in(&mut self, head: Box<ForInHead>) -> Result<StmtData> {
+        let obj = try!(self.allow_in(true, |this| this.assignment_expression()));
+        try!(self.expect(TokenData::RParen));
+        let body = Box::new(try!(self.iteration_body()));
+        Ok(StmtData::ForIn(head, obj, body))
+    }
+
+    // 'for' '(' head 'of' .
+    fn more_for_of(&mut self, head: Box<ForOfHead>) -> Result<StmtData> {
+        try!(self.require_edition(Edition::ES2015, "for-of loops", head.location));
+        let obj = try!(self.allow_in(true, |this| this.assignment_expression()));
+        try!(self.expect(TokenData::RParen));
+        let body = Box::new(try!(self.iteration_body()));
+        Ok(StmtData::ForOf(head, obj, body))
+    }
+
+    fn expression_opt_semi(&mut self) -> Result<Option<Expr>> {
+        Ok(if try!(self.matches(TokenData::Semi)) {
+            None
+        } else {
+            let expr = try!(self.allow_in(true, |this| this.expression()));
+            try!(self.expect(TokenData::Semi));
+            Some(expr)
+        })
+    }
+
+    fn more_dtors(&mut self, dtors: &mut Vec<Dtor>) -> Result<()> {
+        while try!(self.matches(TokenData::Comma)) {
+            dtors.push(try!(self.declarator()));
+        }
+        Ok(())
+    }
+
+    fn switch_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::Switch));
+            let disc = try!(this.paren_expression());
+            let outer_switch = replace(&mut this.parser_cx.switch, true);
+            let cases = this.switch_cases();
+            replace(&mut this.parser_cx.switch, outer_switch);
+            Ok(StmtData::Switch(disc, try!(cases)))
+        })
+    }
+
+    fn switch_cases(&mut self) -> Result<Vec<Case>> {
+        let open = try!(self.expect(TokenData::LBrace));
+        let mut cases = Vec::new();
+        let mut found_default = false;
+        loop {
+            match try!(self.peek()).value {
+                TokenData::Reserved(Reserved::Case) => { cases.push(try!(self.case())); }
+                TokenData::Reserved(Reserved::Default) => {
+                    if found_default {
+                        let token = self.reread(TokenData::Reserved(Reserved::Default));
+                        return Err(Error::DuplicateDefault(token));
+                    }
+                    found_default = true;
+                    cases.push(try!(self.default()));
+                }
+                _ => { break; }
+            }
+        }
+        try!(self.expect_closing(&open, TokenData::RBrace));
+        Ok(cases)
+    }
+
+    fn case(&mut self) -> Result<Case> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::Case));
+            let test = try!(this.allow_in(true, |this| this.expression()));
+            try!(this.expect(TokenData::Colon));
+            let body = try!(this.case_body());
+            Ok(CaseData { test: Some(test), body: body })
+        })
+    }
+
+    fn case_body(&mut self) -> Result<Vec<StmtListItem>> {
+        let mut items = Vec::new();
+        loop {
+            match try!(self.peek()).value {
+                TokenData::Reserved(Reserved::Case)
+              | TokenData::Reserved(Reserved::Default)
+              | TokenData::RBrace => { break; }
+                _ => { }
+            }
+            match try!(self.declaration_opt()) {
+                Some(decl) => { items.push(StmtListItem::Decl(decl)); }
+                None       => { items.push(StmtListItem::Stmt(try!(self.statement()))); }
+            }
+        }
+        Ok(items)
+    }
+
+    fn default(&mut self) -> Result<Case> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::Default));
+            try!(this.expect(TokenData::Colon));
+            let body = try!(this.case_body());
+            Ok(CaseData { test: None, body: body })
+        })
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        let break_token = self.reread(TokenData::Reserved(Reserved::Break));
+        let arg = if try!(self.has_arg_same_line()) {
+            let id = try!(self.id());
+            if !self.parser_cx.labels.contains_key(&id.value.name) {
+                return Err(Error::InvalidLabel(id));
+            }
+            Some(id)
+        } else {
+            if !self.parser_cx.iteration && !self.parser_cx.switch {
+                return Err(Error::IllegalBreak(break_token));
+            }
+            None
+        };
+        span.end_with_auto_semi(self, Newline::Required, |semi| {
+            StmtData::Break(arg, semi)
+        })
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        let continue_token = self.reread(TokenData::Reserved(Reserved::Continue));
+        let arg = if try!(self.has_arg_same_line()) {
+            let id = try!(self.id());
+            match self.parser_cx.labels.get(&id.value.name) {
+                None                        => { return Err(Error::InvalidLabel(id)); }
+                Some(&LabelType::Statement) => { return Err(Error::InvalidLabelType(id)); }
+                _                           => { }
+            }
+            Some(id)
+        } else {
+            if !self.parser_cx.iteration {
+                return Err(Error::IllegalContinue(continue_token));
+            }
+            None
+        };
+        span.end_with_auto_semi(self, Newline::Required, |semi| {
+            StmtData::Cont(arg, semi)
+        })
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        self.reread(TokenData::Reserved(Reserved::Return));
+        let arg = if try!(self.has_arg_same_line()) {
+            Some(try!(self.allow_in(true, |this| this.expression())))
+        } else {
+            None
+        };
+        let result = try!(span.end_with_auto_semi(self, Newline::Required, |semi| {
+            StmtData::Return(arg, semi)
+        }));
+        if !self.parser_cx.function {
+            Err(Error::TopLevelReturn(result.location.unwrap()))
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn with_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            let token = this.reread(TokenData::Reserved(Reserved::With));
+            if this.shared_cx.get().mode.is_strict() {
+                return Err(Error::StrictWith(token));
+            }
+            let obj = try!(this.paren_expression());
+            let body = Box::new(try!(this.statement()));
+            Ok(StmtData::With(obj, body))
+        })
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        let token = self.reread(TokenData::Reserved(Reserved::Throw));
+        if !try!(self.has_arg_same_line()) {
+            return Err(Error::ThrowArgument(token));
+        }
+        let arg = try!(self.allow_in(true, |this| this.expression()));
+        span.end_with_auto_semi(self, Newline::Required, |semi| {
+            StmtData::Throw(arg, semi)
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<StmtListItem>> {
+        try!(self.expect(TokenData::LBrace));
+        let result = try!(self.statement_list());
+        try!(self.expect(TokenData::RBrace));
+        Ok(result)
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt> {
+        self.span(&mut |this| {
+            this.reread(TokenData::Reserved(Reserved::Try));
+            let body = try!(this.block());
+            match try!(this.peek()).value {
+                TokenData::Reserved(Reserved::Catch) 
+              | TokenData::Reserved(Reserved::Finally) => { }
+                _ => {
+                    return Err(Error::OrphanTry(try!(this.read())));
+                }
+            }
+            let catch = try!(this.catch_opt()).map(Box::new);
+            let finally = try!(this.finally_opt());
+            Ok(StmtData::Try(body, catch, finally))
+        })
+    }
+
+    fn catch_opt(&mut self) -> Result<Option<Catch>> {
+        match try!(self.peek()).value {
+            TokenData::Reserved(Reserved::Catch) => {
+                self.span(&mut |this| {
+                    this.reread(TokenData::Reserved(Reserved::Catch));
+                    try!(this.expect(TokenData::LParen));
+                    let param = try!(this.pattern());
+                    try!(this.expect(TokenData::RParen));
+
+                    let body = try!(this.block());
+                    Ok(CatchData { param: param, body: body })
+                }).map(Some)
+            }
+            _ => Ok(None)
+        }
+    }
+
+    fn finally_opt(&mut self) -> Result<Option<Vec<StmtListItem>>> {
+        Ok(match try!(self.peek()).value {
+            TokenData::Reserved(Reserved::Finally) => {
+                self.reread(TokenData::Reserved(Reserved::Finally));
+                Some(try!(self.block()))
+            }
+            _ => None
+        })
+    }
+
+    fn debugger_statement(&mut self) -> Result<Stmt> {
+        let span = self.start();
+        self.reread(TokenData::Reserved(Reserved::Debugger));
+        Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Debugger(semi))))
+    }
+
+    fn paren_expression(&mut self) -> Result<Expr> {
+        let open = try!(self.expect(TokenData::LParen));
+        let result = try!(self.allow_in(true, |this| this.expression()));
+        try!(self.expect_closing(&open, TokenData::RParen));
+        Ok(result)
+    }
+
+    // PrimaryExpression ::=
+    //   "this"
+    //   IdentifierReference
+    //   Literal
+    //   ArrayLiteral
+    //   ObjectLiteral
+    //   FunctionExpression
+    //   ClassExpression
+    //   GeneratorExpression
+    //   RegularExpressionLiteral
+    //   "(" Expression ")"
+    fn primary_expression(&mut self) -> Result<Expr> {
+        let token = try!(self.read());
+        let location = Some(token.location);
+        let data = match token.value {
+            TokenData::Identifier(name)          => {
+                if self.parser_cx.reserves(&name) {
+                    return Err(Error::ContextualKeyword(Id::new(name, location)));
+                }
+                try!(self.count_node());
+                ExprData::Id(Id::new(name, location))
+            }
+            TokenData::Reserved(Reserved::Null)  => ExprData::Null,
+            TokenData::Reserved(Reserved::This)  => ExprData::This,
+            TokenData::Reserved(Reserved::True)  => ExprData::True,
+            TokenData::Reserved(Reserved::False) => ExprData::False,
+            TokenData::Number(literal)           => ExprData::Number(literal),
+            TokenData::String(literal)           => ExprData::String(literal),
+            TokenData::RegExp(literal)           => ExprData::RegExp(literal),
+            TokenData::LBrack                    => { return self.array_literal(token); }
+            TokenData::LBrace                    => { return self.object_literal(token); }
+            TokenData::Reserved(Reserved::Function) => {
+                self.lexer.unread_token(token);
+                let fun = try!(self.function());
+                let location = fun.location();
+                return self.node(ExprData::Fun(fun), location);
+            }
+            TokenData::LParen => {
+                self.lexer.unread_token(token);
+                if !self.options.preserve_parens {
+                    return self.paren_expression();
+                }
+                return self.span(&mut |this| {
+                    Ok(ExprData::Paren(Box::new(try!(this.paren_expression()))))
+                });
+            }
+            TokenData::Reserved(Reserved::Class) | TokenData::At => {
+                self.lexer.unread_token(token);
+                let class = try!(self.class(false));
+                let location = class.location();
+                return self.node(ExprData::Class(class), location);
+            }
+            TokenData::LAngle if self.shared_cx.get().jsx => {
+                return self.jsx_expression(token);
+            }
+            // ES6: more cases
+            _ if self.recovering && token.follow_expression() => {
+                // Leave the token to the enclosing construct and stand in an
+                // empty placeholder for the missing expression.
+                let here = token.location.start;
+                self.errors.push(Error::UnexpectedToken(token.clone()));
+                self.lexer.unread_token(token);
+                return self.node(ExprData::Invalid, Some(Span { start: here, end: here }));
+            }
+            _ => { return Err(Error::UnexpectedToken(token)); }
+        };
+        self.node(data, location)
+    }
+
+    fn set_jsx_mode(&mut self, mode: JSXMode) {
+        let mut cx = self.shared_cx.get();
+        cx.jsx_mode = mode;
+        self.shared_cx.set(cx);
+    }
+
+    // The lexer's JSX mode must be set before the token that follows a
+    // mode change is peeked, so each of these functions stops right after
+    // reading the last token of its production.
+
+    // '<' .
+    fn jsx_expression(&mut self, start: Token) -> Result<Expr> {
+        self.set_jsx_mode(JSXMode::Tag);
+        let expr = if try!(self.peek()).value == TokenData::RAngle {
+            let fragment = try!(self.jsx_fragment(start));
+            let location = fragment.location();
+            try!(self.node(ExprData::JSXFragment(fragment), location))
+        } else {
+            let element = try!(self.jsx_element(start));
+            let location = element.location();
+            try!(self.node(ExprData::JSXElement(element), location))
+        };
+        self.set_jsx_mode(JSXMode::Off);
+        Ok(expr)
+    }
+
+    // JSXFragment ::= "<" ">" JSXChildren "<" "/" ">"
+    fn jsx_fragment(&mut self, start: Token) -> Result<JSXFragment> {
+        self.reread(TokenData::RAngle);
+        let children = try!(self.jsx_children());
+        let end = try!(self.expect(TokenData::RAngle));
+        self.node(JSXFragmentData { children: children }, span(&start, &end))
+    }
+
+    // JSXElement ::= "<" JSXElementName JSXAttributes "/" ">"
+    //              | "<" JSXElementName JSXAttributes ">" JSXChildren "<" "/" JSXElementName ">"
+    fn jsx_element(&mut self, start: Token) -> Result<JSXElement> {
+        self.nested(|this| this.jsx_element_tail(start))
+    }
+
+    fn jsx_element_tail(&mut self, start: Token) -> Result<JSXElement> {
+        let name = try!(self.jsx_element_name());
+        let mut attrs = Vec::new();
+        loop {
+            match try!(self.peek()).value {
+                TokenData::Slash | TokenData::RAngle => { break; }
+                _ => { attrs.push(try!(self.jsx_attribute())); }
+            }
+        }
+        if try!(self.matches(TokenData::Slash)) {
+            let end = try!(self.expect(TokenData::RAngle));
+            return self.node(JSXElementData { name: name, attrs: attrs, children: None }, span(&start, &end));
+        }
+        self.reread(TokenData::RAngle);
+        let children = try!(self.jsx_children());
+        let closing = try!(self.jsx_element_name());
+        if closing.value.qualified_name() != name.value.qualified_name() {
+            return Err(Error::MismatchedJSXTag(name, closing));
+        }
+        let end = try!(self.expect(TokenData::RAngle));
+        self.node(JSXElementData { name: name, attrs: attrs, children: Some(children) }, span(&start, &end))
+    }
+
+    fn jsx_id(&mut self) -> Result<JSXId> {
+        let token = try!(self.read());
+        match token.value {
+            TokenData::JSXIdentifier(name) => self.node(JSXIdData(name), Some(token.location)),
+            _ => Err(Error::UnexpectedToken(token))
+        }
+    }
+
+    // JSXElementName ::= JSXIdentifier
+    //                  | JSXIdentifier ":" JSXIdentifier
+    //                  | JSXIdentifier ("." JSXIdentifier)+
+    fn jsx_element_name(&mut self) -> Result<JSXName> {
+        let name = try!(self.jsx_attribute_name());
+        let mut result = match name.value {
+            JSXNameData::Id(_) => name,
+            _                  => { return Ok(name); }
+        };
+        while try!(self.matches(TokenData::Dot)) {
+            try!(self.count_node());
+            let prop = try!(self.jsx_id());
+            let location = span(&result, &prop);
+            result = JSXNameData::Member(Box::new(result), prop).tracked(location);
+        }
+        Ok(result)
+    }
+
+    // JSXAttributeName ::= JSXIdentifier (":" JSXIdentifier)?
+    fn jsx_attribute_name(&mut self) -> Result<JSXName> {
+        let id = try!(self.jsx_id());
+        if try!(self.matches(TokenData::Colon)) {
+            let name = try!(self.jsx_id());
+            let location = span(&id, &name);
+            return self.node(JSXNameData::Namespaced(id, name), location);
+        }
+        let location = id.location();
+        self.node(JSXNameData::Id(id), location)
+    }
+
+    // JSXAttribute ::= "{" "..." AssignmentExpression "}"
+    //                | JSXAttributeName ("=" JSXAttributeValue)?
+    fn jsx_attribute(&mut self) -> Result<JSXAttr> {
+        if let Some(start) = try!(self.matches_token(TokenData::LBrace)) {
+            self.set_jsx_mode(JSXMode::Off);
+            try!(self.expect(TokenData::Ellipsis));
+            let expr = try!(self.allow_in(true, |this| this.assignment_expression()));
+            let end = try!(self.expect(TokenData::RBrace));
+            self.set_jsx_mode(JSXMode::Tag);
+            return self.node(JSXAttrData::Spread(expr), span(&start, &end));
+        }
+        let name = try!(self.jsx_attribute_name());
+        if !try!(self.matches(TokenData::Assign)) {
+            let location = name.location();
+            return self.node(JSXAttrData::Attr(name, None), location);
+        }
+        let val = try!(self.jsx_attribute_value());
+        let location = span(&name, &val);
+        self.node(JSXAttrData::Attr(name, Some(val)), location)
+    }
+
+    // JSXAttributeValue ::= StringLiteral | "{" AssignmentExpression "}" | JSXElement | JSXFragment
+    fn jsx_attribute_value(&mut self) -> Result<JSXAttrVal> {
+        let token = try!(self.read());
+        let location = Some(token.location);
+        match token.value {
+            TokenData::String(literal) => self.node(JSXAttrValData::String(literal), location),
+            TokenData::LBrace => {
+                self.set_jsx_mode(JSXMode::Off);
+                let expr = try!(self.allow_in(true, |this| this.assignment_expression()));
+                let end = try!(self.expect(TokenData::RBrace));
+                self.set_jsx_mode(JSXMode::Tag);
+                self.node(JSXAttrValData::Expr(expr), span(&token.location, &end))
+            }
+            TokenData::LAngle => {
+                let val = if try!(self.peek()).value == TokenData::RAngle {
+                    let fragment = try!(self.jsx_fragment(token));
+                    let location = fragment.location();
+                    try!(self.node(JSXAttrValData::Fragment(fragment), location))
+                } else {
+                    let element = try!(self.jsx_element(token));
+                    let location = element.location();
+                    try!(self.node(JSXAttrValData::Element(element), location))
+                };
+                self.set_jsx_mode(JSXMode::Tag);
+                Ok(val)
+            }
+            _ => Err(Error::UnexpectedToken(token))
+        }
+    }
+
+    // JSXChildren ::= JSXChild*
+    // JSXChild ::= JSXText | JSXElement | JSXFragment | "{" ("..."? AssignmentExpression)? "}"
+    //
+    // Consumes the "<" "/" that begins the closing tag.
+    fn jsx_children(&mut self) -> Result<Vec<JSXChild>> {
+        let mut children = Vec::new();
+        loop {
+            self.set_jsx_mode(JSXMode::Text);
+            let token = try!(self.read());
+            let location = Some(token.location);
+            match token.value {
+                TokenData::JSXText(text) => {
+                    children.push(try!(self.node(JSXChildData::Text(text), location)));
+                }
+                TokenData::LBrace => {
+                    self.set_jsx_mode(JSXMode::Off);
+                    let child = if try!(self.peek()).value == TokenData::RBrace {
+                        JSXChildData::Expr(None)
+                    } else if try!(self.matches(TokenData::Ellipsis)) {
+                        JSXChildData::Spread(try!(self.allow_in(true, |this| this.assignment_expression())))
+                    } else {
+                        JSXChildData::Expr(Some(try!(self.allow_in(true, |this| this.assignment_expression()))))
+                    };
+                    let end = try!(self.expect(TokenData::RBrace));
+                    children.push(try!(self.node(child, span(&token.location, &end))));
+                }
+                TokenData::LAngle => {
+                    self.set_jsx_mode(JSXMode::Tag);
+                    if try!(self.matches(TokenData::Slash)) {
+                        return Ok(children);
+                    }
+                    let child = if try!(self.peek()).value == TokenData::RAngle {
+                        let fragment = try!(self.jsx_fragment(token));
+                        let location = fragment.location();
+                        try!(self.node(JSXChildData::Fragment(fragment), location))
+                    } else {
+                        let element = try!(self.jsx_element(token));
+                        let location = element.location();
+                        try!(self.node(JSXChildData::Element(element), location))
+                    };
+                    children.push(child);
+                }
+                _ => { return Err(Error::UnexpectedToken(token)); }
+            }
+        }
+    }
+
+    // TypeAnnotation ::= ":" Type
+    fn type_annotation_opt(&mut self) -> Result<Option<Ty>> {
+        if !try!(self.matches_op(TokenData::Colon)) {
+            return Ok(None);
+        }
+        self.ty().map(Some)
+    }
+
+    // Type ::=
+    //   FunctionType
+    //   "new" FunctionType
+    //   UnionType ("extends" UnionType "?" Type ":" Type)?
+    // Template literals aren't lexed, so the backtick that starts a template
+    // literal type is reported as unsupported.
+    fn ty(&mut self) -> Result<Ty> {
+        match self.nested(|this| this.any_ty()) {
+            Err(Error::LexError(ref err)) if err.value == ErrorData::IllegalChar('`') => {
+                Err(Error::UnsupportedFeature("template literal types", Some(err.location)))
+            }
+            result => result
+        }
+    }
+
+    fn any_ty(&mut self) -> Result<Ty> {
+        match try!(self.peek()).value {
+            TokenData::LAngle => {
+                let fun = try!(self.fun_type(true));
+                let location = fun.location();
+                return self.node(TyData::Fun(fun), location);
+            }
+            TokenData::Reserved(Reserved::New) => {
+                return self.span(&mut |this| {
+                    this.reread(TokenData::Reserved(Reserved::New));
+                    Ok(TyData::Ctor(try!(this.fun_type(true))))
+                });
+            }
+            // A parenthesized type unless it turns out to be a parameter list.
+            TokenData::LParen => {
+                if let Some(fun) = self.speculate(|this| this.fun_type(true)) {
+                    let location = fun.location();
+                    return self.node(TyData::Fun(fun), location);
+                }
+            }
+            _ => { }
+        }
+        let check = try!(self.union_type());
+        if !try!(self.matches_op(TokenData::Reserved(Reserved::Extends))) {
+            return Ok(check);
+        }
+        let extends = try!(self.union_type());
+        try!(self.expect(TokenData::Question));
+        let cons = try!(self.ty());
+        try!(self.expect(TokenData::Colon));
+        let alt = try!(self.ty());
+        let location = span(&check, &alt);
+        self.node(TyData::Cond(Box::new(check), Box::new(extends), Box::new(cons), Box::new(alt)), location)
+    }
+
+    // UnionType ::= "|"? IntersectionType+["|"]
+    fn union_type(&mut self) -> Result<Ty> {
+        try!(self.matches(TokenData::BitOr));
+        let first = try!(self.intersection_type());
+        if try!(self.peek_op()).value != TokenData::BitOr {
+            return Ok(first);
+        }
+        let mut tys = vec![first];
+        while try!(self.matches_op(TokenData::BitOr)) {
+            tys.push(try!(self.intersection_type()));
+        }
+        let location = self.vec_span(&tys);
+        self.node(TyData::Union(tys), location)
+    }
+
+    // IntersectionType ::= "&"? TypeOperator+["&"]
+    fn intersection_type(&mut self) -> Result<Ty> {
+        try!(self.matches(TokenData::BitAnd));
+        let first = try!(self.operator_type());
+        if try!(self.peek_op()).value != TokenData::BitAnd {
+            return Ok(first);
+        }
+        let mut tys = vec![first];
+        while try!(self.matches_op(TokenData::BitAnd)) {
+            tys.push(try!(self.operator_type()));
+        }
+        let location = self.vec_span(&tys);
+        self.node(TyData::Intersection(tys), location)
+    }
+
+    // TypeOperator ::=
+    //   ("keyof" | "unique" | "readonly") TypeOperator
+    //   "infer" BindingIdentifier
+    //   PostfixType
+    fn operator_type(&mut self) -> Result<Ty> {
+        let op = match try!(self.peek()).value {
+            TokenData::Identifier(Name::Atom(Atom::Keyof))    => TyOp::Keyof,
+            TokenData::Identifier(Name::Atom(Atom::Unique))   => TyOp::Unique,
+            TokenData::Identifier(Name::Atom(Atom::Readonly)) => TyOp::Readonly,
+            TokenData::Identifier(Name::Atom(Atom::Infer))    => {
+                return self.span(&mut |this| {
+                    this.reread(TokenData::Identifier(Name::Atom(Atom::Infer)));
+                    Ok(TyData::Infer(try!(this.binding_id())))
+                });
+            }
+            _ => { return self.postfix_type(); }
+        };
+        self.span(&mut |this| {
+            try!(this.read());
+            Ok(TyData::Op(op, Box::new(try!(this.nested(|this| this.operator_type())))))
+        })
+    }
+
+    // PostfixType ::=
+    //   PrimaryType ([no line terminator] "[" Type? "]")*
+    fn postfix_type(&mut self) -> Result<Ty> {
+        let mut ty = try!(self.primary_type());
+        loop {
+            match *try!(self.peek_op()) {
+                Token { newline: false, value: TokenData::LBrack, .. } => { }
+                _ => { break; }
+            }
+            try!(self.count_node());
+            self.reread(TokenData::LBrack);
+            if let Some(end) = try!(self.matches_token(TokenData::RBrack)) {
+                let location = span(&ty, &end);
+                ty = TyData::Array(Box::new(ty)).tracked(location);
+                continue;
+            }
+            let index = try!(self.ty());
+            let end = try!(self.expect(TokenData::RBrack));
+            let location = span(&ty, &end);
+            ty = TyData::Index(Box::new(ty), Box::new(index)).tracked(location);
+        }
+        Ok(ty)
+    }
+
+    // PrimaryType ::=
+    //   "(" Type ")"
+    //   PredefinedType
+    //   TypeReference
+    //   ObjectType
+    //   MappedType
+    //   "[" Type*[","] "]"
+    //   "typeof" EntityName
+    //   "this"
+    //   StringLiteral
+    //   "-"? NumericLiteral
+    //   "true"
+    //   "false"
+    fn primary_type(&mut self) -> Result<Ty> {
+        let token = try!(self.read());
+        let location = Some(token.location);
+        let data = match token.value {
+            TokenData::Identifier(name) => {
+                let keyword = match name {
+                    Name::Symbol(symbol) => symbol.text().and_then(|text| TyKeyword::from_name(&text)),
+                    _                    => None
+                };
+                if keyword.is_none() || try!(self.peek_op()).value == TokenData::Dot {
+                    try!(self.count_node());
+                    return self.type_reference(Id::new(name, location));
+                }
+                TyData::Keyword(keyword.unwrap())
+            }
+            TokenData::Reserved(Reserved::Void)  => TyData::Keyword(TyKeyword::Void),
+            TokenData::Reserved(Reserved::Null)  => TyData::Keyword(TyKeyword::Null),
+            TokenData::Reserved(Reserved::This)  => TyData::Keyword(TyKeyword::This),
+            TokenData::Reserved(Reserved::True)  => TyData::True,
+            TokenData::Reserved(Reserved::False) => TyData::False,
+            TokenData::String(literal)           => TyData::String(literal),
+            TokenData::Number(literal)           => TyData::Number(literal),
+            TokenData::Minus => {
+                let token = try!(self.read());
+                let location = span(&location, &token);
+                return match token.value {
+                    TokenData::Number(literal) => self.node(TyData::Negative(literal), location),
+                    _ => Err(Error::UnexpectedToken(token))
+                };
+            }
+            TokenData::Reserved(Reserved::Typeof) => {
+                let first = try!(self.id());
+                let name = try!(self.entity_name(first));
+                let location = span(&location, &name[name.len() - 1]);
+                return self.node(TyData::Typeof(name), location);
+            }
+            TokenData::LParen => {
+                let ty = try!(self.ty());
+                try!(self.expect(TokenData::RParen));
+                return Ok(ty);
+            }
+            TokenData::LBrace => {
+                if try!(self.mapped_type_ahead()) {
+                    return self.mapped_type(token);
+                }
+                self.lexer.unread_token(token);
+                return self.span(&mut |this| Ok(TyData::Obj(try!(this.type_members()))));
+            }
+            TokenData::LBrack => {
+                let mut elts = Vec::new();
+                if try!(self.peek()).value != TokenData::RBrack {
+                    loop {
+                        elts.push(try!(self.ty()));
+                        if !try!(self.matches_op(TokenData::Comma)) {
+                            break;
+                        }
+                    }
+                }
+                let end = try!(self.expect(TokenData::RBrack));
+                return self.node(TyData::Tuple(elts), span(&location, &end));
+            }
+            _ => { return Err(Error::UnexpectedToken(token)); }
+        };
+        self.node(data, location)
+    }
+
+    // Whether the `{` just read opens a mapped type, looking past any
+    // `readonly` modifier for `[` Identifier "in".
+    fn mapped_type_ahead(&mut self) -> Result<bool> {
+        let checkpoint = self.lexer.checkpoint();
+        let result = self.mapped_type_head();
+        self.lexer.rewind(checkpoint);
+        result
+    }
+
+    fn mapped_type_head(&mut self) -> Result<bool> {
+        if !try!(self.matches_op(TokenData::Plus)) {
+            try!(self.matches_op(TokenData::Minus));
+        }
+        try!(self.matches(TokenData::Identifier(Name::Atom(Atom::Readonly))));
+        if !try!(self.matches(TokenData::LBrack)) {
+            return Ok(false);
+        }
+        match try!(self.read()).value {
+            TokenData::Identifier(_) => { }
+            _ => { return Ok(false); }
+        }
+        Ok(try!(self.peek()).value == TokenData::Reserved(Reserved::In))
+    }
+
+    // MappedType ::=
+    //   "{" MappedModifier<"readonly">? "[" Identifier "in" Type ("as" Type)? "]"
+    //   MappedModifier<"?">? TypeAnnotation? ";"? "}"
+    fn mapped_type(&mut self, open: Token) -> Result<Ty> {
+        let readonly = try!(self.mapped_modifier(TokenData::Identifier(Name::Atom(Atom::Readonly))));
+        try!(self.expect(TokenData::LBrack));
+        let param = try!(self.binding_id());
+        try!(self.expect(TokenData::Reserved(Reserved::In)));
+        let constraint = try!(self.ty());
+        let name = if try!(self.matches_op(TokenData::Identifier(Name::Atom(Atom::As)))) {
+            Some(try!(self.ty()))
+        } else {
+            None
+        };
+        try!(self.expect(TokenData::RBrack));
+        let optional = try!(self.mapped_modifier(TokenData::Question));
+        let ty = try!(self.type_annotation_opt());
+        if !try!(self.matches_op(TokenData::Semi)) {
+            try!(self.matches_op(TokenData::Comma));
+        }
+        let end = try!(self.expect_closing(&open, TokenData::RBrace));
+        let mapped = MappedTy {
+            readonly: readonly,
+            param: param,
+            constraint: constraint,
+            name: name,
+            optional: optional,
+            ty: ty
+        };
+        self.node(TyData::Mapped(Box::new(mapped)), span(&open, &end))
+    }
+
+    // MappedModifier<M> ::= ("+" | "-")? M
+    fn mapped_modifier(&mut self, modifier: TokenData) -> Result<Option<MappedModifier>> {
+        let sign = if try!(self.matches_op(TokenData::Plus)) {
+            MappedModifier::Add
+        } else if try!(self.matches_op(TokenData::Minus)) {
+            MappedModifier::Remove
+        } else if try!(self.matches_op(modifier.clone())) {
+            return Ok(Some(MappedModifier::Add));
+        } else {
+            return Ok(None);
+        };
+        try!(self.expect(modifier));
+        Ok(Some(sign))
+    }
+
+    // `as const` asserts the narrowest type of a literal. It is read as a
+    // reference to a type named `const`.
+    fn asserted_type(&mut self, atom: Atom) -> Result<Ty> {
+        if atom == Atom::As {
+            if let Some(token) = try!(self.matches_token(TokenData::Reserved(Reserved::Const))) {
+                try!(self.count_node());
+                let id = Id::new(Name::from(Reserved::Const.name()), Some(token.location));
+                return self.node(TyData::Ref(vec![id], Vec::new()), Some(token.location));
+            }
+        }
+        self.ty()
+    }
+
+    // TypeReference ::=
+    //   EntityName ([no line terminator] TypeArguments)?
+    fn type_reference(&mut self, first: Id) -> Result<Ty> {
+        let name = try!(self.entity_name(first));
+        let mut location = self.vec_span(&name);
+        let mut args = Vec::new();
+        match *try!(self.peek_op()) {
+            Token { newline: false, value: TokenData::LAngle, .. } => {
+                let (tys, end) = try!(self.type_arguments());
+                location = span(&location, &end);
+                args = tys;
+            }
+            _ => { }
+        }
+        self.node(TyData::Ref(name, args), location)
+    }
+
+    // EntityName ::= Identifier ("." Identifier)*
+    fn entity_name(&mut self, first: Id) -> Result<Vec<Id>> {
+        let mut name = vec![first];
+        while try!(self.matches_op(TokenData::Dot)) {
+            name.push(try!(self.id()));
+        }
+        Ok(name)
+    }
+
+    // FunctionType ::= TypeParameters? FormalParameters "=>" ReturnType
+    // CallSignature ::= TypeParameters? FormalParameters (":" ReturnType)?
+    fn fun_type(&mut self, arrow: bool) -> Result<FunTy> {
+        self.span(&mut |this| {
+            let ty_params = try!(this.type_parameters_opt());
+            let params = try!(this.formal_parameters());
+            let ret_ty = if arrow {
+                try!(this.expect(TokenData::Arrow));
+                Some(try!(this.return_type()))
+            } else if this.shared_cx.get().typescript && try!(this.matches_op(TokenData::Colon)) {
+                Some(try!(this.return_type()))
+            } else {
+                None
+            };
+            Ok(FunTyData { ty_params: ty_params, params: params, ret_ty: ret_ty.map(Box::new) })
+        })
+    }
+
+    // ReturnType ::=
+    //   Identifier [no line terminator] "is" Type
+    //   Type
+    fn return_type(&mut self) -> Result<Ty> {
+        let predicate = match try!(self.peek()).value {
+            TokenData::Identifier(_) => try!(self.peek2(|next| match *next {
+                Token { newline: false, value: TokenData::Identifier(Name::Atom(Atom::Is)), .. } => true,
+                _ => false
+            })),
+            _ => false
+        };
+        if !predicate {
+            return self.ty();
+        }
+        let id = try!(self.id());
+        try!(self.read());
+        let ty = try!(self.ty());
+        let location = span(&id, &ty);
+        self.node(TyData::Predicate(id, Box::new(ty)), location)
+    }
+
+    // ObjectType ::= "{" TypeMember*[";" | ","] "}"
+    fn type_members(&mut self) -> Result<Vec<TyMember>> {
+        try!(self.expect(TokenData::LBrace));
+        let mut members = Vec::new();
+        while !try!(self.matches(TokenData::RBrace)) {
+            members.push(try!(self.type_member()));
+            if try!(self.matches_op(TokenData::Semi)) || try!(self.matches_op(TokenData::Comma)) {
+                continue;
+            }
+            match *try!(self.peek()) {
+                Token { newline: true, .. }
+              | Token { value: TokenData::RBrace, .. } => { }
+                _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+            }
+        }
+        Ok(members)
+    }
+
+    // TypeMember ::=
+    //   "readonly"? PropertyName "?"? TypeAnnotation?
+    //   "readonly"? IndexSignature
+    //   PropertyName "?"? CallSignature
+    //   CallSignature
+    //   "new" CallSignature
+    fn type_member(&mut self) -> Result<TyMember> {
+        self.span(&mut |this| {
+            let mut readonly = false;
+            let mut optional = false;
+            if try!(this.peek()).value == TokenData::Identifier(Name::Atom(Atom::Readonly)) &&
+               !try!(this.peek2(|next| next.newline || next.follow_type_member_key())) {
+                this.reread(TokenData::Identifier(Name::Atom(Atom::Readonly)));
+                readonly = true;
+            }
+            let sig = match try!(this.peek()).value {
+                TokenData::LParen | TokenData::LAngle => TySig::Call(try!(this.fun_type(false))),
+                TokenData::LBrack => try!(this.index_signature()),
+                TokenData::Reserved(Reserved::New) if !readonly => {
+                    let ctor = try!(this.peek2(|next| {
+                        next.value == TokenData::LParen || next.value == TokenData::LAngle
+                    }));
+                    if ctor {
+                        this.reread(TokenData::Reserved(Reserved::New));
+                        TySig::Ctor(try!(this.fun_type(false)))
+                    } else {
+                        try!(this.property_signature(&mut optional))
+                    }
+                }
+                _ => try!(this.property_signature(&mut optional))
+            };
+            Ok(TyMemberData { readonly: readonly, optional: optional, sig: sig })
+        })
+    }
+
+    // PropertyName "?"? (CallSignature | TypeAnnotation?)
+    fn property_signature(&mut self, optional: &mut bool) -> Result<TySig> {
+        let key = try!(self.property_key());
+        *optional = try!(self.matches_op(TokenData::Question));
+        match try!(self.peek()).value {
+            TokenData::LParen | TokenData::LAngle => Ok(TySig::Method(key, try!(self.fun_type(false)))),
+            _ => Ok(TySig::Prop(key, try!(self.type_annotation_opt())))
+        }
+    }
+
+    // IndexSignature ::= "[" BindingIdentifier TypeAnnotation "]" TypeAnnotation
+    fn index_signature(&mut self) -> Result<TySig> {
+        self.reread(TokenData::LBrack);
+        let id = try!(self.binding_id());
+        let id = try!(self.annotated_id(id, false));
+        try!(self.expect(TokenData::RBrack));
+        try!(self.expect(TokenData::Colon));
+        let ty = try!(self.ty());
+        Ok(TySig::Index(id, ty))
+    }
+
+    // TypeParameters ::= "<" TypeParameter+[","] ","? ">"
+    fn type_parameters_opt(&mut self) -> Result<Vec<TyParam>> {
+        let mut params = Vec::new();
+        if !self.shared_cx.get().typescript || !try!(self.matches_op(TokenData::LAngle)) {
+            return Ok(params);
+        }
+        loop {
+            params.push(try!(self.type_parameter()));
+            // The list may end with a comma.
+            if !try!(self.matches_op(TokenData::Comma)) || try!(self.peek_op()).value == TokenData::RAngle {
+                break;
+            }
+        }
+        try!(self.expect_type_close());
+        Ok(params)
+    }
+
+    // TypeParameter ::= BindingIdentifier ("extends" Type)? ("=" Type)?
+    fn type_parameter(&mut self) -> Result<TyParam> {
+        self.span(&mut |this| {
+            let id = try!(this.binding_id());
+            let constraint = if try!(this.matches_op(TokenData::Reserved(Reserved::Extends))) {
+                Some(try!(this.ty()))
+            } else {
+                None
+            };
+            let default = if try!(this.matches_op(TokenData::Assign)) {
+                Some(try!(this.ty()))
+            } else {
+                None
+            };
+            Ok(TyParamData { id: id, constraint: constraint, default: default })
+        })
+    }
+
+    // TypeArguments ::= "<" Type+[","] ">"
+    fn type_arguments(&mut self) -> Result<(Vec<Ty>, Token)> {
+        try!(self.expect(TokenData::LAngle));
+        let mut args = Vec::new();
+        loop {
+            args.push(try!(self.ty()));
+            if !try!(self.matches_op(TokenData::Comma)) {
+                break;
+            }
+        }
+        let end = try!(self.expect_type_close());
+        Ok((args, end))
+    }
+
+    // The lexer greedily combines `>` with the characters after it, so a
+    // closing `>>`, `>=` etc. is split and the remainder put back.
+    fn expect_type_close(&mut self) -> Result<Token> {
+        let token = try!(self.read_op());
+        let rest = match token.value {
+            TokenData::RAngle        => { return Ok(token); }
+            TokenData::RShift        => TokenData::RAngle,
+            TokenData::URShift       => TokenData::RShift,
+            TokenData::GEq           => TokenData::Assign,
+            TokenData::RShiftAssign  => TokenData::GEq,
+            TokenData::URShiftAssign => TokenData::RShiftAssign,
+            _ => { return Err(Error::UnexpectedToken(token)); }
+        };
+        let Span { start, end } = token.location;
+        let mid = start.after('>');
+        self.lexer.unread_token(Token {
+            location: Span { start: mid, end: end },
+            newline: false,
+            value: rest
+        });
+        Ok(Token {
+            location: Span { start: start, end: mid },
+            newline: token.newline,
+            value: TokenData::RAngle
+        })
+    }
+
+    fn array_literal(&mut self, start: Token) -> Result<Expr> {
+        let mut elts = Vec::new();
+        if let Some(end) = try!(self.matches_token(TokenData::RBrack)) {
+            return self.node(ExprData::Arr(elts), span(&start, &end));
+        }
+        loop {
+            let elt = try!(self.array_element());
+            elts.push(elt);
+            if !try!(self.matches(TokenData::Comma)) {
+                break;
+            }
+            // Optional final comma does not count as an element.
+            if try!(self.peek()).value == TokenData::RBrack {
+                break;
+            }
+        }
+        let end = try!(self.expect_closing(&start, TokenData::RBrack));
+        self.node(ExprData::Arr(elts), span(&start, &end))
+    }
+
+    fn array_element(&mut self) -> Result<Option<Expr>> {
+        if { let t = try!(self.peek()); t.value == TokenData::Comma || t.value == TokenData::RBrack } {
+            return Ok(None);
+        }
+        // ES6: ellipsis
+        self.allow_in(true, |this| this.assignment_expression().map(Some))
+    }
+
+    fn object_literal(&mut self, start: Token) -> Result<Expr> {
+        let mut props = Vec::new();
+        if let Some(end) = try!(self.matches_token(TokenData::RBrace)) {
+            return self.node(ExprData::Obj(props), span(&start, &end));
+        }
+        loop {
+            let prop = try!(self.object_property());
+            props.push(prop);
+            if !try!(self.matches(TokenData::Comma)) {
+                break;
+            }
+            if try!(self.peek()).value == TokenData::RBrack {
+                break;
+            }
+        }
+        let end = try!(self.expect_closing(&start, TokenData::RBrace));
+        self.node(ExprData::Obj(props), span(&start, &end))
+    }
+
+    fn more_prop_init(&mut self, key: PropKey) -> Result<Prop> {
+        self.reread(TokenData::Colon);
+        let val = try!(self.allow_in(true, |this| this.assignment_expression()));
+        let key_location = key.location();
+        let val_location = val.location();
+        let val = try!(self.node(PropValData::Init(val), val_location));
+        self.node(PropData { key: key, val: val }, span(&key_location, &val_location))
+    }
+
+    fn property_key_opt(&mut self) -> Result<Option<PropKey>> {
+        let token = try!(self.read());
+        let location = Some(token.location);
+        let data = match token.value {
+            TokenData::Identifier(name) => PropKeyData::Id(name),
+            TokenData::Reserved(word) => PropKeyData::Id(Name::from(word.name())),
+            TokenData::String(s) => PropKeyData::String(s),
+            TokenData::Number(n) => PropKeyData::Number(n),
+            _ => {
+                self.lexer.unread_token(token);
+                return Ok(None);
+            }
+        };
+        self.node(data, location).map(Some)
+    }
+
+    fn property_key(&mut self) -> Result<PropKey> {
+        match try!(self.property_key_opt()) {
+            Some(key) => Ok(key),
+            None => Err(Error::UnexpectedToken(try!(self.read())))
+        }
+    }
+
+    fn object_property(&mut self) -> Result<Prop> {
+        let first = try!(self.read());
+        match first.value {
+            TokenData::Identifier(Name::Atom(Atom::Get)) => {
+                if let Some(key) = try!(self.property_key_opt()) {
+                    let paren = try!(self.expect(TokenData::LParen));
+                    try!(self.expect(TokenData::RParen));
+                    try!(self.expect(TokenData::LBrace));
+                    let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
+                    let body = self.statement_list();
+                    replace(&mut self.parser_cx, outer_cx);
+                    let body = try!(body);
+                    let end = try!(self.expect(TokenData::RBrace));
+                    let val_location = span(&paren, &end);
+                    let prop_location = span(&key, &end);
+                    let val = try!(self.node(PropValData::Get(body), val_location));
+                    return self.node(PropData { key: key, val: val }, prop_location);
+                }
+                match try!(self.peek()).value {
+                    // ES6: TokenData::LParen => unimplemented!(),
+                    TokenData::Colon => {
+                        let key_location = Some(first.location);
+                        let key = try!(self.node(PropKeyData::Id(Name::Atom(Atom::Get)), key_location));
+                        self.more_prop_init(key)
+                    }
+                    // ES6: treat as elided optional initializer
+                    _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                }
+            }
+            TokenData::Identifier(Name::Atom(Atom::Set)) => {
+                if let Some(key) = try!(self.property_key_opt()) {
+                    let paren = try!(self.expect(TokenData::LParen));
+                    let param = try!(self.pattern());
+                    try!(self.expect(TokenData::RParen));
+                    try!(self.expect(TokenData::LBrace));
+                    let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
+                    let body = self.statement_list();
+                    replace(&mut self.parser_cx, outer_cx);
+                    let body = try!(body);
+                    let end = try!(self.expect(TokenData::RBrace));
+                    let val_location = span(&paren, &end);
+                    let prop_location = span(&key, &end);
+                    let val = try!(self.node(PropValData::Set(param, body), val_location));
+                    return self.node(PropData { key: key, val: val }, prop_location);
+                }
+                match try!(self.peek()).value {
+                    // ES6: TokenData::LParen => unimplemented!(),
+                    TokenData::Colon => {
+                        let key_location = Some(first.location);
+                        let key = try!(self.node(PropKeyData::Id(Name::Atom(Atom::Set)), key_location));
+                        self.more_prop_init(key)
+                    }
+                    // ES6: treat as elided optional initializer
+                    _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                }
+            }
+            // ES6: TokenData::Star
+            _ => {
+                self.lexer.unread_token(first);
+                let key = try!(self.property_key());
+                match try!(self.peek()).value {
+                    TokenData::Colon => self.more_prop_init(key),
+                    // ES6: TokenData::LParen =>
+                    // ES6: treat as elided optional initializer
+                    _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                }
+            }
+        }
+    }
+
+    // MemberBaseExpression ::=
+    //   PrimaryExpression
+    //   "new" "." "target"
+    //   "import" "." "meta"
+    //   "import" "(" AssignmentExpression ")"
+    fn member_base_expression(&mut self) -> Result<Expr> {
+        if let Some(new) = try!(self.matches_token(TokenData::Reserved(Reserved::New))) {
+            try!(self.expect(TokenData::Dot));
+            let target = try!(self.expect(TokenData::Identifier(Name::Atom(Atom::Target))));
+            let location = span(&new, &target);
+            try!(self.require_edition(Edition::ES2015, "new.target", location));
+            return self.node(ExprData::NewTarget, location);
+        }
+        if let Some(import) = try!(self.matches_token(TokenData::Reserved(Reserved::Import))) {
+            let next = try!(self.peek()).value.clone();
+            return match next {
+                TokenData::LParen => self.more_import_call(import),
+                TokenData::Dot    => self.more_import_meta(import),
+                // Import declarations, and so TypeScript's `import type`,
+                // aren't parsed yet.
+                _ if self.shared_cx.get().mode == Mode::Module => {
+                    Err(Error::UnsupportedFeature("import declarations", Some(import.location)))
+                }
+                _ => self.more_import_meta(import)
+            };
+        }
+        self.primary_expression()
+    }
+
+    // "import" . "." "meta"
+    fn more_import_meta(&mut self, import: Token) -> Result<Expr> {
+        try!(self.expect(TokenData::Dot));
+        let meta = try!(self.expect(TokenData::Identifier(Name::Atom(Atom::Meta))));
+        let location = span(&import, &meta);
+        try!(self.require_edition(Edition::ES2020, "import.meta", location));
+        if self.shared_cx.get().mode != Mode::Module {
+            return Err(Error::ImportMetaOutsideModule(location.unwrap()));
+        }
+        self.node(ExprData::ImportMeta, location)
+    }
+
+    // "import" . "(" AssignmentExpression ")"
+    fn more_import_call(&mut self, import: Token) -> Result<Expr> {
+        try!(self.expect(TokenData::LParen));
+        let specifier = try!(self.allow_in(true, |this| this.assignment_expression()));
+        let end = try!(self.expect(TokenData::RParen));
+        let location = span(&import, &end);
+        try!(self.require_edition(Edition::ES2020, "dynamic import", location));
+        self.node(ExprData::Import(Box::new(specifier)), location)
+    }
+
+    // "new"+n . (MemberBaseExpression | "super" Deref) Deref* Arguments<n Suffix*
+    fn new_expression(&mut self, news: Vec<Token>) -> Result<Expr> {
+        // ES6: if let Some(super) = try!(self.match_token(TokenData::Reserved(Reserved::Super))) {
+        // An import call is a CallExpression, so it can't be the callee of `new`.
+        if let Some(import) = try!(self.matches_token(TokenData::Reserved(Reserved::Import))) {
+            let meta = try!(self.more_import_meta(import));
+            return self.more_new_expression(news, meta);
+        }
+        let base = try!(self.member_base_expression());
+        self.more_new_expression(news, base)
+    }
+
+    // "new"+n MemberBaseExpression . Deref* Arguments<n Suffix*
+    fn more_new_expression(&mut self, news: Vec<Token>, mut base: Expr) -> Result<Expr> {
+        let mut derefs = Vec::new();
+        while let Some(deref) = try!(self.deref_opt()) {
+            try!(self.count_node());
+            derefs.push(deref);
+        }
+        let mut args_lists = Vec::new();
+        for _ in 0..news.len() {
+            if try!(self.peek_op()).value != TokenData::LParen {
+                break;
+            }
+            args_lists.push(try!(self.arguments()));
+        }
+        let suffixes = try!(self.suffixes());
+        for deref in derefs {
+            base = deref.append_to(base);
+        }
+        let mut news = news.into_iter().rev();
+        for args in args_lists {
+            base = args.append_to_new(news.next().unwrap(), base);
+        }
+        for new in news {
+            let location = span(&new, &base);
+            base = ExprData::New(Box::new(base), None).tracked(location);
+        }
+        for suffix in suffixes {
+            base = suffix.append_to(base);
+        }
+        Ok(base)
+    }
+
+    // CallExpression ::=
+    //   (MemberBaseExpression | "super" Suffix) Suffix*
+    fn call_expression(&mut self) -> Result<Expr> {
+        // ES6: super
+        let base = try!(self.member_base_expression());
+        self.more_call_expression(base)
+    }
+
+    // Suffix ::=
+    //   Deref
+    //   Arguments
+    //   TypeArguments                  // TypeScript mode only
+    //   [no line terminator] "!"       // TypeScript mode only
+    fn suffix_opt(&mut self) -> Result<Option<Suffix>> {
+        let typescript = self.shared_cx.get().typescript;
+        let newline = try!(self.peek_op()).newline;
+        match try!(self.peek_op()).value {
+            TokenData::Dot    => self.deref_dot().map(|deref| Some(Suffix::Deref(deref))),
+            TokenData::LBrack => self.deref_brack().map(|deref| Some(Suffix::Deref(deref))),
+            TokenData::LParen => self.arguments().map(|args| Some(Suffix::Arguments(args))),
+            TokenData::LAngle if typescript => Ok(self.speculate(|this| this.type_arguments_suffix())),
+            TokenData::Bang if typescript && !newline => {
+                Ok(Some(Suffix::NonNull(try!(self.read_op()))))
+            }
+            _ => Ok(None)
+        }
+    }
+
+    // In TypeScript, `<` after an expression may open type arguments, as in
+    // `f<T>(x)` or `f<T>;`. They are only taken to be type arguments when the
+    // parse succeeds and is followed by a call, the end of the expression or
+    // a new line.
+    fn type_arguments_suffix(&mut self) -> Result<Suffix> {
+        let (args, end) = try!(self.type_arguments());
+        let next = try!(self.peek_op());
+        if next.value != TokenData::LParen && !next.newline && !next.follow_expression() {
+            return Err(Error::UnexpectedToken(try!(self.read_op())));
+        }
+        Ok(Suffix::TypeArguments(args, end))
+    }
+
+
+    // Argument ::= "..."? AssignmentExpression
+    fn argument(&mut self) -> Result<Expr> {
+        // ES6: if let ellipsis = try!(self.matches(TokenData::Ellipsis)) { ... }
+        self.allow_in(true, |this| this.assignment_expression())
+    }
+
+    // Arguments ::= "(" Argument*[","] ")"
+    fn arguments(&mut self) -> Result<Arguments> {
+        let open = try!(self.expect(TokenData::LParen));
+        if let Some(end) = try!(self.matches_token(TokenData::RParen)) {
+            return Ok(Arguments { args: Vec::new(), end: end });
+        }
+        let mut args = Vec::new();
+        loop {
+            args.push(try!(self.argument()));
+            if !try!(self.matches(TokenData::Comma)) {
+                break;
+            }
+        }
+        let end = try!(self.expect_closing(&open, TokenData::RParen));
+        Ok(Arguments { args: args, end: end })
+    }
+
+/*
+    fn deref(&mut self) -> Result<Deref> {
+        match try!(self.peek_op()).value {
+            TokenData::LBrack => self.deref_brack(),
+            TokenData::Dot    => self.deref_dot(),
+            _ => Err(Error::UnexpectedToken(try!(self.read_op())))
+        }
+    }
+*/
+    
+    // Deref ::=
+    //   "[" Expression "]"
+    //   "." IdentifierName
+    fn deref_opt(&mut self) -> Result<Option<Deref>> {
+        match try!(self.peek_op()).value {
+            TokenData::LBrack => self.deref_brack().map(Some),
+            TokenData::Dot    => self.deref_dot().map(Some),
+            _ => Ok(None)
+        }
+    }
+
+    fn deref_brack(&mut self) -> Result<Deref> {
+        self.reread(TokenData::LBrack);
+        let expr = try!(self.allow_in(true, |this| this.expression()));
+        let end = try!(self.expect(TokenData::RBrack));
+        Ok(Deref::Brack(expr, end))
+    }
+
+    fn id_name(&mut self) -> Result<DotKey> {
+        let token = try!(self.read());
+        let location = Some(token.location);
+        let data = match token.value {
+            TokenData::Identifier(name) => DotKeyData(name),
+            TokenData::Reserved(word) => DotKeyData(Name::from(word.name())),
+            _ => { return Err(Error::UnexpectedToken(token)); }
+        };
+        self.node(data, location)
+    }
+
+    fn deref_dot(&mut self) -> Result<Deref> {
+        self.reread(TokenData::Dot);
+        let key = try!(self.id_name());
+        Ok(Deref::Dot(key))
+    }
+
+    // MemberBaseExpression . Suffix*
+    fn more_call_expression(&mut self, base: Expr) -> Result<Expr> {
+        let mut result = base;
+        let suffixes = try!(self.suffixes());
+        for suffix in suffixes {
+            result = suffix.append_to(result);
+        }
+        Ok(result)
+    }
+
+    fn suffixes(&mut self) -> Result<Vec<Suffix>> {
+        let mut suffixes = Vec::new();
+        while let Some(suffix) = try!(self.suffix_opt()) {
+            try!(self.count_node());
+            suffixes.push(suffix);
+        }
+        Ok(suffixes)
+    }
+
+    // LHSExpression ::=
+    //   NewExpression
+    //   CallExpression
+    fn lhs_expression(&mut self) -> Result<Expr> {
+        let mut news = Vec::new();
+        while try!(self.peek()).value == TokenData::Reserved(Reserved::New) {
+            try!(self.count_node());
+            news.push(self.reread(TokenData::Reserved(Reserved::New)));
+        }
+        if news.len() > 0 {
+            if try!(self.matches_op(TokenData::Dot)) {
+                let target = try!(self.expect(TokenData::Identifier(Name::Atom(Atom::Target))));
+                let new = news.pop();
+                let location = span(&new, &target);
+                try!(self.require_edition(Edition::ES2015, "new.target", location));
+                let new_target = try!(self.node(ExprData::NewTarget, location));
+                if news.len() > 0 {
+                    self.more_new_expression(news, new_target)
+                } else {
+                    self.more_call_expression(new_target)
+                }
+            } else {
+                self.new_expression(news)
+            }
+        } else {
+            self.call_expression()
+        }
+    }
+
+    // IDUnaryExpression ::=
+    //   IdentifierReference Suffix* PostfixOperator?
+    fn id_unary_expression(&mut self, id: Id) -> Result<Expr> {
+        let location = id.location();
+        let mut result = try!(self.node(ExprData::Id(id), location));
+        let suffixes = try!(self.suffixes());
+        for suffix in suffixes {
+            result = suffix.append_to(result);
+        }
+        if let Some(postfix) = try!(self.match_postfix_operator_opt()) {
+            try!(self.count_node());
+            result = match postfix {
+                Postfix::Inc(location) => ExprData::PostInc(Box::new(result)).tracked(Some(location)),
+                Postfix::Dec(location) => ExprData::PostDec(Box::new(result)).tracked(Some(location))
+            };
+        }
+        Ok(result)
+    }
+
+    // UnaryExpression ::=
+    //   Prefix* LHSExpression PostfixOperator?
+    //
+    // A type assertion `<T>x` is the older spelling of `x as T` and builds
+    // the same node.
+    fn unary_expression(&mut self) -> Result<Expr> {
+        let mut prefixes = Vec::new();
+        while let Some(prefix) = try!(self.match_prefix()) {
+            try!(self.count_node());
+            prefixes.push(prefix);
+        }
+        let mut arg = try!(self.lhs_expression());
+        if let Some(postfix) = try!(self.match_postfix_operator_opt()) {
+            try!(self.count_node());
+            arg = match postfix {
+                Postfix::Inc(location) => ExprData::PostInc(Box::new(arg)).tracked(Some(location)),
+                Postfix::Dec(location) => ExprData::PostDec(Box::new(arg)).tracked(Some(location))
+            };
+        }
+        for prefix in prefixes.into_iter().rev() {
+            match prefix {
+                Prefix::Unop(op)      => {
+                    let location = span(&op, &arg);
+                    arg = ExprData::Unop(op, Box::new(arg)).tracked(location);
+                }
+                Prefix::Inc(location) => { arg = ExprData::PreInc(Box::new(arg)).tracked(Some(location)); }
+                Prefix::Dec(location) => { arg = ExprData::PreDec(Box::new(arg)).tracked(Some(location)); }
+                Prefix::Assert(open, ty) => {
+                    let location = span(&open, &arg);
+                    arg = ExprData::As(Box::new(arg), ty).tracked(location);
+                }
+            }
+        }
+        Ok(arg)
+    }
+
+    // Prefix ::=
+    //   Unop
+    //   "++"
+    //   "--"
+    //   "<" Type ">"                                       // TypeScript without JSX only
+    fn match_prefix(&mut self) -> Result<Option<Prefix>> {
+        let token = try!(self.read());
+        let cx = self.shared_cx.get();
+        Ok(match token.value {
+            TokenData::Inc => Some(Prefix::Inc(token.location)),
+            TokenData::Dec => Some(Prefix::Dec(token.location)),
+            TokenData::LAngle if cx.typescript && !cx.jsx => {
+                let ty = try!(self.ty());
+                try!(self.expect_type_close());
+                Some(Prefix::Assert(token, ty))
+            }
+            _ => {
+                self.lexer.unread_token(token);
+                try!(self.match_unop()).map(Prefix::Unop)
+            }
+        })
+    }
+
+    // Unop ::=
+    //   "delete"
+    //   "void"
+    //   "typeof"
+    //   "+"
+    //   "-"
+    //   "~"
+    //   "!"
+    fn match_unop(&mut self) -> Result<Option<Unop>> {
+        let token = try!(self.read());
+        let tag = match token.value {
+            TokenData::Reserved(Reserved::Delete) => UnopTag::Delete,
+            TokenData::Reserved(Reserved::Void)   => UnopTag::Void,
+            TokenData::Reserved(Reserved::Typeof) => UnopTag::Typeof,
+            TokenData::Plus                       => UnopTag::Plus,
+            TokenData::Minus                      => UnopTag::Minus,
+            TokenData::Tilde                      => UnopTag::BitNot,
+            TokenData::Bang                       => UnopTag::Not,
+            _ => { self.lexer.unread_token(token); return Ok(None); }
+        };
+        Ok(Some(try!(self.node(tag, token.location()))))
+    }
+
+    // PostfixOperator ::=
+    //   [no line terminator] "++"
+    //   [no line terminator] "--"
+    fn match_postfix_operator_opt(&mut self) -> Result<Option<Postfix>> {
+        let next = try!(self.read_op());
+        if !next.newline {
+            match next.value {
+                TokenData::Inc => { return Ok(Some(Postfix::Inc(next.location))); }
+                TokenData::Dec => { return Ok(Some(Postfix::Dec(next.location))); }
+                _ => { }
             }
-            _ => Ok(None)
         }
+        self.lexer.unread_token(next);
+        Ok(None)
     }
 
-    fn finally_opt(&mut self) -> Result<Option<Vec<StmtListItem>>> {
-        Ok(match try!(self.peek()).value {
-            TokenData::Reserved(Reserved::Finally) => {
-                self.reread(TokenData::Reserved(Reserved::Finally));
-                Some(try!(self.block()))
-            }
-            _ => None
-        })
+    // ConditionalExpression ::=
+    //   UnaryExpression (Infix UnaryExpression)* ("?" AssignmentExpression ":" AssignmentExpression)?
+    fn conditional_expression(&mut self) -> Result<Expr> {
+        let left = try!(self.unary_expression());
+        let test = try!(self.more_infix_expressions(left));
+        self.more_conditional(test)
     }
 
-    fn debugger_statement(&mut self) -> Result<Stmt> {
-        let span = self.start();
-        self.reread(TokenData::Reserved(Reserved::Debugger));
-        Ok(try!(span.end_with_auto_semi(self, Newline::Required, |semi| StmtData::Debugger(semi))))
+    // IDConditionalExpression ::=
+    //   IDUnaryExpression (Infix UnaryExpression)* ("?" AssignmentExpression ":" AssignmentExpression)?
+    fn id_conditional_expression(&mut self, id: Id) -> Result<Expr> {
+        let left = try!(self.id_unary_expression(id));
+        let test = try!(self.more_infix_expressions(left));
+        self.more_conditional(test)
     }
 
-/*
-    pub fn module(&mut self) -> Result<Module> {
-        unimplemented!()
+    fn more_conditional(&mut self, left: Expr) -> Result<Expr> {
+        if try!(self.matches_op(TokenData::Question)) {
+            let cons = try!(self.allow_in(true, |this| this.assignment_expression()));
+            try!(self.expect(TokenData::Colon));
+            let alt = try!(self.assignment_expression());
+            let location = span(&left, &alt);
+            return self.node(ExprData::Cond(Box::new(left), Box::new(cons), Box::new(alt)), location);
+        }
+        Ok(left)
     }
-*/
 
-    fn paren_expression(&mut self) -> Result<Expr> {
-        try!(self.expect(TokenData::LParen));
-        let result = try!(self.allow_in(true, |this| this.expression()));
-        try!(self.expect(TokenData::RParen));
-        Ok(result)
+    // AssignmentExpression ::=
+    //   YieldPrefix* "yield"
+    //   YieldPrefix* ConditionalExpression (("=" | AssignmentOperator) AssignmentExpression)?
+    fn assignment_expression(&mut self) -> Result<Expr> {
+        self.nested(|this| {
+            let left = try!(this.conditional_expression());
+            this.more_assignment(left)
+        })
     }
 
-    // PrimaryExpression ::=
-    //   "this"
-    //   IdentifierReference
-    //   Literal
-    //   ArrayLiteral
-    //   ObjectLiteral
-    //   FunctionExpression
-    //   ClassExpression
-    //   GeneratorExpression
-    //   RegularExpressionLiteral
-    //   "(" Expression ")"
-    fn primary_expression(&mut self) -> Result<Expr> {
-        let token = try!(self.read());
-        let location = Some(token.location);
-        Ok(match token.value {
-            TokenData::Identifier(name)          => ExprData::Id(Id::new(name, location)),
-            TokenData::Reserved(Reserved::Null)  => ExprData::Null,
-            TokenData::Reserved(Reserved::This)  => ExprData::This,
-            TokenData::Reserved(Reserved::True)  => ExprData::True,
-            TokenData::Reserved(Reserved::False) => ExprData::False,
-            TokenData::Number(literal)           => ExprData::Number(literal),
-            TokenData::String(literal)           => ExprData::String(literal),
-            TokenData::RegExp(literal)           => ExprData::RegExp(literal),
-            TokenData::LBrack                    => { return self.array_literal(token); }
-            TokenData::LBrace                    => { return self.object_literal(token); }
-            TokenData::Reserved(Reserved::Function) => {
-                self.lexer.unread_token(token);
-                let fun = try!(self.function());
-                let location = fun.location();
-                return Ok(ExprData::Fun(fun).tracked(location));
-            }
-            TokenData::LParen => {
-                self.lexer.unread_token(token);
-                return self.paren_expression();
-            }
-            // ES6: more cases
-            _ => { return Err(Error::UnexpectedToken(token)); }
-        }.tracked(location))
+    // IDAssignmentExpression ::=
+    //   YieldPrefix* "yield"
+    //   YieldPrefix+ ConditionalExpression (("=" | AssignmentOperator) AssignmentExpression)?
+    //   IDConditionalExpression (("=" | AssignmentOperator) AssignmentExpression)?
+    fn id_assignment_expression(&mut self, id: Id) -> Result<Expr> {
+        let left = try!(self.id_conditional_expression(id));
+        self.more_assignment(left)
     }
 
-    fn array_literal(&mut self, start: Token) -> Result<Expr> {
-        let mut elts = Vec::new();
-        if let Some(end) = try!(self.matches_token(TokenData::RBrack)) {
-            return Ok(ExprData::Arr(elts).tracked(span(&start, &end)));
+    fn more_assignment(&mut self, left: Expr) -> Result<Expr> {
+        let token = try!(self.read_op());
+        if let Some(op) = token.to_assop() {
+            let left_location = left.location();
+            let left = match left.into_assign_patt() {
+                Ok(left) => left,
+                Err(cover_err) => { return Err(Error::InvalidLHS(left_location, cover_err)); }
+            };
+            let right = try!(self.assignment_expression());
+            let location = span(&left, &right);
+            return self.node(ExprData::Assign(op, left, Box::new(right)), location);
         }
+        self.lexer.unread_token(token);
+        Ok(left)
+    }
+
+    fn more_infix_expressions(&mut self, left: Expr) -> Result<Expr> {
+        let mut stack = Stack::new();
+        let mut operand = left;
         loop {
-            let elt = try!(self.array_element());
-            elts.push(elt);
-            if !try!(self.matches(TokenData::Comma)) {
-                break;
-            }
-            // Optional final comma does not count as an element.
-            if try!(self.peek()).value == TokenData::RBrack {
+            if let Some(op) = try!(self.match_infix()) {
+                try!(self.count_node());
+                stack.extend(operand, op);
+                //println!("{}\n", stack);
+                operand = try!(self.unary_expression());
+            } else if let Some(atom) = try!(self.match_type_assertion()) {
+                try!(self.count_node());
+                // `as` and `satisfies` group like relational operators but
+                // take a type as their right operand.
+                let left = stack.reduce(operand, BinopTag::Lt.precedence());
+                let ty = try!(self.asserted_type(atom));
+                let location = span(&left, &ty);
+                operand = match atom {
+                    Atom::As => ExprData::As(Box::new(left), ty),
+                    _        => ExprData::Satisfies(Box::new(left), ty)
+                }.tracked(location);
+            } else {
                 break;
             }
         }
-        let end = try!(self.expect(TokenData::RBrack));
-        Ok(ExprData::Arr(elts).tracked(span(&start, &end)))
+        Ok(stack.finish(operand))
     }
 
-    fn array_element(&mut self) -> Result<Option<Expr>> {
-        if { let t = try!(self.peek()); t.value == TokenData::Comma || t.value == TokenData::RBrack } {
+    // TypeAssertion ::=
+    //   [no line terminator] "as"
+    //   [no line terminator] "satisfies"
+    fn match_type_assertion(&mut self) -> Result<Option<Atom>> {
+        if !self.shared_cx.get().typescript {
             return Ok(None);
         }
-        // ES6: ellipsis
-        self.allow_in(true, |this| this.assignment_expression().map(Some))
+        let atom = match *try!(self.peek_op()) {
+            Token { newline: false, value: TokenData::Identifier(Name::Atom(Atom::As)), .. }        => Atom::As,
+            Token { newline: false, value: TokenData::Identifier(Name::Atom(Atom::Satisfies)), .. } => Atom::Satisfies,
+            _ => { return Ok(None); }
+        };
+        try!(self.read_op());
+        Ok(Some(atom))
     }
 
-    fn object_literal(&mut self, start: Token) -> Result<Expr> {
-        let mut props = Vec::new();
-        if let Some(end) = try!(self.matches_token(TokenData::RBrace)) {
-            return Ok(ExprData::Obj(props).tracked(span(&start, &end)));
-        }
-        loop {
-            let prop = try!(self.object_property());
-            props.push(prop);
-            if !try!(self.matches(TokenData::Comma)) {
-                break;
-            }
-            if try!(self.peek()).value == TokenData::RBrack {
-                break;
-            }
+    fn match_infix(&mut self) -> Result<Option<Infix>> {
+        let token = try!(self.read_op());
+        let result = token.to_binop(self.parser_cx.allow_in).map_or_else(|| {
+            token.to_logop().map(Infix::Logop)
+        }, |op| Some(Infix::Binop(op)));
+        if result.is_none() {
+            self.lexer.unread_token(token);
         }
-        let end = try!(self.expect(TokenData::RBrace));
-        Ok(ExprData::Obj(props).tracked(span(&start, &end)))
+        Ok(result)
     }
 
-    fn more_prop_init(&mut self, key: PropKey) -> Result<Prop> {
-        self.reread(TokenData::Colon);
-        let val = try!(self.allow_in(true, |this| this.assignment_expression()));
-        let key_location = key.location();
-        let val_location = val.location();
-        Ok(PropData {
-            key: key,
-            val: PropValData::Init(val).tracked(val_location)
-        }.tracked(span(&key_location, &val_location)))
+    // Expression ::=
+    //   AssignmentExpression ("," AssignmentExpression)*
+    fn expression(&mut self) -> Result<Expr> {
+        let first = try!(self.assignment_expression());
+        self.more_expressions(first)
     }
 
-    fn property_key_opt(&mut self) -> Result<Option<PropKey>> {
-        let token = try!(self.read());
-        let location = Some(token.location);
-        Ok(Some(match token.value {
-            TokenData::Identifier(name) => PropKeyData::Id(name.into_string()),
-            TokenData::Reserved(word) => PropKeyData::Id(word.into_string()),
-            TokenData::String(s) => PropKeyData::String(s),
-            TokenData::Number(n) => PropKeyData::Number(n),
-            _ => {
-                self.lexer.unread_token(token);
-                return Ok(None);
-            }
-        }.tracked(location)))
+    // IDExpression ::=
+    //   IDAssignmentExpression ("," AssignmentExpression)*
+    fn id_expression(&mut self, id: Id) -> Result<Expr> {
+        let first = try!(self.id_assignment_expression(id));
+        self.more_expressions(first)
     }
 
-    fn property_key(&mut self) -> Result<PropKey> {
-        match try!(self.property_key_opt()) {
-            Some(key) => Ok(key),
-            None => Err(Error::UnexpectedToken(try!(self.read())))
+    fn more_expressions(&mut self, first: Expr) -> Result<Expr> {
+        if try!(self.peek()).value != TokenData::Comma {
+            return Ok(first);
+        }
+        let mut elts = vec![first];
+        while try!(self.matches(TokenData::Comma)) {
+            elts.push(try!(self.assignment_expression()));
         }
+        let location = self.vec_span(&elts);
+        self.node(ExprData::Seq(elts), location)
     }
+}
 
-    fn object_property(&mut self) -> Result<Prop> {
-        let first = try!(self.read());
-        match first.value {
-            TokenData::Identifier(Name::Atom(Atom::Get)) => {
-                if let Some(key) = try!(self.property_key_opt()) {
-                    let paren = try!(self.expect(TokenData::LParen));
-                    try!(self.expect(TokenData::RParen));
-                    try!(self.expect(TokenData::LBrace));
-                    let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
-                    let body = self.statement_list();
-                    replace(&mut self.parser_cx, outer_cx);
-                    let body = try!(body);
-                    let end = try!(self.expect(TokenData::RBrace));
-                    let val_location = span(&paren, &end);
-                    let prop_location = span(&key, &end);
-                    return Ok(PropData {
-                        key: key,
-                        val: PropValData::Get(body).tracked(val_location)
-                    }.tracked(prop_location));
-                }
-                match try!(self.peek()).value {
-                    // ES6: TokenData::LParen => unimplemented!(),
-                    TokenData::Colon => {
-                        let key_location = Some(first.location);
-                        self.more_prop_init(PropKeyData::Id("get".to_string()).tracked(key_location))
+#[cfg(test)]
+mod tests {
+
+    use std::{thread, env};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use test::{deserialize_parser_tests, ParserTest};
+    use joker::track::{Untrack, Relocation};
+    use joker::lexer::Lexer;
+    use joker::intern::Interner;
+    use joker::context::{Context, Mode};
+    use parser::Parser;
+    use easter::stmt::{Stmt, StmtData, StmtListItem};
+    use easter::expr::{Expr, ExprData};
+    use easter::jsx::JSXChildData;
+    use easter::decl::{Decl, DeclData};
+    use easter::class::{ClassMemberVal, Access};
+    use easter::patt::Patt;
+    use easter::prog::{Script, Program};
+    use easter::fun::Fun;
+    use easter::ty::{TyData, MappedModifier};
+    use result::Result;
+    use options::{ParseOptions, Edition, FunctionKind, SourceType};
+    use error::Error;
+    use builder::Easter;
+    use joker::comment::CommentKind;
+    use comments::CommentMap;
+    use joker::lexeme::Lexeme;
+    use joker::token::{Token, TokenData};
+    use joker::track::Track;
+    use ::{script, script_with, script_recovering, module, module_with, script_with_builder, script_with_comments, tokens};
+    use ::{expression, statement, function_body, formal_parameters, reparse_function};
+    use ::{script_from_bytes, script_from_reader, script_from_file, Encoding};
+    use ::{reparse_script, Edit, parse_many};
+    use std::path::Path;
+    use std::io;
+    use std::panic;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn script_jsx(src: &str) -> Result<Script> {
+        script_with(src, ParseOptions::new().jsx(true))
+    }
+
+    fn script_ts(src: &str) -> Result<Script> {
+        script_with(src, ParseOptions::new().typescript(true))
+    }
+
+    fn script_decorators(src: &str) -> Result<Script> {
+        script_with(src, ParseOptions::new().decorators(true))
+    }
+
+    // Checks each parse against the ESTree AST it is expected to produce, or
+    // for a failure if there is none.
+    fn check_parser_tests<F>(tests: Vec<ParserTest>, parse: F)
+      where F: Fn(&str) -> Result<Script>
+    {
+        for ParserTest { source, expected, .. } in tests {
+            let result = parse(&source[..]);
+            match (result, expected) {
+                (Ok(mut actual_ast), Some(expected_ast)) => {
+                    actual_ast.untrack();
+                    if actual_ast != expected_ast {
+                        println!("");
+                        println!("test:         {}", source);
+                        println!("expected AST: {:#?}", expected_ast);
+                        println!("actual AST:   {:#?}", actual_ast);
                     }
-                    // ES6: treat as elided optional initializer
-                    _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
-                }
-            }
-            TokenData::Identifier(Name::Atom(Atom::Set)) => {
-                if let Some(key) = try!(self.property_key_opt()) {
-                    let paren = try!(self.expect(TokenData::LParen));
-                    let param = try!(self.pattern());
-                    try!(self.expect(TokenData::RParen));
-                    try!(self.expect(TokenData::LBrace));
-                    let outer_cx = replace(&mut self.parser_cx, context::Context::new_function());
-                    let body = self.statement_list();
-                    replace(&mut self.parser_cx, outer_cx);
-                    let body = try!(body);
-                    let end = try!(self.expect(TokenData::RBrace));
-                    let val_location = span(&paren, &end);
-                    let prop_location = span(&key, &end);
-                    return Ok(PropData {
-                        key: key,
-                        val: PropValData::Set(param, body).tracked(val_location)
-                    }.tracked(prop_location));
+                    assert!(actual_ast == expected_ast);
                 }
-                match try!(self.peek()).value {
-                    // ES6: TokenData::LParen => unimplemented!(),
-                    TokenData::Colon => {
-                        let key_location = Some(first.location);
-                        self.more_prop_init(PropKeyData::Id("set".to_string()).tracked(key_location))
-                    }
-                    // ES6: treat as elided optional initializer
-                    _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                (Err(_), None) => { }
+                (Ok(mut actual_ast), None) => {
+                    actual_ast.untrack();
+                    println!("");
+                    println!("test:                {}", source);
+                    println!("expected error, got: {:?}", actual_ast);
+                    panic!("expected error");
                 }
-            }
-            // ES6: TokenData::Star
-            _ => {
-                self.lexer.unread_token(first);
-                let key = try!(self.property_key());
-                match try!(self.peek()).value {
-                    TokenData::Colon => self.more_prop_init(key),
-                    // ES6: TokenData::LParen =>
-                    // ES6: treat as elided optional initializer
-                    _ => { return Err(Error::UnexpectedToken(try!(self.read()))); }
+                (Err(actual_err), Some(expected_ast)) => {
+                    println!("");
+                    println!("test:         {}", source);
+                    println!("expected AST: {:?}", expected_ast);
+                    println!("actual error: {:?}", actual_err);
+                    panic!("unexpected error");
                 }
             }
         }
     }
 
-    // MemberBaseExpression ::=
-    //   PrimaryExpression
-    //   "new" "." "target"
-    fn member_base_expression(&mut self) -> Result<Expr> {
-        if let Some(new) = try!(self.matches_token(TokenData::Reserved(Reserved::New))) {
-            try!(self.expect(TokenData::Dot));
-            let target = try!(self.expect(TokenData::Identifier(Name::Atom(Atom::Target))));
-            return Ok(ExprData::NewTarget.tracked(span(&new, &target)));
-        }
-        self.primary_expression()
+    #[test]
+    pub fn unit_tests() {
+        check_parser_tests(deserialize_parser_tests(include_str!("../tests/build/unit.json")), script);
     }
 
-    // "new"+n . (MemberBaseExpression | "super" Deref) Deref* Arguments<n Suffix*
-    fn new_expression(&mut self, news: Vec<Token>) -> Result<Expr> {
-        // ES6: if let Some(super) = try!(self.match_token(TokenData::Reserved(Reserved::Super))) {
-        let base = try!(self.member_base_expression());
-        self.more_new_expression(news, base)
+    // Round-trips JSX through ESTree, written in the shape Babel and Esprima 4
+    // produce.
+    #[test]
+    pub fn jsx_unit_tests() {
+        check_parser_tests(deserialize_parser_tests(include_str!("../tests/jsx.json")), script_jsx);
     }
 
-    // "new"+n MemberBaseExpression . Deref* Arguments<n Suffix*
-    fn more_new_expression(&mut self, news: Vec<Token>, mut base: Expr) -> Result<Expr> {
-        let mut derefs = Vec::new();
-        while let Some(deref) = try!(self.deref_opt()) {
-            derefs.push(deref);
+    #[test]
+    pub fn builder() {
+        let tests = deserialize_parser_tests(include_str!("../tests/build/unit.json"));
+        for ParserTest { source, .. } in tests {
+            if let Ok(expected) = script(&source[..]) {
+                let actual = script_with_builder(&source[..], ParseOptions::new(), &mut Easter).unwrap();
+                assert!(actual == expected, "builder output differs for: {}", source);
+            }
         }
-        let mut args_lists = Vec::new();
-        for _ in 0..news.len() {
-            if try!(self.peek_op()).value != TokenData::LParen {
-                break;
+
+        let src = "class A extends B { m() { return <a/> as any; } }\nfor (let x of z) a.b = c[d];";
+        let options = ParseOptions::new().jsx(true).typescript(true);
+        assert_eq!(script_with_builder(src, options, &mut Easter).unwrap(), script_with(src, options).unwrap());
+    }
+
+    #[test]
+    pub fn comments() {
+        let src = "#!/usr/bin/env node\n/* license */\n\n/** doc */\nfunction f(a) {\n  return g(a); // trailing\n}\nx = /* @__PURE__ */ h();\nif (x) { /* empty */ }";
+        let (script, comments) = script_with_comments(src, ParseOptions::new()).unwrap();
+        let kinds: Vec<CommentKind> = comments.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, vec![CommentKind::Hashbang, CommentKind::Block, CommentKind::Block,
+                               CommentKind::Line, CommentKind::Block, CommentKind::Block]);
+
+        let map = CommentMap::from_script(&script, comments);
+        let body = &script.value.body;
+        let leading: Vec<&str> = map.leading(&body[0]).iter().map(|c| &c.text[..]).collect();
+        assert_eq!(leading, vec!["/usr/bin/env node", " license ", "* doc "]);
+
+        match body[0] {
+            StmtListItem::Decl(Decl { value: DeclData::Fun(ref fun), .. }) => {
+                let trailing: Vec<&str> = map.trailing(&fun.value.body[0]).iter().map(|c| &c.text[..]).collect();
+                assert_eq!(trailing, vec![" trailing"]);
             }
-            args_lists.push(try!(self.arguments()));
+            _ => panic!("expected function declaration")
         }
-        let suffixes = try!(self.suffixes());
-        for deref in derefs {
-            base = deref.append_to(base);
+        match body[1] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::Assign(_, _, ref rhs), .. }, _), .. }) => {
+                assert_eq!(map.leading(&**rhs)[0].text, " @__PURE__ ");
+            }
+            _ => panic!("expected assignment")
         }
-        let mut news = news.into_iter().rev();
-        for args in args_lists {
-            base = args.append_to_new(news.next().unwrap(), base);
+        match body[2] {
+            StmtListItem::Stmt(Stmt { value: StmtData::If(_, ref cons, _), .. }) => {
+                assert_eq!(map.dangling(&**cons)[0].text, " empty ");
+            }
+            _ => panic!("expected if statement")
         }
-        for new in news {
-            let location = span(&new, &base);
-            base = ExprData::New(Box::new(base), None).tracked(location);
+
+        assert!(script_with_comments("a; /* x", ParseOptions::new()).is_err());
+        let mut parser = Parser::from("a /* x */");
+        parser.script().unwrap();
+        assert!(parser.comments().is_empty());
+    }
+
+    #[test]
+    pub fn lazy_functions() {
+        fn lazy_fun(script: &Script) -> &Fun {
+            match script.value.body[0] {
+                StmtListItem::Decl(Decl { value: DeclData::Fun(ref fun), .. }) => fun,
+                _ => panic!("expected function declaration")
+            }
         }
-        for suffix in suffixes {
-            base = suffix.append_to(base);
+
+        let sources = [
+            "function f(a) { return a / 2 / b; }",
+            "function f() { if (x) /}/.test(y); while (x) /{/g; }",
+            "function f() { var o = { a: 1 } / 2; }\n/[)]/;",
+            "function f() { var g = function() { return '}'; } / 2; h = { a: /}/ }; }",
+            "function f() { {}\n/}/.exec(s); a++ / 2; L: /]/; }",
+            "function f() { function g() { } /}/; return [a] / b; }",
+            "function f() { a = b\n{ c() } /* } */ // }\n}",
+            "function f() { return x.delete / 2 / y.if / 3; }",
+            "function f() { return\n{}\n/}/.test(s); }",
+            "function f() { x = a ? { b: 1 } / 2 : /}/; }",
+            "function f() { switch (x) { case 1: /}/.test(y); } }",
+            "function f() { a\n++b / 2; }",
+            "function f() { return }"
+        ];
+        let options = ParseOptions::new().lazy_functions(true);
+        for &src in sources.iter() {
+            let eager = script(src).unwrap();
+            let lazy = script_with(src, options).unwrap();
+            let fun = lazy_fun(&lazy);
+            assert!(fun.value.body.is_empty(), "body parsed for: {}", src);
+            let record = fun.value.lazy.expect("expected a lazy body");
+            assert_eq!(record.mode, Mode::Sloppy);
+            assert_eq!(record.kind, FunctionKind::Normal);
+            let body = reparse_function(src, &record, ParseOptions::new()).unwrap();
+            assert_eq!(body, lazy_fun(&eager).value.body);
+            assert_eq!(lazy.value.body[1..], eager.value.body[1..]);
         }
-        Ok(base)
-    }
 
-    // CallExpression ::=
-    //   (MemberBaseExpression | "super" Suffix) Suffix*
-    fn call_expression(&mut self) -> Result<Expr> {
-        // ES6: super
-        let base = try!(self.primary_expression());
-        self.more_call_expression(base)
-    }
+        // Nested functions are left lazy in the reparse, too.
+        let src = "function f() { return function() { return 1; }; }";
+        let record = lazy_fun(&script_with(src, options).unwrap()).value.lazy.unwrap();
+        match reparse_function(src, &record, options).unwrap()[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Return(Some(Expr { value: ExprData::Fun(ref fun), .. }), _), .. }) => {
+                assert!(fun.value.body.is_empty());
+                let inner = reparse_function(src, &fun.value.lazy.unwrap(), options).unwrap();
+                assert_eq!(inner.len(), 1);
+            }
+            _ => panic!("expected return of a function")
+        }
 
-    // Suffix ::=
-    //   Deref
-    //   Arguments
-    fn suffix_opt(&mut self) -> Result<Option<Suffix>> {
-        match try!(self.peek_op()).value {
-            TokenData::Dot    => self.deref_dot().map(|deref| Some(Suffix::Deref(deref))),
-            TokenData::LBrack => self.deref_brack().map(|deref| Some(Suffix::Deref(deref))),
-            TokenData::LParen => self.arguments().map(|args| Some(Suffix::Arguments(args))),
-            _ => Ok(None)
+        let strict = script_with("function f() { x = 1; }", options.strict(true)).unwrap();
+        assert_eq!(lazy_fun(&strict).value.lazy.unwrap().mode, Mode::Strict);
+        assert!(script_with("function f() { with (o) { } }", options.strict(true)).is_err());
+
+        // The reparse treats `yield` and `await` as the function's kind does.
+        let src = "function f() { return yield; }";
+        let mut record = lazy_fun(&script_with(src, options).unwrap()).value.lazy.unwrap();
+        assert!(reparse_function(src, &record, options).is_ok());
+        record.kind = FunctionKind::Generator;
+        assert!(reparse_function(src, &record, options).is_err());
+
+        // Lexical errors in a lazy body are reported by the first parse, just
+        // as they are without it, and so are unbalanced brackets.
+        for &src in ["function f() { return 'a; }",
+                     "function f() { return '\\u12'; }",
+                     "function f() { return /a; }",
+                     "function f() { /* }",
+                     "function f() { if (x) { }"].iter() {
+            assert_eq!(script_with(src, options), script(src));
+            assert!(script(src).is_err());
+        }
+        match script_with("function f() { g(]; }", options) {
+            Err(Error::Unclosed(_, TokenData::RParen, _)) => { }
+            result => panic!("expected unclosed parenthesis, got {:?}", result)
+        }
+        // Other errors are left to the reparse.
+        for &src in ["function f() { a b; }", "function f() { break; }"].iter() {
+            let record = lazy_fun(&script_with(src, options).unwrap()).value.lazy.unwrap();
+            assert!(reparse_function(src, &record, options).is_err());
         }
-    }
 
+        // Only the function itself is built: its body is never parsed.
+        let src = format!("function f() {{ {} }}", "g(a + b);".repeat(100));
+        match script_with(&src, ParseOptions::new().max_nodes(10)) {
+            Err(Error::TooManyNodes(_)) => { }
+            result => panic!("expected too many nodes, got {:?}", result)
+        }
+        assert!(script_with(&src, options.max_nodes(10)).is_ok());
 
-    // Argument ::= "..."? AssignmentExpression
-    fn argument(&mut self) -> Result<Expr> {
-        // ES6: if let ellipsis = try!(self.matches(TokenData::Ellipsis)) { ... }
-        self.allow_in(true, |this| this.assignment_expression())
+        let src = "function f() { return <a>{'}'}</a>; }";
+        let jsx = script_with(src, options.jsx(true)).unwrap();
+        let record = lazy_fun(&jsx).value.lazy.unwrap();
+        assert_eq!(reparse_function(src, &record, options.jsx(true)).unwrap().len(), 1);
+
+        // Builders are handed the lazy record.
+        let src = "function f() { g(); }";
+        let built = script_with_builder(src, options, &mut Easter).unwrap();
+        assert_eq!(built, script_with(src, options).unwrap());
+        assert!(lazy_fun(&built).value.lazy.is_some());
     }
 
-    // Arguments ::= "(" Argument*[","] ")"
-    fn arguments(&mut self) -> Result<Arguments> {
-        try!(self.expect(TokenData::LParen));
-        if let Some(end) = try!(self.matches_token(TokenData::RParen)) {
-            return Ok(Arguments { args: Vec::new(), end: end });
-        }
-        let mut args = Vec::new();
-        loop {
-            args.push(try!(self.argument()));
-            if !try!(self.matches(TokenData::Comma)) {
-                break;
+    #[test]
+    pub fn incremental() {
+        // A small xorshift generator, so that failures are reproducible.
+        struct Rng(u64);
+        impl Rng {
+            fn below(&mut self, n: usize) -> usize {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                (self.0 % n as u64) as usize
             }
         }
-        let end = try!(self.expect(TokenData::RParen));
-        Ok(Arguments { args: args, end: end })
-    }
 
-/*
-    fn deref(&mut self) -> Result<Deref> {
-        match try!(self.peek_op()).value {
-            TokenData::LBrack => self.deref_brack(),
-            TokenData::Dot    => self.deref_dot(),
-            _ => Err(Error::UnexpectedToken(try!(self.read_op())))
+        let mut sources: Vec<String> = vec![
+            "var a = 1;\nfunction f(x) {\n  if (x) /re/.test(x);\n  function g() { return x / 2 }\n  return g()\n}\na\n(b)\nwhile (a) { a-- }\r\nL: for (;;) break L;".to_string(),
+            "function outer() { function inner(a, b) { var c = a + b; return c; } return inner; }\nouter()(1, 2);\n// done".to_string(),
+            "#!/usr/bin/env node\nx = { a: 1, b: [2, 3] };\nfunction h() { try { y() } catch (e) { } finally { z = 'é' } }\nswitch (x) { case 1: h(); default: }".to_string()
+        ];
+        for ParserTest { source, .. } in deserialize_parser_tests(include_str!("../tests/build/unit.json")).into_iter().take(200) {
+            sources.push(source);
         }
-    }
-*/
-    
-    // Deref ::=
-    //   "[" Expression "]"
-    //   "." IdentifierName
-    fn deref_opt(&mut self) -> Result<Option<Deref>> {
-        match try!(self.peek_op()).value {
-            TokenData::LBrack => self.deref_brack().map(Some),
-            TokenData::Dot    => self.deref_dot().map(Some),
-            _ => Ok(None)
+        let snippets = ["", ";", "\n", "\r", "}", "{", "(", ")", "/", "x", " ", "a = b", "function q() {", "'", "/*", "*/",
+                        "// c\n", "return", "var y = 1;", "if (a) ", "é", "++", "\n/re/g", "function f() { x }"];
+
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for (i, source) in sources.into_iter().enumerate() {
+            let options = ParseOptions::new();
+            // Longer runs over the longer sources.
+            let steps = if i < 3 { 500 } else { 20 };
+            let mut source = source;
+            let mut old = match script_with(&source, options) {
+                Ok(old) => old,
+                Err(_)  => { continue; }
+            };
+            for _ in 0..steps {
+                let mut start = rng.below(source.len() + 1);
+                let mut end = start + rng.below(8);
+                while !source.is_char_boundary(start) { start -= 1; }
+                end = if end > source.len() { source.len() } else { end };
+                while !source.is_char_boundary(end) { end += 1; }
+                let edit = Edit::new(start..end, snippets[rng.below(snippets.len())]);
+                let new_source = edit.apply(&source).unwrap();
+                let expected = script_with(&new_source, options);
+                let actual = reparse_script(old.clone(), &source, &edit, options);
+                assert!(actual == expected, "incremental parse differs after {:?} in {:?}", edit, source);
+                if let Ok(new) = actual {
+                    old = new;
+                    source = new_source;
+                }
+            }
         }
-    }
 
-    fn deref_brack(&mut self) -> Result<Deref> {
-        self.reread(TokenData::LBrack);
-        let expr = try!(self.allow_in(true, |this| this.expression()));
-        let end = try!(self.expect(TokenData::RBrack));
-        Ok(Deref::Brack(expr, end))
-    }
+        // An edit inside a function body leaves the rest of the tree alone,
+        // but moves it.
+        let src = "function f() {\n  a;\n}\nb;";
+        let old = script(src).unwrap();
+        let edit = Edit::new(17..18, "x\n  y");
+        let new = reparse_script(old.clone(), src, &edit, ParseOptions::new()).unwrap();
+        assert_eq!(new, script(&edit.apply(src).unwrap()).unwrap());
+        assert_eq!(new.value.body[1].location().unwrap().start.line, 4);
 
-    fn id_name(&mut self) -> Result<DotKey> {
-        let token = try!(self.read());
-        let location = Some(token.location);
-        Ok(match token.value {
-            TokenData::Identifier(name) => DotKeyData(name.into_string()),
-            TokenData::Reserved(word) => DotKeyData(word.into_string()),
-            _ => { return Err(Error::UnexpectedToken(token)); }
-        }.tracked(location))
+        // Edits that don't fit the source are errors.
+        let src = "a = 'é';";
+        let old = script(src).unwrap();
+        for range in vec![6..7, 3..100, 2..1, 100..100] {
+            let edit = Edit { range: range.clone(), text: "x".to_string() };
+            assert_eq!(edit.apply(src), Err(Error::InvalidEdit(range.clone())));
+            assert_eq!(reparse_script(old.clone(), src, &edit, ParseOptions::new()), Err(Error::InvalidEdit(range)));
+        }
     }
 
-    fn deref_dot(&mut self) -> Result<Deref> {
-        self.reread(TokenData::Dot);
-        let key = try!(self.id_name());
-        Ok(Deref::Dot(key))
+    #[test]
+    pub fn interners() {
+        // Trees compare by name, whichever interners their names went into,
+        // and can still be printed once an interner is gone.
+        let src = "function f(a) { return a.b + c; }";
+        let interner = Interner::new();
+        let inside = interner.enter(|| script(src).unwrap());
+        assert_eq!(inside, script(src).unwrap());
+        assert!(interner.enter(|| script("a.b + d;").unwrap()) != script("a.b + c;").unwrap());
+        drop(interner);
+        assert!(format!("{:?}", inside).contains("dropped symbol"));
     }
 
-    // MemberBaseExpression . Suffix*
-    fn more_call_expression(&mut self, base: Expr) -> Result<Expr> {
-        let mut result = base;
-        let suffixes = try!(self.suffixes());
-        for suffix in suffixes {
-            result = suffix.append_to(result);
+    #[test]
+    pub fn many() {
+        let deep = format!("x = {}1{};", "(".repeat(1000), ")".repeat(1000));
+        let mut inputs = vec![("a.js", "a + b;".to_string()),
+                              ("b.js", "if (".to_string()),
+                              ("deep.js", deep)];
+        for i in 0..50 {
+            inputs.push(("gen.js", format!("function f{}() {{ return {}; }}", i, i)));
         }
-        Ok(result)
+        let options = ParseOptions::new().max_depth(1100);
+        // The deep tree would overflow the test thread's stack, so it is only
+        // used on its worker.
+        let results = parse_many(&inputs, options, |parsed| {
+            if parsed.path == Path::new("deep.js") {
+                let program = parsed.result.unwrap();
+                assert!(program.clone() == program && !format!("{:?}", program).is_empty());
+                return (parsed.path, None);
+            }
+            (parsed.path, Some(parsed.result))
+        });
+        assert_eq!(results.len(), inputs.len());
+        for (&(path, ref result), &(input, ref source)) in results.iter().zip(inputs.iter()) {
+            assert_eq!(path, Path::new(input));
+            if input != "deep.js" {
+                let expected = script_with(source, options).map(Program::Script);
+                assert!(*result == Some(expected), "parse_many differs for {}", source);
+            }
+        }
+        assert!(results[1].1.as_ref().unwrap().is_err());
+
+        let modules = parse_many(&[("m.js", "f();")], ParseOptions::new().source_type(SourceType::Module), |parsed| parsed.result);
+        assert_eq!(modules[0], module("f();").map(Program::Module));
+        assert!(parse_many::<&str, &str, _, ()>(&[], ParseOptions::new(), |_| ()).is_empty());
+
+        // A panic in the callback reaches the caller.
+        let panicked = panic::catch_unwind(|| {
+            parse_many(&[("a.js", "a;")], ParseOptions::new(), |_| -> () { panic!("callback") })
+        });
+        assert_eq!(*panicked.unwrap_err().downcast::<&str>().unwrap(), "callback");
     }
 
-    fn suffixes(&mut self) -> Result<Vec<Suffix>> {
-        let mut suffixes = Vec::new();
-        while let Some(suffix) = try!(self.suffix_opt()) {
-            suffixes.push(suffix);
+    #[test]
+    pub fn limits() {
+        let options = ParseOptions::new();
+        assert!(script_with("a + b;", options.max_source_len(6)).is_ok());
+        match script_with("a + b;", options.max_source_len(5)) {
+            Err(err @ Error::SourceTooLong(_)) => { assert_eq!(err.code(), "E0027"); }
+            result => panic!("expected SourceTooLong, got {:?}", result)
+        }
+        assert!(script_with("a; // a long comment", options.max_source_len(4)).is_err());
+
+        // Four tokens and the end of input.
+        assert!(script_with("a + b;", options.max_tokens(5)).is_ok());
+        match script_with("a + b;", options.max_tokens(4)) {
+            Err(err @ Error::TooManyTokens(_)) => { assert_eq!(err.code(), "E0028"); }
+            result => panic!("expected TooManyTokens, got {:?}", result)
+        }
+
+        // Every located node counts, including those in function bodies, but
+        // not the positions of semicolons.
+        let src = "a + b; function f() { return [c, d, e]; }";
+        let mut nodes = 0;
+        for item in script(src).unwrap().value.body.iter_mut() {
+            item.relocate(&mut Relocation::Map(&mut |span| {
+                if span.start != span.end {
+                    nodes += 1;
+                }
+                span
+            }));
+        }
+        assert_eq!(script_with(src, options.max_nodes(nodes)), script(src));
+        match script_with(src, options.max_nodes(nodes - 1)) {
+            Err(err @ Error::TooManyNodes(_)) => { assert_eq!(err.code(), "E0029"); }
+            result => panic!("expected TooManyNodes, got {:?}", result)
+        }
+        assert!(script_with(src, options.max_nodes(6)).is_err());
+
+        // The count fails as soon as the limit is passed, well before the
+        // end of a long statement.
+        let src = format!("[{}0];", "0, ".repeat(100000));
+        match script_with(&src, options.max_nodes(10)) {
+            Err(Error::TooManyNodes(Some(span))) => { assert!(span.start.offset < 40); }
+            result => panic!("expected TooManyNodes, got {:?}", result)
+        }
+
+        // Cancellation is checked before each token.
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut parser = Parser::from("a + b;");
+        parser.cancel_on(cancel.clone());
+        cancel.store(true, Ordering::Relaxed);
+        match parser.script() {
+            Err(err @ Error::Cancelled(_)) => { assert_eq!(err.code(), "E0030"); }
+            result => panic!("expected Cancelled, got {:?}", result)
+        }
+
+        // Recovery stops at a limit instead of skipping past it.
+        let mut parser = Parser::with_options("a +; b; c; d;".chars(), options.max_tokens(4));
+        let (_, errors) = parser.script_recovering();
+        assert!(errors[errors.len() - 1].is_fatal());
+        let mut parser = Parser::from("a +; b; c; d;");
+        parser.cancel_on(cancel.clone());
+        let (_, errors) = parser.script_recovering();
+        assert_eq!(errors.len(), 1);
+        let lexemes = tokens("a b c", options.max_tokens(2));
+        match lexemes[lexemes.len() - 1] {
+            Lexeme::Error(ref err) => { assert!(err.value.is_fatal()); }
+            _ => panic!("expected the stream to end at the limit")
         }
-        Ok(suffixes)
     }
 
-    // LHSExpression ::=
-    //   NewExpression
-    //   CallExpression
-    fn lhs_expression(&mut self) -> Result<Expr> {
-        let mut news = Vec::new();
-        while try!(self.peek()).value == TokenData::Reserved(Reserved::New) {
-            news.push(self.reread(TokenData::Reserved(Reserved::New)));
+    #[test]
+    pub fn parens() {
+        fn expr(script: &Script) -> &Expr {
+            match script.value.body[0] {
+                StmtListItem::Stmt(Stmt { value: StmtData::Expr(ref expr, _), .. }) => expr,
+                _ => panic!("expected expression statement")
+            }
         }
-        if news.len() > 0 {
-            if try!(self.matches_op(TokenData::Dot)) {
-                let target = try!(self.expect(TokenData::Identifier(Name::Atom(Atom::Target))));
-                let new = news.pop();
-                let new_target = ExprData::NewTarget.tracked(span(&new, &target));
-                if news.len() > 0 {
-                    self.more_new_expression(news, new_target)
-                } else {
-                    self.more_call_expression(new_target)
+
+        let src = "x = (a + b) * ((c));";
+        let options = ParseOptions::new().preserve_parens(true);
+        let kept = script_with(src, options).unwrap();
+        let rhs = match expr(&kept).value {
+            ExprData::Assign(_, _, ref rhs) => rhs,
+            _ => panic!("expected assignment")
+        };
+        match rhs.value {
+            ExprData::Binop(_, ref left, ref right) => {
+                match left.value {
+                    ExprData::Paren(ref inner) => {
+                        assert_eq!(left.location.unwrap().byte_range(), 4..11);
+                        assert_eq!(inner.location.unwrap().byte_range(), 5..10);
+                    }
+                    _ => panic!("expected parenthesized sum")
+                }
+                match right.value {
+                    ExprData::Paren(ref inner) => match inner.value {
+                        ExprData::Paren(ref id) => { assert_eq!(id.location.unwrap().byte_range(), 16..17); }
+                        _ => panic!("expected nested parentheses")
+                    },
+                    _ => panic!("expected parenthesized identifier")
                 }
-            } else {
-                self.new_expression(news)
             }
-        } else {
-            self.call_expression()
+            _ => panic!("expected product")
+        }
+        match script(src).unwrap().value.body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::Assign(_, _, ref rhs), .. }, _), .. }) => {
+                assert_eq!(format!("{:?}", rhs).find("Paren"), None);
+            }
+            _ => panic!("expected assignment")
+        }
+
+        // Statement parentheses are not expressions, and both tree builders
+        // agree.
+        let src = "if ((a)) (b); while (c) d = (e, f); g((h));";
+        let expected = script_with(src, options).unwrap();
+        assert_eq!(format!("{:?}", expected).matches("Paren").count(), 4);
+        assert_eq!(script_with_builder(src, options, &mut Easter).unwrap(), expected);
+
+        // Only simple assignment targets can be parenthesized.
+        for &src in ["(a) = 1;", "((a.b)) += 2;", "[(a), (b[0])] = c;", "({ x: (y) } = z);"].iter() {
+            assert!(script_with(src, options).is_ok(), "failed to parse: {}", src);
+        }
+        for &src in ["([a]) = 1;", "({ x: ([y]) } = z);", "(a + b) = c;"].iter() {
+            match script_with(src, options) {
+                Err(Error::InvalidLHS(_, _)) => { }
+                result => panic!("expected InvalidLHS for {}, got {:?}", src, result)
+            }
         }
     }
 
-    // IDUnaryExpression ::=
-    //   IdentifierReference Suffix* PostfixOperator?
-    fn id_unary_expression(&mut self, id: Id) -> Result<Expr> {
-        let location = id.location();
-        let mut result = ExprData::Id(id).tracked(location);
-        let suffixes = try!(self.suffixes());
-        for suffix in suffixes {
-            result = suffix.append_to(result);
+    #[test]
+    pub fn raw_literals() {
+        let src = "x = ['it\\'s', \"\\x41\", 0XFF, 1., .5e-3, 017, 1e21, 99999999999999999999];";
+        let raws: Vec<String> = match expr(&script(src).unwrap()) {
+            &Expr { value: ExprData::Assign(_, _, ref rhs), .. } => match rhs.value {
+                ExprData::Arr(ref elts) => elts.iter().map(|elt| match elt.as_ref().unwrap().value {
+                    ExprData::String(ref lit) => lit.raw().unwrap().to_string(),
+                    ExprData::Number(ref lit) => lit.raw().unwrap(),
+                    _ => panic!("expected a literal")
+                }).collect(),
+                _ => panic!("expected array")
+            },
+            _ => panic!("expected assignment")
+        };
+        assert_eq!(raws, ["'it\\'s'", "\"\\x41\"", "0XFF", "1.", ".5e-3", "017", "1e21", "99999999999999999999"]);
+
+        // Property keys and JSX attributes keep theirs, too.
+        let src = "o = { 'a': <b c='&amp;' />, 0x10: 1 };";
+        let debug = format!("{:?}", script_with(src, ParseOptions::new().jsx(true)).unwrap());
+        assert!(debug.contains("source: Some(\"'a'\")"));
+        assert!(debug.contains("source: Some(\"'&amp;'\")"));
+        assert!(debug.contains("RadixInt(Hex(LowerCase), \"10\")"));
+
+        fn expr(script: &Script) -> &Expr {
+            match script.value.body[0] {
+                StmtListItem::Stmt(Stmt { value: StmtData::Expr(ref expr, _), .. }) => expr,
+                _ => panic!("expected expression statement")
+            }
         }
-        if let Some(postfix) = try!(self.match_postfix_operator_opt()) {
-            result = match postfix {
-                Postfix::Inc(location) => ExprData::PostInc(Box::new(result)).tracked(Some(location)),
-                Postfix::Dec(location) => ExprData::PostDec(Box::new(result)).tracked(Some(location))
-            };
+    }
+
+    #[test]
+    pub fn token_stream() {
+        let src = "a = b / c / 2;\r\nr = /re/g; // x\nif (x) /y/.test(z) # q\n\tw = 'unterminated\nv /* c */ = 1";
+        let lexemes = tokens(src, ParseOptions::new());
+
+        // The stream covers the source without gaps or overlaps.
+        let mut offset = 0;
+        for lexeme in &lexemes {
+            let span = lexeme.location().unwrap();
+            assert_eq!(span.start.offset, offset);
+            offset = span.end.offset;
+        }
+        assert_eq!(offset as usize, src.chars().count());
+
+        let count = |pred: &Fn(&Lexeme) -> bool| lexemes.iter().filter(|l| pred(l)).count();
+        assert_eq!(count(&|l| match *l { Lexeme::Token(ref t) => t.value == TokenData::Slash, _ => false }), 2);
+        assert_eq!(count(&|l| match *l { Lexeme::Token(ref t) => match t.value { TokenData::RegExp(_) => true, _ => false }, _ => false }), 2);
+        assert_eq!(count(&|l| match *l { Lexeme::Error(_) => true, _ => false }), 2);
+        assert_eq!(count(&|l| match *l { Lexeme::Comment(_) => true, _ => false }), 2);
+        assert_eq!(count(&|l| match *l { Lexeme::Newline(_) => true, _ => false }), 3);
+        match lexemes[lexemes.len() - 1] {
+            Lexeme::Token(Token { value: TokenData::Number(ref n), .. }) => { assert_eq!(n.value, 1.0); }
+            _ => panic!("expected the stream to end with a token")
         }
-        Ok(result)
     }
 
-    // UnaryExpression ::=
-    //   Prefix* LHSExpression PostfixOperator?
-    fn unary_expression(&mut self) -> Result<Expr> {
-        let mut prefixes = Vec::new();
-        while let Some(prefix) = try!(self.match_prefix()) {
-            prefixes.push(prefix);
+    #[test]
+    pub fn byte_ranges() {
+        let src = "/* ü */ a = '😀' + f(ä);";
+        let script = script(src).unwrap();
+        match script.value.body[0] {
+            StmtListItem::Stmt(ref stmt @ Stmt { value: StmtData::Expr(Expr { value: ExprData::Assign(_, _, ref rhs), .. }, _), .. }) => {
+                assert_eq!(&src[stmt.location.unwrap().byte_range()], "a = '😀' + f(ä);");
+                assert_eq!(rhs.location.unwrap().slice(src), "'😀' + f(ä)");
+                assert_eq!(rhs.location.unwrap().utf16_range(), 12..23);
+            }
+            _ => panic!("expected assignment")
+        }
+    }
+
+    #[test]
+    pub fn nesting_limit() {
+        let options = ParseOptions::new().max_depth(16);
+        assert!(script_with(&format!("{}a{}", "(".repeat(10), ")".repeat(10)), options).is_ok());
+        for src in &[format!("{}a{}", "(".repeat(1000), ")".repeat(1000)),
+                     format!("{}{}", "{".repeat(1000), "}".repeat(1000)),
+                     format!("{}{}", "function f() {".repeat(1000), "}".repeat(1000))] {
+            match script_with(src, options) {
+                Err(err @ Error::NestingTooDeep(_)) => { assert_eq!(err.code(), "E0024"); }
+                result => panic!("expected NestingTooDeep, got {:?}", result)
+            }
+        }
+
+        // Chains read in a loop don't count against the limit, however long
+        // they are; only recursion does.
+        for src in &[format!("x = {}1;", "!".repeat(100)),
+                     format!("x = 1{};", "+1".repeat(100)),
+                     format!("x = a{};", ".b".repeat(100)),
+                     format!("x = a{};", "()".repeat(100)),
+                     format!("x = {}a;", "new ".repeat(100)),
+                     format!("x = {}1{}{};", "(".repeat(10), ")".repeat(10), ".b".repeat(100)),
+                     format!("if (a) b; {}", "else if (a) b; ".repeat(100)),
+                     format!("{}b;", "a: ".repeat(100))] {
+            assert!(script_with(src, options).is_ok());
         }
-        let mut arg = try!(self.lhs_expression());
-        if let Some(postfix) = try!(self.match_postfix_operator_opt()) {
-            arg = match postfix {
-                Postfix::Inc(location) => ExprData::PostInc(Box::new(arg)).tracked(Some(location)),
-                Postfix::Dec(location) => ExprData::PostDec(Box::new(arg)).tracked(Some(location))
-            };
+        match script_with(&format!("x = {}a{};", "!(".repeat(20), ")".repeat(20)), options) {
+            Err(Error::NestingTooDeep(_)) => { }
+            result => panic!("expected NestingTooDeep, got {:?}", result)
         }
-        for prefix in prefixes.into_iter().rev() {
-            match prefix {
-                Prefix::Unop(op)      => {
-                    let location = span(&op, &arg);
-                    arg = ExprData::Unop(op, Box::new(arg)).tracked(location);
-                }
-                Prefix::Inc(location) => { arg = ExprData::PreInc(Box::new(arg)).tracked(Some(location)); }
-                Prefix::Dec(location) => { arg = ExprData::PreDec(Box::new(arg)).tracked(Some(location)); }
+
+        // Ordinary code parses with the default limit.
+        for src in &[format!("s = 'x'{};", " + 'x'".repeat(149)),
+                     format!("$(x){};", ".f()".repeat(60)),
+                     format!("if (a) b; {}", "else if (a) b; ".repeat(119)),
+                     format!("x = a{};", " || b".repeat(150))] {
+            assert!(script(src).is_ok());
+        }
+
+        let src = format!("if (a) b; {}", "else if (a) b; ".repeat(10));
+        let script = script_with(&src, options).unwrap();
+        match script.value.body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::If(_, _, Some(ref alt)), location: Some(location) }) => {
+                assert_eq!(alt.location.unwrap().slice(&src), &src[15..src.len() - 1]);
+                assert_eq!(location.slice(&src), &src[..src.len() - 1]);
             }
+            _ => panic!("expected if statement")
         }
-        Ok(arg)
-    }
 
-    // Prefix ::=
-    //   Unop
-    //   "++"
-    //   "--"
-    fn match_prefix(&mut self) -> Result<Option<Prefix>> {
-        let token = try!(self.read());
-        Ok(match token.value {
-            TokenData::Inc => Some(Prefix::Inc(token.location)),
-            TokenData::Dec => Some(Prefix::Dec(token.location)),
-            _ => {
-                self.lexer.unread_token(token);
-                try!(self.match_unop()).map(Prefix::Unop)
+        // Whatever the default limit accepts can be parsed and dropped on a
+        // small stack, however the input nests.
+        let deep = ParseOptions::new().max_depth;
+        let inputs = vec![format!("x = {}1{};", "(".repeat(deep - 2), ")".repeat(deep - 2)),
+                          format!("x = {}1{};", "[".repeat(deep - 2), "]".repeat(deep - 2)),
+                          format!("x = {}1{};", "{a:".repeat(deep - 2), "}".repeat(deep - 2)),
+                          format!("{}{}", "{".repeat(deep), "}".repeat(deep)),
+                          format!("x = 1{};", "+1".repeat(deep - 2)),
+                          format!("x = a{};", ".b".repeat(deep - 2))];
+        // Chains aren't limited at all, so they are dropped without recursion
+        // and cloned, compared and printed on a stack that grows as needed.
+        let long = 20000;
+        let chains = vec![format!("x = 1{};", "+1".repeat(long)),
+                          format!("x = a{};", " || b".repeat(long)),
+                          format!("x = {}1;", "!".repeat(long)),
+                          format!("x = a{};", ".b()".repeat(long)),
+                          format!("if (a) b; {}", "else if (a) b; ".repeat(long))];
+        ::std::thread::Builder::new().stack_size(2 << 20).spawn(move || {
+            for src in &inputs {
+                let parsed = script_with(src, ParseOptions::new()).unwrap();
+                assert!(parsed.clone() == parsed);
             }
-        })
+            for src in &chains {
+                let parsed = script_with(src, ParseOptions::new()).unwrap();
+                assert!(parsed.clone() == parsed);
+                assert!(format!("{:?}", parsed).len() > src.len());
+            }
+            script_ts(&format!("var x: T{};", "[]".repeat(long))).unwrap();
+            script_jsx(&format!("<a{} />;", ".b".repeat(long))).unwrap();
+        }).unwrap().join().unwrap();
     }
 
-    // Unop ::=
-    //   "delete"
-    //   "void"
-    //   "typeof"
-    //   "+"
-    //   "-"
-    //   "~"
-    //   "!"
-    fn match_unop(&mut self) -> Result<Option<Unop>> {
-        let token = try!(self.read());
-        let tag = match token.value {
-            TokenData::Reserved(Reserved::Delete) => UnopTag::Delete,
-            TokenData::Reserved(Reserved::Void)   => UnopTag::Void,
-            TokenData::Reserved(Reserved::Typeof) => UnopTag::Typeof,
-            TokenData::Plus                       => UnopTag::Plus,
-            TokenData::Minus                      => UnopTag::Minus,
-            TokenData::Tilde                      => UnopTag::BitNot,
-            TokenData::Bang                       => UnopTag::Not,
-            _ => { self.lexer.unread_token(token); return Ok(None); }
-        };
-        Ok(Some(tag.tracked(token.location())))
+    #[test]
+    pub fn standalone() {
+        match expression("a + b * c").unwrap().value {
+            ExprData::Binop(..) => { }
+            expr => panic!("expected binary expression, got {:?}", expr)
+        }
+        assert!(match expression("a; b") { Err(Error::UnexpectedToken(_)) => true, _ => false });
+
+        assert!(statement("if (a) b; else c;").is_ok());
+        assert!(statement("a; b").is_err());
+        assert!(match statement("return 1") { Err(Error::TopLevelReturn(_)) => true, _ => false });
+
+        assert_eq!(function_body("x = 1; return x", FunctionKind::Normal).unwrap().len(), 2);
+        assert!(function_body("return yield", FunctionKind::Normal).is_ok());
+        assert!(match function_body("return yield", FunctionKind::Generator) {
+            Err(Error::ContextualKeyword(_)) => true,
+            _ => false
+        });
+        assert!(function_body("await;", FunctionKind::Generator).is_ok());
+        assert!(function_body("await;", FunctionKind::AsyncGenerator).is_err());
+        assert!(function_body("}", FunctionKind::Normal).is_err());
+
+        assert_eq!(formal_parameters("a, b").unwrap().value.list.len(), 2);
+        assert_eq!(formal_parameters("").unwrap().value.list.len(), 0);
+        assert!(formal_parameters("(a)").is_err());
+        assert!(formal_parameters("a) { }; (b").is_err());
     }
 
-    // PostfixOperator ::=
-    //   [no line terminator] "++"
-    //   [no line terminator] "--"
-    fn match_postfix_operator_opt(&mut self) -> Result<Option<Postfix>> {
-        let next = try!(self.read_op());
-        if !next.newline {
-            match next.value {
-                TokenData::Inc => { return Ok(Some(Postfix::Inc(next.location))); }
-                TokenData::Dec => { return Ok(Some(Postfix::Dec(next.location))); }
-                _ => { }
+    #[test]
+    pub fn encodings() {
+        let src = "x = 'é😀';\ny;";
+        let expected = script(src).unwrap();
+        let utf16 = |bom: &[u8], big: bool| {
+            let mut bytes = bom.to_vec();
+            for unit in src.encode_utf16() {
+                let (hi, lo) = ((unit >> 8) as u8, unit as u8);
+                bytes.extend_from_slice(&if big { [hi, lo] } else { [lo, hi] });
             }
+            bytes
+        };
+
+        let mut bom = b"\xEF\xBB\xBF".to_vec();
+        bom.extend_from_slice(src.as_bytes());
+        assert_eq!(script_from_bytes(&bom, ParseOptions::new()).unwrap(), expected);
+        assert_eq!(script_from_reader(&bom[..], ParseOptions::new()).unwrap(), expected);
+        for bytes in &[utf16(b"\xFF\xFE", false), utf16(b"\xFE\xFF", true)] {
+            assert_eq!(script_from_bytes(bytes, ParseOptions::new()).unwrap(), expected);
+            assert_eq!(script_from_reader(&bytes[..], ParseOptions::new()).unwrap(), expected);
         }
-        self.lexer.unread_token(next);
-        Ok(None)
-    }
+        let declared = ParseOptions::new().encoding(Encoding::from_label(" UTF-16BE").unwrap());
+        assert_eq!(script_from_reader(&utf16(b"", true)[..], declared).unwrap(), expected);
 
-    // ConditionalExpression ::=
-    //   UnaryExpression (Infix UnaryExpression)* ("?" AssignmentExpression ":" AssignmentExpression)?
-    fn conditional_expression(&mut self) -> Result<Expr> {
-        let left = try!(self.unary_expression());
-        let test = try!(self.more_infix_expressions(left));
-        self.more_conditional(test)
+        let invalid = b"a = 1;\nb = '\xC3\x28';";
+        for result in vec![script_from_bytes(invalid, ParseOptions::new()),
+                           script_from_reader(&invalid[..], ParseOptions::new())] {
+            match result {
+                Err(Error::InvalidEncoding(Encoding::Utf8, span)) => {
+                    assert_eq!((span.start.line, span.start.column, span.start.byte), (1, 5, 12));
+                }
+                result => panic!("expected InvalidEncoding, got {:?}", result)
+            }
+        }
+        let mut unpaired = utf16(b"\xFF\xFE", false);
+        unpaired.extend_from_slice(b"\x00\xD8");
+        assert!(match script_from_bytes(&unpaired, ParseOptions::new()) {
+            Err(Error::InvalidEncoding(Encoding::Utf16LE, _)) => true,
+            _ => false
+        });
+
+        assert!(match script_from_file("/nonexistent/input.js", ParseOptions::new()) {
+            Err(Error::Io(io::ErrorKind::NotFound, _)) => true,
+            _ => false
+        });
     }
 
-    // IDConditionalExpression ::=
-    //   IDUnaryExpression (Infix UnaryExpression)* ("?" AssignmentExpression ":" AssignmentExpression)?
-    fn id_conditional_expression(&mut self, id: Id) -> Result<Expr> {
-        let left = try!(self.id_unary_expression(id));
-        let test = try!(self.more_infix_expressions(left));
-        self.more_conditional(test)
+    #[test]
+    pub fn import_expressions() {
+        assert!(script("import('./a.js').then(f)").is_ok());
+        assert!(script("x = import(a, b)").is_err());
+        assert!(script("new import('./a.js')").is_err());
+        assert!(script("import.meta.url").is_err());
+
+        let cx = Rc::new(Cell::new(Context::new(Mode::Module)));
+        let lexer = Lexer::new("import.meta.url; new import.meta.C".chars(), cx.clone());
+        assert!(Parser::new(lexer, cx).script().is_ok());
     }
 
-    fn more_conditional(&mut self, left: Expr) -> Result<Expr> {
-        if try!(self.matches_op(TokenData::Question)) {
-            let cons = try!(self.allow_in(true, |this| this.assignment_expression()));
-            try!(self.expect(TokenData::Colon));
-            let alt = try!(self.assignment_expression());
-            let location = span(&cons, &alt);
-            return Ok(ExprData::Cond(Box::new(left), Box::new(cons), Box::new(alt)).tracked(location));
+    #[test]
+    pub fn annex_b() {
+        assert!(script("a <!-- b\n--> c\nd").is_ok());
+        assert!(script("x = 010 + '\\07'").is_ok());
+        assert!(script("L: function f() {}").is_ok());
+        assert!(script("if (a) function f() {} else function g() {}").is_ok());
+        assert!(script("for (var x = 1 in obj);").is_ok());
+        assert!(script("a --> b").is_ok());
+        assert!(script("a\n--> b").is_ok());
+
+        assert!(script_with("<!-- b", ParseOptions::new().annex_b(false)).is_err());
+        assert!(script_with("L: function f() {}", ParseOptions::new().annex_b(false)).is_err());
+        assert!(script_with("for (var x = 1 in obj);", ParseOptions::new().annex_b(false)).is_err());
+        assert!(script_with("x = '\\0'", ParseOptions::new().annex_b(false)).is_ok());
+
+        for &(mode, src) in &[(Mode::Strict, "x = 010"),
+                              (Mode::Strict, "x = '\\07'"),
+                              (Mode::Strict, "L: function f() {}"),
+                              (Mode::Module, "<!-- b")] {
+            let cx = Rc::new(Cell::new(Context::new(mode)));
+            let lexer = Lexer::new(src.chars(), cx.clone());
+            assert!(Parser::new(lexer, cx).script().is_err());
         }
-        Ok(left)
     }
 
-    // AssignmentExpression ::=
-    //   YieldPrefix* "yield"
-    //   YieldPrefix* ConditionalExpression (("=" | AssignmentOperator) AssignmentExpression)?
-    fn assignment_expression(&mut self) -> Result<Expr> {
-        let left = try!(self.conditional_expression());
-        self.more_assignment(left)
-    }
+    #[test]
+    pub fn jsx() {
+        assert!(script("<a/>").is_err());
+        assert!(script_jsx("x = <div class=\"a &amp; b\" data-x='1' {...props} disabled/>;").is_ok());
+        assert!(script_jsx("<svg:rect width={w / 2}>text {/* empty */} {...kids}</svg:rect>").is_ok());
+        assert!(script_jsx("<A.B.C><>frag <b>{ a < b ? <i/> : null }</b></></A.B.C>").is_ok());
+        assert!(script_jsx("f(<a b=<c/>></a>, 1 < 2)").is_ok());
+        assert!(script_jsx("<a></b>").is_err());
+        assert!(script_jsx("<a>}</a>").is_err());
+        assert!(script_jsx("<a>").is_err());
 
-    // IDAssignmentExpression ::=
-    //   YieldPrefix* "yield"
-    //   YieldPrefix+ ConditionalExpression (("=" | AssignmentOperator) AssignmentExpression)?
-    //   IDConditionalExpression (("=" | AssignmentOperator) AssignmentExpression)?
-    fn id_assignment_expression(&mut self, id: Id) -> Result<Expr> {
-        let left = try!(self.id_conditional_expression(id));
-        self.more_assignment(left)
+        let mut actual = script_jsx("<p>a &lt; b{c}</p>").unwrap();
+        actual.untrack();
+        match actual.value.body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::JSXElement(ref el), .. }, _), .. }) => {
+                let children = el.value.children.as_ref().unwrap();
+                assert_eq!(children.len(), 2);
+                match children[0].value {
+                    JSXChildData::Text(ref text) => { assert_eq!(text.value, "a < b"); }
+                    _ => panic!("expected JSX text")
+                }
+            }
+            _ => panic!("expected JSX element")
+        }
     }
 
-    fn more_assignment(&mut self, left: Expr) -> Result<Expr> {
-        let token = try!(self.read_op());
-        if let Some(op) = token.to_assop() {
-            let left_location = left.location();
-            let left = match left.into_assign_patt() {
-                Ok(left) => left,
-                Err(cover_err) => { return Err(Error::InvalidLHS(left_location, cover_err)); }
-            };
-            let right = try!(self.assignment_expression());
-            let location = span(&left, &right);
-            return Ok(ExprData::Assign(op, left, Box::new(right)).tracked(location));
+    #[test]
+    pub fn typescript() {
+        assert!(script("var x: number = 1").is_err());
+        assert!(script("type = 1; interface\nFoo; x = a < b > (c)").is_ok());
+        assert!(script_ts("type = 1; interface\nFoo").is_ok());
+        assert!(script_ts("var x: Array<Map<string, number[]>>= y, z: A | B & C;").is_ok());
+        assert!(script_ts("function f<T extends object = {}>(a?: T, b: keyof T): a is T { return a as any satisfies T; }").is_ok());
+        assert!(script_ts("function g(x: string): void; function g(x: number): void; function g(x) {}").is_ok());
+        assert!(script_ts("interface I<T> extends J, K.L<T> { readonly [k: string]: T; m?(x: T): void, new (): I<T>; readonly: boolean }").is_ok());
+        assert!(script_ts("type F<T> = (x: T, ...) => void").is_err());
+        assert!(script_ts("type F<T> = T extends (infer U)[] ? U : new (x: T) => typeof y.z").is_ok());
+        assert!(script_ts("type T = [string, (a: number) => void][0]; enum E { A = 1, B, 'C' }").is_ok());
+        assert!(script_ts("declare var x: number; declare function f(): void; declare const enum D { A }").is_ok());
+        assert!(script_ts("f<string>(x); a < b; a < b > c; x = a as\nb").is_ok());
+
+        let mut actual = script_ts("x < y as T; f<T>(y)").unwrap();
+        actual.untrack();
+        match actual.value.body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::As(ref left, _), .. }, _), .. }) => {
+                match left.value {
+                    ExprData::Binop(..) => { }
+                    _ => panic!("expected `as` to group like a relational operator")
+                }
+            }
+            _ => panic!("expected `as` expression")
+        }
+        match actual.value.body[1] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::Call(ref callee, _), .. }, _), .. }) => {
+                match callee.value {
+                    ExprData::Instantiation(_, ref args) => { assert_eq!(args.len(), 1); }
+                    _ => panic!("expected type arguments")
+                }
+            }
+            _ => panic!("expected call expression")
         }
-        self.lexer.unread_token(token);
-        Ok(left)
-    }
 
-    fn more_infix_expressions(&mut self, left: Expr) -> Result<Expr> {
-        let mut stack = Stack::new();
-        let mut operand = left;
-        while let Some(op) = try!(self.match_infix()) {
-            stack.extend(operand, op);
-            //println!("{}\n", stack);
-            operand = try!(self.unary_expression());
+        assert!(script_ts("declare module \"x\" { export function f(): void; } declare module \"y\";").is_ok());
+        assert!(script_ts("namespace N.M { export var x: number; function g() {} } declare namespace D { interface I {} }").is_ok());
+        assert!(script_ts("var namespace, module; namespace\nN; module\n'x'").is_ok());
+        assert!(script_ts("namespace N { return; }").is_err());
+        assert!(script_ts("export var x").is_err());
+        assert!(script_ts("x!.y; f()!; a! + b; var c = [1, 2] as const, n: -1 | 1 = f<T>; g<T>\nh").is_ok());
+        assert!(script_ts("function f(this: Window, x: number) {}").is_ok());
+        assert!(script("function f(this) {}").is_err());
+        assert!(script_ts("type M<T> = { -readonly [K in keyof T as K]+?: T[K] }; type N = { [k: string]: number, readonly [k: number]: 1 }").is_ok());
+        match script_ts("type T = `a${B}`") {
+            Err(Error::UnsupportedFeature("template literal types", Some(_))) => { }
+            result => panic!("expected template literal types to be unsupported, got {:?}", result)
         }
-        Ok(stack.finish(operand))
-    }
+        assert!(script_ts("function f<T,>(x: T) {} type P<A, B,> = [A, B]; class C<T,> {}").is_ok());
+        assert!(script_ts("function f<,>() {}").is_err());
+        match module_with("import type { A } from './a';", ParseOptions::new().typescript(true)) {
+            Err(Error::UnsupportedFeature("import declarations", Some(_))) => { }
+            result => panic!("expected import declarations to be unsupported, got {:?}", result)
+        }
+        assert!(module_with("import('./a'); import.meta", ParseOptions::new().typescript(true)).is_ok());
 
-    fn match_infix(&mut self) -> Result<Option<Infix>> {
-        let token = try!(self.read_op());
-        let result = token.to_binop(self.parser_cx.allow_in).map_or_else(|| {
-            token.to_logop().map(Infix::Logop)
-        }, |op| Some(Infix::Binop(op)));
-        if result.is_none() {
-            self.lexer.unread_token(token);
+        let mut actual = script_ts("<T>x; y = <any><U>z.w++ + 1;").unwrap();
+        actual.untrack();
+        match actual.value.body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::As(ref arg, _), .. }, _), .. }) => {
+                match arg.value {
+                    ExprData::Id(_) => { }
+                    _ => panic!("expected asserted identifier")
+                }
+            }
+            _ => panic!("expected type assertion")
         }
-        Ok(result)
-    }
+        let mut expected = script_ts("x as T; y = (z.w++ as U as any) + 1;").unwrap();
+        expected.untrack();
+        assert_eq!(actual, expected);
+        assert!(script_ts("<T>").is_err());
+        assert!(script_with("<T>x", ParseOptions::new().typescript(true).jsx(true)).is_err());
 
-    // Expression ::=
-    //   AssignmentExpression ("," AssignmentExpression)*
-    fn expression(&mut self) -> Result<Expr> {
-        let first = try!(self.assignment_expression());
-        self.more_expressions(first)
+        let mut actual = script_ts("x!; y as const; f<T>; namespace A.B { export var z; }").unwrap();
+        actual.untrack();
+        match actual.value.body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::NonNull(_), .. }, _), .. }) => { }
+            _ => panic!("expected non-null assertion")
+        }
+        match actual.value.body[1] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::As(_, ref ty), .. }, _), .. }) => {
+                match ty.value {
+                    TyData::Ref(ref name, _) => { assert_eq!(name[0].value.name.into_string(), "const"); }
+                    _ => panic!("expected `as const`")
+                }
+            }
+            _ => panic!("expected `as` expression")
+        }
+        match actual.value.body[2] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(Expr { value: ExprData::Instantiation(..), .. }, _), .. }) => { }
+            _ => panic!("expected instantiation expression")
+        }
+        match actual.value.body[3] {
+            StmtListItem::Decl(Decl { value: DeclData::Namespace(ref name, ref body), .. }) => {
+                assert_eq!(name.len(), 2);
+                match body[0] {
+                    StmtListItem::Decl(Decl { value: DeclData::Export(_), .. }) => { }
+                    _ => panic!("expected export")
+                }
+            }
+            _ => panic!("expected namespace")
+        }
+
+        let actual = script_ts("type M = { -readonly [K in T]?: -1 }").unwrap();
+        match actual.value.body[0] {
+            StmtListItem::Decl(Decl { value: DeclData::TyAlias(_, _, ref ty), .. }) => match ty.value {
+                TyData::Mapped(ref mapped) => {
+                    assert_eq!(mapped.readonly, Some(MappedModifier::Remove));
+                    assert_eq!(mapped.optional, Some(MappedModifier::Add));
+                    match mapped.ty.as_ref().unwrap().value {
+                        TyData::Negative(ref literal) => { assert_eq!(literal.value, 1.0); }
+                        _ => panic!("expected negative literal type")
+                    }
+                }
+                _ => panic!("expected mapped type")
+            },
+            _ => panic!("expected type alias")
+        }
+
+        let actual = script_ts("declare function f(): void").unwrap();
+        match actual.value.body[0] {
+            StmtListItem::Decl(ref decl) => match decl.value {
+                DeclData::Declare(ref item) => match **item {
+                    StmtListItem::Decl(ref decl) => match decl.value {
+                        DeclData::FunSig(..) => { }
+                        _ => panic!("expected function signature")
+                    },
+                    _ => panic!("expected declaration")
+                },
+                _ => panic!("expected ambient declaration")
+            },
+            _ => panic!("expected declaration")
+        }
     }
 
-    // IDExpression ::=
-    //   IDAssignmentExpression ("," AssignmentExpression)*
-    fn id_expression(&mut self, id: Id) -> Result<Expr> {
-        let first = try!(self.id_assignment_expression(id));
-        self.more_expressions(first)
+    #[test]
+    pub fn classes() {
+        assert!(script("class A extends B.C { constructor(x) { this.x = x; } static get y() {} static() {} get = 1; z; }").is_ok());
+        assert!(script("x = class { set v(a) {} }; y = class Named {}").is_ok());
+        assert!(script("class { }").is_err());
+        assert!(script("if (a) class A {}").is_err());
+        assert!(script("class A { x = 010 }").is_err());
+        assert!(script("class A { get x = 1 }").is_err());
+        assert!(script("@dec class A {}").is_err());
+
+        assert!(script_ts("class A<T> extends B<T> implements I, J<T> { private readonly x: T; public static m(): void; m(x?: T) {} }").is_ok());
+        assert!(script("class A { private x }").is_err());
+        assert!(script_ts("class A<T> { constructor(private x: T, readonly y, protected readonly z?: T, w) {} }").is_ok());
+        assert!(script_ts("class A { constructor(readonly) {} }").is_ok());
+        assert!(script_ts("class A { m(private x) {} }").is_err());
+        assert!(script_ts("class A { static constructor(private x) {} }").is_err());
+        assert!(script_ts("class A { constructor(x) { function f(private y) {} } }").is_err());
+        assert!(script_ts("function f(private x) {}").is_err());
+        assert!(script_ts("abstract class A<T> extends B { abstract m(): T; protected abstract x: number; n() {} }").is_ok());
+        assert!(script_ts("var abstract; abstract\nclass A {}").is_ok());
+        assert!(script_ts("class A { abstract m(): void; }").is_err());
+        assert!(script_ts("x = abstract class {}").is_err());
+        assert!(script("abstract class A {}").is_err());
+
+        let actual = script_ts("abstract class A { constructor(public readonly x: number) {} abstract m(): void; }").unwrap();
+        match actual.value.body[0] {
+            StmtListItem::Decl(ref decl) => match decl.value {
+                DeclData::Class(ref class) => {
+                    assert!(class.value.is_abstract);
+                    match class.value.body[0].value.val {
+                        ClassMemberVal::Method(ref fun) => {
+                            match fun.value.params.value.list[0] {
+                                Patt::Simple(ref id) => {
+                                    assert_eq!(id.value.access, Some(Access::Public));
+                                    assert!(id.value.readonly);
+                                    assert_eq!(id.location.unwrap().start.offset, 31);
+                                }
+                                _ => panic!("expected parameter property")
+                            }
+                        }
+                        _ => panic!("expected constructor")
+                    }
+                    let member = &class.value.body[1].value;
+                    assert!(member.is_abstract);
+                    match member.val {
+                        ClassMemberVal::Sig(_) => { }
+                        _ => panic!("expected abstract method signature")
+                    }
+                }
+                _ => panic!("expected class")
+            },
+            _ => panic!("expected declaration")
+        }
+
+        assert!(script_decorators("@a @b.c @d.e(f) @(g[0]) class A { @logged m() {} @x static y = 1 }").is_ok());
+        assert!(script_decorators("x = @dec class {}").is_ok());
+        assert!(script_decorators("@a[0] class A {}").is_err());
+        assert!(script_decorators("@a() () class A {}").is_err());
+        assert!(script_decorators("@dec function f() {}").is_err());
+
+        let actual = script_decorators("class C { @a static private() {} }").unwrap();
+        match actual.value.body[0] {
+            StmtListItem::Decl(ref decl) => match decl.value {
+                DeclData::Class(ref class) => {
+                    let member = &class.value.body[0].value;
+                    assert_eq!(member.decorators.len(), 1);
+                    assert!(member.is_static);
+                    match member.val {
+                        ClassMemberVal::Method(_) => { }
+                        _ => panic!("expected method")
+                    }
+                }
+                _ => panic!("expected class")
+            },
+            _ => panic!("expected declaration")
+        }
     }
 
-    fn more_expressions(&mut self, first: Expr) -> Result<Expr> {
-        if try!(self.peek()).value != TokenData::Comma {
-            return Ok(first);
+    #[test]
+    pub fn options() {
+        let es5 = ParseOptions::new().edition(Edition::ES5);
+        assert!(script_with("var x = function() { return this; }", es5).is_ok());
+        assert!(script_with("class A {}", es5).is_err());
+        assert!(script_with("for (x of xs);", es5).is_err());
+        assert!(script_with("function F() { new.target }", es5).is_err());
+        assert!(script_with("import('./a.js')", ParseOptions::new().edition(Edition::ES2019)).is_err());
+        assert!(script_with("class A { x = 1 }", ParseOptions::new().edition(Edition::ES2021)).is_err());
+        assert!(script_with("class A { x() {} }", ParseOptions::new().edition(Edition::ES2021)).is_ok());
+        match script_with("for (let x of xs);", es5) {
+            Err(Error::UnsupportedEdition("let declarations", Edition::ES2015, Some(_))) => { }
+            result => panic!("expected let declarations to require ES2015, got {:?}", result)
         }
-        let mut elts = vec![first];
-        while try!(self.matches(TokenData::Comma)) {
-            elts.push(try!(self.assignment_expression()));
+
+        assert!(script_with("x = 010", ParseOptions::new().strict(true)).is_err());
+        assert!(script("x = 010").is_ok());
+        assert!(module("import.meta.url; x = '\\0'").is_ok());
+        assert!(module("x = 010").is_err());
+        assert!(module_with("x", es5).is_err());
+
+        assert!(script_with("x", ParseOptions::new()).unwrap().location.is_some());
+        let actual = script_with("f(x)", ParseOptions::new().locations(false)).unwrap();
+        assert!(actual.location.is_none());
+        match actual.value.body[0] {
+            StmtListItem::Stmt(ref stmt) => assert!(stmt.location.is_none()),
+            _ => panic!("expected statement")
         }
-        let location = self.vec_span(&elts);
-        Ok(ExprData::Seq(elts).tracked(location))
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    pub fn diagnostics() {
+        let err = script("f(a, b\n}").unwrap_err();
+        assert_eq!(err.code(), "E0023");
+        assert_eq!(err.to_string(), "expected `)`, found `}`");
+        assert_eq!(err.render("f(a, b\n}"), "\
+error[E0023]: expected `)`, found `}`
+ --> 2:1
+  |
+1 | f(a, b
+  |  - `(` opened here
+2 | }
+  | ^ expected `)`
+  |
+  = hint: expected `)` to close `(` opened here
+");
 
-    use std::{thread, env};
-    use test::{deserialize_parser_tests, ParserTest};
-    use joker::track::Untrack;
-    use ::script;
+        let err = script("if (x) { y = 1;").unwrap_err();
+        assert_eq!(err.to_string(), "expected `}`, found end of input");
+        let err = script("var class = 1").unwrap_err();
+        assert_eq!(err.to_string(), "unexpected `class`");
+        assert_eq!(err.span().map(|span| span.start.column), Some(4));
+        let err = script("x\n= y z").unwrap_err();
+        assert_eq!(err.render("x\n= y z"), "\
+error[E0002]: expected `;`, found identifier `z`
+ --> 2:5
+  |
+2 | = y z
+  |     ^ expected `;`
+  |
+  = hint: a semicolon is only inserted before `}`, at the end of input or after a line break
+");
+        let err = script("s = 'abc\nx").unwrap_err();
+        assert_eq!(err.code(), "E0003");
+        assert_eq!(err.render("s = 'abc\nx"), "\
+error[E0003]: unterminated string
+ --> 1:9
+  |
+1 | s = 'abc
+  |     - string starts here
+  |         ^ line ends here
+");
+        match script_with("class A {}", ParseOptions::new().edition(Edition::ES5)) {
+            Err(err) => assert_eq!(err.to_string(), "classes requires ES2015 or later"),
+            Ok(_)    => panic!("expected an error")
+        }
+    }
 
     #[test]
-    pub fn unit_tests() {
-        let tests = deserialize_parser_tests(include_str!("../tests/build/unit.json"));
-        for ParserTest { source, expected, .. } in tests {
-            let result = script(&source[..]);
-            match (result, expected) {
-                (Ok(mut actual_ast), Some(expected_ast)) => {
-                    actual_ast.untrack();
-                    if actual_ast != expected_ast {
-                        println!("");
-                        println!("test:         {}", source);
-                        println!("expected AST: {:#?}", expected_ast);
-                        println!("actual AST:   {:#?}", actual_ast);
-                    }
-                    assert!(actual_ast == expected_ast);
-                }
-                (Err(_), None) => { }
-                (Ok(mut actual_ast), None) => {
-                    actual_ast.untrack();
-                    println!("");
-                    println!("test:                {}", source);
-                    println!("expected error, got: {:?}", actual_ast);
-                    panic!("expected error");
-                }
-                (Err(actual_err), Some(expected_ast)) => {
-                    println!("");
-                    println!("test:         {}", source);
-                    println!("expected AST: {:?}", expected_ast);
-                    println!("actual error: {:?}", actual_err);
-                    panic!("unexpected error");
+    pub fn recovery() {
+        let src = "var a = ;\nfoo(1 2);\nfunction f() {\n  if (x { y(); }\n  return 1;\n}\n)\nb = '#' # 1;\nc = 2;";
+        let (actual, diagnostics) = script_recovering(src);
+        let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["E0001", "E0023", "E0023", "E0001", "E0003"]);
+
+        let body = &actual.value.body;
+        assert_eq!(body.len(), 5);
+        match body[0] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Var(ref dtors, _), .. }) => {
+                assert_eq!(dtors.len(), 1);
+            }
+            _ => panic!("expected var statement")
+        }
+        match body[2] {
+            StmtListItem::Decl(Decl { value: DeclData::Fun(ref fun), .. }) => {
+                assert_eq!(fun.value.body.len(), 2);
+            }
+            _ => panic!("expected function declaration")
+        }
+        for &i in &[1, 3] {
+            match body[i] {
+                StmtListItem::Stmt(Stmt { value: StmtData::Invalid, location: Some(span) }) => {
+                    assert!(span.start.offset <= span.end.offset);
+                    assert!(span.end.offset as usize <= src.len());
                 }
+                ref item => panic!("expected invalid statement, got {:?}", item)
             }
         }
+        match body[4] {
+            StmtListItem::Stmt(Stmt { value: StmtData::Expr(..), .. }) => { }
+            _ => panic!("expected expression statement")
+        }
+
+        let (actual, diagnostics) = script_recovering("x = 1; y = 2");
+        assert!(diagnostics.is_empty());
+        assert_eq!(actual.value.body.len(), 2);
+        assert!(script("a; } b").is_err());
     }
 
     const DEFAULT_MB: usize = 4;
@@ -0,0 +1,73 @@
+use joker::token::{Token, TokenData};
+use joker::word::Reserved;
+
+// What an open bracket in a skipped function body was opened for. Only as
+// much is told apart as deciding whether a '/' after its closing bracket is
+// division or the start of a regexp takes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GroupKind {
+    // The parenthesized head of `if`, `while`, `for`, `with`, `switch` or
+    // `catch`, followed by a statement.
+    Head,
+    Paren,
+    Brack,
+    Block,
+    Object,
+    // The body of a function or class, and whether it is an expression.
+    Body(bool)
+}
+
+// An open bracket in a skipped function body.
+#[derive(Debug)]
+pub struct Group {
+    pub open: Token,
+    pub kind: GroupKind,
+    // The `?` read in this group that haven't met their `:` yet.
+    pub conds: usize
+}
+
+impl Group {
+    pub fn new(open: Token, kind: GroupKind) -> Group {
+        Group { open: open, kind: kind, conds: 0 }
+    }
+
+    pub fn close(&self) -> TokenData {
+        match self.kind {
+            GroupKind::Head | GroupKind::Paren => TokenData::RParen,
+            GroupKind::Brack                   => TokenData::RBrack,
+            _                                  => TokenData::RBrace
+        }
+    }
+
+    // Whether the group ends an operand, so that a '/' after it is division.
+    pub fn is_operand(&self) -> bool {
+        match self.kind {
+            GroupKind::Paren | GroupKind::Brack | GroupKind::Object | GroupKind::Body(true) => true,
+            _ => false
+        }
+    }
+}
+
+// Whether a keyword is followed by the parenthesized head of a statement.
+pub fn has_head(word: Reserved) -> bool {
+    match word {
+        Reserved::If | Reserved::While | Reserved::For | Reserved::With | Reserved::Switch | Reserved::Catch => true,
+        _ => false
+    }
+}
+
+// Whether a keyword can be followed directly by a statement.
+pub fn precedes_statement(word: Reserved) -> bool {
+    match word {
+        Reserved::Else | Reserved::Do | Reserved::Try | Reserved::Finally => true,
+        _ => false
+    }
+}
+
+// Whether a keyword is an operand by itself, like an identifier.
+pub fn is_operand(word: Reserved) -> bool {
+    match word {
+        Reserved::This | Reserved::Super | Reserved::Null | Reserved::True | Reserved::False => true,
+        _ => false
+    }
+}
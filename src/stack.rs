@@ -132,6 +132,15 @@ impl Stack {
         self.frames.push(Frame { left: left, op: op });
     }
 
+    // Folds every frame that binds at least as tightly as `precedence` into
+    // `right`, for postfix operators such as TypeScript's `as`.
+    pub fn reduce(&mut self, mut right: Expr, precedence: u32) -> Expr {
+        while self.frames.last().map_or(false, |frame| frame.precedence() >= precedence) {
+            right = self.frames.pop().unwrap().fill(right);
+        }
+        right
+    }
+
     pub fn finish(mut self, mut right: Expr) -> Expr {
         while self.frames.len() > 0 {
             right = self.frames.pop().unwrap().fill(right);
@@ -10,6 +10,7 @@ pub trait State {
     fn peek(&mut self) -> Result<&Token>;
     fn peek_op(&mut self) -> Result<&Token>;
     fn expect(&mut self, expected: TokenData) -> Result<Token>;
+    fn expect_closing(&mut self, open: &Token, expected: TokenData) -> Result<Token>;
     fn matches_token(&mut self, expected: TokenData) -> Result<Option<Token>>;
     fn matches(&mut self, expected: TokenData) -> Result<bool>;
     fn matches_op(&mut self, expected: TokenData) -> Result<bool>;
@@ -19,18 +20,18 @@ pub trait State {
 
 impl<I: Iterator<Item=char>> State for Parser<I> {
     fn skip(&mut self) -> Result<()> {
-        self.lexer.skip_token().map_err(Error::LexError)
+        self.lexer.skip_token().map_err(Error::from)
     }
 
     fn read(&mut self) -> Result<Token> {
-        self.lexer.read_token().map_err(Error::LexError)
+        self.lexer.read_token().map_err(Error::from)
     }
 
     fn read_op(&mut self) -> Result<Token> {
         let mut cx = self.shared_cx.get();
         cx.operator = true;
         self.shared_cx.set(cx);
-        let result = self.lexer.read_token().map_err(Error::LexError);
+        let result = self.lexer.read_token().map_err(Error::from);
         let mut cx = self.shared_cx.get();
         cx.operator = false;
         self.shared_cx.set(cx);
@@ -38,14 +39,14 @@ impl<I: Iterator<Item=char>> State for Parser<I> {
     }
 
     fn peek(&mut self) -> Result<&Token> {
-        self.lexer.peek_token().map_err(Error::LexError)
+        self.lexer.peek_token().map_err(Error::from)
     }
 
     fn peek_op(&mut self) -> Result<&Token> {
         let mut cx = self.shared_cx.get();
         cx.operator = true;
         self.shared_cx.set(cx);
-        let result = self.lexer.peek_token().map_err(Error::LexError);
+        let result = self.lexer.peek_token().map_err(Error::from);
         let mut cx = self.shared_cx.get();
         cx.operator = false;
         self.shared_cx.set(cx);
@@ -55,7 +56,16 @@ impl<I: Iterator<Item=char>> State for Parser<I> {
     fn expect(&mut self, expected: TokenData) -> Result<Token> {
         let token = try!(self.read());
         if token.value != expected {
-            return Err(Error::UnexpectedToken(token));
+            return Err(Error::Expected(expected, token));
+        }
+        Ok(token)
+    }
+
+    // Like `expect`, but reports an unclosed `open` delimiter on failure.
+    fn expect_closing(&mut self, open: &Token, expected: TokenData) -> Result<Token> {
+        let token = try!(self.read());
+        if token.value != expected {
+            return Err(Error::Unclosed(open.clone(), expected, token));
         }
         Ok(token)
     }
@@ -96,6 +106,6 @@ impl<I: Iterator<Item=char>> State for Parser<I> {
 
     fn has_arg_same_line(&mut self) -> Result<bool> {
         let next = try!(self.peek());
-        Ok(!next.newline && next.value != TokenData::Semi && next.value != TokenData::RBrace)
+        Ok(!next.newline && next.value != TokenData::Semi && next.value != TokenData::RBrace && next.value != TokenData::EOF)
     }
 }
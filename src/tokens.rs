@@ -4,10 +4,14 @@ use context::LabelType;
 
 pub trait First {
     fn first_binding(&self) -> bool;
+    fn first_property_key(&self) -> bool;
+    fn first_statement_keyword(&self) -> bool;
 }
 
 pub trait Follows {
     fn follow_statement_list(&self) -> bool;
+    fn follow_expression(&self) -> bool;
+    fn follow_type_member_key(&self) -> bool;
 }
 
 impl First for Token {
@@ -28,6 +32,44 @@ impl First for Token {
             _ => false
         }
     }
+
+    // first(PropertyName) =
+    //   IdentifierName
+    // U first(StringLiteral)
+    // U first(NumericLiteral)
+    fn first_property_key(&self) -> bool {
+        match self.value {
+            TokenData::Identifier(_)
+          | TokenData::Reserved(_)
+          | TokenData::String(_)
+          | TokenData::Number(_) => true,
+            _ => false
+        }
+    }
+
+    // Keywords that can only begin a statement or declaration. The
+    // error-recovering parser resumes at one of these on a new line.
+    fn first_statement_keyword(&self) -> bool {
+        match self.value {
+            TokenData::Reserved(Reserved::Var)
+          | TokenData::Reserved(Reserved::Const)
+          | TokenData::Reserved(Reserved::Function)
+          | TokenData::Reserved(Reserved::Class)
+          | TokenData::Reserved(Reserved::If)
+          | TokenData::Reserved(Reserved::For)
+          | TokenData::Reserved(Reserved::While)
+          | TokenData::Reserved(Reserved::Do)
+          | TokenData::Reserved(Reserved::Return)
+          | TokenData::Reserved(Reserved::Break)
+          | TokenData::Reserved(Reserved::Continue)
+          | TokenData::Reserved(Reserved::Switch)
+          | TokenData::Reserved(Reserved::Throw)
+          | TokenData::Reserved(Reserved::Try)
+          | TokenData::Reserved(Reserved::With)
+          | TokenData::Reserved(Reserved::Debugger) => true,
+            _ => false
+        }
+    }
 }
 
 impl Follows for Token {
@@ -65,6 +107,36 @@ impl Follows for Token {
             _ => false
         }
     }
+
+    // Tokens that can end an expression, where the error-recovering parser
+    // substitutes a placeholder for a missing expression.
+    fn follow_expression(&self) -> bool {
+        match self.value {
+              TokenData::Semi
+            | TokenData::Comma
+            | TokenData::Colon
+            | TokenData::RParen
+            | TokenData::RBrack
+            | TokenData::RBrace
+            | TokenData::EOF => true,
+            _ => false
+        }
+    }
+
+    // follow(PropertyName) within a TypeScript TypeMember
+    // = { '(', '<', '?', ':', ';', ',', '}' }
+    fn follow_type_member_key(&self) -> bool {
+        match self.value {
+              TokenData::LParen
+            | TokenData::LAngle
+            | TokenData::Question
+            | TokenData::Colon
+            | TokenData::Semi
+            | TokenData::Comma
+            | TokenData::RBrace => true,
+            _ => false
+        }
+    }
 }
 
 pub trait HasLabelType {
@@ -8,8 +8,9 @@ use state::State;
 
 pub trait Tracking {
     fn vec_span<T: Track>(&self, v: &Vec<T>) -> Option<Span>;
-    fn posn(&self) -> Posn;
-    fn start(&self) -> SpanTracker;
+    fn start_posn(&mut self) -> Posn;
+    fn end_posn(&self) -> Posn;
+    fn start(&mut self) -> SpanTracker;
     fn span<F, T>(&mut self, parse: &mut F) -> Result<Tracked<T>>
       where F: FnMut(&mut Self) -> Result<T>;
 }
@@ -18,26 +19,33 @@ impl<I> Tracking for Parser<I> where I: Iterator<Item=char> {
     fn vec_span<T: Track>(&self, v: &Vec<T>) -> Option<Span> {
         let len = v.len();
         if len == 0 {
-            let here = self.posn();
+            let here = self.end_posn();
             return Some(Span { start: here, end: here });
         }
         span(&v[0], &v[len - 1])
     }
 
-    fn posn(&self) -> Posn {
-        self.lexer.posn()
+    // Where the next token starts, past any whitespace and comments.
+    fn start_posn(&mut self) -> Posn {
+        self.lexer.next_token_start()
     }
 
-    fn start(&self) -> SpanTracker {
-        SpanTracker { start: self.posn() }
+    // Where the last consumed token ends.
+    fn end_posn(&self) -> Posn {
+        self.lexer.last_token_end()
+    }
+
+    fn start(&mut self) -> SpanTracker {
+        SpanTracker { start: self.start_posn() }
     }
 
     fn span<F, T>(&mut self, parse: &mut F) -> Result<Tracked<T>>
       where F: FnMut(&mut Self) -> Result<T>
     {
-        let start = self.posn();
+        let start = self.start_posn();
         let value = try!(parse(self));
-        let end = self.posn();
+        let end = self.end_posn();
+        try!(self.count_node());
         Ok(Tracked { value: value, location: Some(Span { start: start, end: end }) })
     }
 }
@@ -53,6 +61,10 @@ pub struct SpanTracker {
 }
 
 impl SpanTracker {
+    pub fn at(start: Posn) -> SpanTracker {
+        SpanTracker { start: start }
+    }
+
 /*
     pub fn end<I, T>(&self, parser: &Parser<I>, value: T) -> Tracked<T>
       where I: Iterator<Item=char>
@@ -66,31 +78,35 @@ impl SpanTracker {
       where I: Iterator<Item=char>,
             F: FnOnce(Semi) -> T
     {
-        let before = parser.posn();
+        let (semi, end) = try!(self.semi(parser, newline));
+        try!(parser.count_node());
+        Ok(Tracked {
+            value: cons(semi),
+            location: Some(Span { start: self.start, end: end })
+        })
+    }
+
+    // Reads the terminator of a statement, returning it along with where the
+    // statement ends.
+    pub fn semi<I>(&self, parser: &mut Parser<I>, newline: Newline) -> Result<(Semi, Posn)>
+      where I: Iterator<Item=char>
+    {
+        let before = parser.end_posn();
         match try!(parser.peek()) {
             &Token { value: TokenData::Semi, location, .. } => {
                 parser.reread(TokenData::Semi);
-                Ok(Tracked {
-                    value: cons(Semi::Explicit(Some(location.start))),
-                    location: Some(Span { start: self.start, end: parser.posn() })
-                })
+                Ok((Semi::Explicit(Some(location.start)), parser.end_posn()))
             }
             &Token { value: TokenData::RBrace, .. }
           | &Token { value: TokenData::EOF, .. } => {
-                Ok(Tracked {
-                    value: cons(Semi::Inserted),
-                    location: Some(Span { start: self.start, end: before })
-                })
+                Ok((Semi::Inserted, before))
             }
             &Token { newline: found_newline, .. } => {
                 if newline == Newline::Required && !found_newline {
                     let token = try!(parser.read());
                     return Err(Error::FailedASI(token));
                 }
-                Ok(Tracked {
-                    value: cons(Semi::Inserted),
-                    location: Some(Span { start: self.start, end: before })
-                })
+                Ok((Semi::Inserted, before))
             }
         }
     }